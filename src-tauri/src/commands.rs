@@ -1,12 +1,19 @@
 use a2l_editor::{
-    A2lEntry, A2lEntryInfo, A2lEntryStore, A2lGenerator, A2lParser, A2lVariable, DataPackage,
-    ElfParser, ExportKind, PackageMeta, SaveResult, VariableChanges, VariableEdit,
+    apply_module_params, create_groups, delete_group, diff_a2l, insert_conversions,
+    is_cancelled_error, list_groups, merge_a2l, read_module_params, read_xcp_config,
+    rename_variable, set_xcp_if_data, update_addresses, update_group, A2lDiff, A2lEntry,
+    A2lEntryInfo, A2lEntryStore, A2lGenerator, A2lGroup, A2lProject, A2lVariable, CancelToken,
+    CompuMethodSpec, Conversions, DataPackage, Diagnostic, ElfParser, Endianness, EntryTree,
+    EnumType, ExpandConfig, ExpandLimits, ExportKind, GroupBy, GroupKind, MergeOptions,
+    MergeReport, ModuleParams, NamingPolicy, PackageContents, PackageMeta, ParseOptions, Progress,
+    ProgressReporter, Reference, SaveResult, TextEncoding, TreeNode, UpdateReport, VariableChanges,
+    VariableEdit, XcpConfig,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
 
 #[derive(Default)]
 pub struct AppState {
@@ -14,9 +21,15 @@ pub struct AppState {
     pub data_package: Option<DataPackage>,
     pub elf_path: Option<PathBuf>,
     pub a2l_path: Option<PathBuf>,
+    /// 手动指定的目标 A2L 编码，`None` 时自动识别
+    pub a2l_encoding: Option<TextEncoding>,
     pub a2l_names: HashSet<String>,
     pub a2l_variables: Vec<A2lVariable>,
-    pub endianness: String,
+    pub endianness: Endianness,
+    pub generate_cancel: Option<CancelToken>,
+    pub naming: NamingPolicy,
+    /// 条目树，首次浏览时从数据包加载
+    pub tree: Option<EntryTree>,
 }
 
 #[derive(Serialize)]
@@ -31,15 +44,18 @@ pub struct PackageMetaInfo {
     pub elf_path: Option<String>,
     pub entry_count: usize,
     pub created_at: i64,
+    /// 数据包生效的字节序 ("little"/"big")
+    pub endianness: String,
 }
 
-impl From<PackageMeta> for PackageMetaInfo {
-    fn from(meta: PackageMeta) -> Self {
+impl PackageMetaInfo {
+    fn new(meta: PackageMeta, endianness: Endianness) -> Self {
         PackageMetaInfo {
             file_name: meta.file_name,
             elf_path: meta.elf_path,
             entry_count: meta.entry_count,
             created_at: meta.created_at,
+            endianness: endianness.as_str().to_string(),
         }
     }
 }
@@ -49,6 +65,11 @@ pub struct A2lLoadResult {
     pub path: String,
     pub variable_count: usize,
     pub existing_names: Vec<String>,
+    /// 与命名风格无关的匹配键，和 `EntryInfo::match_key` 比较
+    pub existing_keys: Vec<String>,
+    /// 主文件的字符编码与换行符，保存时按原样写回
+    pub encoding: String,
+    pub line_ending: String,
 }
 
 #[derive(Serialize)]
@@ -56,6 +77,7 @@ pub struct ExportResult {
     pub added: usize,
     pub skipped: usize,
     pub existing: usize,
+    pub warnings: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -68,6 +90,28 @@ pub struct EntryInfo {
     pub type_name: String,
     pub bit_offset: Option<usize>,
     pub bit_size: Option<usize>,
+    #[serde(default)]
+    pub array_dims: Option<Vec<usize>>,
+    #[serde(default)]
+    pub enum_type: Option<EnumType>,
+    #[serde(default)]
+    pub source_file: Option<String>,
+    #[serde(default)]
+    pub match_key: String,
+}
+
+impl EntryInfo {
+    fn new(index: usize, entry: &A2lEntry, naming: &NamingPolicy) -> Self {
+        // 导出时块名会被合法化，按写入 A2L 的标识符比较
+        EntryInfo {
+            match_key: if entry.is_array_element() {
+                naming.element_match_key(&naming.identifier(&entry.full_name))
+            } else {
+                naming.match_key(&naming.identifier(&entry.full_name))
+            },
+            ..EntryInfo::from((index, entry))
+        }
+    }
 }
 
 impl From<(usize, &A2lEntry)> for EntryInfo {
@@ -81,6 +125,10 @@ impl From<(usize, &A2lEntry)> for EntryInfo {
             type_name: entry.type_name.clone(),
             bit_offset: entry.bit_offset,
             bit_size: entry.bit_size,
+            array_dims: entry.array_dims.clone(),
+            enum_type: entry.enum_type.clone(),
+            source_file: entry.source_file.clone(),
+            match_key: String::new(),
         }
     }
 }
@@ -92,6 +140,23 @@ pub struct VariableInfo {
     pub data_type: String,
     pub var_type: String,
     pub bit_mask: Option<String>,
+    pub kind: Option<String>,
+    pub dims: Option<Vec<usize>>,
+    pub record_layout: Option<String>,
+    pub lower_limit: Option<String>,
+    pub upper_limit: Option<String>,
+    pub axis_refs: Vec<Option<String>>,
+    pub compu_method: Option<String>,
+    pub long_identifier: String,
+    pub phys_unit: Option<String>,
+    pub format: Option<String>,
+    pub extended_lower_limit: Option<String>,
+    pub extended_upper_limit: Option<String>,
+    pub display_identifier: Option<String>,
+    pub ecu_address_extension: Option<String>,
+    pub read_write: bool,
+    /// 所在的被包含文件，主文件中的变量为空
+    pub file: Option<String>,
 }
 
 impl From<&A2lVariable> for VariableInfo {
@@ -102,6 +167,22 @@ impl From<&A2lVariable> for VariableInfo {
             data_type: var.data_type.clone(),
             var_type: var.var_type.clone(),
             bit_mask: var.bit_mask.clone(),
+            kind: var.kind.clone(),
+            dims: var.dims.clone(),
+            record_layout: var.record_layout.clone(),
+            lower_limit: var.lower_limit.clone(),
+            upper_limit: var.upper_limit.clone(),
+            axis_refs: var.axis_refs.clone(),
+            compu_method: var.compu_method.clone(),
+            long_identifier: var.long_identifier.clone(),
+            phys_unit: var.phys_unit.clone(),
+            format: var.format.clone(),
+            extended_lower_limit: var.extended_lower_limit.clone(),
+            extended_upper_limit: var.extended_upper_limit.clone(),
+            display_identifier: var.display_identifier.clone(),
+            ecu_address_extension: var.ecu_address_extension.clone(),
+            read_write: var.read_write,
+            file: var.file.clone(),
         }
     }
 }
@@ -132,11 +213,18 @@ fn load_from_package_internal(
         .map_err(|e| format!("无法加载条目: {}", e))?;
     let entry_count = store.len();
 
+    state.naming = pkg
+        .naming_policy()
+        .map_err(|e| format!("无法读取命名策略: {}", e))?;
+    state.endianness = pkg
+        .endianness()
+        .map_err(|e| format!("无法读取字节序: {}", e))?;
     state.store = Some(store);
     state.data_package = Some(pkg);
+    state.tree = None;
 
     Ok(LoadResult {
-        meta: PackageMetaInfo::from(meta),
+        meta: PackageMetaInfo::new(meta, state.endianness),
         entry_count,
     })
 }
@@ -158,70 +246,186 @@ pub fn load_package(path: String, state: State<Mutex<AppState>>) -> Result<LoadR
         .map_err(|e| format!("无法加载条目: {}", e))?;
     let entry_count = store.len();
 
+    state.naming = pkg
+        .naming_policy()
+        .map_err(|e| format!("无法读取命名策略: {}", e))?;
+    state.endianness = pkg
+        .endianness()
+        .map_err(|e| format!("无法读取字节序: {}", e))?;
     state.store = Some(store);
     state.data_package = Some(pkg);
+    state.tree = None;
 
     Ok(LoadResult {
-        meta: PackageMetaInfo::from(meta),
+        meta: PackageMetaInfo::new(meta, state.endianness),
         entry_count,
     })
 }
 
 #[tauri::command]
-pub fn generate_package(
+pub async fn generate_package(
     elf_path: String,
     output_path: Option<String>,
-    state: State<Mutex<AppState>>,
+    expand_limits: Option<ExpandLimits>,
+    naming: Option<NamingPolicy>,
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
 ) -> Result<PackageMetaInfo, String> {
+    let cancel = CancelToken::new();
+    {
+        let mut state = state.lock().map_err(|e| e.to_string())?;
+        if state.generate_cancel.is_some() {
+            return Err("已有数据包正在生成".to_string());
+        }
+        state.generate_cancel = Some(cancel.clone());
+    }
+
     let elf = PathBuf::from(&elf_path);
-    let parser = ElfParser::parse_deep(&elf).map_err(|e| format!("解析失败: {}", e))?;
-    let store = parser.a2l_entries().ok_or("未找到 A2L 条目")?.clone();
+    let worker_elf = elf.clone();
+    let worker_cancel = cancel.clone();
 
-    let mut pkg = if let Some(ref output) = output_path {
-        DataPackage::create_at(&PathBuf::from(output), &elf)
-            .map_err(|e| format!("创建数据包失败: {}", e))?
-    } else {
-        DataPackage::create(&elf).map_err(|e| format!("创建数据包失败: {}", e))?
-    };
+    // 解析耗时较长，放到阻塞线程中执行，进度通过事件推送到前端
+    let built = tauri::async_runtime::spawn_blocking(move || {
+        let emit_progress = move |p: &Progress| {
+            app.emit("package-progress", p).ok();
+        };
+        let reporter = ProgressReporter::new()
+            .with_callback(&emit_progress)
+            .with_cancel(&worker_cancel);
+
+        let describe = |e, context: &str| {
+            if is_cancelled_error(&e) {
+                "已取消生成".to_string()
+            } else {
+                format!("{}: {}", context, e)
+            }
+        };
+
+        let options = ParseOptions::new()
+            .with_expand_config(ExpandConfig::new(expand_limits.unwrap_or_default()))
+            .with_naming_policy(naming.unwrap_or_default());
+        let parser = ElfParser::parse_with_options(&worker_elf, true, &options, reporter)
+            .map_err(|e| describe(e, "解析失败"))?;
+        let store = parser.a2l_entries().ok_or("未找到 A2L 条目")?.clone();
+
+        let mut contents = PackageContents::new(&store)
+            .with_variables(parser.variables())
+            .with_naming_policy(&options.naming)
+            .with_endianness(parser.endianness());
+        if let Some(type_cache) = parser.type_cache() {
+            contents = contents.with_type_cache(type_cache);
+        }
+
+        let output = output_path.map(PathBuf::from);
+        let pkg =
+            DataPackage::create_with_entries(output.as_deref(), &worker_elf, &contents, reporter)
+                .map_err(|e| describe(e, "创建数据包失败"))?;
+        Ok::<_, String>((pkg, store, options.naming))
+    })
+    .await;
+
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    state.generate_cancel = None;
+
+    let (pkg, store, naming) = built.map_err(|e| format!("生成任务异常: {}", e))??;
 
-    pkg.save_entries(&store)
-        .map_err(|e| format!("保存数据包失败: {}", e))?;
     let meta = pkg
         .get_meta()
         .map_err(|e| format!("读取元信息失败: {}", e))?;
+    let endianness = pkg
+        .endianness()
+        .map_err(|e| format!("读取字节序失败: {}", e))?;
 
-    let mut state = state.lock().map_err(|e| e.to_string())?;
     state.store = Some(store);
     state.data_package = Some(pkg);
     state.elf_path = Some(elf);
+    state.naming = naming;
+    state.endianness = endianness;
+    state.tree = None;
 
-    Ok(PackageMetaInfo::from(meta))
+    Ok(PackageMetaInfo::new(meta, endianness))
 }
 
 #[tauri::command]
-pub fn load_a2l(path: String, state: State<Mutex<AppState>>) -> Result<A2lLoadResult, String> {
-    let a2l_path = PathBuf::from(&path);
-    let content =
-        std::fs::read_to_string(&a2l_path).map_err(|e| format!("读取 A2L 文件失败: {}", e))?;
+pub fn cancel_generate_package(state: State<Mutex<AppState>>) -> Result<bool, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    match state.generate_cancel {
+        Some(ref token) => {
+            token.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
 
-    let variables = A2lParser::parse_all_variables(&content);
+#[tauri::command]
+pub fn load_a2l(
+    path: String,
+    encoding: Option<String>,
+    state: State<Mutex<AppState>>,
+) -> Result<A2lLoadResult, String> {
+    let a2l_path = PathBuf::from(&path);
+    let encoding = encoding
+        .filter(|e| !e.is_empty())
+        .map(|e| e.parse::<TextEncoding>())
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let project = A2lProject::load_with_encoding(&a2l_path, encoding)
+        .map_err(|e| format!("读取 A2L 文件失败: {}", e))?;
+
+    let variables = project
+        .variables()
+        .map_err(|e| format!("解析 A2L 文件失败: {}", e))?;
     let existing_names: Vec<String> = variables.iter().map(|v| v.name.clone()).collect();
     let name_set: HashSet<String> = existing_names.iter().cloned().collect();
 
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    // A2L 中的名称无法区分是否为数组元素，两种键都保留
+    let existing_keys: Vec<String> = existing_names
+        .iter()
+        .flat_map(|n| [state.naming.match_key(n), state.naming.element_match_key(n)])
+        .collect();
+
     let result = A2lLoadResult {
         path: path.clone(),
         variable_count: variables.len(),
         existing_names,
+        existing_keys,
+        encoding: project.format().encoding.to_string(),
+        line_ending: project.format().line_ending.to_string(),
     };
 
-    let mut state = state.lock().map_err(|e| e.to_string())?;
     state.a2l_path = Some(a2l_path);
+    state.a2l_encoding = encoding;
     state.a2l_names = name_set;
     state.a2l_variables = variables;
 
     Ok(result)
 }
 
+/// 读取目标 A2L 及其 `/include` 的文件
+fn load_a2l_project(state: &AppState) -> Result<A2lProject, String> {
+    let a2l_path = state.a2l_path.as_ref().ok_or("未选择目标 A2L 文件")?;
+    A2lProject::load_with_encoding(a2l_path, state.a2l_encoding)
+        .map_err(|e| format!("读取 A2L 文件失败: {}", e))
+}
+
+/// 把修改写回对象所在的文件，并刷新变量列表
+fn save_a2l_project(
+    state: &mut AppState,
+    project: &mut A2lProject,
+    content: &str,
+) -> Result<(), String> {
+    project
+        .write(content)
+        .map_err(|e| format!("写入 A2L 文件失败: {}", e))?;
+    state.a2l_variables = project
+        .variables()
+        .map_err(|e| format!("解析 A2L 文件失败: {}", e))?;
+    state.a2l_names = state.a2l_variables.iter().map(|v| v.name.clone()).collect();
+    Ok(())
+}
+
 #[tauri::command]
 pub fn search_elf_entries(
     query: String,
@@ -266,12 +470,70 @@ pub fn search_elf_entries(
         .into_iter()
         .skip(offset)
         .take(limit)
-        .map(|(i, e)| EntryInfo::from((i, e)))
+        .map(|(i, e)| EntryInfo::new(i, e, &state.naming))
         .collect();
 
     Ok(result)
 }
 
+fn ensure_tree(state: &mut AppState) -> Result<&EntryTree, String> {
+    if state.tree.is_none() {
+        let pkg = state.data_package.as_ref().ok_or("未加载数据包")?;
+        let tree = pkg
+            .load_tree()
+            .map_err(|e| format!("无法加载条目树: {}", e))?;
+        state.tree = Some(tree);
+    }
+    Ok(state.tree.as_ref().unwrap())
+}
+
+#[derive(Serialize)]
+pub struct TreePage {
+    pub total: usize,
+    pub nodes: Vec<TreeNode>,
+}
+
+#[tauri::command]
+pub fn get_tree_roots(
+    offset: usize,
+    limit: usize,
+    state: State<Mutex<AppState>>,
+) -> Result<TreePage, String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let tree = ensure_tree(&mut state)?;
+
+    Ok(TreePage {
+        total: tree.root_count(),
+        nodes: tree.roots(offset, limit),
+    })
+}
+
+#[tauri::command]
+pub fn get_tree_children(
+    id: String,
+    offset: usize,
+    limit: usize,
+    state: State<Mutex<AppState>>,
+) -> Result<TreePage, String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let tree = ensure_tree(&mut state)?;
+
+    let total = tree.child_count(&id).map_err(|e| e.to_string())?;
+    let nodes = tree
+        .children(&id, offset, limit)
+        .map_err(|e| e.to_string())?;
+
+    Ok(TreePage { total, nodes })
+}
+
+#[tauri::command]
+pub fn get_tree_node(id: String, state: State<Mutex<AppState>>) -> Result<TreeNode, String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let tree = ensure_tree(&mut state)?;
+
+    tree.node(&id).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn get_elf_count(state: State<Mutex<AppState>>) -> Result<usize, String> {
     let state = state.lock().map_err(|e| e.to_string())?;
@@ -314,6 +576,7 @@ pub fn search_a2l_variables(
 pub fn export_entries(
     indices: Vec<usize>,
     mode: String,
+    group_by: Option<String>,
     state: State<Mutex<AppState>>,
 ) -> Result<ExportResult, String> {
     let mut state = state.lock().map_err(|e| e.to_string())?;
@@ -334,19 +597,29 @@ pub fn export_entries(
         return Err("没有选中任何条目".to_string());
     }
 
-    let export_kind = match mode.as_str() {
-        "measurement" => ExportKind::Measurement,
-        "characteristic" => ExportKind::Characteristic,
-        _ => return Err("无效的导出模式".to_string()),
-    };
-
-    let result = A2lGenerator::append_to_file(&entries, &a2l_path, export_kind)
-        .map_err(|e| format!("导出失败: {}", e))?;
+    let export_kind = mode.parse::<ExportKind>().map_err(|e| e.to_string())?;
+    let group_by = group_by
+        .unwrap_or_default()
+        .parse::<GroupBy>()
+        .map_err(|e| e.to_string())?;
+
+    let result = A2lGenerator::append_to_file(
+        &entries,
+        &a2l_path,
+        export_kind,
+        &state.naming,
+        group_by,
+        state.endianness,
+        state.a2l_encoding,
+    )
+    .map_err(|e| format!("导出失败: {}", e))?;
 
     // 重新加载 A2L
-    let content =
-        std::fs::read_to_string(&a2l_path).map_err(|e| format!("重新读取 A2L 失败: {}", e))?;
-    let variables = A2lParser::parse_all_variables(&content);
+    let project = A2lProject::load_with_encoding(&a2l_path, state.a2l_encoding)
+        .map_err(|e| format!("重新读取 A2L 失败: {}", e))?;
+    let variables = project
+        .variables()
+        .map_err(|e| format!("解析 A2L 文件失败: {}", e))?;
     state.a2l_variables = variables;
     state.a2l_names = state.a2l_variables.iter().map(|v| v.name.clone()).collect();
 
@@ -354,6 +627,7 @@ pub fn export_entries(
         added: result.added,
         skipped: result.skipped,
         existing: result.existing,
+        warnings: result.warnings,
     })
 }
 
@@ -364,29 +638,17 @@ pub fn delete_variables(
 ) -> Result<usize, String> {
     let mut state = state.lock().map_err(|e| e.to_string())?;
 
-    let a2l_path = state.a2l_path.as_ref().ok_or("未选择目标 A2L 文件")?;
+    let mut project = load_a2l_project(&state)?;
 
     if names.is_empty() {
         return Err("没有选中任何变量".to_string());
     }
 
-    let content =
-        std::fs::read_to_string(a2l_path).map_err(|e| format!("读取 A2L 文件失败: {}", e))?;
-    let new_content = A2lGenerator::remove_variables(&content, &names)
+    let new_content = A2lGenerator::remove_variables(project.content(), &names)
         .map_err(|e| format!("删除变量失败: {}", e))?;
+    save_a2l_project(&mut state, &mut project, &new_content)?;
 
-    std::fs::write(a2l_path, new_content).map_err(|e| format!("写入 A2L 文件失败: {}", e))?;
-
-    let deleted_count = names.len();
-
-    // 重新加载 A2L
-    let content =
-        std::fs::read_to_string(a2l_path).map_err(|e| format!("重新读取 A2L 失败: {}", e))?;
-    let variables = A2lParser::parse_all_variables(&content);
-    state.a2l_variables = variables;
-    state.a2l_names = state.a2l_variables.iter().map(|v| v.name.clone()).collect();
-
-    Ok(deleted_count)
+    Ok(names.len())
 }
 
 #[derive(Serialize, Deserialize)]
@@ -398,6 +660,32 @@ pub struct VariableEditInput {
     pub data_type: Option<String>,
     pub var_type: Option<String>,
     pub bit_mask: Option<String>,
+    #[serde(default)]
+    pub record_layout: Option<String>,
+    #[serde(default)]
+    pub lower_limit: Option<String>,
+    #[serde(default)]
+    pub upper_limit: Option<String>,
+    #[serde(default)]
+    pub axis_refs: Option<Vec<String>>,
+    #[serde(default)]
+    pub compu_method: Option<String>,
+    #[serde(default)]
+    pub long_identifier: Option<String>,
+    #[serde(default)]
+    pub phys_unit: Option<String>,
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub extended_lower_limit: Option<String>,
+    #[serde(default)]
+    pub extended_upper_limit: Option<String>,
+    #[serde(default)]
+    pub display_identifier: Option<String>,
+    #[serde(default)]
+    pub ecu_address_extension: Option<String>,
+    #[serde(default)]
+    pub read_write: Option<bool>,
     pub entry: Option<EntryInfo>,
     pub export_mode: Option<String>,
 }
@@ -409,7 +697,7 @@ pub fn save_a2l_changes(
 ) -> Result<SaveResult, String> {
     let mut state = state.lock().map_err(|e| e.to_string())?;
 
-    let a2l_path = state.a2l_path.as_ref().ok_or("未选择目标 A2L 文件")?;
+    let mut project = load_a2l_project(&state)?;
 
     let variable_edits: Vec<VariableEdit> = edits
         .into_iter()
@@ -421,6 +709,19 @@ pub fn save_a2l_changes(
                 || e.data_type.is_some()
                 || e.var_type.is_some()
                 || e.bit_mask.is_some()
+                || e.record_layout.is_some()
+                || e.lower_limit.is_some()
+                || e.upper_limit.is_some()
+                || e.axis_refs.is_some()
+                || e.compu_method.is_some()
+                || e.long_identifier.is_some()
+                || e.phys_unit.is_some()
+                || e.format.is_some()
+                || e.extended_lower_limit.is_some()
+                || e.extended_upper_limit.is_some()
+                || e.display_identifier.is_some()
+                || e.ecu_address_extension.is_some()
+                || e.read_write.is_some()
             {
                 Some(VariableChanges {
                     name: e.name,
@@ -428,6 +729,19 @@ pub fn save_a2l_changes(
                     data_type: e.data_type,
                     var_type: e.var_type,
                     bit_mask: e.bit_mask,
+                    record_layout: e.record_layout,
+                    lower_limit: e.lower_limit,
+                    upper_limit: e.upper_limit,
+                    axis_refs: e.axis_refs,
+                    compu_method: e.compu_method,
+                    long_identifier: e.long_identifier,
+                    phys_unit: e.phys_unit,
+                    format: e.format,
+                    extended_lower_limit: e.extended_lower_limit,
+                    extended_upper_limit: e.extended_upper_limit,
+                    display_identifier: e.display_identifier,
+                    ecu_address_extension: e.ecu_address_extension,
+                    read_write: e.read_write,
                 })
             } else {
                 None
@@ -440,34 +754,287 @@ pub fn save_a2l_changes(
                 type_name: info.type_name,
                 bit_offset: info.bit_offset,
                 bit_size: info.bit_size,
+                array_dims: info.array_dims,
+                enum_type: info.enum_type,
+                source_file: info.source_file,
             }),
             export_mode: e.export_mode,
         })
         .collect();
 
-    let content =
-        std::fs::read_to_string(a2l_path).map_err(|e| format!("读取 A2L 文件失败: {}", e))?;
+    let (new_content, result) = A2lGenerator::apply_changes(
+        project.content(),
+        &variable_edits,
+        &state.naming,
+        state.endianness,
+    )
+    .map_err(|e| format!("应用变更失败: {}", e))?;
+    save_a2l_project(&mut state, &mut project, &new_content)?;
 
-    let (new_content, result) = A2lGenerator::apply_changes(&content, &variable_edits)
-        .map_err(|e| format!("应用变更失败: {}", e))?;
+    Ok(result)
+}
 
-    std::fs::write(a2l_path, new_content).map_err(|e| format!("写入 A2L 文件失败: {}", e))?;
+/// 按当前加载的 ELF 刷新目标 A2L 中的地址与 BIT_MASK
+#[tauri::command]
+pub fn update_a2l_addresses(state: State<Mutex<AppState>>) -> Result<UpdateReport, String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let mut project = load_a2l_project(&state)?;
+    let store = state.store.as_ref().ok_or("未加载 ELF 文件")?;
 
-    let variables = A2lParser::parse_all_variables(
-        &std::fs::read_to_string(a2l_path).map_err(|e| format!("重新读取 A2L 失败: {}", e))?,
-    );
-    state.a2l_variables = variables;
-    state.a2l_names = state.a2l_variables.iter().map(|v| v.name.clone()).collect();
+    let (new_content, report) =
+        update_addresses(project.content(), store, &state.naming, state.endianness)
+            .map_err(|e| format!("更新地址失败: {}", e))?;
+    save_a2l_project(&mut state, &mut project, &new_content)?;
 
-    Ok(result)
+    Ok(report)
+}
+
+/// 比较 `old_path` 与目标 A2L（或 `new_path`）
+#[tauri::command]
+pub fn diff_a2l_files(
+    old_path: String,
+    new_path: Option<String>,
+    state: State<Mutex<AppState>>,
+) -> Result<A2lDiff, String> {
+    let new_path = match new_path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let state = state.lock().map_err(|e| e.to_string())?;
+            state.a2l_path.clone().ok_or("未选择目标 A2L 文件")?
+        }
+    };
+
+    let load =
+        |path: &Path| A2lProject::load(path).map_err(|e| format!("读取 A2L 文件失败: {}", e));
+    diff_a2l(
+        load(Path::new(&old_path))?.content(),
+        load(&new_path)?.content(),
+    )
+    .map_err(|e| format!("比较 A2L 文件失败: {}", e))
+}
+
+/// 把来源 A2L 合并到目标 A2L
+#[tauri::command]
+pub fn merge_a2l_files(
+    sources: Vec<String>,
+    options: MergeOptions,
+    state: State<Mutex<AppState>>,
+) -> Result<MergeReport, String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let mut project = load_a2l_project(&state)?;
+
+    let sources = sources
+        .iter()
+        .map(|path| {
+            A2lProject::load(Path::new(path))
+                .and_then(|source| A2lProject::flatten(source.content()))
+                .map_err(|e| format!("读取 {} 失败: {}", path, e))
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+    let sources: Vec<&str> = sources.iter().map(|s| s.as_str()).collect();
+
+    let (new_content, report) = merge_a2l(project.content(), &sources, &options)
+        .map_err(|e| format!("合并 A2L 失败: {}", e))?;
+    save_a2l_project(&mut state, &mut project, &new_content)?;
+
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn list_conversions(state: State<Mutex<AppState>>) -> Result<Conversions, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let project = load_a2l_project(&state)?;
+
+    Conversions::parse(project.content()).map_err(|e| format!("解析 A2L 文件失败: {}", e))
+}
+
+#[tauri::command]
+pub fn create_conversions(
+    specs: Vec<CompuMethodSpec>,
+    state: State<Mutex<AppState>>,
+) -> Result<Conversions, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let mut project = load_a2l_project(&state)?;
+
+    let new_content = insert_conversions(project.content(), &specs)
+        .map_err(|e| format!("创建转换失败: {}", e))?;
+    project
+        .write(&new_content)
+        .map_err(|e| format!("写入 A2L 文件失败: {}", e))?;
+
+    Conversions::parse(&new_content).map_err(|e| format!("解析 A2L 文件失败: {}", e))
+}
+
+#[tauri::command]
+pub fn list_a2l_groups(state: State<Mutex<AppState>>) -> Result<Vec<A2lGroup>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let project = load_a2l_project(&state)?;
+
+    list_groups(project.content()).map_err(|e| format!("解析 A2L 文件失败: {}", e))
+}
+
+/// 变量的声明与全部引用，用于改名前预览
+#[tauri::command]
+pub fn find_variable_references(
+    name: String,
+    state: State<Mutex<AppState>>,
+) -> Result<Vec<Reference>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let project = load_a2l_project(&state)?;
+
+    project
+        .find_references(&name)
+        .map_err(|e| format!("解析 A2L 文件失败: {}", e))
+}
+
+/// 改名并更新全部引用，返回改动的位置
+#[tauri::command]
+pub fn rename_a2l_variable(
+    old_name: String,
+    new_name: String,
+    state: State<Mutex<AppState>>,
+) -> Result<Vec<Reference>, String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let mut project = load_a2l_project(&state)?;
+
+    let references = project
+        .find_references(&old_name)
+        .map_err(|e| format!("解析 A2L 文件失败: {}", e))?;
+    let (new_content, _) = rename_variable(project.content(), &old_name, &new_name)
+        .map_err(|e| format!("改名失败: {}", e))?;
+    save_a2l_project(&mut state, &mut project, &new_content)?;
+    Ok(references)
+}
+
+#[tauri::command]
+pub fn validate_a2l(state: State<Mutex<AppState>>) -> Result<Vec<Diagnostic>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let project = load_a2l_project(&state)?;
+
+    project
+        .validate()
+        .map_err(|e| format!("解析 A2L 文件失败: {}", e))
+}
+
+/// 读取模块级 IF_DATA XCP 配置，没有时返回空
+#[tauri::command]
+pub fn get_xcp_config(state: State<Mutex<AppState>>) -> Result<Option<XcpConfig>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let project = load_a2l_project(&state)?;
+
+    read_xcp_config(project.content()).map_err(|e| format!("解析 A2L 文件失败: {}", e))
+}
+
+/// 按配置插入或更新模块级 IF_DATA XCP
+#[tauri::command]
+pub fn set_xcp_config(config: XcpConfig, state: State<Mutex<AppState>>) -> Result<(), String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let mut project = load_a2l_project(&state)?;
+
+    let new_content = set_xcp_if_data(project.content(), &config)
+        .map_err(|e| format!("生成 IF_DATA XCP 失败: {}", e))?;
+    project
+        .write(&new_content)
+        .map_err(|e| format!("写入 A2L 文件失败: {}", e))?;
+    Ok(())
+}
+
+/// 读取 MOD_PAR 与 MOD_COMMON 中的模块参数
+#[tauri::command]
+pub fn get_module_params(state: State<Mutex<AppState>>) -> Result<ModuleParams, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let project = load_a2l_project(&state)?;
+
+    read_module_params(project.content()).map_err(|e| format!("解析 A2L 文件失败: {}", e))
+}
+
+/// 从当前 ELF 推断模块参数，只返回建议值，不写入文件
+#[tauri::command]
+pub fn propose_module_params(
+    epk_symbol: Option<String>,
+    state: State<Mutex<AppState>>,
+) -> Result<ModuleParams, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let elf_path = state.elf_path.as_ref().ok_or("未加载 ELF 文件")?;
+
+    a2l_editor::propose_module_params(elf_path, epk_symbol.as_deref())
+        .map_err(|e| format!("推断模块参数失败: {}", e))
+}
+
+#[tauri::command]
+pub fn set_module_params(
+    params: ModuleParams,
+    state: State<Mutex<AppState>>,
+) -> Result<(), String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let mut project = load_a2l_project(&state)?;
+
+    let new_content = apply_module_params(project.content(), &params)
+        .map_err(|e| format!("更新模块参数失败: {}", e))?;
+    project
+        .write(&new_content)
+        .map_err(|e| format!("写入 A2L 文件失败: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn create_a2l_group(
+    group: A2lGroup,
+    state: State<Mutex<AppState>>,
+) -> Result<Vec<A2lGroup>, String> {
+    edit_a2l_groups(&state, |content| {
+        create_groups(content, std::slice::from_ref(&group)).map_err(|e| e.to_string())
+    })
+}
+
+#[tauri::command]
+pub fn update_a2l_group(
+    kind: GroupKind,
+    original_name: String,
+    group: A2lGroup,
+    state: State<Mutex<AppState>>,
+) -> Result<Vec<A2lGroup>, String> {
+    edit_a2l_groups(&state, |content| {
+        update_group(content, kind, &original_name, &group).map_err(|e| e.to_string())
+    })
+}
+
+#[tauri::command]
+pub fn delete_a2l_group(
+    kind: GroupKind,
+    name: String,
+    state: State<Mutex<AppState>>,
+) -> Result<Vec<A2lGroup>, String> {
+    edit_a2l_groups(&state, |content| {
+        delete_group(content, kind, &name).map_err(|e| e.to_string())
+    })
+}
+
+/// 读取目标 A2L、应用分组修改并写回，返回修改后的全部分组
+fn edit_a2l_groups(
+    state: &State<Mutex<AppState>>,
+    edit: impl FnOnce(&str) -> Result<String, String>,
+) -> Result<Vec<A2lGroup>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let mut project = load_a2l_project(&state)?;
+
+    let new_content = edit(project.content()).map_err(|e| format!("修改分组失败: {}", e))?;
+    project
+        .write(&new_content)
+        .map_err(|e| format!("写入 A2L 文件失败: {}", e))?;
+
+    list_groups(&new_content).map_err(|e| format!("解析 A2L 文件失败: {}", e))
 }
 
 #[tauri::command]
 pub fn set_endianness(endianness: String, state: State<Mutex<AppState>>) -> Result<(), String> {
-    if endianness != "little" && endianness != "big" {
-        return Err("无效的字节序，必须是 'little' 或 'big'".to_string());
-    }
+    let endianness: Endianness = endianness.parse().map_err(|e| format!("{}", e))?;
     let mut state = state.lock().map_err(|e| e.to_string())?;
+    // 手动切换的字节序记录在数据包中，下次打开时沿用
+    if let Some(pkg) = &state.data_package {
+        pkg.set_endianness_override(Some(endianness))
+            .map_err(|e| format!("保存字节序失败: {}", e))?;
+    }
     state.endianness = endianness;
     Ok(())
 }
@@ -1,7 +1,7 @@
 import { listen } from '@tauri-apps/api/event';
 import { 
-  packagePath, elfPath, elfFileName, elfTotalCount,
-  a2lPath, a2lNames, elfEntries, a2lVariables,
+  packagePath, elfPath, elfFileName, elfTotalCount, endianness,
+  a2lPath, a2lNames, a2lMatchKeys, a2lFormat, a2lEncoding, elfEntries, a2lVariables,
   isLoading, statusMessage
 } from './stores';
 import { 
@@ -23,6 +23,7 @@ export function setupAutoLoad() {
           elfPath.set(result.meta.elf_path || null);
           elfFileName.set(result.meta.file_name);
           elfTotalCount.set(result.entry_count);
+          endianness.set(result.meta.endianness);
           const entries = await searchElfEntries('', 0, 10000);
           elfEntries.set(entries);
           statusMessage.set(`✅ 已加载 ${result.entry_count} 个条目`);
@@ -37,7 +38,10 @@ export function setupAutoLoad() {
           statusMessage.set('⏳ 正在加载 A2L...');
           const result = await loadA2l(a2l);
           a2lPath.set(a2l);
+          a2lEncoding.set('');
+          a2lFormat.set(`${result.encoding} · ${result.line_ending}`);
           a2lNames.set(new Set(result.existing_names));
+          a2lMatchKeys.set(new Set(result.existing_keys));
           const vars = await searchA2lVariables('', 0, 10000);
           a2lVariables.set(vars);
           statusMessage.set(`✅ 已加载目标 A2L (${result.variable_count} 个变量)`);
@@ -69,6 +73,7 @@ export async function testLoadFiles(pkgPath?: string, a2lFilePath?: string): Pro
       elfPath.set(result.meta.elf_path || null);
       elfFileName.set(result.meta.file_name);
       elfTotalCount.set(result.entry_count);
+      endianness.set(result.meta.endianness);
       
       console.log('[testLoadFiles] Step 3: State updated, entry_count:', result.entry_count);
       
@@ -105,6 +110,7 @@ export async function testLoadFiles(pkgPath?: string, a2lFilePath?: string): Pro
       
       a2lPath.set(a2lFilePath);
       a2lNames.set(new Set(result.existing_names));
+      a2lMatchKeys.set(new Set(result.existing_keys));
       const vars = await searchA2lVariables('', 0, 10000);
       console.log('[testLoadFiles] A2L vars loaded:', vars.length);
       debug.a2lVarsCount = vars.length;
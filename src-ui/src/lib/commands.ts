@@ -1,14 +1,31 @@
 import { invoke } from '@tauri-apps/api/core';
 import type { 
   A2lEntry, 
+  A2lGroup,
   A2lVariable, 
   A2lVariableEdit,
+  CompuMethodSpec,
+  Conversions,
+  ExpandLimits,
+  NamingPolicy,
+  TreeNode,
+  TreePage,
   LoadResult, 
   PackageMeta, 
   A2lLoadResult, 
   ExportResult,
   SaveResult,
-  ExportMode 
+  Diagnostic,
+  Reference,
+  UpdateReport,
+  A2lDiff,
+  MergeOptions,
+  MergeReport,
+  XcpConfig,
+  ModuleParams,
+  ExportMode,
+  GroupBy,
+  GroupKind
 } from './types';
 
 // 文件操作
@@ -20,12 +37,21 @@ export async function loadPackage(path: string): Promise<LoadResult> {
   return invoke('load_package', { path });
 }
 
-export async function generatePackage(elfPath: string, outputPath?: string): Promise<PackageMeta> {
-  return invoke('generate_package', { elfPath, outputPath });
+export async function generatePackage(
+  elfPath: string,
+  outputPath?: string,
+  expandLimits?: ExpandLimits,
+  naming?: NamingPolicy
+): Promise<PackageMeta> {
+  return invoke('generate_package', { elfPath, outputPath, expandLimits, naming });
 }
 
-export async function loadA2l(path: string): Promise<A2lLoadResult> {
-  return invoke('load_a2l', { path });
+export async function cancelGeneratePackage(): Promise<boolean> {
+  return invoke('cancel_generate_package');
+}
+
+export async function loadA2l(path: string, encoding?: string): Promise<A2lLoadResult> {
+  return invoke('load_a2l', { path, encoding: encoding || null });
 }
 
 // 变量查询
@@ -49,13 +75,25 @@ export async function getElfCount(): Promise<number> {
   return invoke('get_elf_count');
 }
 
+export async function getTreeRoots(offset: number, limit: number): Promise<TreePage> {
+  return invoke('get_tree_roots', { offset, limit });
+}
+
+export async function getTreeChildren(id: string, offset: number, limit: number): Promise<TreePage> {
+  return invoke('get_tree_children', { id, offset, limit });
+}
+
+export async function getTreeNode(id: string): Promise<TreeNode> {
+  return invoke('get_tree_node', { id });
+}
+
 export async function searchA2lVariables(query: string, offset = 0, limit = 10000): Promise<A2lVariable[]> {
   return invoke('search_a2l_variables', { query, offset, limit });
 }
 
 // 导出/删除
-export async function exportEntries(indices: number[], mode: ExportMode): Promise<ExportResult> {
-  return invoke('export_entries', { indices, mode });
+export async function exportEntries(indices: number[], mode: ExportMode, groupBy: GroupBy = 'none'): Promise<ExportResult> {
+  return invoke('export_entries', { indices, mode, groupBy });
 }
 
 export async function deleteVariables(names: string[]): Promise<number> {
@@ -71,12 +109,100 @@ export async function saveA2lChanges(edits: A2lVariableEdit[]): Promise<SaveResu
     data_type: e.data_type,
     var_type: e.var_type,
     bit_mask: e.bit_mask,
+    record_layout: e.record_layout,
+    lower_limit: e.lower_limit,
+    upper_limit: e.upper_limit,
+    axis_refs: e.axis_refs,
+    compu_method: e.compu_method,
+    long_identifier: e.long_identifier,
+    phys_unit: e.phys_unit,
+    format: e.format,
+    extended_lower_limit: e.extended_lower_limit,
+    extended_upper_limit: e.extended_upper_limit,
+    display_identifier: e.display_identifier,
+    ecu_address_extension: e.ecu_address_extension,
+    read_write: e.read_write,
     entry: e.entry,
     export_mode: e.exportMode,
   }));
   return invoke('save_a2l_changes', { edits: inputEdits });
 }
 
+// 转换方法
+export async function listConversions(): Promise<Conversions> {
+  return invoke('list_conversions');
+}
+
+export async function createConversions(specs: CompuMethodSpec[]): Promise<Conversions> {
+  return invoke('create_conversions', { specs });
+}
+
+// 分组
+export async function listGroups(): Promise<A2lGroup[]> {
+  return invoke('list_a2l_groups');
+}
+
+export async function updateA2lAddresses(): Promise<UpdateReport> {
+  return invoke('update_a2l_addresses');
+}
+
+// newPath 为空时与目标 A2L 比较
+export async function diffA2lFiles(oldPath: string, newPath?: string): Promise<A2lDiff> {
+  return invoke('diff_a2l_files', { oldPath, newPath });
+}
+
+export async function mergeA2lFiles(sources: string[], options: MergeOptions): Promise<MergeReport> {
+  return invoke('merge_a2l_files', { sources, options });
+}
+
+// 模块级 IF_DATA XCP，没有时返回 null
+export async function getXcpConfig(): Promise<XcpConfig | null> {
+  return invoke('get_xcp_config');
+}
+
+export async function setXcpConfig(config: XcpConfig): Promise<void> {
+  return invoke('set_xcp_config', { config });
+}
+
+// MOD_PAR 与 MOD_COMMON
+export async function getModuleParams(): Promise<ModuleParams> {
+  return invoke('get_module_params');
+}
+
+// 从当前 ELF 推断，不写入文件
+export async function proposeModuleParams(epkSymbol?: string): Promise<ModuleParams> {
+  return invoke('propose_module_params', { epkSymbol });
+}
+
+export async function setModuleParams(params: ModuleParams): Promise<void> {
+  return invoke('set_module_params', { params });
+}
+
+export async function validateA2l(): Promise<Diagnostic[]> {
+  return invoke('validate_a2l');
+}
+
+export async function findVariableReferences(name: string): Promise<Reference[]> {
+  return invoke('find_variable_references', { name });
+}
+
+// 改名并更新全部引用，返回改动的位置
+export async function renameA2lVariable(oldName: string, newName: string): Promise<Reference[]> {
+  return invoke('rename_a2l_variable', { oldName, newName });
+}
+
+export async function createGroup(group: A2lGroup): Promise<A2lGroup[]> {
+  return invoke('create_a2l_group', { group });
+}
+
+export async function updateGroup(kind: GroupKind, originalName: string, group: A2lGroup): Promise<A2lGroup[]> {
+  return invoke('update_a2l_group', { kind, originalName, group });
+}
+
+export async function deleteGroup(kind: GroupKind, name: string): Promise<A2lGroup[]> {
+  return invoke('delete_a2l_group', { kind, name });
+}
+
 export async function setEndianness(endianness: 'little' | 'big'): Promise<void> {
   return invoke('set_endianness', { endianness });
 }
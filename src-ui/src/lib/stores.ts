@@ -1,5 +1,5 @@
 import { writable, derived, get } from 'svelte/store';
-import type { A2lEntry, A2lVariable, ThemeName } from './types';
+import type { A2lEntry, A2lVariable, ExportMode, GroupBy, ThemeName } from './types';
 
 // 排序类型定义
 export type SortField = 'name' | 'address';
@@ -32,6 +32,11 @@ export const elfFileSize = writable<string | null>(null);
 export const packagePath = writable<string | null>(null);
 export const a2lPath = writable<string | null>(null);
 export const a2lNames = writable<Set<string>>(new Set());
+// 与命名风格无关的匹配键，用于判断 ELF 条目是否已在 A2L 中
+export const a2lMatchKeys = writable<Set<string>>(new Set());
+// 目标 A2L 的编码与换行（如 "GBK · CRLF"），以及手动指定的编码，空字符串表示自动识别
+export const a2lFormat = writable<string | null>(null);
+export const a2lEncoding = writable<string>('');
 
 // 应用状态
 export const statusMessage = writable<string>('💡 文件 → 打开 ELF 开始使用');
@@ -43,7 +48,15 @@ export const showExportDialog = writable<boolean>(false);
 export const showGenerateDialog = writable<boolean>(false);
 export const showAboutDialog = writable<boolean>(false);
 export const showHelpDialog = writable<boolean>(false);
-export const exportMode = writable<'measurement' | 'characteristic'>('measurement');
+export const showGroupDialog = writable<boolean>(false);
+export const showValidateDialog = writable<boolean>(false);
+export const showUpdateDialog = writable<boolean>(false);
+export const showDiffDialog = writable<boolean>(false);
+export const showMergeDialog = writable<boolean>(false);
+export const showXcpDialog = writable<boolean>(false);
+export const showModParDialog = writable<boolean>(false);
+export const exportMode = writable<ExportMode>('measurement');
+export const exportGroupBy = writable<GroupBy>('none');
 export const exportPreview = writable<{ added: number; skipped: number; existing: number } | null>(null);
 
 // 主题
@@ -7,14 +7,46 @@ export interface A2lEntry {
   type_name: string;
   bit_offset: number | null;
   bit_size: number | null;
+  array_dims?: number[] | null;
+  enum_type?: EnumType | null;
+  source_file?: string | null;
+  match_key: string;
+}
+
+export interface EnumType {
+  name: string;
+  variants: { name: string; value: number }[];
+}
+
+export interface ExpandLimits {
+  max_array_expand: number;
+  max_nesting_depth: number;
 }
 
 export interface A2lVariable {
   name: string;
   address: string | null;
   data_type: string;
-  var_type: 'MEASUREMENT' | 'CHARACTERISTIC';
+  var_type: 'MEASUREMENT' | 'CHARACTERISTIC' | 'AXIS_PTS';
   bit_mask: string | null;
+  kind: string | null;
+  dims: number[] | null;
+  record_layout: string | null;
+  lower_limit: string | null;
+  upper_limit: string | null;
+  axis_refs: (string | null)[];
+  compu_method: string | null;
+  long_identifier: string;
+  phys_unit: string | null;
+  format: string | null;
+  extended_lower_limit: string | null;
+  extended_upper_limit: string | null;
+  display_identifier: string | null;
+  ecu_address_extension: string | null;
+  /** MEASUREMENT 带 READ_WRITE，或标定量不带 READ_ONLY */
+  read_write: boolean;
+  /** 所在的被包含文件，主文件中的变量为 null */
+  file: string | null;
 }
 
 export interface LoadResult {
@@ -27,21 +59,78 @@ export interface PackageMeta {
   elf_path: string | null;
   entry_count: number;
   created_at: number;
+  endianness: 'little' | 'big';
+}
+
+export type ProgressPhase = 'symbols' | 'dwarf' | 'expand' | 'save' | 'done';
+
+export interface PackageProgress {
+  phase: ProgressPhase;
+  processed: number;
+  total: number;
+  entries: number;
 }
 
 export interface A2lLoadResult {
   path: string;
   variable_count: number;
   existing_names: string[];
+  existing_keys: string[];
+  encoding: string;
+  line_ending: 'LF' | 'CRLF';
+}
+
+export type TreeNodeKind = 'variable' | 'member' | 'element';
+
+export interface TreeNode {
+  id: string;
+  label: string;
+  full_name: string;
+  kind: TreeNodeKind;
+  address: number;
+  size: number;
+  a2l_type: string;
+  type_name: string;
+  bit_offset: number | null;
+  bit_size: number | null;
+  array_dims: number[] | null;
+  child_count: number;
+}
+
+export interface TreePage {
+  total: number;
+  nodes: TreeNode[];
+}
+
+export type IndexStyle = 'underscore' | 'bracket' | 'flat' | 'dotted';
+
+export interface NamingPolicy {
+  index_style: IndexStyle;
+  separator: string;
+  strip_prefixes: string[];
+  max_length: number | null;
 }
 
 export interface ExportResult {
   added: number;
   skipped: number;
   existing: number;
+  /** 目标 A2L 的 ASAP2 版本不支持或已降级的构造 */
+  warnings: string[];
 }
 
-export type ExportMode = 'measurement' | 'characteristic';
+export type ExportMode =
+  | 'measurement'
+  | 'characteristic'
+  | 'val_blk'
+  | 'ascii'
+  | 'curve'
+  | 'map'
+  | 'cuboid'
+  | 'axis_pts';
+
+/** 导出时自动创建分组的方式 */
+export type GroupBy = 'none' | 'struct' | 'source_file';
 export type ThemeName = 'dark' | 'light' | 'midnight' | 'ocean';
 
 export type EditActionType = 'modify' | 'delete' | 'add';
@@ -52,8 +141,22 @@ export interface A2lVariableEdit {
   name?: string;
   address?: string;
   data_type?: string;
-  var_type?: 'MEASUREMENT' | 'CHARACTERISTIC';
+  var_type?: 'MEASUREMENT' | 'CHARACTERISTIC' | 'AXIS_PTS';
   bit_mask?: string;
+  record_layout?: string;
+  lower_limit?: string;
+  upper_limit?: string;
+  axis_refs?: string[];
+  compu_method?: string;
+  long_identifier?: string;
+  /** 以下可选关键字为空字符串时删除 */
+  phys_unit?: string;
+  format?: string;
+  extended_lower_limit?: string;
+  extended_upper_limit?: string;
+  display_identifier?: string;
+  ecu_address_extension?: string;
+  read_write?: boolean;
   entry?: A2lEntry;
   exportMode?: ExportMode;
 }
@@ -63,4 +166,213 @@ export interface SaveResult {
   deleted: number;
   added: number;
   skipped: number;
+  // 修改后新出现的校验警告
+  diagnostics: Diagnostic[];
+}
+
+export interface FieldChange {
+  field: string;
+  old?: string;
+  new?: string;
+}
+
+export interface DiffObject {
+  name: string;
+  keyword: string;
+  address?: string;
+}
+
+export interface ObjectChange {
+  old_name: string;
+  name: string;
+  keyword: string;
+  changes: FieldChange[];
+}
+
+export interface A2lDiff {
+  added: DiffObject[];
+  removed: DiffObject[];
+  renamed: ObjectChange[];
+  changed: ObjectChange[];
+}
+
+export type ConflictPolicy = 'skip' | 'overwrite' | 'rename';
+
+export interface MergeOptions {
+  policy: ConflictPolicy;
+  prefix: string;
+}
+
+export type MergeAction = 'imported' | 'skipped' | 'overwritten' | 'renamed' | 'reused';
+
+export interface MergeItem {
+  keyword: string;
+  name: string;
+  action: MergeAction;
+  new_name?: string;
+}
+
+export interface MergeReport {
+  items: MergeItem[];
+}
+
+export type UpdateStatus = 'updated' | 'unchanged' | 'missing' | 'type_changed';
+
+export interface UpdateItem {
+  name: string;
+  keyword: string;
+  symbol: string;
+  status: UpdateStatus;
+  old_address?: string;
+  new_address?: string;
+  old_type?: string;
+  new_type?: string;
+}
+
+export interface UpdateReport {
+  items: UpdateItem[];
+}
+
+export type Severity = 'error' | 'warning';
+
+export interface Diagnostic {
+  severity: Severity;
+  code: string;
+  message: string;
+  line: number;
+  column: number;
+  name?: string;
+  file?: string | null;
+  // 重名时首次定义的位置
+  first_line?: number | null;
+  first_file?: string | null;
+}
+
+// 变量名出现的位置：声明处 keyword 为块关键字，owner 为空
+export interface Reference {
+  keyword: string;
+  owner: string | null;
+  line: number;
+  column: number;
+  file: string | null;
+}
+
+export interface CompuMethod {
+  name: string;
+  long_identifier: string;
+  conversion_type: string;
+  format: string;
+  unit: string;
+  coeffs: number[] | null;
+  compu_tab_ref: string | null;
+}
+
+export interface CompuTable {
+  keyword: 'COMPU_TAB' | 'COMPU_VTAB';
+  name: string;
+  long_identifier: string;
+  conversion_type: string;
+  pairs: [string, string][];
+  default_value: string | null;
+}
+
+export interface Conversions {
+  methods: CompuMethod[];
+  tables: CompuTable[];
+}
+
+export type ConversionKind =
+  | { type: 'IDENTICAL' }
+  | { type: 'LINEAR'; factor: number; offset: number }
+  | { type: 'RAT_FUNC'; coeffs: [number, number, number, number, number, number] }
+  | { type: 'TAB_INTP'; pairs: [number, number][] }
+  | { type: 'TAB_VERB'; pairs: [number, string][]; default_value?: string };
+
+export interface CompuMethodSpec {
+  name: string;
+  long_identifier?: string;
+  kind: ConversionKind;
+  format?: string;
+  unit?: string;
+}
+
+export type GroupKind = 'GROUP' | 'FUNCTION';
+
+export interface A2lGroup {
+  kind: GroupKind;
+  name: string;
+  long_identifier: string;
+  root: boolean;
+  /** GROUP 的 REF_MEASUREMENT，FUNCTION 的 LOC_MEASUREMENT */
+  measurements: string[];
+  /** GROUP 的 REF_CHARACTERISTIC，FUNCTION 的 DEF_CHARACTERISTIC */
+  characteristics: string[];
+  /** SUB_GROUP 或 SUB_FUNCTION */
+  sub_groups: string[];
+}
+
+export type DaqConfigType = 'static' | 'dynamic';
+export type EventDirection = 'daq' | 'stim' | 'daq_stim';
+
+export interface EventChannel {
+  name: string;
+  short_name: string;
+  number: number;
+  direction: EventDirection;
+  /** 周期（微秒），0 表示非周期事件 */
+  cycle_us: number;
+  max_daq_list: number;
+  priority: number;
+}
+
+export type XcpTransport =
+  | { kind: 'can'; baudrate: number; master_id: number; slave_id: number; broadcast_id?: number | null; extended: boolean }
+  | { kind: 'ethernet'; protocol: 'udp' | 'tcp'; address: string; port: number };
+
+export interface XcpConfig {
+  protocol: {
+    timeouts: number[];
+    max_cto: number;
+    max_dto: number;
+    byte_order: 'Little' | 'Big';
+    optional_commands: string[];
+  };
+  daq: {
+    config_type: DaqConfigType;
+    max_daq: number;
+    min_daq: number;
+    max_odt_entry_size: number;
+    lists: { number: number; max_odt: number; max_odt_entries: number; event_fixed?: number | null }[];
+  };
+  events: EventChannel[];
+  transport: XcpTransport;
+}
+
+export interface MemorySegment {
+  name: string;
+  long_identifier: string;
+  /** CODE、DATA、VARIABLES 等 */
+  prg_type: string;
+  /** FLASH、RAM、EEPROM 等 */
+  memory_type: string;
+  attribute: string;
+  address: number;
+  size: number;
+}
+
+/** 模块参数，null 表示保持文件中的现状 */
+export interface ModuleParams {
+  byte_order: 'Little' | 'Big' | null;
+  alignments: {
+    byte: number | null;
+    word: number | null;
+    long: number | null;
+    int64: number | null;
+    float32: number | null;
+    float64: number | null;
+  };
+  epk: string | null;
+  addr_epk: number | null;
+  ecu: string | null;
+  memory_segments: MemorySegment[] | null;
 }
@@ -1,9 +1,15 @@
 use a2l_editor::{
-    compute_file_hash, format_file_size, A2lGenerator, Cache, CacheEntry, DataPackage, DwarfParser,
-    ElfParser, TypeInfo,
+    apply_module_params, compute_file_hash, diff_a2l, format_file_size, list_groups, merge_a2l,
+    propose_module_params, read_module_params, read_xcp_config, rename_variable, set_xcp_if_data,
+    update_addresses, A2lGenerator, A2lParser, A2lProject, Asap2Version, Cache, CacheEntry,
+    ConflictPolicy, Conversions, DataPackage, DwarfParser, ElfParser, Endianness, EntryTree,
+    ExpandConfig, MergeAction, MergeOptions, NamingPolicy, PackageContents, ParseOptions, Progress,
+    ProgressPhase, ProgressReporter, TextEncoding, TypeInfo, TypeRules, UpdateStatus,
+    VariableChanges, XcpConfig,
 };
-use anyhow::Result;
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 fn main() -> Result<()> {
@@ -37,7 +43,7 @@ fn main() -> Result<()> {
         }
         "export" => {
             if args.len() < 3 {
-                eprintln!("用法: a2l-cli export <elf文件路径> [-o 输出文件] [-n 变量数量]");
+                eprintln!("用法: a2l-cli export <elf文件路径> [-o 输出文件] [-n 变量数量] [--asap2-version 1.71]");
                 return Ok(());
             }
             let path = PathBuf::from(&args[2]);
@@ -52,7 +58,22 @@ fn main() -> Result<()> {
                 .and_then(|i| args.get(i + 1))
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(100);
-            export_a2l(&path, output.as_deref(), limit)?;
+            let naming = parse_naming_policy(&args)?;
+            let type_rules = parse_type_rules(&args)?;
+            let endianness = parse_endianness(&args)?;
+            let version = flag_value(&args, "--asap2-version")
+                .map(|value| value.parse())
+                .transpose()?
+                .unwrap_or_default();
+            export_a2l(
+                &path,
+                output.as_deref(),
+                limit,
+                naming,
+                type_rules,
+                endianness,
+                version,
+            )?;
         }
         "cache" => {
             list_cache()?;
@@ -150,9 +171,204 @@ fn main() -> Result<()> {
                 .unwrap_or(50);
             list_a2l_entries(&path, search, limit)?;
         }
+        "tree" => {
+            if args.len() < 3 {
+                eprintln!("用法: a2l-cli tree <elf文件路径> [节点ID] [-n 数量] [--offset 起始]");
+                return Ok(());
+            }
+            let path = PathBuf::from(&args[2]);
+            let node_id = args
+                .get(3)
+                .filter(|s| !s.starts_with('-'))
+                .map(|s| s.as_str());
+            let limit: usize = flag_value(&args, "-n")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(50);
+            let offset: usize = flag_value(&args, "--offset")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0);
+            show_tree(&path, node_id, offset, limit)?;
+        }
+        "guesses" => {
+            if args.len() < 3 {
+                eprintln!("用法: a2l-cli guesses <elf文件路径> [--type-rules 文件] [--unknown]");
+                return Ok(());
+            }
+            let path = PathBuf::from(&args[2]);
+            let options = ParseOptions::new().with_type_rules(parse_type_rules(&args)?);
+            let unknown_only = args.contains(&"--unknown".to_string());
+            list_type_guesses(&path, &options, unknown_only)?;
+        }
+        "conversions" => {
+            if args.len() < 3 {
+                eprintln!("用法: a2l-cli conversions <a2l文件路径>");
+                return Ok(());
+            }
+            list_conversions(Path::new(&args[2]))?;
+        }
+        "groups" => {
+            if args.len() < 3 {
+                eprintln!("用法: a2l-cli groups <a2l文件路径>");
+                return Ok(());
+            }
+            list_a2l_groups(Path::new(&args[2]))?;
+        }
+        "validate" => {
+            if args.len() < 3 {
+                eprintln!("用法: a2l-cli validate <a2l文件路径> [--strict]");
+                eprintln!("  --strict    有警告时也返回非零退出码");
+                return Ok(());
+            }
+            let strict = args.contains(&"--strict".to_string());
+            let encoding = parse_encoding(&args)?;
+            if !validate_a2l(Path::new(&args[2]), strict, encoding)? {
+                std::process::exit(1);
+            }
+        }
+        "diff" => {
+            if args.len() < 4 {
+                eprintln!("用法: a2l-cli diff <旧a2l文件> <新a2l文件> [--json]");
+                eprintln!("  --json    输出 JSON 格式");
+                eprintln!("  有差异时返回退出码 1");
+                return Ok(());
+            }
+            let json = args.contains(&"--json".to_string());
+            if !diff_a2l_files(Path::new(&args[2]), Path::new(&args[3]), json)? {
+                std::process::exit(1);
+            }
+        }
+        "merge" => {
+            if args.len() < 4 {
+                eprintln!("用法: a2l-cli merge <目标a2l文件> <来源a2l文件>... [-o 输出文件] [--policy skip|overwrite|rename] [--prefix 前缀]");
+                eprintln!("  --policy    同名变量与分组的处理方式，默认 skip");
+                eprintln!("  --prefix    改名时添加的前缀");
+                eprintln!("  不指定 -o 时直接改写目标文件");
+                return Ok(());
+            }
+            let target = PathBuf::from(&args[2]);
+            let sources: Vec<PathBuf> = args[3..]
+                .iter()
+                .take_while(|a| !a.starts_with('-'))
+                .map(PathBuf::from)
+                .collect();
+            let output = flag_value(&args, "-o").map(PathBuf::from);
+            let mut options = MergeOptions::new(match flag_value(&args, "--policy") {
+                Some(policy) => policy.parse()?,
+                None => ConflictPolicy::Skip,
+            });
+            if let Some(prefix) = flag_value(&args, "--prefix") {
+                options = options.with_prefix(prefix);
+            }
+            let encoding = parse_encoding(&args)?;
+            merge_a2l_files(&target, &sources, output.as_deref(), &options, encoding)?;
+        }
+        "update" => {
+            if args.len() < 4 {
+                eprintln!("用法: a2l-cli update <a2l文件> <elf文件或.a2ldata数据包> [-o 输出文件] [--max-array N] [--max-depth N] [--expand-config 文件] [--endianness little|big]");
+                eprintln!("  不指定 -o 时直接改写原 A2L 文件");
+                eprintln!(
+                    "  --endianness    计算 BIT_MASK 的字节序，默认取数据包设置或 ELF 文件头"
+                );
+                return Ok(());
+            }
+            let a2l_path = PathBuf::from(&args[2]);
+            let source = PathBuf::from(&args[3]);
+            let output = flag_value(&args, "-o").map(PathBuf::from);
+            let options = parse_parse_options(&args)?;
+            let endianness = parse_endianness(&args)?;
+            let encoding = parse_encoding(&args)?;
+            update_a2l(
+                &a2l_path,
+                &source,
+                output.as_deref(),
+                &options,
+                endianness,
+                encoding,
+            )?;
+        }
+        "xcp" => {
+            if args.len() < 3 {
+                eprintln!("用法: a2l-cli xcp <a2l文件> [配置JSON] [-o 输出文件]");
+                eprintln!("  不指定配置时以 JSON 打印现有的 IF_DATA XCP 配置");
+                return Ok(());
+            }
+            let a2l_path = PathBuf::from(&args[2]);
+            let config = args.get(3).filter(|a| *a != "-o").map(PathBuf::from);
+            let output = flag_value(&args, "-o").map(PathBuf::from);
+            let encoding = parse_encoding(&args)?;
+            xcp_if_data(&a2l_path, config.as_deref(), output.as_deref(), encoding)?;
+        }
+        "modpar" => {
+            if args.len() < 3 {
+                eprintln!(
+                    "用法: a2l-cli modpar <a2l文件> [elf文件] [--epk-symbol 符号] [-o 输出文件]"
+                );
+                eprintln!("  不指定 ELF 时以 JSON 打印现有的 MOD_PAR/MOD_COMMON 参数");
+                eprintln!("  --epk-symbol    从 ELF 符号读取 EPK 字符串与地址");
+                return Ok(());
+            }
+            let a2l_path = PathBuf::from(&args[2]);
+            let elf = args
+                .get(3)
+                .filter(|a| !a.starts_with('-'))
+                .map(PathBuf::from);
+            let epk_symbol = flag_value(&args, "--epk-symbol").map(String::as_str);
+            let output = flag_value(&args, "-o").map(PathBuf::from);
+            let encoding = parse_encoding(&args)?;
+            module_params(
+                &a2l_path,
+                elf.as_deref(),
+                epk_symbol,
+                output.as_deref(),
+                encoding,
+            )?;
+        }
+        "set" => {
+            if args.len() < 4 {
+                eprintln!("用法: a2l-cli set <a2l文件> <变量名> [属性选项] [-o 输出文件]");
+                eprintln!("  --long-id <文本>      说明字符串");
+                eprintln!("  --unit <单位>         PHYS_UNIT");
+                eprintln!("  --format <格式>       FORMAT，如 %8.3");
+                eprintln!("  --lower/--upper <值>  下限/上限");
+                eprintln!("  --ext-lower/--ext-upper <值>  EXTENDED_LIMITS");
+                eprintln!("  --display <名称>      DISPLAY_IDENTIFIER");
+                eprintln!("  --addr-ext <值>       ECU_ADDRESS_EXTENSION");
+                eprintln!("  --read-write / --read-only  是否可写");
+                eprintln!("  --kind <类型>         转换为 measurement 或 characteristic");
+                eprintln!("  可选关键字的值为空字符串时删除该关键字");
+                return Ok(());
+            }
+            let a2l_path = PathBuf::from(&args[2]);
+            let changes = parse_variable_changes(&args);
+            let output = flag_value(&args, "-o").map(PathBuf::from);
+            let encoding = parse_encoding(&args)?;
+            set_variable(&a2l_path, &args[3], &changes, output.as_deref(), encoding)?;
+        }
+        "rename" => {
+            if args.len() < 5 {
+                eprintln!(
+                    "用法: a2l-cli rename <a2l文件> <旧名称> <新名称> [--dry-run] [-o 输出文件]"
+                );
+                eprintln!("  同时更新分组列表、轴输入量、AXIS_PTS_REF、COMPARISON_QUANTITY 等引用");
+                eprintln!("  --dry-run    只列出会改动的位置");
+                return Ok(());
+            }
+            let a2l_path = PathBuf::from(&args[2]);
+            let dry_run = args.contains(&"--dry-run".to_string());
+            let output = flag_value(&args, "-o").map(PathBuf::from);
+            let encoding = parse_encoding(&args)?;
+            rename_a2l_variable(
+                &a2l_path,
+                &args[3],
+                &args[4],
+                dry_run,
+                output.as_deref(),
+                encoding,
+            )?;
+        }
         "create-package" => {
             if args.len() < 3 {
-                eprintln!("用法: a2l-cli create-package <elf文件路径> [-o 输出路径]");
+                eprintln!("用法: a2l-cli create-package <elf文件路径> [-o 输出路径] [--max-array N] [--max-depth N] [--expand-config 文件] [--endianness little|big]");
                 return Ok(());
             }
             let path = PathBuf::from(&args[2]);
@@ -160,8 +376,10 @@ fn main() -> Result<()> {
                 .iter()
                 .position(|a| a == "-o")
                 .and_then(|i| args.get(i + 1))
-                .map(|s| PathBuf::from(s));
-            create_package(&path, output.as_ref())?;
+                .map(PathBuf::from);
+            let options = parse_parse_options(&args)?;
+            let endianness = parse_endianness(&args)?;
+            create_package(&path, output.as_ref(), &options, endianness)?;
         }
         _ => {
             print_usage();
@@ -185,9 +403,22 @@ fn print_usage() {
     println!("    选项:");
     println!("      -o <文件>   输出文件路径 (默认: 输出到控制台)");
     println!("      -n <数量>   导出变量数量 (默认: 100)");
+    println!(
+        "      --asap2-version <版本>   目标 ASAP2 版本 1.51/1.60/1.61/1.70/1.71 (默认: 1.71)"
+    );
+    println!("      命名选项同 create-package");
     println!("  a2l-cli create-package <elf文件> [选项]  创建数据包");
     println!("    选项:");
     println!("      -o <路径>   输出路径 (默认: <elf文件>.a2ldata)");
+    println!("      --max-array <数量>       数组展开上限，超过则只保留数组条目 (默认: 1000)");
+    println!("      --max-depth <层数>       结构体嵌套展开深度 (默认: 50)");
+    println!("      --expand-config <文件>   按变量/类型覆盖展开限制的 JSON 配置");
+    println!("      --index-style <风格>     数组下标风格: underscore(var._3_) / bracket(var[3]) / flat(var_3) / dotted(var.3)");
+    println!("      --separator <分隔符>     结构体成员分隔符 (默认: .)");
+    println!("      --strip-prefix <前缀>    去除变量名前缀，可重复指定");
+    println!("      --max-name-len <长度>    标识符最大长度，超长时截断并追加哈希");
+    println!("      --type-rules <文件>      缺少 DWARF 类型时使用的推断规则 JSON，替换默认规则");
+    println!("      --endianness <字节序>    覆盖 ELF 文件头的字节序 (little/big)，记录在数据包中");
     println!("  a2l-cli type <elf文件> <变量名>        显示变量类型信息");
     println!("  a2l-cli arrays <elf文件> [数量]        列出数组类型及维度");
     println!("  a2l-cli enums <elf文件> [数量]         列出枚举类型及变体");
@@ -195,11 +426,29 @@ fn print_usage() {
     println!("  a2l-cli struct-instances <elf文件> [数量]  列出结构体实例变量");
     println!("  a2l-cli bitfields <elf文件> [数量]     列出含位域的结构体");
     println!("  a2l-cli entries <elf文件> [搜索词] [-n 数量]  列出 A2L 条目");
+    println!("  a2l-cli tree <elf文件> [节点ID] [-n 数量] [--offset 起始]  按层级浏览条目");
+    println!(
+        "  a2l-cli guesses <elf文件> [--type-rules 文件] [--unknown]  列出按规则推断类型的变量"
+    );
+    println!("  a2l-cli conversions <a2l文件>          列出 COMPU_METHOD 与转换表");
+    println!("  a2l-cli groups <a2l文件>               列出 GROUP 与 FUNCTION 层级");
+    println!("  a2l-cli validate <a2l文件> [--strict]  校验 A2L，有错误时返回非零退出码");
+    println!("  a2l-cli update <a2l文件> <elf文件>     按新 ELF 刷新 A2L 中的地址与 BIT_MASK");
+    println!("  a2l-cli diff <旧a2l> <新a2l> [--json]  按对象比较两个 A2L 文件");
+    println!("  a2l-cli merge <目标a2l> <来源a2l>...    合并来源 A2L 的变量、转换与分组");
+    println!("  a2l-cli xcp <a2l文件> [配置JSON]       按配置生成或更新模块的 IF_DATA XCP");
+    println!("  a2l-cli modpar <a2l文件> [elf文件]     按 ELF 更新 MOD_PAR 与 MOD_COMMON");
+    println!(
+        "  a2l-cli set <a2l文件> <变量名> [选项]  修改变量的说明、单位、格式、上下限、读写属性与类型"
+    );
+    println!("  a2l-cli rename <a2l文件> <旧名称> <新名称> [--dry-run]  改名并更新全部引用");
+    println!("    读写 A2L 的命令可用 --encoding <编码> 指定文件编码 (如 utf-8/gbk/latin1)，");
+    println!("    默认按 BOM、文件头声明与内容自动识别，写回时保留原编码与换行符");
     println!("  a2l-cli cache                          列出缓存");
     println!("  a2l-cli clear                          清除缓存");
 }
 
-fn search_struct(path: &PathBuf, name: &str, export: bool) -> Result<()> {
+fn search_struct(path: &Path, name: &str, export: bool) -> Result<()> {
     println!("查找结构体: {}", name);
     println!();
 
@@ -287,7 +536,7 @@ fn print_struct_info(struct_info: &TypeInfo) {
     }
 }
 
-fn export_struct_members(path: &PathBuf, struct_info: &TypeInfo) -> Result<()> {
+fn export_struct_members(path: &Path, struct_info: &TypeInfo) -> Result<()> {
     println!();
     println!("=== 导出结构体成员到 A2L ===");
 
@@ -379,7 +628,7 @@ fn export_struct_members(path: &PathBuf, struct_info: &TypeInfo) -> Result<()> {
     Ok(())
 }
 
-fn parse_elf(path: &PathBuf, deep: bool) -> Result<()> {
+fn parse_elf(path: &Path, deep: bool) -> Result<()> {
     println!("解析文件: {}", path.display());
 
     let metadata = std::fs::metadata(path)?;
@@ -518,7 +767,7 @@ fn parse_elf(path: &PathBuf, deep: bool) -> Result<()> {
     Ok(())
 }
 
-fn show_variable_type(path: &PathBuf, var_name: &str) -> Result<()> {
+fn show_variable_type(path: &Path, var_name: &str) -> Result<()> {
     println!("查找变量: {}", var_name);
     println!();
 
@@ -596,7 +845,15 @@ fn show_variable_type(path: &PathBuf, var_name: &str) -> Result<()> {
     Ok(())
 }
 
-fn export_a2l(path: &PathBuf, output: Option<&str>, limit: usize) -> Result<()> {
+fn export_a2l(
+    path: &Path,
+    output: Option<&str>,
+    limit: usize,
+    naming: NamingPolicy,
+    type_rules: TypeRules,
+    endianness: Option<Endianness>,
+    version: Asap2Version,
+) -> Result<()> {
     println!("导出文件: {}", path.display());
 
     let hash = compute_file_hash(path)?;
@@ -614,8 +871,17 @@ fn export_a2l(path: &PathBuf, output: Option<&str>, limit: usize) -> Result<()>
 
     let export_count = limit.min(variables.len());
     println!("导出变量: {} / {}", export_count, variables.len());
+    println!("ASAP2 版本: {}", version);
 
-    let mut generator = A2lGenerator::new("A2L_Editor_Project", "ECU_Module");
+    let endianness = match endianness {
+        Some(endianness) => endianness,
+        None => ElfParser::read_endianness(path)?,
+    };
+    let mut generator = A2lGenerator::new("A2L_Editor_Project", "ECU_Module")
+        .with_naming_policy(naming)
+        .with_type_rules(type_rules)
+        .with_endianness(endianness)
+        .with_asap2_version(version);
 
     for var in variables.iter().take(export_count) {
         generator.add_variable(var.clone());
@@ -751,7 +1017,7 @@ fn clear_cache() -> Result<()> {
     Ok(())
 }
 
-fn list_array_types(path: &PathBuf, limit: usize) -> Result<()> {
+fn list_array_types(path: &Path, limit: usize) -> Result<()> {
     println!("解析 DWARF 数组类型...");
     println!();
 
@@ -779,11 +1045,7 @@ fn list_array_types(path: &PathBuf, limit: usize) -> Result<()> {
 
     for arr in arrays {
         let total_elements: usize = arr.array_dims.iter().product();
-        let element_size = if total_elements > 0 {
-            arr.size / total_elements
-        } else {
-            0
-        };
+        let element_size = arr.size.checked_div(total_elements).unwrap_or(0);
         let dims_str: Vec<String> = arr.array_dims.iter().map(|d| d.to_string()).collect();
         println!(
             "{} - {} bytes (element: {} bytes)",
@@ -796,7 +1058,7 @@ fn list_array_types(path: &PathBuf, limit: usize) -> Result<()> {
     Ok(())
 }
 
-fn list_enum_types(path: &PathBuf, limit: usize) -> Result<()> {
+fn list_enum_types(path: &Path, limit: usize) -> Result<()> {
     println!("解析 DWARF 枚举类型...");
     println!();
 
@@ -838,7 +1100,7 @@ fn list_enum_types(path: &PathBuf, limit: usize) -> Result<()> {
     Ok(())
 }
 
-fn list_dwarf_variables(path: &PathBuf, limit: usize) -> Result<()> {
+fn list_dwarf_variables(path: &Path, limit: usize) -> Result<()> {
     println!("解析 DWARF 变量...");
     println!();
 
@@ -876,7 +1138,7 @@ fn list_dwarf_variables(path: &PathBuf, limit: usize) -> Result<()> {
     Ok(())
 }
 
-fn list_struct_instances(path: &PathBuf, limit: usize) -> Result<()> {
+fn list_struct_instances(path: &Path, limit: usize) -> Result<()> {
     println!("查找结构体实例变量...");
     println!();
 
@@ -916,7 +1178,7 @@ fn list_struct_instances(path: &PathBuf, limit: usize) -> Result<()> {
     Ok(())
 }
 
-fn debug_member_type(path: &PathBuf, struct_name: &str) -> Result<()> {
+fn debug_member_type(path: &Path, struct_name: &str) -> Result<()> {
     let parser = DwarfParser::parse_from_file(path)?;
 
     if !parser.has_dwarf_info() {
@@ -929,7 +1191,7 @@ fn debug_member_type(path: &PathBuf, struct_name: &str) -> Result<()> {
     Ok(())
 }
 
-fn check_type_offset(path: &PathBuf, offset: u64) -> Result<()> {
+fn check_type_offset(path: &Path, offset: u64) -> Result<()> {
     let parser = DwarfParser::parse_from_file(path)?;
 
     if !parser.has_dwarf_info() {
@@ -942,7 +1204,7 @@ fn check_type_offset(path: &PathBuf, offset: u64) -> Result<()> {
     Ok(())
 }
 
-fn list_bitfields(path: &PathBuf, limit: usize) -> Result<()> {
+fn list_bitfields(path: &Path, limit: usize) -> Result<()> {
     println!("查找含位域的结构体...");
     println!();
 
@@ -995,7 +1257,7 @@ fn list_bitfields(path: &PathBuf, limit: usize) -> Result<()> {
     Ok(())
 }
 
-fn list_a2l_entries(path: &PathBuf, search: Option<&str>, limit: usize) -> Result<()> {
+fn list_a2l_entries(path: &Path, search: Option<&str>, limit: usize) -> Result<()> {
     println!("加载 A2L 条目...");
     let start = Instant::now();
 
@@ -1039,7 +1301,7 @@ fn list_a2l_entries(path: &PathBuf, search: Option<&str>, limit: usize) -> Resul
 
                 let variables = parser.variables().to_vec();
                 cache.save(&hash, &cache_entry, &variables)?;
-                cache.save_a2l_entries(&hash, entry_count, &store)?;
+                cache.save_a2l_entries(&hash, entry_count, store)?;
 
                 let elapsed = start.elapsed();
                 println!("解析并缓存: {:?}", elapsed);
@@ -1097,7 +1359,88 @@ fn list_a2l_entries(path: &PathBuf, search: Option<&str>, limit: usize) -> Resul
     Ok(())
 }
 
-fn create_package(elf_path: &PathBuf, output_path: Option<&PathBuf>) -> Result<()> {
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a String> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+}
+
+/// `--encoding` 指定的 A2L 编码，未指定时返回 `None` 表示自动识别
+fn parse_encoding(args: &[String]) -> Result<Option<TextEncoding>> {
+    flag_value(args, "--encoding")
+        .map(|value| value.parse())
+        .transpose()
+}
+
+/// `--endianness` 指定的字节序，未指定时返回 `None`
+fn parse_endianness(args: &[String]) -> Result<Option<Endianness>> {
+    flag_value(args, "--endianness")
+        .map(|value| value.parse())
+        .transpose()
+}
+
+fn parse_naming_policy(args: &[String]) -> Result<NamingPolicy> {
+    let mut naming = NamingPolicy::new();
+    if let Some(style) = flag_value(args, "--index-style") {
+        naming = naming.with_index_style(style.parse()?);
+    }
+    if let Some(sep) = flag_value(args, "--separator") {
+        naming = naming.with_separator(sep);
+    }
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--strip-prefix" {
+            if let Some(prefix) = args.get(i + 1) {
+                naming = naming.with_strip_prefix(prefix);
+            }
+        }
+    }
+    if let Some(n) = flag_value(args, "--max-name-len") {
+        naming = naming.with_max_length(n.parse().context("--max-name-len 需要整数")?);
+    }
+    Ok(naming)
+}
+
+fn parse_type_rules(args: &[String]) -> Result<TypeRules> {
+    match flag_value(args, "--type-rules") {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("无法读取类型规则: {}", path))?;
+            TypeRules::from_json(&json)
+        }
+        None => Ok(TypeRules::default()),
+    }
+}
+
+fn parse_parse_options(args: &[String]) -> Result<ParseOptions> {
+    let flag_value = |flag: &str| flag_value(args, flag);
+
+    let mut config = match flag_value("--expand-config") {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("无法读取展开配置: {}", path))?;
+            ExpandConfig::from_json(&json).context("无法解析展开配置")?
+        }
+        None => ExpandConfig::default(),
+    };
+    if let Some(n) = flag_value("--max-array") {
+        config.limits.max_array_expand = n.parse().context("--max-array 需要整数")?;
+    }
+    if let Some(n) = flag_value("--max-depth") {
+        config.limits.max_nesting_depth = n.parse().context("--max-depth 需要整数")?;
+    }
+
+    Ok(ParseOptions::new()
+        .with_expand_config(config)
+        .with_naming_policy(parse_naming_policy(args)?)
+        .with_type_rules(parse_type_rules(args)?))
+}
+
+fn create_package(
+    elf_path: &PathBuf,
+    output_path: Option<&PathBuf>,
+    options: &ParseOptions,
+    endianness: Option<Endianness>,
+) -> Result<()> {
     let metadata = std::fs::metadata(elf_path)?;
     let file_size = metadata.len();
 
@@ -1108,7 +1451,10 @@ fn create_package(elf_path: &PathBuf, output_path: Option<&PathBuf>) -> Result<(
     let start = Instant::now();
     println!("深度解析中...");
 
-    let parser = ElfParser::parse_deep(elf_path)?;
+    let reporter = ProgressReporter::new().with_callback(&print_progress_line);
+
+    let parser = ElfParser::parse_with_options(elf_path, true, options, reporter)?;
+    eprintln!();
 
     let store = match parser.a2l_entries() {
         Some(s) => s,
@@ -1120,20 +1466,39 @@ fn create_package(elf_path: &PathBuf, output_path: Option<&PathBuf>) -> Result<(
 
     let entry_count = store.len();
     println!("解析完成: {} 条目", entry_count);
+    if let Some(report) = parser.type_guesses().filter(|r| !r.is_empty()) {
+        println!(
+            "类型推断: {} 个变量缺少 DWARF 类型，{} 个按规则推断，{} 个未知（按字节块输出）",
+            report.len(),
+            report.guessed().count(),
+            report.unknown().count()
+        );
+        println!("  使用 a2l-cli guesses 查看明细");
+    }
 
     println!();
     println!("保存数据包...");
 
-    let pkg = if let Some(custom_path) = output_path {
-        DataPackage::create_at(custom_path, elf_path)?
-    } else {
-        DataPackage::create(elf_path)?
-    };
+    let mut contents = PackageContents::new(store)
+        .with_variables(parser.variables())
+        .with_naming_policy(&options.naming)
+        .with_endianness(parser.endianness());
+    if let Some(type_cache) = parser.type_cache() {
+        contents = contents.with_type_cache(type_cache);
+    }
 
-    let pkg_path = pkg.path().to_path_buf();
+    let pkg = DataPackage::create_with_entries(
+        output_path.map(|p| p.as_path()),
+        elf_path,
+        &contents,
+        reporter,
+    )?;
+    eprintln!();
+    if endianness.is_some() {
+        pkg.set_endianness_override(endianness)?;
+    }
 
-    let mut pkg = pkg;
-    pkg.save_entries(&store)?;
+    let pkg_path = pkg.path().to_path_buf();
     let pkg_metadata = std::fs::metadata(&pkg_path)?;
     let pkg_size = pkg_metadata.len();
 
@@ -1144,7 +1509,551 @@ fn create_package(elf_path: &PathBuf, output_path: Option<&PathBuf>) -> Result<(
     println!("数据包路径: {}", pkg_path.display());
     println!("数据包大小: {}", format_file_size(pkg_size));
     println!("条目数量: {}", entry_count);
+    println!("字节序: {}", pkg.endianness()?);
     println!("耗时: {:.1} 秒", elapsed.as_secs_f64());
 
     Ok(())
 }
+
+fn list_type_guesses(path: &Path, options: &ParseOptions, unknown_only: bool) -> Result<()> {
+    println!("深度解析中...");
+    let parser = ElfParser::parse_with_options(path, true, options, ProgressReporter::new())?;
+    let report = match parser.type_guesses() {
+        Some(r) => r,
+        None => return Ok(()),
+    };
+
+    println!(
+        "缺少 DWARF 类型: {} 个（推断 {}，未知 {}）",
+        report.len(),
+        report.guessed().count(),
+        report.unknown().count()
+    );
+    println!();
+
+    for record in &report.records {
+        if unknown_only && !record.is_unknown() {
+            continue;
+        }
+        match (&record.a2l_type, &record.rule) {
+            (Some(a2l_type), Some(rule)) => println!(
+                "  {} @ 0x{:08X} {}B -> {} (规则: {})",
+                record.variable, record.address, record.size, a2l_type, rule
+            ),
+            _ => println!(
+                "  {} @ 0x{:08X} {}B -> 未知",
+                record.variable, record.address, record.size
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+fn list_conversions(path: &Path) -> Result<()> {
+    let project = A2lProject::load(path)?;
+    let conversions = Conversions::parse(project.content())?;
+
+    println!("COMPU_METHOD: {} 个", conversions.methods.len());
+    for method in &conversions.methods {
+        let detail = match (&method.coeffs, &method.compu_tab_ref) {
+            (Some(coeffs), _) => format!(" {:?}", coeffs),
+            (None, Some(table)) => format!(" -> {}", table),
+            (None, None) => String::new(),
+        };
+        println!(
+            "  {} {}{} 格式 \"{}\" 单位 \"{}\"",
+            method.name, method.conversion_type, detail, method.format, method.unit
+        );
+    }
+
+    println!();
+    println!("转换表: {} 个", conversions.tables.len());
+    for table in &conversions.tables {
+        println!(
+            "  {} {} {} ({} 组)",
+            table.keyword,
+            table.name,
+            table.conversion_type,
+            table.pairs.len()
+        );
+        for (raw, value) in &table.pairs {
+            println!("    {} -> {}", raw, value);
+        }
+        if let Some(ref default) = table.default_value {
+            println!("    默认 -> {}", default);
+        }
+    }
+
+    Ok(())
+}
+
+fn merge_a2l_files(
+    target: &Path,
+    sources: &[PathBuf],
+    output: Option<&Path>,
+    options: &MergeOptions,
+    encoding: Option<TextEncoding>,
+) -> Result<()> {
+    let mut project = A2lProject::load_with_encoding(target, encoding)?;
+    let sources = sources
+        .iter()
+        .map(|p| A2lProject::load(p).and_then(|source| A2lProject::flatten(source.content())))
+        .collect::<Result<Vec<String>>>()?;
+    let sources: Vec<&str> = sources.iter().map(|s| s.as_str()).collect();
+
+    let (merged, report) = merge_a2l(project.content(), &sources, options)?;
+    save_project(&mut project, &merged, output)?;
+    let output = output.unwrap_or(target);
+
+    for item in &report.items {
+        match item.action {
+            MergeAction::Renamed => println!(
+                "  改名 {} {} -> {}",
+                item.keyword,
+                item.name,
+                item.new_name.as_deref().unwrap_or_default()
+            ),
+            MergeAction::Skipped => println!("  跳过 {} {}", item.keyword, item.name),
+            MergeAction::Overwritten => println!("  覆盖 {} {}", item.keyword, item.name),
+            MergeAction::Imported | MergeAction::Reused => {}
+        }
+    }
+    println!(
+        "导入: {}  复用: {}  改名: {}  跳过: {}  覆盖: {}",
+        report.count(MergeAction::Imported),
+        report.count(MergeAction::Reused),
+        report.count(MergeAction::Renamed),
+        report.count(MergeAction::Skipped),
+        report.count(MergeAction::Overwritten)
+    );
+    println!("已保存到: {}", output.display());
+
+    Ok(())
+}
+
+/// 保存编辑后的工程：未指定其他输出文件时写回对象所在的各个文件，
+/// 否则按主文件的编码与换行输出内联了被包含文件的单个 A2L
+fn save_project(project: &mut A2lProject, content: &str, output: Option<&Path>) -> Result<()> {
+    match output {
+        Some(output) if output != project.path() => project
+            .format()
+            .write(output, &A2lProject::flatten(content)?),
+        _ => project.write(content).map(|_| ()),
+    }
+}
+
+/// 打印两个 A2L 的对象差异，返回是否相同
+fn diff_a2l_files(old_path: &Path, new_path: &Path, json: bool) -> Result<bool> {
+    let diff = diff_a2l(
+        A2lProject::load(old_path)?.content(),
+        A2lProject::load(new_path)?.content(),
+    )?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        println!("--- {}", old_path.display());
+        println!("+++ {}", new_path.display());
+        println!("{}", diff);
+    }
+
+    Ok(diff.is_empty())
+}
+
+/// 从数据包或 ELF 加载条目，刷新 A2L 中的地址
+fn update_a2l(
+    a2l_path: &Path,
+    source: &Path,
+    output: Option<&Path>,
+    options: &ParseOptions,
+    endianness: Option<Endianness>,
+    encoding: Option<TextEncoding>,
+) -> Result<()> {
+    let mut project = A2lProject::load_with_encoding(a2l_path, encoding)?;
+
+    let (store, naming, detected) = if source.extension().is_some_and(|e| e == "a2ldata") {
+        println!("加载数据包: {}", source.display());
+        let pkg = DataPackage::open_path(source)?;
+        (pkg.load_entries()?, pkg.naming_policy()?, pkg.endianness()?)
+    } else {
+        println!("深度解析 ELF: {}", source.display());
+        let reporter = ProgressReporter::new().with_callback(&print_progress_line);
+        let parser = ElfParser::parse_with_options(source, true, options, reporter)?;
+        eprintln!();
+        let store = parser
+            .a2l_entries()
+            .cloned()
+            .context("未找到 A2L 条目（需要 DWARF 信息）")?;
+        (store, options.naming.clone(), parser.endianness())
+    };
+
+    let endianness = endianness.unwrap_or(detected);
+    let (updated, report) = update_addresses(project.content(), &store, &naming, endianness)?;
+    save_project(&mut project, &updated, output)?;
+    let output = output.unwrap_or(a2l_path);
+
+    println!();
+    println!(
+        "更新: {}  未变: {}  缺失: {}  类型变化: {}",
+        report.count(UpdateStatus::Updated),
+        report.count(UpdateStatus::Unchanged),
+        report.count(UpdateStatus::Missing),
+        report.count(UpdateStatus::TypeChanged)
+    );
+    for item in report.with_status(UpdateStatus::Missing) {
+        println!(
+            "  缺失     {} {} (符号 {})",
+            item.keyword, item.name, item.symbol
+        );
+    }
+    for item in report.with_status(UpdateStatus::TypeChanged) {
+        println!(
+            "  类型变化 {} {}: {} -> {}",
+            item.keyword,
+            item.name,
+            item.old_type.as_deref().unwrap_or("?"),
+            item.new_type.as_deref().unwrap_or("?")
+        );
+    }
+    println!("已保存到: {}", output.display());
+
+    Ok(())
+}
+
+/// 打印校验结果，返回是否通过
+fn validate_a2l(path: &Path, strict: bool, encoding: Option<TextEncoding>) -> Result<bool> {
+    let project = A2lProject::load_with_encoding(path, encoding)?;
+    let format = project.format();
+    println!("编码: {}, 换行: {}", format.encoding, format.line_ending);
+    let diagnostics = project.validate()?;
+
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    for diagnostic in &diagnostics {
+        match diagnostic.file {
+            Some(ref file) => println!("{}:{}", dir.join(file).display(), diagnostic),
+            None => println!("{}:{}", path.display(), diagnostic),
+        }
+    }
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    let warnings = diagnostics.len() - errors;
+    println!("\n校验完成: {} 个错误, {} 个警告", errors, warnings);
+
+    Ok(errors == 0 && (!strict || warnings == 0))
+}
+
+/// 按 JSON 配置写入 IF_DATA XCP；没有配置时打印现有配置
+fn xcp_if_data(
+    a2l_path: &Path,
+    config: Option<&Path>,
+    output: Option<&Path>,
+    encoding: Option<TextEncoding>,
+) -> Result<()> {
+    let mut project = A2lProject::load_with_encoding(a2l_path, encoding)?;
+
+    let Some(config) = config else {
+        match read_xcp_config(project.content())? {
+            Some(config) => println!("{}", serde_json::to_string_pretty(&config)?),
+            None => println!("未找到模块级 IF_DATA XCP"),
+        }
+        return Ok(());
+    };
+
+    let json = std::fs::read_to_string(config)
+        .with_context(|| format!("无法读取文件: {}", config.display()))?;
+    let config: XcpConfig = serde_json::from_str(&json)
+        .with_context(|| format!("无法解析 XCP 配置: {}", config.display()))?;
+    let updated = set_xcp_if_data(project.content(), &config)?;
+    save_project(&mut project, &updated, output)?;
+
+    println!("已写入 IF_DATA XCP: {} 个事件通道", config.events.len());
+    println!("已保存到: {}", output.unwrap_or(a2l_path).display());
+
+    Ok(())
+}
+
+/// 按 ELF 推断并写入模块参数；没有 ELF 时打印现有参数
+fn module_params(
+    a2l_path: &Path,
+    elf: Option<&Path>,
+    epk_symbol: Option<&str>,
+    output: Option<&Path>,
+    encoding: Option<TextEncoding>,
+) -> Result<()> {
+    let mut project = A2lProject::load_with_encoding(a2l_path, encoding)?;
+
+    let Some(elf) = elf else {
+        let params = read_module_params(project.content())?;
+        println!("{}", serde_json::to_string_pretty(&params)?);
+        return Ok(());
+    };
+
+    let params = propose_module_params(elf, epk_symbol)?;
+    let updated = apply_module_params(project.content(), &params)?;
+    save_project(&mut project, &updated, output)?;
+
+    let segments = params.memory_segments.as_deref().unwrap_or_default();
+    println!("存储段: {} 个", segments.len());
+    for segment in segments {
+        println!(
+            "  {:<16} {:<9} {:<5} 0x{:08X} 0x{:X}",
+            segment.name, segment.prg_type, segment.memory_type, segment.address, segment.size
+        );
+    }
+    if let Some(epk) = &params.epk {
+        println!("EPK: \"{}\" @ 0x{:X}", epk, params.addr_epk.unwrap_or(0));
+    }
+    println!("已保存到: {}", output.unwrap_or(a2l_path).display());
+
+    Ok(())
+}
+
+/// `set` 命令的属性选项，未指定的属性保持不变
+fn parse_variable_changes(args: &[String]) -> VariableChanges {
+    let value = |flag| flag_value(args, flag).cloned();
+    let read_write = if args.contains(&"--read-write".to_string()) {
+        Some(true)
+    } else if args.contains(&"--read-only".to_string()) {
+        Some(false)
+    } else {
+        None
+    };
+    VariableChanges {
+        long_identifier: value("--long-id"),
+        phys_unit: value("--unit"),
+        format: value("--format"),
+        lower_limit: value("--lower"),
+        upper_limit: value("--upper"),
+        extended_lower_limit: value("--ext-lower"),
+        extended_upper_limit: value("--ext-upper"),
+        display_identifier: value("--display"),
+        ecu_address_extension: value("--addr-ext"),
+        read_write,
+        var_type: value("--kind").map(|kind| kind.to_uppercase()),
+        ..Default::default()
+    }
+}
+
+fn set_variable(
+    a2l_path: &Path,
+    name: &str,
+    changes: &VariableChanges,
+    output: Option<&Path>,
+    encoding: Option<TextEncoding>,
+) -> Result<()> {
+    let mut project = A2lProject::load_with_encoding(a2l_path, encoding)?;
+    let variables = A2lParser::parse_all_variables(project.content())?;
+    if !variables.iter().any(|v| v.name == name) {
+        anyhow::bail!("A2L 中没有变量: {}", name);
+    }
+
+    let updated = A2lGenerator::modify_variable(project.content(), name, changes)?;
+    save_project(&mut project, &updated, output)?;
+
+    let variable = A2lParser::parse_all_variables(&updated)?
+        .into_iter()
+        .find(|v| v.name == name)
+        .context("修改后找不到变量")?;
+    println!(
+        "{} {} \"{}\"",
+        variable.var_type, variable.name, variable.long_identifier
+    );
+    println!(
+        "  上下限: {} .. {}",
+        variable.lower_limit.as_deref().unwrap_or("-"),
+        variable.upper_limit.as_deref().unwrap_or("-")
+    );
+    if let (Some(lower), Some(upper)) = (
+        &variable.extended_lower_limit,
+        &variable.extended_upper_limit,
+    ) {
+        println!("  扩展上下限: {} .. {}", lower, upper);
+    }
+    for (label, value) in [
+        ("单位", &variable.phys_unit),
+        ("格式", &variable.format),
+        ("显示名称", &variable.display_identifier),
+        ("地址扩展", &variable.ecu_address_extension),
+    ] {
+        if let Some(value) = value {
+            println!("  {}: {}", label, value);
+        }
+    }
+    println!(
+        "  {}",
+        if variable.read_write {
+            "可写"
+        } else {
+            "只读"
+        }
+    );
+    println!("已保存到: {}", output.unwrap_or(a2l_path).display());
+
+    Ok(())
+}
+
+/// 改名并更新全部引用，逐行列出改动的位置
+fn rename_a2l_variable(
+    a2l_path: &Path,
+    old: &str,
+    new: &str,
+    dry_run: bool,
+    output: Option<&Path>,
+    encoding: Option<TextEncoding>,
+) -> Result<()> {
+    let mut project = A2lProject::load_with_encoding(a2l_path, encoding)?;
+    let references = project.find_references(old)?;
+    let (renamed, _) = rename_variable(project.content(), old, new)?;
+
+    let dir = a2l_path.parent().unwrap_or_else(|| Path::new(""));
+    for reference in &references {
+        let file = match reference.file {
+            Some(ref file) => dir.join(file),
+            None => a2l_path.to_path_buf(),
+        };
+        println!(
+            "{}:{}:{}  {}  {}",
+            file.display(),
+            reference.line,
+            reference.column,
+            reference.keyword,
+            reference.owner.as_deref().unwrap_or("")
+        );
+    }
+
+    if dry_run {
+        println!("\n共 {} 处，未写入", references.len());
+        return Ok(());
+    }
+    save_project(&mut project, &renamed, output)?;
+    println!("\n{} → {}: 已更新 {} 处", old, new, references.len());
+    println!("已保存到: {}", output.unwrap_or(a2l_path).display());
+
+    Ok(())
+}
+
+fn list_a2l_groups(path: &Path) -> Result<()> {
+    let project = A2lProject::load(path)?;
+    let groups = list_groups(project.content())?;
+
+    println!("分组: {} 个", groups.len());
+    for group in &groups {
+        println!(
+            "  {} {}{} \"{}\"",
+            group.kind.keyword(),
+            group.name,
+            if group.root { " (ROOT)" } else { "" },
+            group.long_identifier
+        );
+        for (label, names) in [
+            ("观测", &group.measurements),
+            ("标定", &group.characteristics),
+            ("子分组", &group.sub_groups),
+        ] {
+            if !names.is_empty() {
+                println!("    {}: {}", label, names.join(" "));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn show_tree(path: &Path, node_id: Option<&str>, offset: usize, limit: usize) -> Result<()> {
+    let start = Instant::now();
+
+    let package = if DataPackage::exists(path) {
+        Some(DataPackage::open(path)?).filter(|pkg| pkg.has_tree_source())
+    } else {
+        None
+    };
+    let tree = match package {
+        Some(pkg) => {
+            println!("从数据包加载...");
+            pkg.load_tree()?
+        }
+        None => {
+            println!("深度解析中...");
+            let parser = ElfParser::parse_deep(path)?;
+            EntryTree::new(
+                parser.variables().to_vec(),
+                parser.type_cache().cloned().unwrap_or_default(),
+            )
+        }
+    };
+    println!("加载完成: {:?}", start.elapsed());
+    println!();
+
+    let (total, children) = match node_id {
+        Some(id) => {
+            let node = tree.node(id)?;
+            println!(
+                "{} ({}) @ 0x{:08X} {}B {}",
+                node.full_name, node.type_name, node.address, node.size, node.a2l_type
+            );
+            (node.child_count, tree.children(id, offset, limit)?)
+        }
+        None => {
+            println!("变量数: {}", tree.root_count());
+            (tree.root_count(), tree.roots(offset, limit))
+        }
+    };
+    println!();
+
+    for child in &children {
+        let expand = if child.child_count > 0 {
+            format!(" +{}", child.child_count)
+        } else {
+            String::new()
+        };
+        let dims = child
+            .array_dims
+            .as_ref()
+            .map(|d| {
+                format!(
+                    " [{}]",
+                    d.iter()
+                        .map(|i| i.to_string())
+                        .collect::<Vec<_>>()
+                        .join("][")
+                )
+            })
+            .unwrap_or_default();
+        println!(
+            "{:30} {:40} @ 0x{:08X} {:5}B {}{}{}",
+            child.id, child.label, child.address, child.size, child.a2l_type, dims, expand
+        );
+    }
+
+    let shown_end = offset + children.len();
+    if shown_end < total {
+        println!(
+            "... 还有 {} 个子节点（--offset {}）",
+            total - shown_end,
+            shown_end
+        );
+    }
+
+    Ok(())
+}
+
+fn print_progress_line(progress: &Progress) {
+    let percent = if progress.total > 0 {
+        format!(
+            "{:5.1}%",
+            progress.processed as f64 * 100.0 / progress.total as f64
+        )
+    } else {
+        "   - %".to_string()
+    };
+    let entries = if progress.phase == ProgressPhase::Dwarf {
+        String::new()
+    } else {
+        format!("  条目: {}", progress.entries)
+    };
+    eprint!(
+        "\r[{}] {} {}/{}{}    ",
+        progress.phase, percent, progress.processed, progress.total, entries
+    );
+    let _ = std::io::stderr().flush();
+}
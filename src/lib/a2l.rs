@@ -1,17 +1,66 @@
-use crate::types::{infer_a2l_type, A2lEntry, Variable};
+use crate::a2l_syntax::{A2lDocument, Block, Span, TextEdits, Value};
+use crate::compu::{
+    ensure_conversions, enum_conversion, enum_conversion_name, quote, CompuMethodSpec, Conversions,
+    NO_COMPU_METHOD,
+};
+use crate::convert::convert_variable;
+use crate::encoding::TextEncoding;
+use crate::group::{groups_for_entries, merge_groups, remove_variable_references, GroupBy};
+use crate::include::{included_spans, A2lProject};
+use crate::modpar::read_module_params;
+use crate::naming::NamingPolicy;
+use crate::record_layout::complete_record_layouts;
+use crate::rename::rename_variable;
+use crate::type_rules::TypeRules;
+use crate::types::{
+    infer_a2l_type_from_encoding, A2lEntry, Endianness, EnumType, TypeKind, Variable,
+};
+use crate::validate::{new_errors, new_warnings, validate, Diagnostic};
+use crate::version::{read_asap2_version, Asap2Version};
 use anyhow::{Context, Result};
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::io::Write;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct VariableChanges {
     pub name: Option<String>,
     pub address: Option<String>,
     pub data_type: Option<String>,
     pub var_type: Option<String>,
     pub bit_mask: Option<String>,
+    /// CHARACTERISTIC/AXIS_PTS 的记录布局，优先于按数据类型推导的布局
+    #[serde(default)]
+    pub record_layout: Option<String>,
+    #[serde(default)]
+    pub lower_limit: Option<String>,
+    #[serde(default)]
+    pub upper_limit: Option<String>,
+    /// 按 AXIS_DESCR 顺序的 AXIS_PTS_REF，空字符串表示该轴不变
+    #[serde(default)]
+    pub axis_refs: Option<Vec<String>>,
+    /// 引用的 COMPU_METHOD，必须已在文件中定义或为 NO_COMPU_METHOD
+    #[serde(default)]
+    pub compu_method: Option<String>,
+    /// 说明字符串（不含引号），空字符串表示清空
+    #[serde(default)]
+    pub long_identifier: Option<String>,
+    /// 以下可选关键字为空字符串时从块中删除
+    #[serde(default)]
+    pub phys_unit: Option<String>,
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub extended_lower_limit: Option<String>,
+    #[serde(default)]
+    pub extended_upper_limit: Option<String>,
+    #[serde(default)]
+    pub display_identifier: Option<String>,
+    #[serde(default)]
+    pub ecu_address_extension: Option<String>,
+    /// 是否可写：MEASUREMENT 对应 READ_WRITE，CHARACTERISTIC/AXIS_PTS 对应没有 READ_ONLY
+    #[serde(default)]
+    pub read_write: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +81,12 @@ pub struct A2lEntryInfo {
     pub type_name: String,
     pub bit_offset: Option<usize>,
     pub bit_size: Option<usize>,
+    #[serde(default)]
+    pub array_dims: Option<Vec<usize>>,
+    #[serde(default)]
+    pub enum_type: Option<EnumType>,
+    #[serde(default)]
+    pub source_file: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -40,6 +95,8 @@ pub struct SaveResult {
     pub deleted: usize,
     pub added: usize,
     pub skipped: usize,
+    /// 修改后新出现的校验警告
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 pub struct A2lGenerator {
@@ -47,6 +104,35 @@ pub struct A2lGenerator {
     module_name: String,
     variables: Vec<Variable>,
     entries: Vec<A2lEntry>,
+    naming: NamingPolicy,
+    type_rules: TypeRules,
+    conversions: Vec<CompuMethodSpec>,
+    endianness: Endianness,
+    version: Asap2Version,
+}
+
+/// 生成变量块时的字节序：数据本身的字节序与文件 MOD_COMMON 声明的默认字节序，
+/// 两者不同时在块内写入 BYTE_ORDER
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ByteOrder {
+    pub data: Endianness,
+    pub module: Endianness,
+}
+
+impl ByteOrder {
+    pub fn new(data: Endianness) -> Self {
+        Self { data, module: data }
+    }
+
+    /// 按文件中 MOD_COMMON 的 BYTE_ORDER 确定默认字节序，未声明时按小端
+    pub fn for_content(content: &str, data: Endianness) -> Result<Self> {
+        let module = read_module_params(content)?.byte_order.unwrap_or_default();
+        Ok(Self { data, module })
+    }
+
+    fn object_byte_order(&self) -> Option<Endianness> {
+        (self.data != self.module).then_some(self.data)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -54,20 +140,75 @@ pub struct AppendResult {
     pub added: usize,
     pub skipped: usize,
     pub existing: usize,
+    /// 文件 ASAP2 版本不支持、已降级或原样写出的构造
+    pub warnings: Vec<String>,
 }
 
+/// 条目导出为 A2L 时的块类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExportKind {
     Measurement,
+    /// 标量为 VALUE，数组为 VAL_BLK
     Characteristic,
+    ValBlk,
+    Ascii,
+    /// 一维数组，固定轴
+    Curve,
+    /// 二维数组，固定轴
+    Map,
+    /// 三维数组，固定轴
+    Cuboid,
+    /// 一维数组作为共享轴点
+    AxisPts,
+}
+
+impl std::str::FromStr for ExportKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "measurement" => Ok(ExportKind::Measurement),
+            "characteristic" => Ok(ExportKind::Characteristic),
+            "val_blk" => Ok(ExportKind::ValBlk),
+            "ascii" => Ok(ExportKind::Ascii),
+            "curve" => Ok(ExportKind::Curve),
+            "map" => Ok(ExportKind::Map),
+            "cuboid" => Ok(ExportKind::Cuboid),
+            "axis_pts" => Ok(ExportKind::AxisPts),
+            _ => anyhow::bail!("无效的导出模式: {}", s),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct A2lVariable {
     pub name: String,
     pub address: Option<String>,
+    /// MEASUREMENT / CHARACTERISTIC / AXIS_PTS
     pub var_type: String,
     pub data_type: String,
     pub bit_mask: Option<String>,
+    /// CHARACTERISTIC 的类型（VALUE、CURVE、MAP 等）
+    pub kind: Option<String>,
+    /// 各维度大小：曲线/图为各轴点数，数组为 MATRIX_DIM/NUMBER
+    pub dims: Option<Vec<usize>>,
+    pub record_layout: Option<String>,
+    pub lower_limit: Option<String>,
+    pub upper_limit: Option<String>,
+    /// 按 AXIS_DESCR 顺序的 AXIS_PTS_REF
+    pub axis_refs: Vec<Option<String>>,
+    pub compu_method: Option<String>,
+    pub long_identifier: String,
+    pub phys_unit: Option<String>,
+    pub format: Option<String>,
+    pub extended_lower_limit: Option<String>,
+    pub extended_upper_limit: Option<String>,
+    pub display_identifier: Option<String>,
+    pub ecu_address_extension: Option<String>,
+    /// MEASUREMENT 带 READ_WRITE，或 CHARACTERISTIC/AXIS_PTS 不带 READ_ONLY
+    pub read_write: bool,
+    /// 所在的被包含文件（相对主文件目录），主文件中的变量为 `None`
+    pub file: Option<String>,
 }
 
 impl A2lGenerator {
@@ -77,9 +218,38 @@ impl A2lGenerator {
             module_name: module_name.to_string(),
             variables: Vec::new(),
             entries: Vec::new(),
+            naming: NamingPolicy::default(),
+            type_rules: TypeRules::default(),
+            conversions: Vec::new(),
+            endianness: Endianness::default(),
+            version: Asap2Version::default(),
         }
     }
 
+    /// 符号表变量导出时使用的命名策略，条目在展开时已按策略命名
+    pub fn with_naming_policy(mut self, naming: NamingPolicy) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// 符号表变量缺少 DWARF 类型时使用的推断规则
+    pub fn with_type_rules(mut self, type_rules: TypeRules) -> Self {
+        self.type_rules = type_rules;
+        self
+    }
+
+    /// ELF 的字节序，写入 MOD_COMMON 并用于计算 BIT_MASK
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// 目标 ASAP2 版本，写入 ASAP2_VERSION 并限制生成的关键字
+    pub fn with_asap2_version(mut self, version: Asap2Version) -> Self {
+        self.version = version;
+        self
+    }
+
     pub fn add_variable(&mut self, variable: Variable) {
         self.variables.push(variable);
     }
@@ -96,6 +266,11 @@ impl A2lGenerator {
         self.entries.extend(entries.iter().cloned());
     }
 
+    /// 随文件一起生成的转换方法
+    pub fn add_compu_method(&mut self, spec: CompuMethodSpec) {
+        self.conversions.push(spec);
+    }
+
     pub fn variable_count(&self) -> usize {
         self.variables.len() + self.entries.len()
     }
@@ -108,27 +283,52 @@ impl A2lGenerator {
     pub fn generate(&self) -> String {
         let mut output = String::new();
 
-        output.push_str("/begin ASAP2_VERSION\n");
-        output.push_str("  1 71\n");
-        output.push_str("/end ASAP2_VERSION\n\n");
+        output.push_str(&format!("{}\n\n", self.version.keyword_line()));
 
         output.push_str(&format!("/begin PROJECT {} \"\"\n", self.project_name));
         output.push_str(&format!("  /begin MODULE {} \"\"\n", self.module_name));
 
-        output.push_str("    /begin CHARACTERISTIC __PLACEHOLDER__ \"\"\n");
-        output.push_str("      VALUE 0x0 NO_COMPU_METHOD 0 0 0 0\n");
-        output.push_str("    /end CHARACTERISTIC\n\n");
-
-        output.push_str("    /begin COMPU_METHOD\n");
-        output.push_str("      NO_COMPU_METHOD \"\" NO_COMPU_VTAB \"\" \"\" \"\"\n");
-        output.push_str("    /end COMPU_METHOD\n\n");
+        output.push_str("    /begin MOD_COMMON \"\"\n");
+        output.push_str(&format!(
+            "      BYTE_ORDER {}\n",
+            self.endianness.byte_order_keyword()
+        ));
+        output.push_str("    /end MOD_COMMON\n\n");
 
         for var in &self.variables {
             output.push_str(&self.generate_measurement(var));
         }
 
+        let order = ByteOrder::new(self.endianness);
         for entry in &self.entries {
-            output.push_str(&Self::generate_measurement_block(entry));
+            let bytes;
+            let entry = if self.version.supports_type(&entry.a2l_type) {
+                entry
+            } else {
+                bytes = Self::byte_block(entry);
+                &bytes
+            };
+            output.push_str(&Self::generate_measurement_block(
+                entry,
+                order,
+                &self.naming,
+                self.version,
+            ));
+        }
+
+        let enum_types = self
+            .variables
+            .iter()
+            .filter_map(|v| v.type_info.as_ref().and_then(EnumType::from_type))
+            .chain(self.entries.iter().filter_map(|e| e.enum_type.clone()));
+        let mut conversions = self.conversions.clone();
+        for spec in enum_types.filter_map(|t| enum_conversion(&t)) {
+            if !conversions.iter().any(|c| c.name == spec.name) {
+                conversions.push(spec);
+            }
+        }
+        for spec in &conversions {
+            output.push_str(&spec.to_a2l());
         }
 
         output.push_str("  /end MODULE\n");
@@ -137,104 +337,394 @@ impl A2lGenerator {
         output
     }
 
+    /// 有 DWARF 标量类型时按编码确定，否则按规则推断，未知时返回 `None`
+    fn variable_a2l_type(&self, var: &Variable) -> Option<&'static str> {
+        match var.type_info {
+            Some(ref t) if matches!(t.kind, TypeKind::Primitive | TypeKind::Enum) => {
+                Some(infer_a2l_type_from_encoding(var.size, t.encoding))
+            }
+            _ => self
+                .type_rules
+                .infer(None, &var.name, var.size)
+                .map(|g| g.a2l_type),
+        }
+    }
+
     fn generate_measurement(&self, var: &Variable) -> String {
-        let a2l_type = infer_a2l_type(var.size, &var.type_name);
+        // 类型未知或目标版本不支持（64 位整数）时按原始字节块输出
+        let a2l_type = self
+            .variable_a2l_type(var)
+            .filter(|t| self.version.supports_type(t));
+        let raw_len = if a2l_type.is_none() && var.size > 1 {
+            Some(var.size)
+        } else {
+            None
+        };
+        let a2l_type = a2l_type.unwrap_or("UBYTE");
         let format_str = Self::get_format_string(a2l_type);
-        let (min_val, max_val) = Self::get_min_max(a2l_type);
+        let enum_type = var
+            .type_info
+            .as_ref()
+            .and_then(EnumType::from_type)
+            .filter(|_| raw_len.is_none());
+        let (min_val, max_val) = Self::enum_limits(enum_type.as_ref()).unwrap_or_else(|| {
+            let (min, max) = Self::get_min_max(a2l_type);
+            (min.to_string(), max.to_string())
+        });
 
+        let name = self.naming.finalize(&self.naming.root_name(&var.name));
         let mut output = String::new();
 
-        output.push_str(&format!("    /begin MEASUREMENT {} \"\"\n", var.name));
         output.push_str(&format!(
-            "      {} NO_COMPU_METHOD 0 0 {} {}\n",
-            a2l_type, min_val, max_val
+            "    /begin MEASUREMENT {} \"\"\n",
+            self.naming.identifier(&name)
+        ));
+        output.push_str(&format!(
+            "      {} {} 0 0 {} {}\n",
+            a2l_type,
+            Self::conversion_name(enum_type.as_ref()),
+            min_val,
+            max_val
         ));
         output.push_str(&format!("      ECU_ADDRESS 0x{:08X}\n", var.address));
         output.push_str("      ECU_ADDRESS_EXTENSION 0x0\n");
         output.push_str(&format!("      FORMAT \"{}\"\n", format_str));
-        output.push_str(&format!("      SYMBOL_LINK \"{}\" 0\n", var.name));
+        if let Some(len) = raw_len {
+            let dims = self.version.matrix_dim(&[len]);
+            let dims: Vec<String> = dims.iter().map(|d| d.to_string()).collect();
+            output.push_str(&format!("      MATRIX_DIM {}\n", dims.join(" ")));
+        }
+        output.push_str(&Self::symbol_lines(
+            &name,
+            &var.name,
+            &self.naming,
+            self.version,
+        ));
         output.push_str("    /end MEASUREMENT\n\n");
 
         output
     }
 
-    fn generate_measurement_block(entry: &A2lEntry) -> String {
+    fn generate_measurement_block(
+        entry: &A2lEntry,
+        order: ByteOrder,
+        naming: &NamingPolicy,
+        version: Asap2Version,
+    ) -> String {
         let a2l_type = entry.a2l_type.as_str();
         let format_str = Self::get_format_string(a2l_type);
 
-        // bitfield 使用 bit_size 计算上限
-        let (min_val, max_val) = if entry.is_bitfield() {
-            let size = entry.bit_size.unwrap();
-            ("0".to_string(), format!("{}", Self::get_bitfield_max(size)))
-        } else {
-            let (min, max) = Self::get_min_max(a2l_type);
-            (min.to_string(), max.to_string())
-        };
+        let (min_val, max_val) = Self::characteristic_limits(entry);
 
         let mut output = String::new();
 
         output.push_str(&format!(
             "    /begin MEASUREMENT {} \"\"\n",
-            entry.full_name
+            naming.identifier(&entry.full_name)
         ));
         output.push_str(&format!(
-            "      {} NO_COMPU_METHOD 0 0 {} {}\n",
-            a2l_type, min_val, max_val
+            "      {} {} 0 0 {} {}\n",
+            a2l_type,
+            Self::conversion_name(entry.enum_type.as_ref()),
+            min_val,
+            max_val
         ));
 
-        // bitfield 添加 BIT_MASK
-        if entry.is_bitfield() {
-            let mask = Self::calculate_bit_mask(entry.bit_offset, entry.bit_size);
-            output.push_str(&format!("      BIT_MASK 0x{:X}\n", mask));
+        output.push_str(&Self::byte_order_lines(entry, order));
+
+        if let Some(dims) = Self::format_matrix_dim(entry, version) {
+            output.push_str(&format!("      MATRIX_DIM {}\n", dims));
         }
 
         output.push_str(&format!("      ECU_ADDRESS 0x{:08X}\n", entry.address));
         output.push_str("      ECU_ADDRESS_EXTENSION 0x0\n");
         output.push_str(&format!("      FORMAT \"{}\"\n", format_str));
-        output.push_str(&format!("      SYMBOL_LINK \"{}\" 0\n", entry.full_name));
+        output.push_str(&Self::symbol_lines(
+            &entry.full_name,
+            &entry.full_name,
+            naming,
+            version,
+        ));
         output.push_str("    /end MEASUREMENT\n\n");
 
         output
     }
 
-    fn generate_characteristic_block(entry: &A2lEntry) -> String {
+    fn generate_characteristic_block(
+        entry: &A2lEntry,
+        order: ByteOrder,
+        naming: &NamingPolicy,
+        version: Asap2Version,
+    ) -> String {
+        let kind = if entry.is_array() { "VAL_BLK" } else { "VALUE" };
+        Self::generate_characteristic_kind(entry, kind, order, naming, version)
+    }
+
+    /// 块名被合法化时以 DISPLAY_IDENTIFIER 保留可读名称，SYMBOL_LINK 始终指向原符号
+    fn symbol_lines(
+        name: &str,
+        symbol: &str,
+        naming: &NamingPolicy,
+        version: Asap2Version,
+    ) -> String {
+        let mut output = String::new();
+        if let Some(display) = naming.display_identifier(name) {
+            output.push_str(&format!("      DISPLAY_IDENTIFIER {}\n", display));
+        }
+        // SYMBOL_LINK 自 1.60 起可用，旧版本只能按块名关联符号
+        if version.supports_symbol_link() {
+            output.push_str(&format!("      SYMBOL_LINK \"{}\" 0\n", symbol));
+        }
+        output
+    }
+
+    /// bitfield 的 BIT_MASK，以及多字节数据字节序与 MOD_COMMON 不同时的 BYTE_ORDER
+    fn byte_order_lines(entry: &A2lEntry, order: ByteOrder) -> String {
+        let mut output = String::new();
+        if let Some(mask) = entry.bit_mask(order.data) {
+            output.push_str(&format!("      BIT_MASK 0x{:X}\n", mask));
+        }
+        if let Some(endianness) = order.object_byte_order() {
+            if !matches!(entry.a2l_type.as_str(), "UBYTE" | "SBYTE") {
+                output.push_str(&format!(
+                    "      BYTE_ORDER {}\n",
+                    endianness.byte_order_keyword()
+                ));
+            }
+        }
+        output
+    }
+
+    /// 按导出类型生成条目对应的块，条目维度与类型不符时返回错误
+    pub fn generate_block(
+        entry: &A2lEntry,
+        kind: ExportKind,
+        order: ByteOrder,
+        naming: &NamingPolicy,
+        version: Asap2Version,
+    ) -> Result<String> {
+        // 目标版本没有 64 位整数类型时按字节块写出，观测量保持 MEASUREMENT，其余为 VAL_BLK
+        if !version.supports_type(&entry.a2l_type) {
+            let kind = match kind {
+                ExportKind::Measurement => kind,
+                _ => ExportKind::Characteristic,
+            };
+            return Self::generate_block(&Self::byte_block(entry), kind, order, naming, version);
+        }
+
+        let dims = entry.array_dims.as_deref().unwrap_or_default();
+        let require_dims = |n: usize, what: &str| -> Result<()> {
+            if dims.len() != n {
+                anyhow::bail!(
+                    "条目 {} 不是{}维数组，无法生成 {}",
+                    entry.full_name,
+                    n,
+                    what
+                );
+            }
+            Ok(())
+        };
+
+        Ok(match kind {
+            ExportKind::Measurement => {
+                Self::generate_measurement_block(entry, order, naming, version)
+            }
+            ExportKind::Characteristic => {
+                Self::generate_characteristic_block(entry, order, naming, version)
+            }
+            ExportKind::ValBlk => {
+                if dims.is_empty() {
+                    anyhow::bail!("条目 {} 不是数组，无法生成 VAL_BLK", entry.full_name);
+                }
+                Self::generate_characteristic_kind(entry, "VAL_BLK", order, naming, version)
+            }
+            ExportKind::Ascii => {
+                require_dims(1, "ASCII")?;
+                if !matches!(entry.a2l_type.as_str(), "UBYTE" | "SBYTE") {
+                    anyhow::bail!("条目 {} 不是字节数组，无法生成 ASCII", entry.full_name);
+                }
+                Self::generate_characteristic_kind(entry, "ASCII", order, naming, version)
+            }
+            ExportKind::Curve => {
+                require_dims(1, "CURVE")?;
+                Self::generate_characteristic_kind(entry, "CURVE", order, naming, version)
+            }
+            ExportKind::Map => {
+                require_dims(2, "MAP")?;
+                Self::generate_characteristic_kind(entry, "MAP", order, naming, version)
+            }
+            ExportKind::Cuboid => {
+                require_dims(3, "CUBOID")?;
+                Self::generate_characteristic_kind(entry, "CUBOID", order, naming, version)
+            }
+            ExportKind::AxisPts => {
+                require_dims(1, "AXIS_PTS")?;
+                Self::generate_axis_pts_block(entry, order, naming, version)
+            }
+        })
+    }
+
+    /// 64 位整数条目按字节块表示，用于不支持该类型的旧版本
+    fn byte_block(entry: &A2lEntry) -> A2lEntry {
+        let mut bytes = entry.clone();
+        bytes.a2l_type = "UBYTE".to_string();
+        bytes.array_dims = Some(vec![8 * entry.element_count()]);
+        bytes.bit_offset = None;
+        bytes.bit_size = None;
+        bytes.enum_type = None;
+        bytes
+    }
+
+    /// 枚举类型对应的转换方法，非枚举或匿名枚举为 NO_COMPU_METHOD
+    fn conversion_name(enum_type: Option<&EnumType>) -> String {
+        enum_type
+            .and_then(|t| enum_conversion_name(&t.name))
+            .unwrap_or_else(|| NO_COMPU_METHOD.to_string())
+    }
+
+    /// 枚举值范围作为上下限
+    fn enum_limits(enum_type: Option<&EnumType>) -> Option<(String, String)> {
+        let (min, max) = enum_type?.range()?;
+        Some((min.to_string(), max.to_string()))
+    }
+
+    /// 条目的上下限：枚举取枚举值范围，bitfield 使用 bit_size 计算上限
+    fn characteristic_limits(entry: &A2lEntry) -> (String, String) {
+        if let Some(limits) = Self::enum_limits(entry.enum_type.as_ref()) {
+            limits
+        } else if entry.is_bitfield() {
+            let size = entry.bit_size.unwrap_or(0);
+            ("0".to_string(), Self::get_bitfield_max(size).to_string())
+        } else {
+            let (min, max) = Self::get_min_max(&entry.a2l_type);
+            (min.to_string(), max.to_string())
+        }
+    }
+
+    fn generate_characteristic_kind(
+        entry: &A2lEntry,
+        kind: &str,
+        order: ByteOrder,
+        naming: &NamingPolicy,
+        version: Asap2Version,
+    ) -> String {
         let a2l_type = entry.a2l_type.as_str();
         let record_layout = Self::get_record_layout(a2l_type);
-
-        // bitfield 使用 bit_size 计算上限
-        let max_val = if entry.is_bitfield() {
-            let size = entry.bit_size.unwrap();
-            format!("{}", Self::get_bitfield_max(size))
+        let (min_val, max_val) = Self::characteristic_limits(entry);
+        let conversion = if kind == "ASCII" {
+            NO_COMPU_METHOD.to_string()
         } else {
-            let (_, max) = Self::get_min_max(a2l_type);
-            max.to_string()
+            Self::conversion_name(entry.enum_type.as_ref())
         };
 
         let mut output = String::new();
 
         output.push_str(&format!(
             "    /begin CHARACTERISTIC {} \"\"\n",
-            entry.full_name
+            naming.identifier(&entry.full_name)
         ));
         output.push_str(&format!(
-            "      VALUE 0x{:08X} {} 0 NO_COMPU_METHOD 0 {}\n",
-            entry.address, record_layout, max_val
+            "      {} 0x{:08X} {} 0 {} {} {}\n",
+            kind, entry.address, record_layout, conversion, min_val, max_val
         ));
 
-        // bitfield 添加 BIT_MASK
-        if entry.is_bitfield() {
-            let mask = Self::calculate_bit_mask(entry.bit_offset, entry.bit_size);
-            output.push_str(&format!("      BIT_MASK 0x{:X}\n", mask));
+        output.push_str(&Self::byte_order_lines(entry, order));
+
+        match kind {
+            "VAL_BLK" => {
+                if let Some(dims) = Self::format_matrix_dim(entry, version) {
+                    output.push_str(&format!("      MATRIX_DIM {}\n", dims));
+                }
+            }
+            "ASCII" => {
+                output.push_str(&format!("      NUMBER {}\n", entry.element_count()));
+            }
+            "CURVE" | "MAP" | "CUBOID" => {
+                // C 数组最后一维变化最快，对应 X 轴
+                let dims = entry.array_dims.clone().unwrap_or_default();
+                for &points in dims.iter().rev() {
+                    output.push_str(&Self::generate_fix_axis(points));
+                }
+            }
+            _ => {}
         }
 
-        output.push_str(&format!("      EXTENDED_LIMITS 0 {}\n", max_val));
-        output.push_str(&format!("      SYMBOL_LINK \"{}\" 0\n", entry.full_name));
+        output.push_str(&format!("      EXTENDED_LIMITS {} {}\n", min_val, max_val));
+        output.push_str(&Self::symbol_lines(
+            &entry.full_name,
+            &entry.full_name,
+            naming,
+            version,
+        ));
         output.push_str("    /end CHARACTERISTIC\n\n");
 
         output
     }
 
-    fn get_record_layout(a2l_type: &str) -> &'static str {
+    /// 以下标为轴点的固定轴
+    fn generate_fix_axis(points: usize) -> String {
+        let mut output = String::new();
+        output.push_str("      /begin AXIS_DESCR\n");
+        output.push_str(&format!(
+            "        FIX_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD {} 0 {}\n",
+            points,
+            points.saturating_sub(1)
+        ));
+        output.push_str(&format!("        FIX_AXIS_PAR_DIST 0 1 {}\n", points));
+        output.push_str("      /end AXIS_DESCR\n");
+        output
+    }
+
+    fn generate_axis_pts_block(
+        entry: &A2lEntry,
+        order: ByteOrder,
+        naming: &NamingPolicy,
+        version: Asap2Version,
+    ) -> String {
+        let a2l_type = entry.a2l_type.as_str();
+        let (min_val, max_val) = Self::characteristic_limits(entry);
+
+        let mut output = String::new();
+
+        output.push_str(&format!(
+            "    /begin AXIS_PTS {} \"\"\n",
+            naming.identifier(&entry.full_name)
+        ));
+        output.push_str(&format!(
+            "      0x{:08X} NO_INPUT_QUANTITY {} 0 NO_COMPU_METHOD {} {} {}\n",
+            entry.address,
+            Self::get_axis_layout(a2l_type),
+            entry.element_count(),
+            min_val,
+            max_val
+        ));
+        output.push_str(&Self::byte_order_lines(entry, order));
+        output.push_str(&format!("      EXTENDED_LIMITS {} {}\n", min_val, max_val));
+        output.push_str(&Self::symbol_lines(
+            &entry.full_name,
+            &entry.full_name,
+            naming,
+            version,
+        ));
+        output.push_str("    /end AXIS_PTS\n\n");
+
+        output
+    }
+
+    /// 数组条目的 MATRIX_DIM 参数，非数组返回 None
+    fn format_matrix_dim(entry: &A2lEntry, version: Asap2Version) -> Option<String> {
+        let dims = entry.array_dims.as_ref().filter(|d| !d.is_empty())?;
+        Some(
+            version
+                .matrix_dim(dims)
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+
+    pub(crate) fn get_record_layout(a2l_type: &str) -> &'static str {
         match a2l_type {
             "UBYTE" => "__UByte_Value",
             "SBYTE" => "__SByte_Value",
@@ -250,6 +740,22 @@ impl A2lGenerator {
         }
     }
 
+    pub(crate) fn get_axis_layout(a2l_type: &str) -> &'static str {
+        match a2l_type {
+            "UBYTE" => "__UByte_Axis",
+            "SBYTE" => "__SByte_Axis",
+            "UWORD" => "__UWord_Axis",
+            "SWORD" => "__SWord_Axis",
+            "ULONG" => "__ULong_Axis",
+            "SLONG" => "__SLong_Axis",
+            "A_UINT64" => "__UInt64_Axis",
+            "A_INT64" => "__Int64_Axis",
+            "FLOAT32_IEEE" => "__Float32_Axis",
+            "FLOAT64_IEEE" => "__Float64_Axis",
+            _ => "__ULong_Axis",
+        }
+    }
+
     fn get_format_string(a2l_type: &str) -> &'static str {
         match a2l_type {
             "UBYTE" | "SBYTE" => "%3.0",
@@ -262,7 +768,7 @@ impl A2lGenerator {
         }
     }
 
-    fn get_min_max(a2l_type: &str) -> (&'static str, &'static str) {
+    pub(crate) fn get_min_max(a2l_type: &str) -> (&'static str, &'static str) {
         match a2l_type {
             "UBYTE" => ("0", "255"),
             "SBYTE" => ("-128", "127"),
@@ -278,14 +784,6 @@ impl A2lGenerator {
         }
     }
 
-    fn calculate_bit_mask(bit_offset: Option<usize>, bit_size: Option<usize>) -> u64 {
-        if let (Some(offset), Some(size)) = (bit_offset, bit_size) {
-            ((1u64 << size) - 1) << offset
-        } else {
-            0
-        }
-    }
-
     fn get_bitfield_max(bit_size: usize) -> u64 {
         (1u64 << bit_size) - 1
     }
@@ -297,140 +795,144 @@ impl A2lGenerator {
         Ok(())
     }
 
-    pub fn parse_existing_names(content: &str) -> HashSet<String> {
-        let mut names = HashSet::new();
-        let mut in_measurement = false;
-        let mut in_characteristic = false;
-
-        for line in content.lines() {
-            let trimmed = line.trim();
-
-            if trimmed.starts_with("/begin MEASUREMENT") {
-                // 从同一行提取变量名，标准格式：/begin MEASUREMENT name ""
-                if let Some(rest) = trimmed.strip_prefix("/begin MEASUREMENT") {
-                    let name = rest.trim().split_whitespace().next().unwrap_or("");
-                    if !name.is_empty() && !name.starts_with('"') {
-                        names.insert(name.to_string());
-                    }
-                }
-                in_measurement = true;
-                continue;
-            }
-
-            if trimmed.starts_with("/begin CHARACTERISTIC") {
-                // 从同一行提取变量名，标准格式：/begin CHARACTERISTIC name ""
-                if let Some(rest) = trimmed.strip_prefix("/begin CHARACTERISTIC") {
-                    let name = rest.trim().split_whitespace().next().unwrap_or("");
-                    if !name.is_empty() && !name.starts_with('"') {
-                        names.insert(name.to_string());
-                    }
-                }
-                in_characteristic = true;
-                continue;
-            }
+    pub fn parse_existing_names(content: &str) -> Result<HashSet<String>> {
+        let doc = A2lDocument::parse(content)?;
+        Ok(variable_blocks(&doc)
+            .filter_map(|b| b.name())
+            .map(|n| n.to_string())
+            .collect())
+    }
 
-            if trimmed.starts_with("/end MEASUREMENT") {
-                in_measurement = false;
-                continue;
-            }
+    /// 已有变量名的匹配键集合，用于跨命名风格判断条目是否已存在
+    pub fn existing_match_keys(content: &str, naming: &NamingPolicy) -> Result<HashSet<String>> {
+        Ok(Self::match_keys(
+            &Self::parse_existing_names(content)?,
+            naming,
+        ))
+    }
 
-            if trimmed.starts_with("/end CHARACTERISTIC") {
-                in_characteristic = false;
-                continue;
-            }
+    fn match_keys(names: &HashSet<String>, naming: &NamingPolicy) -> HashSet<String> {
+        // A2L 中的名称无法区分是否为数组元素，两种键都保留，数量因此不等于名称数
+        names
+            .iter()
+            .flat_map(|n| [naming.match_key(n), naming.element_match_key(n)])
+            .collect()
+    }
 
-            if in_measurement || in_characteristic {
-                let parts: Vec<&str> = trimmed.split_whitespace().collect();
-                if !parts.is_empty() {
-                    let name = parts[0].trim();
-                    if !name.is_empty()
-                        && !name.starts_with('/')
-                        && !name.starts_with('"')
-                        && !name.parse::<f64>().is_ok()
-                    {
-                        names.insert(name.to_string());
-                    }
-                }
+    /// 条目是否已在 A2L 中，按原名或合法化后的标识符匹配。
+    /// 只有数组元素（`is_element`）才按元素的匹配键比较
+    fn is_existing(
+        existing: &HashSet<String>,
+        name: &str,
+        is_element: bool,
+        naming: &NamingPolicy,
+    ) -> bool {
+        let key = |name: &str| {
+            if is_element {
+                naming.element_match_key(name)
+            } else {
+                naming.match_key(name)
             }
-        }
-
-        names
+        };
+        existing.contains(&key(name)) || existing.contains(&key(&naming.identifier(name)))
     }
 
     pub fn append_to_file(
         entries: &[A2lEntry],
         path: &std::path::Path,
         kind: ExportKind,
+        naming: &NamingPolicy,
+        group_by: GroupBy,
+        endianness: Endianness,
+        encoding: Option<TextEncoding>,
     ) -> Result<AppendResult> {
-        let content = std::fs::read_to_string(path)
-            .with_context(|| format!("无法读取文件: {}", path.display()))?;
+        let mut project = A2lProject::load_with_encoding(path, encoding)?;
+        let content = project.content();
+        let order = ByteOrder::for_content(content, endianness)?;
+        let version = read_asap2_version(content)?.unwrap_or_default();
 
-        let existing_names = Self::parse_existing_names(&content);
+        let names = Self::parse_existing_names(content)?;
+        let existing_names = Self::match_keys(&names, naming);
 
-        let (to_add, to_skip): (Vec<_>, Vec<_>) = entries
-            .iter()
-            .partition(|e| !existing_names.contains(&e.full_name));
+        let (to_add, to_skip): (Vec<_>, Vec<_>) = entries.iter().partition(|e| {
+            !Self::is_existing(&existing_names, &e.full_name, e.is_array_element(), naming)
+        });
 
-        let new_blocks: String = to_add
+        let new_blocks = to_add
             .iter()
-            .map(|e| match kind {
-                ExportKind::Measurement => Self::generate_measurement_block(e),
-                ExportKind::Characteristic => Self::generate_characteristic_block(e),
-            })
-            .collect();
-
-        // 优先找到第一个 /begin GROUP 的位置，如果没有则使用 /end MODULE
-        let insert_pos = content
-            .find("/begin GROUP")
-            .or_else(|| {
-                content
-                    .rfind("/end MEASUREMENT")
-                    .or_else(|| content.rfind("/end MODULE"))
-            })
-            .with_context(|| "无法找到合适的插入位置")?;
-
-        // 修复缩进问题：如果插入位置所在行只有空白字符，则移动到行首
-        let actual_insert_pos = {
-            let before = &content[..insert_pos];
-            if let Some(last_newline) = before.rfind('\n') {
-                let line_start = last_newline + 1;
-                let prefix = &content[line_start..insert_pos];
-                if prefix.chars().all(|c| c.is_whitespace()) {
-                    line_start
-                } else {
-                    insert_pos
-                }
-            } else {
-                0
-            }
-        };
-
-        let new_content = format!(
-            "{}{}{}",
-            &content[..actual_insert_pos],
-            new_blocks,
-            &content[actual_insert_pos..]
-        );
+            .map(|e| Self::generate_block(e, kind, order, naming, version))
+            .collect::<Result<String>>()?;
 
-        let mut file = std::fs::File::create(path)?;
-        file.write_all(new_content.as_bytes())?;
+        let new_content = Self::insert_blocks(content, &new_blocks)?;
+        let new_content = Self::ensure_enum_conversions(&new_content, to_add.iter().copied())?;
+        let new_content = complete_record_layouts(&new_content)?;
+        let groups = groups_for_entries(to_add.iter().copied(), kind, group_by, naming);
+        let new_content = merge_groups(&new_content, &groups)?;
+        project.write(&new_content)?;
 
         Ok(AppendResult {
             added: to_add.len(),
             skipped: to_skip.len(),
-            existing: existing_names.len(),
+            existing: names.len(),
+            warnings: Self::version_warnings(to_add.iter().copied(), version),
         })
     }
 
-    pub fn preview_append(entries: &[A2lEntry], path: &std::path::Path) -> Result<AppendResult> {
-        let content = std::fs::read_to_string(path)
-            .with_context(|| format!("无法读取文件: {}", path.display()))?;
+    /// 目标版本缺少的构造：旧版本不写 SYMBOL_LINK、MATRIX_DIM 固定三维，
+    /// 64 位整数类型按字节块写出
+    fn version_warnings<'e>(
+        entries: impl IntoIterator<Item = &'e A2lEntry>,
+        version: Asap2Version,
+    ) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mut any = false;
+        for entry in entries {
+            any = true;
+            if !version.supports_type(&entry.a2l_type) {
+                warnings.push(format!(
+                    "{} 的类型 {} 需要 ASAP2 1.60 以上，目标版本为 {}，按 {} 字节的 UBYTE 块写出",
+                    entry.full_name,
+                    entry.a2l_type,
+                    version,
+                    8 * entry.element_count()
+                ));
+            }
+            let dims = entry.array_dims.as_deref().unwrap_or_default();
+            if version.fixed_matrix_dim() && dims.len() > 3 {
+                warnings.push(format!(
+                    "{} 的 {} 维数组在 ASAP2 {} 中合并为三维 MATRIX_DIM",
+                    entry.full_name,
+                    dims.len(),
+                    version
+                ));
+            }
+        }
+        if any && !version.supports_symbol_link() {
+            warnings.push(format!(
+                "ASAP2 {} 不支持 SYMBOL_LINK，刷新地址时只能按块名匹配",
+                version
+            ));
+        }
+        warnings
+    }
 
-        let existing_names = Self::parse_existing_names(&content);
+    /// 预览 [`append_to_file`](Self::append_to_file) 的结果而不写入，`encoding` 与其含义相同
+    pub fn preview_append(
+        entries: &[A2lEntry],
+        path: &std::path::Path,
+        naming: &NamingPolicy,
+        encoding: Option<TextEncoding>,
+    ) -> Result<AppendResult> {
+        let project = A2lProject::load_with_encoding(path, encoding)?;
+        let names = Self::parse_existing_names(project.content())?;
+        let existing_names = Self::match_keys(&names, naming);
+        let version = read_asap2_version(project.content())?.unwrap_or_default();
 
         let to_add: Vec<_> = entries
             .iter()
-            .filter(|e| !existing_names.contains(&e.full_name))
+            .filter(|e| {
+                !Self::is_existing(&existing_names, &e.full_name, e.is_array_element(), naming)
+            })
             .collect();
 
         let to_skip = entries.len() - to_add.len();
@@ -438,73 +940,57 @@ impl A2lGenerator {
         Ok(AppendResult {
             added: to_add.len(),
             skipped: to_skip,
-            existing: existing_names.len(),
+            existing: names.len(),
+            warnings: Self::version_warnings(to_add.iter().copied(), version),
         })
     }
 
-    /// 从 A2L 内容中删除指定的变量块
-    pub fn remove_variables(content: &str, names: &[String]) -> Result<String> {
-        use std::collections::HashSet;
-
-        let names_set: HashSet<&str> = names.iter().map(|s| s.as_str()).collect();
-        let mut result = String::new();
-        let lines: Vec<&str> = content.lines().collect();
-        let mut i = 0;
-
-        while i < lines.len() {
-            let trimmed = lines[i].trim();
+    /// 新块的插入位置：第一个 GROUP 之前，否则最后一个 MEASUREMENT/CHARACTERISTIC 之后，
+    /// 否则 MODULE 结束之前。被 `/include` 的文件中的块不作为锚点
+    fn insertion_point(doc: &A2lDocument) -> Result<usize> {
+        let included = included_spans(doc.source());
+        let in_main = |block: &&Block| in_main_file(&included, block);
+        if let Some(group) = doc.find_blocks("GROUP").into_iter().find(in_main) {
+            return Ok(doc.line_extent(group.span()).start);
+        }
+        if let Some(last) = variable_blocks(doc).filter(in_main).last() {
+            return Ok(doc.line_extent(last.span()).end);
+        }
+        module_end(doc).context("无法找到合适的插入位置")
+    }
 
-            if trimmed.starts_with("/begin MEASUREMENT")
-                || trimmed.starts_with("/begin CHARACTERISTIC")
-            {
-                let block_start = i;
-                let is_measurement = trimmed.contains("MEASUREMENT");
-                let end_marker = if is_measurement {
-                    "/end MEASUREMENT"
-                } else {
-                    "/end CHARACTERISTIC"
-                };
+    /// 为枚举条目补充文件中缺少的 TAB_VERB 转换，同一枚举只生成一次
+    fn ensure_enum_conversions<'e>(
+        content: &str,
+        entries: impl IntoIterator<Item = &'e A2lEntry>,
+    ) -> Result<String> {
+        let specs: Vec<CompuMethodSpec> = entries
+            .into_iter()
+            .filter_map(|e| e.enum_type.as_ref().and_then(enum_conversion))
+            .collect();
+        ensure_conversions(content, &specs)
+    }
 
-                // 找块结束
-                let mut block_end = i;
-                for j in i..lines.len() {
-                    if lines[j].trim().starts_with(end_marker) {
-                        block_end = j;
-                        break;
-                    }
-                }
+    pub(crate) fn insert_blocks(content: &str, blocks: &str) -> Result<String> {
+        let doc = A2lDocument::parse(content)?;
+        let pos = Self::insertion_point(&doc)?;
+        insert_text(content, pos, blocks)
+    }
 
-                // 获取变量名（从 /begin 行提取，标准格式：/begin MEASUREMENT name ""）
-                let begin_prefix = if is_measurement {
-                    "/begin MEASUREMENT"
-                } else {
-                    "/begin CHARACTERISTIC"
-                };
-                let mut var_name = "";
-                if let Some(rest) = trimmed.strip_prefix(begin_prefix) {
-                    var_name = rest.trim().split_whitespace().next().unwrap_or("");
-                }
+    /// 从 A2L 内容中删除指定的变量块
+    pub fn remove_variables(content: &str, names: &[String]) -> Result<String> {
+        let names_set: HashSet<&str> = names.iter().map(|s| s.as_str()).collect();
+        let doc = A2lDocument::parse(content)?;
 
-                if names_set.contains(var_name) {
-                    // 跳过这个块（删除）
-                    i = block_end + 1;
-                    continue;
-                } else {
-                    // 保留这个块
-                    for j in block_start..=block_end {
-                        result.push_str(lines[j]);
-                        result.push('\n');
-                    }
-                    i = block_end + 1;
-                }
-            } else {
-                result.push_str(lines[i]);
-                result.push('\n');
-                i += 1;
+        let mut edits = TextEdits::new();
+        for block in variable_blocks(&doc) {
+            if block.name().is_some_and(|n| names_set.contains(n)) {
+                edits.delete(doc.line_extent(block.span()));
             }
         }
 
-        Ok(result)
+        let content = edits.apply(content)?;
+        remove_variable_references(&content, names)
     }
 
     /// 修改指定变量的属性
@@ -513,247 +999,327 @@ impl A2lGenerator {
         original_name: &str,
         changes: &VariableChanges,
     ) -> Result<String> {
-        let lines: Vec<&str> = content.lines().collect();
-        let mut result = String::new();
-        let mut i = 0;
-
-        while i < lines.len() {
-            let trimmed = lines[i].trim();
+        // 改名与 rename 命令做相同的校验，并同时更新全部引用；其余修改针对新名称
+        let mut content = content.to_string();
+        let mut name = original_name;
+        if let Some(new_name) = changes
+            .name
+            .as_deref()
+            .filter(|n| !n.is_empty() && *n != original_name)
+        {
+            content = rename_variable(&content, original_name, new_name)?.0;
+            name = new_name;
+        }
+        // 先转换块类型，其余修改按转换后的块应用
+        if let Some(kind) = changes.var_type.as_deref().filter(|t| !t.is_empty()) {
+            content = convert_variable(&content, name, kind)?;
+        }
+        let doc = A2lDocument::parse(&content)?;
 
-            if trimmed.starts_with("/begin MEASUREMENT")
-                || trimmed.starts_with("/begin CHARACTERISTIC")
+        if let Some(ref method) = changes.compu_method {
+            if !method.is_empty()
+                && method != NO_COMPU_METHOD
+                && Conversions::from_document(&doc).method(method).is_none()
             {
-                let block_start = i;
-                let is_measurement = trimmed.contains("MEASUREMENT");
-                let end_marker = if is_measurement {
-                    "/end MEASUREMENT"
-                } else {
-                    "/end CHARACTERISTIC"
-                };
-
-                let mut block_end = i;
-                for j in i..lines.len() {
-                    if lines[j].trim().starts_with(end_marker) {
-                        block_end = j;
-                        break;
-                    }
-                }
-
-                let begin_prefix = if is_measurement {
-                    "/begin MEASUREMENT"
-                } else {
-                    "/begin CHARACTERISTIC"
-                };
-                let mut current_var_name = "";
-                if let Some(rest) = trimmed.strip_prefix(begin_prefix) {
-                    current_var_name = rest.trim().split_whitespace().next().unwrap_or("");
-                }
-
-                if current_var_name == original_name {
-                    let modified_block = Self::apply_changes_to_block(
-                        &lines[block_start..=block_end],
-                        changes,
-                        is_measurement,
-                    )?;
-                    result.push_str(&modified_block);
-                    i = block_end + 1;
-                    continue;
-                } else {
-                    for j in block_start..=block_end {
-                        result.push_str(lines[j]);
-                        result.push('\n');
-                    }
-                    i = block_end + 1;
-                }
-            } else {
-                result.push_str(lines[i]);
-                result.push('\n');
-                i += 1;
+                anyhow::bail!("未定义的转换方法: {}", method);
             }
         }
 
-        Ok(result)
+        let mut edits = TextEdits::new();
+        for block in variable_blocks(&doc).filter(|b| b.name() == Some(name)) {
+            Self::apply_changes_to_block(&doc, block, changes, &mut edits)?;
+        }
+        edits.apply(&content)
     }
 
-    fn apply_changes_to_block(
-        block_lines: &[&str],
+    pub(crate) fn apply_changes_to_block(
+        doc: &A2lDocument,
+        block: &Block,
         changes: &VariableChanges,
-        _is_measurement: bool,
-    ) -> Result<String> {
-        let new_name = changes.name.as_deref().unwrap_or("");
-        let new_address = changes.address.as_deref().unwrap_or("");
-        let new_data_type = changes.data_type.as_deref().unwrap_or("");
-        let new_bit_mask = changes.bit_mask.as_deref().unwrap_or("");
-
-        let mut original_name = String::new();
-        let mut original_address = String::new();
-        let mut original_data_type = String::new();
-        let mut has_bit_mask = false;
-        let mut bit_mask_indent = 0;
-        let mut ecu_address_indent = 0;
-
-        let a2l_types = [
-            "UBYTE",
-            "SBYTE",
-            "UWORD",
-            "SWORD",
-            "ULONG",
-            "SLONG",
-            "A_UINT64",
-            "A_INT64",
-            "FLOAT32_IEEE",
-            "FLOAT64_IEEE",
-        ];
-
-        for line in block_lines {
-            let trimmed = line.trim();
-            let indent = line.len() - line.trim_start().len();
+        edits: &mut TextEdits,
+    ) -> Result<()> {
+        let non_empty = |v: &Option<String>| v.clone().filter(|s| !s.is_empty());
+        let keyword = block.keyword();
+        let skip = positional_count(keyword);
+        let layout = BlockLayout::of(keyword);
+
+        if let (Some(name), Some(value)) = (non_empty(&changes.name), block.value(0)) {
+            edits.replace(value.span, name);
+        }
 
-            if trimmed.starts_with("/begin MEASUREMENT ")
-                || trimmed.starts_with("/begin CHARACTERISTIC ")
-            {
-                let parts: Vec<&str> = trimmed.split_whitespace().collect();
-                if parts.len() >= 3 {
-                    original_name = parts[2].to_string();
-                }
+        if let Some(address) = non_empty(&changes.address) {
+            let target = match layout.address {
+                Some(pos) => block.value(pos).map(|v| v.span),
+                None => block
+                    .keyword_args("ECU_ADDRESS", skip, 1)
+                    .map(|args| args[0].span),
+            };
+            if let Some(span) = target {
+                edits.replace(span, address.clone());
             }
-            if let Some(addr_pos) = trimmed
-                .split_whitespace()
-                .collect::<Vec<_>>()
-                .iter()
-                .position(|&x| x == "ECU_ADDRESS")
-            {
-                let parts: Vec<&str> = trimmed.split_whitespace().collect();
-                if addr_pos + 1 < parts.len() {
-                    original_address = parts[addr_pos + 1].to_string();
-                    ecu_address_indent = indent;
-                }
+            // LINK_MAP "symbol" address ...
+            if let Some(args) = block.keyword_args("LINK_MAP", skip, 2) {
+                edits.replace(args[1].span, address);
             }
-            if trimmed.starts_with("BIT_MASK") {
-                has_bit_mask = true;
-                bit_mask_indent = indent;
+        }
+
+        // 记录布局与数据类型可能指向同一个位置参数，显式指定的布局优先
+        let deposit = non_empty(&changes.record_layout).or_else(|| {
+            let data_type = non_empty(&changes.data_type)?;
+            match keyword {
+                "CHARACTERISTIC" => Some(Self::get_record_layout(&data_type).to_string()),
+                "AXIS_PTS" => Some(Self::get_axis_layout(&data_type).to_string()),
+                _ => None,
+            }
+        });
+        if let (Some(deposit), Some(pos)) = (deposit, layout.deposit) {
+            if let Some(value) = block.value(pos) {
+                edits.replace(value.span, deposit);
             }
-            let parts: Vec<&str> = trimmed.split_whitespace().collect();
-            if !parts.is_empty() && a2l_types.contains(&parts[0]) {
-                original_data_type = parts[0].to_string();
+        }
+        if keyword == "MEASUREMENT" {
+            if let (Some(data_type), Some(value)) = (non_empty(&changes.data_type), block.value(2))
+            {
+                edits.replace(value.span, data_type);
             }
         }
 
-        let final_name = if !new_name.is_empty() {
-            new_name
-        } else {
-            &original_name
-        };
-        let final_address = if !new_address.is_empty() {
-            new_address
-        } else {
-            &original_address
-        };
-        let final_data_type = if !new_data_type.is_empty() {
-            new_data_type
-        } else {
-            &original_data_type
-        };
+        if let (Some(method), Some(value)) = (
+            non_empty(&changes.compu_method),
+            block.value(layout.conversion),
+        ) {
+            edits.replace(value.span, method);
+        }
 
-        let begin_re = Regex::new(r"^(\s*/begin\s+(?:MEASUREMENT|CHARACTERISTIC)\s+)\S+")?;
-        let ecu_addr_re = Regex::new(r"^(\s*ECU_ADDRESS\s+)(0x[0-9a-fA-F]+)")?;
-        let link_map_addr_re = Regex::new(r#"^(.*LINK_MAP\s+"[^"]+"\s+)(0x[0-9a-fA-F]+)(.*)$"#)?;
-        let data_type_re = Regex::new(&format!("^(\\s*)({})(\\s+.*)$", original_data_type))?;
-        let bit_mask_re = Regex::new(r"^(\s*BIT_MASK\s+)(0x[0-9a-fA-F]+)")?;
-
-        let mut result = String::new();
-        let mut bit_mask_inserted = false;
-
-        for line in block_lines {
-            if !new_name.is_empty() {
-                if let Some(caps) = begin_re.captures(line) {
-                    let mut modified_line = format!("{}{}", &caps[1], final_name);
-                    if let Some(rest_start) =
-                        line.find(&original_name).map(|p| p + original_name.len())
-                    {
-                        if rest_start < line.len() {
-                            modified_line.push_str(&line[rest_start..]);
-                        }
-                    }
-                    modified_line.push('\n');
-                    result.push_str(&modified_line);
-                    continue;
-                }
+        for (limit, pos) in [
+            (&changes.lower_limit, layout.limits),
+            (&changes.upper_limit, layout.limits + 1),
+        ] {
+            if let (Some(limit), Some(value)) = (non_empty(limit), block.value(pos)) {
+                edits.replace(value.span, limit);
             }
+        }
 
-            if !new_bit_mask.is_empty() && !has_bit_mask && !bit_mask_inserted {
-                if ecu_addr_re.is_match(line) {
-                    let indent = if bit_mask_indent > 0 {
-                        bit_mask_indent
-                    } else {
-                        line.len() - line.trim_start().len()
-                    };
-                    result.push_str(&format!(
-                        "{}BIT_MASK {}\n",
-                        " ".repeat(indent),
-                        new_bit_mask
-                    ));
-                    bit_mask_inserted = true;
+        if let Some(ref refs) = changes.axis_refs {
+            let axes: Vec<&Block> = block
+                .blocks()
+                .filter(|b| b.keyword() == "AXIS_DESCR")
+                .collect();
+            for (axis, axis_ref) in axes.iter().zip(refs) {
+                if !axis_ref.is_empty() {
+                    Self::set_axis_ref(doc, axis, axis_ref, edits);
                 }
             }
+        }
 
-            if !new_address.is_empty() {
-                if let Some(caps) = ecu_addr_re.captures(line) {
-                    result.push_str(&format!("{}{}\n", &caps[1], final_address));
-                    continue;
-                }
+        if let Some(bit_mask) = non_empty(&changes.bit_mask) {
+            Self::set_optional_keyword(doc, block, "BIT_MASK", 1, Some(vec![bit_mask]), edits);
+        }
 
-                if let Some(caps) = link_map_addr_re.captures(line) {
-                    result.push_str(&format!("{}{}{}\n", &caps[1], final_address, &caps[3]));
-                    continue;
-                }
+        if let (Some(long_identifier), Some(value)) = (&changes.long_identifier, block.value(1)) {
+            edits.replace(value.span, quote(long_identifier));
+        }
+
+        let optional = |value: &Option<String>, quoted: bool| {
+            value
+                .as_ref()
+                .map(|v| (!v.is_empty()).then(|| vec![if quoted { quote(v) } else { v.clone() }]))
+        };
+        for (keyword, args) in [
+            ("PHYS_UNIT", optional(&changes.phys_unit, true)),
+            ("FORMAT", optional(&changes.format, true)),
+            (
+                "DISPLAY_IDENTIFIER",
+                optional(&changes.display_identifier, false),
+            ),
+            (
+                "ECU_ADDRESS_EXTENSION",
+                optional(&changes.ecu_address_extension, false),
+            ),
+        ] {
+            if let Some(args) = args {
+                Self::set_optional_keyword(doc, block, keyword, 1, args, edits);
             }
+        }
 
-            if !new_data_type.is_empty() && !original_data_type.is_empty() {
-                if let Some(caps) = data_type_re.captures(line) {
-                    result.push_str(&format!("{}{}{}\n", &caps[1], final_data_type, &caps[3]));
-                    continue;
-                }
+        if changes.extended_lower_limit.is_some() || changes.extended_upper_limit.is_some() {
+            // EXTENDED_LIMITS 只能用于 CHARACTERISTIC 与 AXIS_PTS
+            if keyword == "MEASUREMENT" {
+                anyhow::bail!(
+                    "MEASUREMENT {} 不支持 EXTENDED_LIMITS",
+                    block.name().unwrap_or_default()
+                );
             }
+            let current = block.keyword_args("EXTENDED_LIMITS", skip, 2);
+            // 未指定的一侧沿用原值，没有 EXTENDED_LIMITS 时取普通上下限
+            let limit = |change: &Option<String>, fallback: &Option<String>, i: usize| {
+                change
+                    .clone()
+                    .or_else(|| current.as_ref().map(|args| args[i].text.to_string()))
+                    .filter(|v| !v.is_empty())
+                    .or_else(|| non_empty(fallback))
+                    .or_else(|| block.value(layout.limits + i).map(|v| v.text.to_string()))
+            };
+            let lower = limit(&changes.extended_lower_limit, &changes.lower_limit, 0);
+            let upper = limit(&changes.extended_upper_limit, &changes.upper_limit, 1);
+            let clear = changes.extended_lower_limit.as_deref() == Some("")
+                && changes.extended_upper_limit.as_deref() == Some("");
+            let args = match (lower, upper) {
+                (Some(lower), Some(upper)) if !clear => Some(vec![lower, upper]),
+                _ => None,
+            };
+            Self::set_optional_keyword(doc, block, "EXTENDED_LIMITS", 2, args, edits);
+        }
+
+        if let Some(read_write) = changes.read_write {
+            // MEASUREMENT 默认只读，标定量默认可写
+            let (flag, present) = if keyword == "MEASUREMENT" {
+                ("READ_WRITE", read_write)
+            } else {
+                ("READ_ONLY", !read_write)
+            };
+            Self::set_optional_keyword(doc, block, flag, 0, present.then(Vec::new), edits);
+        }
+        Ok(())
+    }
 
-            if !new_bit_mask.is_empty() && has_bit_mask {
-                if let Some(caps) = bit_mask_re.captures(line) {
-                    result.push_str(&format!("{}{}\n", &caps[1], new_bit_mask));
-                    continue;
+    /// 设置变量块中的可选关键字：已有时替换其 `count` 个参数，没有时插入到 ECU_ADDRESS
+    /// 所在行之后（没有时在最后一个位置参数之后）；`args` 为 `None` 时删除关键字及其参数
+    pub(crate) fn set_optional_keyword(
+        doc: &A2lDocument,
+        block: &Block,
+        keyword: &str,
+        count: usize,
+        args: Option<Vec<String>>,
+        edits: &mut TextEdits,
+    ) {
+        let skip = positional_count(block.keyword());
+        let existing = block.keyword_value(keyword, skip).map(|k| {
+            let values = block.keyword_args(keyword, skip, count).unwrap_or_default();
+            (k.span, values)
+        });
+
+        match (existing, args) {
+            (Some((_, values)), Some(args)) => {
+                for (value, arg) in values.iter().zip(args) {
+                    if value.text != arg {
+                        edits.replace(value.span, arg);
+                    }
                 }
             }
+            (Some((span, values)), None) => {
+                let end = values.last().map_or(span.end, |v| v.span.end);
+                edits.delete(doc.line_extent(Span::new(span.start, end)));
+            }
+            (None, Some(args)) => {
+                let anchor = block
+                    .keyword_args("ECU_ADDRESS", skip, 1)
+                    .map(|args| args[0].span)
+                    .or_else(|| block.values().take(skip).last().map(|v| v.span))
+                    .unwrap_or(block.keyword.span);
+                let indent = match block.keyword_value("ECU_ADDRESS", skip) {
+                    Some(v) => doc.indent_at(v.span.start).to_string(),
+                    None => format!("{}  ", doc.indent_at(block.begin.start)),
+                };
+                let line = std::iter::once(keyword.to_string())
+                    .chain(args)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                Self::insert_line(doc, block, anchor.end, &indent, &line, edits);
+            }
+            (None, None) => {}
+        }
+    }
 
-            result.push_str(line);
-            result.push('\n');
+    /// 在偏移量所在行之后插入一行；该行末尾落在子块（如 IF_DATA）内部时，
+    /// 改为紧跟在偏移量之后插入，避免写进子块
+    pub(crate) fn insert_line(
+        doc: &A2lDocument,
+        block: &Block,
+        offset: usize,
+        indent: &str,
+        line: &str,
+        edits: &mut TextEdits,
+    ) {
+        let pos = doc.line_end(offset);
+        if block
+            .blocks()
+            .any(|b| b.span().start < pos && pos < b.span().end)
+        {
+            edits.insert(offset, format!(" {}", line));
+            return;
         }
+        let newline = if doc.source()[..pos].ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        edits.insert(pos, format!("{}{}{}\n", newline, indent, line));
+    }
 
-        if !new_bit_mask.is_empty() && !has_bit_mask && !bit_mask_inserted {
-            let indent = if ecu_address_indent > 0 {
-                ecu_address_indent
-            } else {
-                4
-            };
-            result.push_str(&format!(
-                "{}BIT_MASK {}\n",
-                " ".repeat(indent),
-                new_bit_mask
-            ));
+    /// 设置轴的 AXIS_PTS_REF，固定轴和标准轴改为共享轴并去掉固定轴参数
+    fn set_axis_ref(doc: &A2lDocument, axis: &Block, axis_ref: &str, edits: &mut TextEdits) {
+        // attribute input_quantity conversion max_axis_points lower upper
+        const AXIS_POSITIONAL: usize = 6;
+
+        if let Some(args) = axis.keyword_args("AXIS_PTS_REF", AXIS_POSITIONAL, 1) {
+            edits.replace(args[0].span, axis_ref.to_string());
+            return;
+        }
+
+        if let Some(attr) = axis.value(0) {
+            if matches!(attr.text, "FIX_AXIS" | "STD_AXIS") {
+                edits.replace(attr.span, "COM_AXIS");
+            }
+        }
+        for keyword in ["FIX_AXIS_PAR", "FIX_AXIS_PAR_DIST"] {
+            if let (Some(kw), Some(args)) = (
+                axis.keyword_value(keyword, AXIS_POSITIONAL),
+                axis.keyword_args(keyword, AXIS_POSITIONAL, 3),
+            ) {
+                edits.delete(doc.line_extent(Span::new(kw.span.start, args[2].span.end)));
+            }
+        }
+        if let Some(list) = axis.block("FIX_AXIS_PAR_LIST") {
+            edits.delete(doc.line_extent(list.span()));
         }
 
-        Ok(result)
+        let anchor = axis
+            .values()
+            .take(AXIS_POSITIONAL)
+            .last()
+            .map(|v| v.span)
+            .unwrap_or(axis.keyword.span);
+        let indent = format!("{}  ", doc.indent_at(axis.begin.start));
+        Self::insert_line(
+            doc,
+            axis,
+            anchor.end,
+            &indent,
+            &format!("AXIS_PTS_REF {}", axis_ref),
+            edits,
+        );
     }
 
     /// 统一应用所有变更（修改、删除、添加）
-    pub fn apply_changes(content: &str, edits: &[VariableEdit]) -> Result<(String, SaveResult)> {
+    pub fn apply_changes(
+        content: &str,
+        edits: &[VariableEdit],
+        naming: &NamingPolicy,
+        endianness: Endianness,
+    ) -> Result<(String, SaveResult)> {
         let mut result = content.to_string();
         let mut save_result = SaveResult {
             modified: 0,
             deleted: 0,
             added: 0,
             skipped: 0,
+            diagnostics: Vec::new(),
         };
 
-        let existing_names = Self::parse_existing_names(content);
+        let mut existing_names = Self::existing_match_keys(content, naming)?;
+        let order = ByteOrder::for_content(content, endianness)?;
+        let version = read_asap2_version(content)?.unwrap_or_default();
 
         for edit in edits {
             match edit.action.as_str() {
@@ -764,12 +1330,15 @@ impl A2lGenerator {
                     }
                 }
                 "delete" => {
-                    result = Self::remove_variables(&result, &[edit.original_name.clone()])?;
+                    result =
+                        Self::remove_variables(&result, std::slice::from_ref(&edit.original_name))?;
                     save_result.deleted += 1;
                 }
                 "add" => {
                     if let Some(ref entry_info) = edit.entry {
-                        if existing_names.contains(&entry_info.full_name) {
+                        // 手动添加的变量不是数组元素
+                        if Self::is_existing(&existing_names, &entry_info.full_name, false, naming)
+                        {
                             save_result.skipped += 1;
                         } else {
                             let entry = A2lEntry {
@@ -781,46 +1350,19 @@ impl A2lGenerator {
                                 bit_offset: entry_info.bit_offset,
                                 bit_size: entry_info.bit_size,
                                 array_index: None,
+                                array_dims: entry_info.array_dims.clone(),
+                                enum_type: entry_info.enum_type.clone(),
+                                source_file: entry_info.source_file.clone(),
                             };
                             let kind = match edit.export_mode.as_deref() {
-                                Some("characteristic") => ExportKind::Characteristic,
-                                _ => ExportKind::Measurement,
-                            };
-                            let block = match kind {
-                                ExportKind::Measurement => Self::generate_measurement_block(&entry),
-                                ExportKind::Characteristic => {
-                                    Self::generate_characteristic_block(&entry)
-                                }
-                            };
-                            let insert_pos = result
-                                .find("/begin GROUP")
-                                .or_else(|| result.rfind("/end MEASUREMENT"))
-                                .or_else(|| result.rfind("/end CHARACTERISTIC"))
-                                .or_else(|| result.rfind("/end MODULE"))
-                                .unwrap_or(result.len());
-
-                            // 修复缩进问题：如果插入位置所在行只有空白字符，则移动到行首
-                            let actual_insert_pos = {
-                                let before = &result[..insert_pos];
-                                if let Some(last_newline) = before.rfind('\n') {
-                                    let line_start = last_newline + 1;
-                                    let prefix = &result[line_start..insert_pos];
-                                    if prefix.chars().all(|c| c.is_whitespace()) {
-                                        line_start
-                                    } else {
-                                        insert_pos
-                                    }
-                                } else {
-                                    0
-                                }
+                                Some(mode) => mode.parse()?,
+                                None => ExportKind::Measurement,
                             };
-
-                            result = format!(
-                                "{}{}{}",
-                                &result[..actual_insert_pos],
-                                block,
-                                &result[actual_insert_pos..]
-                            );
+                            let block = Self::generate_block(&entry, kind, order, naming, version)?;
+                            result = Self::insert_blocks(&result, &block)?;
+                            result = Self::ensure_enum_conversions(&result, [&entry])?;
+                            // 同一批中重复添加的条目按已有跳过
+                            existing_names.insert(naming.match_key(&entry.full_name));
                             save_result.added += 1;
                         }
                     }
@@ -829,187 +1371,924 @@ impl A2lGenerator {
             }
         }
 
+        let result = complete_record_layouts(&result)?;
+
+        // 写回前校验，原文件已有的问题不阻止保存
+        let before = validate(content)?;
+        let after = validate(&result)?;
+        let errors = new_errors(&before, &after);
+        if !errors.is_empty() {
+            let details: Vec<String> = errors.iter().map(|d| d.to_string()).collect();
+            anyhow::bail!("修改后的 A2L 校验失败:\n{}", details.join("\n"));
+        }
+        save_result.diagnostics = new_warnings(&before, &after);
         Ok((result, save_result))
     }
 }
 
-pub struct A2lParser;
+pub(crate) const VARIABLE_KEYWORDS: &[&str] = &["MEASUREMENT", "CHARACTERISTIC", "AXIS_PTS"];
 
-impl A2lParser {
-    pub fn parse_measurement_names(content: &str) -> Vec<String> {
-        let mut names = Vec::new();
-
-        for line in content.lines() {
-            let trimmed = line.trim();
-            if trimmed.starts_with("/begin MEASUREMENT") {
-                if let Some(next_line) = content.lines().skip_while(|l| l.trim() != trimmed).nth(1)
-                {
-                    let parts: Vec<&str> = next_line.trim().split_whitespace().collect();
-                    if !parts.is_empty() {
-                        names.push(parts[0].to_string());
-                    }
-                }
-            }
+/// MEASUREMENT、CHARACTERISTIC 与 AXIS_PTS 块，按文档顺序
+pub(crate) fn variable_blocks<'d, 'a>(
+    doc: &'d A2lDocument<'a>,
+) -> impl Iterator<Item = &'d Block<'a>> {
+    doc.blocks()
+        .into_iter()
+        .filter(|b| VARIABLE_KEYWORDS.contains(&b.keyword()))
+}
+
+/// 第一个 MODULE 的 `/end MODULE` 所在行的行首
+pub(crate) fn module_end(doc: &A2lDocument) -> Option<usize> {
+    let module = doc.find_blocks("MODULE").into_iter().next()?;
+    Some(doc.line_start(module.end.start))
+}
+
+/// 依次尝试各组关键字，插入到第一组有块的关键字中最后一个块之后（空行放在新块之前），
+/// 都没有时插入到 MODULE 结束之前；找不到 MODULE 时返回 `None`。被包含文件中的块不作为锚点
+pub(crate) fn insert_after_last(
+    doc: &A2lDocument,
+    anchors: &[&[&str]],
+    blocks: &str,
+) -> Option<Result<String>> {
+    let content = doc.source();
+    let included = included_spans(content);
+    let all = doc.blocks();
+    let last = anchors.iter().find_map(|keywords| {
+        all.iter()
+            .copied()
+            .rfind(|b| keywords.contains(&b.keyword()) && in_main_file(&included, b))
+    });
+    Some(match last {
+        Some(block) => {
+            let pos = doc.line_extent(block.span()).end;
+            let blocks = format!("\n{}\n", blocks.trim_end_matches('\n'));
+            insert_text(content, pos, &blocks)
         }
+        None => insert_text(content, module_end(doc)?, blocks),
+    })
+}
 
-        names
+/// 块是否位于主文件中，而不是展开内容里被包含的文件中
+fn in_main_file(included: &[Span], block: &Block) -> bool {
+    let start = block.span().start;
+    !included.iter().any(|s| s.start <= start && start < s.end)
+}
+
+/// 在偏移量处插入文本，插入点不在行首时（例如最后一行没有换行符）先补换行
+pub(crate) fn insert_text(content: &str, pos: usize, text: &str) -> Result<String> {
+    let mut edits = TextEdits::new();
+    if pos > 0 && !content[..pos].ends_with('\n') {
+        edits.insert(pos, "\n");
+    }
+    edits.insert(pos, text);
+    edits.apply(content)
+}
+
+/// SYMBOL_LINK 的符号名与偏移
+pub(crate) fn symbol_link(block: &Block) -> Option<(String, i64)> {
+    let args = block.keyword_args("SYMBOL_LINK", positional_count(block.keyword()), 2)?;
+    Some((args[0].unquoted(), args[1].text.parse().unwrap_or(0)))
+}
+
+/// 块的必选位置参数个数，可选关键字只在其后查找
+pub(crate) fn positional_count(keyword: &str) -> usize {
+    match keyword {
+        // name long_id datatype conversion resolution accuracy lower upper
+        "MEASUREMENT" => 8,
+        // name long_id type address deposit max_diff conversion lower upper
+        "CHARACTERISTIC" => 9,
+        // name long_id address input_quantity deposit max_diff conversion max_axis_points lower upper
+        "AXIS_PTS" => 10,
+        _ => 0,
+    }
+}
+
+/// 变量块中各位置参数的下标
+pub(crate) struct BlockLayout {
+    /// 地址参数，MEASUREMENT 的地址在可选的 ECU_ADDRESS 中
+    pub(crate) address: Option<usize>,
+    /// 记录布局参数
+    pub(crate) deposit: Option<usize>,
+    /// 转换方法参数
+    pub(crate) conversion: usize,
+    /// 下限参数，上限紧随其后
+    pub(crate) limits: usize,
+}
+
+impl BlockLayout {
+    pub(crate) fn of(keyword: &str) -> Self {
+        match keyword {
+            "CHARACTERISTIC" => Self {
+                address: Some(3),
+                deposit: Some(4),
+                conversion: 6,
+                limits: 7,
+            },
+            "AXIS_PTS" => Self {
+                address: Some(2),
+                deposit: Some(4),
+                conversion: 6,
+                limits: 8,
+            },
+            _ => Self {
+                address: None,
+                deposit: None,
+                conversion: 3,
+                limits: 6,
+            },
+        }
+    }
+}
+
+pub struct A2lParser;
+
+impl A2lParser {
+    pub fn parse_measurement_names(content: &str) -> Result<Vec<String>> {
+        let doc = A2lDocument::parse(content)?;
+        Ok(doc
+            .find_blocks("MEASUREMENT")
+            .into_iter()
+            .filter_map(|b| b.name())
+            .map(|n| n.to_string())
+            .collect())
     }
 
     /// 解析 A2L 文件中所有 MEASUREMENT 和 CHARACTERISTIC 变量
-    pub fn parse_all_variables(content: &str) -> Vec<A2lVariable> {
-        let mut variables = Vec::new();
-        let mut in_measurement = false;
-        let mut in_characteristic = false;
-        let mut current_block_lines = Vec::new();
-
-        for line in content.lines() {
-            let trimmed = line.trim();
-
-            if trimmed.starts_with("/begin MEASUREMENT") {
-                in_measurement = true;
-                in_characteristic = false;
-                current_block_lines.clear();
-                current_block_lines.push(trimmed);
-                continue;
-            }
-
-            if trimmed.starts_with("/begin CHARACTERISTIC") {
-                in_characteristic = true;
-                in_measurement = false;
-                current_block_lines.clear();
-                current_block_lines.push(trimmed);
-                continue;
-            }
-
-            if trimmed.starts_with("/end MEASUREMENT") {
-                if in_measurement {
-                    variables.push(Self::parse_variable_block(
-                        &current_block_lines,
-                        "MEASUREMENT",
-                    ));
-                }
-                in_measurement = false;
-                current_block_lines.clear();
-                continue;
-            }
+    pub fn parse_all_variables(content: &str) -> Result<Vec<A2lVariable>> {
+        let doc = A2lDocument::parse(content)?;
+        Ok(variable_blocks(&doc)
+            .map(|b| Self::parse_variable_block(&doc, b))
+            .collect())
+    }
 
-            if trimmed.starts_with("/end CHARACTERISTIC") {
-                if in_characteristic {
-                    variables.push(Self::parse_variable_block(
-                        &current_block_lines,
-                        "CHARACTERISTIC",
-                    ));
-                }
-                in_characteristic = false;
-                current_block_lines.clear();
-                continue;
-            }
-
-            if in_measurement || in_characteristic {
-                current_block_lines.push(trimmed);
-            }
-        }
-
-        variables
-    }
-
-    fn parse_variable_block(block_lines: &[&str], block_type: &str) -> A2lVariable {
-        let mut name = String::new();
-        let mut address = None;
-        let mut data_type = String::new();
-        let mut bit_mask = None;
-        let mut found_first_name = false;
-
-        let a2l_types = [
-            "UBYTE",
-            "SBYTE",
-            "UWORD",
-            "SWORD",
-            "ULONG",
-            "SLONG",
-            "A_UINT64",
-            "A_INT64",
-            "FLOAT32_IEEE",
-            "FLOAT64_IEEE",
-            "FLOAT16",
-            "FLOAT64",
-            "UFIX16",
-            "UFIX32",
-            "SFIX16",
-            "SFIX32",
-        ];
+    pub(crate) fn parse_variable_block(doc: &A2lDocument, block: &Block) -> A2lVariable {
+        let block_type = block.keyword();
+        let skip = positional_count(block_type);
+        let layout = BlockLayout::of(block_type);
+        let text = |v: Option<&Value>| v.map(|v| v.text.to_string());
+
+        let address = match layout.address {
+            Some(pos) => text(block.value(pos)),
+            None => block
+                .keyword_args("ECU_ADDRESS", skip, 1)
+                .map(|args| args[0].text.to_string()),
+        };
+        let record_layout = layout.deposit.and_then(|pos| text(block.value(pos)));
+        let data_type = match block_type {
+            "MEASUREMENT" => text(block.value(2)),
+            _ => record_layout
+                .as_deref()
+                .and_then(|deposit| Self::record_layout_type(doc, deposit)),
+        };
+        let kind = if block_type == "CHARACTERISTIC" {
+            text(block.value(2))
+        } else {
+            None
+        };
 
-        for line in block_lines {
-            let trimmed = line.trim();
+        let axes: Vec<&Block> = block
+            .blocks()
+            .filter(|b| b.keyword() == "AXIS_DESCR")
+            .collect();
+        let dims = if !axes.is_empty() {
+            // AXIS_DESCR 的第 4 个参数为最大轴点数
+            axes.iter()
+                .map(|a| a.value(3).and_then(|v| v.text.parse().ok()))
+                .collect::<Option<Vec<usize>>>()
+        } else if block_type == "AXIS_PTS" {
+            block
+                .value(7)
+                .and_then(|v| v.text.parse().ok())
+                .map(|n| vec![n])
+        } else {
+            Some(numeric_args(block, "MATRIX_DIM", skip))
+                .filter(|d| !d.is_empty())
+                .or_else(|| Some(numeric_args(block, "NUMBER", skip)).filter(|d| !d.is_empty()))
+                .or_else(|| Some(numeric_args(block, "ARRAY_SIZE", skip)).filter(|d| !d.is_empty()))
+        };
+        let axis_refs = axes
+            .iter()
+            .map(|a| {
+                a.keyword_args("AXIS_PTS_REF", 6, 1)
+                    .map(|args| args[0].text.to_string())
+            })
+            .collect();
+        let keyword_arg = |keyword| block.keyword_args(keyword, skip, 1).map(|args| args[0]);
+        let extended_limits = block.keyword_args("EXTENDED_LIMITS", skip, 2);
 
-            if trimmed.is_empty() {
-                continue;
-            }
+        A2lVariable {
+            name: block.name().unwrap_or_default().to_string(),
+            address,
+            var_type: block_type.to_string(),
+            data_type: data_type.unwrap_or_default(),
+            bit_mask: block
+                .keyword_args("BIT_MASK", skip, 1)
+                .map(|args| args[0].text.to_string()),
+            kind,
+            dims,
+            record_layout,
+            lower_limit: text(block.value(layout.limits)),
+            upper_limit: text(block.value(layout.limits + 1)),
+            axis_refs,
+            compu_method: text(block.value(layout.conversion)),
+            long_identifier: block.value(1).map(|v| v.unquoted()).unwrap_or_default(),
+            phys_unit: keyword_arg("PHYS_UNIT").map(|v| v.unquoted()),
+            format: keyword_arg("FORMAT").map(|v| v.unquoted()),
+            extended_lower_limit: extended_limits
+                .as_ref()
+                .map(|args| args[0].text.to_string()),
+            extended_upper_limit: extended_limits
+                .as_ref()
+                .map(|args| args[1].text.to_string()),
+            display_identifier: text(keyword_arg("DISPLAY_IDENTIFIER")),
+            ecu_address_extension: text(keyword_arg("ECU_ADDRESS_EXTENSION")),
+            read_write: if block_type == "MEASUREMENT" {
+                block.keyword_value("READ_WRITE", skip).is_some()
+            } else {
+                block.keyword_value("READ_ONLY", skip).is_none()
+            },
+            file: None,
+        }
+    }
 
-            if trimmed.starts_with("/begin MEASUREMENT ")
-                || trimmed.starts_with("/begin CHARACTERISTIC ")
-            {
-                let parts: Vec<&str> = trimmed.split_whitespace().collect();
-                if parts.len() >= 3 {
-                    let candidate = parts[2];
-                    if !candidate.parse::<f64>().is_ok() && !a2l_types.contains(&candidate) {
-                        name = candidate.to_string();
-                        found_first_name = true;
-                    }
-                }
-                continue;
+    /// RECORD_LAYOUT 中 FNC_VALUES（轴点为 AXIS_PTS_X）的数据类型，
+    /// 找不到时按生成器的命名约定反推
+    fn record_layout_type(doc: &A2lDocument, layout: &str) -> Option<String> {
+        if let Some(block) = doc.find_block("RECORD_LAYOUT", layout) {
+            // FNC_VALUES position datatype index_mode addressing
+            // AXIS_PTS_X position datatype index_incr addressing
+            let args = block
+                .keyword_args("FNC_VALUES", 1, 2)
+                .or_else(|| block.keyword_args("AXIS_PTS_X", 1, 2));
+            if let Some(args) = args {
+                return Some(args[1].text.to_string());
             }
+        }
+        A2L_DATA_TYPES
+            .iter()
+            .find(|t| {
+                A2lGenerator::get_record_layout(t) == layout
+                    || A2lGenerator::get_axis_layout(t) == layout
+            })
+            .map(|t| t.to_string())
+    }
+}
 
-            if trimmed.starts_with('/') {
-                continue;
+/// 可选关键字之后连续的整数参数，如 `MATRIX_DIM 2 3 4`
+fn numeric_args(block: &Block, keyword: &str, skip: usize) -> Vec<usize> {
+    let values: Vec<&Value> = block.values().skip(skip).collect();
+    values
+        .iter()
+        .position(|v| v.text == keyword)
+        .map(|pos| {
+            let mut dims: Vec<usize> = values[pos + 1..]
+                .iter()
+                .map_while(|v| v.text.parse().ok())
+                .collect();
+            // ASAP2 1.6 的 MATRIX_DIM 固定三个参数，去掉末尾补齐的 1
+            while dims.len() > 1 && dims.last() == Some(&1) {
+                dims.pop();
             }
+            dims
+        })
+        .unwrap_or_default()
+}
 
-            let parts: Vec<&str> = trimmed.split_whitespace().collect();
-            if parts.is_empty() {
-                continue;
-            }
+pub(crate) const A2L_DATA_TYPES: [&str; 10] = [
+    "UBYTE",
+    "SBYTE",
+    "UWORD",
+    "SWORD",
+    "ULONG",
+    "SLONG",
+    "A_UINT64",
+    "A_INT64",
+    "FLOAT32_IEEE",
+    "FLOAT64_IEEE",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"/begin PROJECT P ""
+  /begin MODULE M ""
+    /begin MEASUREMENT speed "old" UWORD NO_COMPU_METHOD 0 0 0 65535
+      ECU_ADDRESS 0x1000
+      FORMAT "%5.0"
+    /end MEASUREMENT
+    /begin CHARACTERISTIC gain "" VALUE 0x2000 __UWord_Value 0 NO_COMPU_METHOD 0 100
+      PHYS_UNIT "rpm"
+      READ_ONLY
+    /end CHARACTERISTIC
+  /end MODULE
+/end PROJECT
+"#;
+
+    fn block_text(content: &str, name: &str) -> String {
+        let doc = A2lDocument::parse(content).unwrap();
+        let block = variable_blocks(&doc)
+            .find(|b| b.name() == Some(name))
+            .unwrap();
+        let span = doc.line_extent(block.span());
+        content[span.start..span.end].to_string()
+    }
 
-            if !found_first_name {
-                if !parts[0].parse::<f64>().is_ok() && !a2l_types.contains(&parts[0]) {
-                    name = parts[0].to_string();
-                    found_first_name = true;
-                }
-            }
+    fn modify(content: &str, name: &str, changes: VariableChanges) -> String {
+        A2lGenerator::modify_variable(content, name, &changes).unwrap()
+    }
 
-            if parts.len() >= 2 {
-                let possible_type = parts[0];
-                if a2l_types.contains(&possible_type) {
-                    data_type = possible_type.to_string();
-                }
-            }
+    fn set(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
 
-            if let Some(addr_pos) = parts.iter().position(|&x| x == "ECU_ADDRESS") {
-                if addr_pos + 1 < parts.len() {
-                    address = Some(parts[addr_pos + 1].to_string());
-                }
-            }
+    fn add(name: &str, address: u64) -> VariableEdit {
+        VariableEdit {
+            action: "add".to_string(),
+            original_name: String::new(),
+            changes: None,
+            entry: Some(A2lEntryInfo {
+                full_name: name.to_string(),
+                address,
+                size: 2,
+                a2l_type: "UWORD".to_string(),
+                type_name: "uint16_t".to_string(),
+                bit_offset: None,
+                bit_size: None,
+                array_dims: None,
+                enum_type: None,
+                source_file: None,
+            }),
+            export_mode: None,
+        }
+    }
 
-            if block_type == "CHARACTERISTIC" {
-                if let Some(value_pos) = parts.iter().position(|&x| x == "VALUE") {
-                    if value_pos + 1 < parts.len() {
-                        address = Some(parts[value_pos + 1].to_string());
-                    }
-                }
-            }
+    #[test]
+    fn test_existing_member_and_global() {
+        let naming = NamingPolicy::new();
+        let (content, result) = A2lGenerator::apply_changes(
+            SAMPLE,
+            &[add("motor_speed", 0x3000), add("motor.speed", 0x3100)],
+            &naming,
+            Endianness::Little,
+        )
+        .unwrap();
+        assert_eq!((result.added, result.skipped), (2, 0));
+
+        // 两者同时存在时各自按原名识别为已有
+        let (_, result) = A2lGenerator::apply_changes(
+            &content,
+            &[
+                add("motor.speed", 0x3100),
+                add("motor_speed", 0x3000),
+                add("motor._1_", 0x3200),
+            ],
+            &naming,
+            Endianness::Little,
+        )
+        .unwrap();
+        assert_eq!((result.added, result.skipped), (1, 2));
+
+        // 不同命名风格的同一元素仍视为已有
+        let existing = A2lGenerator::existing_match_keys(&content, &naming).unwrap();
+        assert!(A2lGenerator::is_existing(
+            &existing,
+            "motor.speed",
+            false,
+            &naming
+        ));
+        assert!(!A2lGenerator::is_existing(
+            &existing,
+            "motor.speed_",
+            false,
+            &naming
+        ));
+        // 同一批中重复添加只插入一次
+        let (added, result) = A2lGenerator::apply_changes(
+            SAMPLE,
+            &[add("twice", 0x3300), add("twice", 0x3300)],
+            &naming,
+            Endianness::Little,
+        )
+        .unwrap();
+        assert_eq!((result.added, result.skipped), (1, 1));
+        assert_eq!(added.matches("/begin MEASUREMENT twice").count(), 1);
+
+        let content = content.replace("motor.speed", "motor[2]");
+        let existing = A2lGenerator::existing_match_keys(&content, &naming).unwrap();
+        assert!(A2lGenerator::is_existing(
+            &existing,
+            "motor._2_",
+            true,
+            &naming
+        ));
+        assert!(!A2lGenerator::is_existing(
+            &existing, "motor_2", true, &naming
+        ));
 
-            if let Some(mask_pos) = parts.iter().position(|&x| x == "BIT_MASK") {
-                if mask_pos + 1 < parts.len() {
-                    bit_mask = Some(parts[mask_pos + 1].to_string());
-                }
-            }
+        // Flat 风格下只有数组元素的 `_2` 视为下标，同名的普通变量不受影响
+        let flat = NamingPolicy::new().with_index_style(crate::naming::IndexStyle::Flat);
+        let existing = A2lGenerator::existing_match_keys(&content, &flat).unwrap();
+        assert!(A2lGenerator::is_existing(&existing, "motor_2", true, &flat));
+        assert!(!A2lGenerator::is_existing(
+            &existing, "motor_2", false, &flat
+        ));
+    }
+
+    const AXES: &str = r#"/begin PROJECT P ""
+  /begin MODULE M ""
+    /begin AXIS_PTS speed_axis "" 0x4000 NO_INPUT_QUANTITY __UWord_Axis 0 NO_COMPU_METHOD 8 0 100
+    /end AXIS_PTS
+    /begin CHARACTERISTIC curve "" CURVE 0x5000 __UWord_Value 0 NO_COMPU_METHOD 0 10
+      /begin AXIS_DESCR COM_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 8 0 100
+        AXIS_PTS_REF speed_axis
+      /end AXIS_DESCR
+    /end CHARACTERISTIC
+    /begin CHARACTERISTIC map "" MAP 0x6000 __UWord_Value 0 NO_COMPU_METHOD 0 10
+      /begin AXIS_DESCR FIX_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 4 0 3
+        FIX_AXIS_PAR_DIST 0 1 4
+      /end AXIS_DESCR
+      /begin AXIS_DESCR COM_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 8 0 100
+        AXIS_PTS_REF speed_axis
+      /end AXIS_DESCR
+    /end CHARACTERISTIC
+    /begin CHARACTERISTIC block "" VAL_BLK 0x7000 __UWord_Value 0 NO_COMPU_METHOD 0 10
+      MATRIX_DIM 2 3 1
+    /end CHARACTERISTIC
+    /begin CHARACTERISTIC text "" ASCII 0x8000 __UByte_Value 0 NO_COMPU_METHOD 0 255
+      NUMBER 16
+    /end CHARACTERISTIC
+  /end MODULE
+/end PROJECT
+"#;
+
+    fn wrap(blocks: &str) -> String {
+        format!(
+            "/begin PROJECT P \"\"\n  /begin MODULE M \"\"\n{}  /end MODULE\n/end PROJECT\n",
+            blocks
+        )
+    }
+
+    fn generate(kind: ExportKind, a2l_type: &str, dims: &[usize]) -> Result<String> {
+        let mut entry = A2lEntry::new(
+            "table".to_string(),
+            0x1000,
+            2,
+            a2l_type.to_string(),
+            "uint16_t".to_string(),
+        );
+        if !dims.is_empty() {
+            entry = entry.with_array_dims(dims.to_vec());
         }
+        A2lGenerator::generate_block(
+            &entry,
+            kind,
+            ByteOrder::new(Endianness::Little),
+            &NamingPolicy::new(),
+            Asap2Version::V1_71,
+        )
+    }
 
-        A2lVariable {
-            name,
-            address,
-            var_type: block_type.to_string(),
-            data_type,
-            bit_mask,
+    #[test]
+    fn test_parse_kinds_and_axes() {
+        let variables = A2lParser::parse_all_variables(AXES).unwrap();
+        let summary: Vec<_> = variables
+            .iter()
+            .map(|v| {
+                (
+                    v.var_type.as_str(),
+                    v.kind.as_deref(),
+                    v.dims.clone(),
+                    v.axis_refs.clone(),
+                )
+            })
+            .collect();
+        let axis = || Some("speed_axis".to_string());
+        assert_eq!(
+            summary,
+            [
+                ("AXIS_PTS", None, Some(vec![8]), vec![]),
+                ("CHARACTERISTIC", Some("CURVE"), Some(vec![8]), vec![axis()]),
+                (
+                    "CHARACTERISTIC",
+                    Some("MAP"),
+                    Some(vec![4, 8]),
+                    vec![None, axis()]
+                ),
+                ("CHARACTERISTIC", Some("VAL_BLK"), Some(vec![2, 3]), vec![]),
+                ("CHARACTERISTIC", Some("ASCII"), Some(vec![16]), vec![]),
+            ]
+        );
+        assert_eq!(variables[0].data_type, "UWORD");
+        assert_eq!(variables[0].address.as_deref(), Some("0x4000"));
+        assert_eq!(variables[4].data_type, "UBYTE");
+    }
+
+    #[test]
+    fn test_generate_kinds_round_trip() {
+        // 导出类型、数据类型、维度 → 块关键字、标定量类型、解析出的维度
+        type Case = (
+            ExportKind,
+            &'static str,
+            &'static [usize],
+            &'static str,
+            Option<&'static str>,
+            Option<Vec<usize>>,
+        );
+        let cases: &[Case] = &[
+            (
+                ExportKind::Measurement,
+                "UWORD",
+                &[],
+                "MEASUREMENT",
+                None,
+                None,
+            ),
+            (
+                ExportKind::Measurement,
+                "UWORD",
+                &[4],
+                "MEASUREMENT",
+                None,
+                Some(vec![4]),
+            ),
+            (
+                ExportKind::Characteristic,
+                "UWORD",
+                &[],
+                "CHARACTERISTIC",
+                Some("VALUE"),
+                None,
+            ),
+            (
+                ExportKind::Characteristic,
+                "UWORD",
+                &[4],
+                "CHARACTERISTIC",
+                Some("VAL_BLK"),
+                Some(vec![4]),
+            ),
+            (
+                ExportKind::ValBlk,
+                "UWORD",
+                &[2, 3],
+                "CHARACTERISTIC",
+                Some("VAL_BLK"),
+                Some(vec![2, 3]),
+            ),
+            (
+                ExportKind::Ascii,
+                "UBYTE",
+                &[16],
+                "CHARACTERISTIC",
+                Some("ASCII"),
+                Some(vec![16]),
+            ),
+            (
+                ExportKind::Curve,
+                "UWORD",
+                &[5],
+                "CHARACTERISTIC",
+                Some("CURVE"),
+                Some(vec![5]),
+            ),
+            (
+                ExportKind::Map,
+                "UWORD",
+                &[2, 3],
+                "CHARACTERISTIC",
+                Some("MAP"),
+                Some(vec![3, 2]),
+            ),
+            (
+                ExportKind::Cuboid,
+                "UWORD",
+                &[2, 3, 4],
+                "CHARACTERISTIC",
+                Some("CUBOID"),
+                Some(vec![4, 3, 2]),
+            ),
+            (
+                ExportKind::AxisPts,
+                "UWORD",
+                &[6],
+                "AXIS_PTS",
+                None,
+                Some(vec![6]),
+            ),
+        ];
+        for (kind, a2l_type, dims, var_type, char_kind, parsed_dims) in cases {
+            let content = wrap(&generate(*kind, a2l_type, dims).unwrap());
+            let errors: Vec<String> = validate(&content)
+                .unwrap()
+                .iter()
+                .filter(|d| d.severity == crate::validate::Severity::Error)
+                .filter(|d| d.code != "undefined-record-layout")
+                .map(|d| d.to_string())
+                .collect();
+            assert!(errors.is_empty(), "{:?}: {:?}", kind, errors);
+
+            let variables = A2lParser::parse_all_variables(&content).unwrap();
+            let variable = &variables[0];
+            assert_eq!(variable.var_type, *var_type, "{:?}", kind);
+            assert_eq!(variable.kind.as_deref(), *char_kind, "{:?}", kind);
+            assert_eq!(&variable.dims, parsed_dims, "{:?}", kind);
+            assert_eq!(variable.data_type, *a2l_type, "{:?}", kind);
+            assert_eq!(
+                variable.address.as_deref(),
+                Some("0x00001000"),
+                "{:?}",
+                kind
+            );
+            let fixed_axes = variable.axis_refs.iter().filter(|r| r.is_none()).count();
+            assert_eq!(fixed_axes, variable.axis_refs.len(), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn test_generate_file_without_placeholder() {
+        let mut generator = A2lGenerator::new("P", "M");
+        let empty = generator.generate();
+        assert!(!empty.contains("CHARACTERISTIC"));
+        assert!(validate(&empty).unwrap().iter().all(|d| !d.is_error()));
+
+        generator.add_entry(A2lEntry::new(
+            "speed".to_string(),
+            0x1000,
+            2,
+            "UWORD".to_string(),
+            "uint16_t".to_string(),
+        ));
+        let content = generator.generate();
+        assert!(!content.contains("__PLACEHOLDER__"));
+        assert!(!content.contains("RECORD_LAYOUT"));
+        assert!(validate(&content).unwrap().iter().all(|d| !d.is_error()));
+        let variables = A2lParser::parse_all_variables(&content).unwrap();
+        assert_eq!(variables.len(), 1);
+        assert_eq!(variables[0].name, "speed");
+    }
+
+    #[test]
+    fn test_generate_int64_before_1_60() {
+        let mut entry = A2lEntry::new(
+            "count".to_string(),
+            0x1000,
+            8,
+            "A_UINT64".to_string(),
+            "uint64_t".to_string(),
+        );
+        let order = ByteOrder::new(Endianness::Little);
+        let naming = NamingPolicy::new();
+        let parse = |kind, entry: &A2lEntry| {
+            let block =
+                A2lGenerator::generate_block(entry, kind, order, &naming, Asap2Version::V1_51)
+                    .unwrap();
+            assert!(!block.contains("A_UINT64"));
+            A2lParser::parse_all_variables(&wrap(&block))
+                .unwrap()
+                .remove(0)
+        };
+
+        let variable = parse(ExportKind::Measurement, &entry);
+        assert_eq!(variable.var_type, "MEASUREMENT");
+        assert_eq!(variable.data_type, "UBYTE");
+        assert_eq!(variable.dims, Some(vec![8]));
+
+        entry = entry.with_array_dims(vec![4]);
+        let variable = parse(ExportKind::Curve, &entry);
+        assert_eq!(variable.kind.as_deref(), Some("VAL_BLK"));
+        assert_eq!(variable.dims, Some(vec![32]));
+
+        let warnings = A2lGenerator::version_warnings([&entry], Asap2Version::V1_51);
+        assert!(warnings[0].contains("32 字节的 UBYTE 块"));
+        assert!(A2lGenerator::version_warnings([&entry], Asap2Version::V1_60).is_empty());
+
+        let mut generator = A2lGenerator::new("P", "M").with_asap2_version(Asap2Version::V1_51);
+        generator.add_entry(entry);
+        let content = generator.generate();
+        assert!(!content.contains("A_UINT64"));
+        let variables = A2lParser::parse_all_variables(&content).unwrap();
+        assert_eq!(variables[0].data_type, "UBYTE");
+        assert_eq!(variables[0].dims, Some(vec![32]));
+    }
+
+    #[test]
+    fn test_generate_dims_mismatch() {
+        for (kind, a2l_type, dims) in [
+            (ExportKind::ValBlk, "UWORD", &[][..]),
+            (ExportKind::Ascii, "UWORD", &[16][..]),
+            (ExportKind::Ascii, "UBYTE", &[2, 8][..]),
+            (ExportKind::Curve, "UWORD", &[2, 3][..]),
+            (ExportKind::Map, "UWORD", &[4][..]),
+            (ExportKind::Cuboid, "UWORD", &[2, 3][..]),
+            (ExportKind::AxisPts, "UWORD", &[][..]),
+        ] {
+            assert!(
+                generate(kind, a2l_type, dims).is_err(),
+                "{:?} {:?}",
+                kind,
+                dims
+            );
         }
     }
+
+    #[test]
+    fn test_set_axis_refs() {
+        // 固定轴改为共享轴，已有引用替换，空字符串保持不变
+        let changes = VariableChanges {
+            axis_refs: Some(vec!["speed_axis".to_string(), String::new()]),
+            ..Default::default()
+        };
+        let content = A2lGenerator::modify_variable(AXES, "map", &changes).unwrap();
+        assert_eq!(
+            block_text(&content, "map"),
+            r#"    /begin CHARACTERISTIC map "" MAP 0x6000 __UWord_Value 0 NO_COMPU_METHOD 0 10
+      /begin AXIS_DESCR COM_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 4 0 3
+        AXIS_PTS_REF speed_axis
+      /end AXIS_DESCR
+      /begin AXIS_DESCR COM_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 8 0 100
+        AXIS_PTS_REF speed_axis
+      /end AXIS_DESCR
+    /end CHARACTERISTIC
+"#
+        );
+
+        let edit = |axis: &str| VariableEdit {
+            action: "modify".to_string(),
+            original_name: "curve".to_string(),
+            changes: Some(VariableChanges {
+                axis_refs: Some(vec![axis.to_string()]),
+                ..Default::default()
+            }),
+            entry: None,
+            export_mode: None,
+        };
+        let naming = NamingPolicy::new();
+        let renamed = AXES.replace("speed_axis \"\"", "rpm_axis \"\"");
+        let (content, _) =
+            A2lGenerator::apply_changes(&renamed, &[edit("rpm_axis")], &naming, Endianness::Little)
+                .unwrap();
+        assert!(block_text(&content, "curve").contains("AXIS_PTS_REF rpm_axis\n"));
+
+        // 引用未定义的轴点时校验失败，不保存
+        let err =
+            A2lGenerator::apply_changes(AXES, &[edit("missing")], &naming, Endianness::Little)
+                .unwrap_err();
+        assert!(err.to_string().contains("missing"), "{}", err);
+    }
+
+    #[test]
+    fn test_modify_renames_with_validation() {
+        let content = format!(
+            "{}    /begin GROUP G \"\"\n      /begin REF_MEASUREMENT speed /end REF_MEASUREMENT\n    /end GROUP\n",
+            block_text(SAMPLE, "speed")
+        );
+        let content = wrap(&content);
+        let renamed = modify(
+            &content,
+            "speed",
+            VariableChanges {
+                name: set("vehicle_speed"),
+                phys_unit: set("km/h"),
+                ..Default::default()
+            },
+        );
+        assert!(block_text(&renamed, "vehicle_speed").contains("PHYS_UNIT \"km/h\""));
+        assert!(renamed.contains("REF_MEASUREMENT vehicle_speed /end"));
+
+        for name in ["1speed", "gain"] {
+            let changes = VariableChanges {
+                name: set(name),
+                ..Default::default()
+            };
+            assert!(A2lGenerator::modify_variable(SAMPLE, "speed", &changes).is_err());
+        }
+    }
+
+    #[test]
+    fn test_modify_optional_keywords() {
+        let inserted = modify(
+            SAMPLE,
+            "speed",
+            VariableChanges {
+                long_identifier: set("vehicle \"speed\""),
+                phys_unit: set("km/h"),
+                format: set("%6.1"),
+                display_identifier: set("Speed"),
+                ecu_address_extension: set("0x1"),
+                read_write: Some(true),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            block_text(&inserted, "speed"),
+            r#"    /begin MEASUREMENT speed "vehicle \"speed\"" UWORD NO_COMPU_METHOD 0 0 0 65535
+      ECU_ADDRESS 0x1000
+      PHYS_UNIT "km/h"
+      DISPLAY_IDENTIFIER Speed
+      ECU_ADDRESS_EXTENSION 0x1
+      READ_WRITE
+      FORMAT "%6.1"
+    /end MEASUREMENT
+"#
+        );
+
+        let replaced = modify(
+            &inserted,
+            "speed",
+            VariableChanges {
+                phys_unit: set("m/s"),
+                display_identifier: set("Speed_ms"),
+                ecu_address_extension: set("0x2"),
+                ..Default::default()
+            },
+        );
+        let text = block_text(&replaced, "speed");
+        assert!(text.contains("PHYS_UNIT \"m/s\"\n"));
+        assert!(text.contains("DISPLAY_IDENTIFIER Speed_ms\n"));
+        assert!(text.contains("ECU_ADDRESS_EXTENSION 0x2\n"));
+
+        let deleted = modify(
+            &replaced,
+            "speed",
+            VariableChanges {
+                long_identifier: set(""),
+                phys_unit: set(""),
+                format: set(""),
+                display_identifier: set(""),
+                ecu_address_extension: set(""),
+                read_write: Some(false),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            block_text(&deleted, "speed"),
+            r#"    /begin MEASUREMENT speed "" UWORD NO_COMPU_METHOD 0 0 0 65535
+      ECU_ADDRESS 0x1000
+    /end MEASUREMENT
+"#
+        );
+        // 其他块不受影响
+        assert_eq!(block_text(&deleted, "gain"), block_text(SAMPLE, "gain"));
+    }
+
+    #[test]
+    fn test_modify_limits_and_access() {
+        let inserted = modify(
+            SAMPLE,
+            "gain",
+            VariableChanges {
+                extended_lower_limit: set("-10"),
+                phys_unit: set("1/min"),
+                read_write: Some(true),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            block_text(&inserted, "gain"),
+            r#"    /begin CHARACTERISTIC gain "" VALUE 0x2000 __UWord_Value 0 NO_COMPU_METHOD 0 100
+      EXTENDED_LIMITS -10 100
+      PHYS_UNIT "1/min"
+    /end CHARACTERISTIC
+"#
+        );
+
+        let replaced = modify(
+            &inserted,
+            "gain",
+            VariableChanges {
+                extended_upper_limit: set("200"),
+                read_write: Some(false),
+                ..Default::default()
+            },
+        );
+        let text = block_text(&replaced, "gain");
+        assert!(text.contains("EXTENDED_LIMITS -10 200\n"));
+        assert!(text.contains("READ_ONLY\n"));
+
+        let deleted = modify(
+            &replaced,
+            "gain",
+            VariableChanges {
+                extended_lower_limit: set(""),
+                extended_upper_limit: set(""),
+                phys_unit: set(""),
+                read_write: Some(true),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            block_text(&deleted, "gain"),
+            r#"    /begin CHARACTERISTIC gain "" VALUE 0x2000 __UWord_Value 0 NO_COMPU_METHOD 0 100
+    /end CHARACTERISTIC
+"#
+        );
+
+        // MEASUREMENT 不支持 EXTENDED_LIMITS
+        let changes = VariableChanges {
+            extended_upper_limit: set("70000"),
+            ..Default::default()
+        };
+        assert!(A2lGenerator::modify_variable(SAMPLE, "speed", &changes).is_err());
+    }
 }
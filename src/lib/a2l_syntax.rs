@@ -0,0 +1,609 @@
+use anyhow::{bail, Result};
+
+/// 源文本中的字节区间 `[start, end)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Whitespace,
+    /// `/* */` 或 `//` 注释
+    Comment,
+    /// 带引号的字符串，包含两侧引号
+    String,
+    /// `/begin`
+    Begin,
+    /// `/end`
+    End,
+    /// 标识符、关键字、数字等其他非空白文本
+    Word,
+}
+
+impl TokenKind {
+    /// 空白和注释不参与语法结构
+    pub fn is_trivia(self) -> bool {
+        matches!(self, TokenKind::Whitespace | TokenKind::Comment)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// 将 ASAP2 文本切分为词法单元，所有单元首尾相接覆盖整个输入
+pub fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let rest = &source[pos..];
+        let c = rest.chars().next().unwrap_or_default();
+
+        let kind = if c.is_whitespace() || c == '\u{feff}' {
+            pos += rest
+                .find(|ch: char| !(ch.is_whitespace() || ch == '\u{feff}'))
+                .unwrap_or(rest.len());
+            TokenKind::Whitespace
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            match body.find("*/") {
+                Some(i) => pos += i + 4,
+                None => bail!("{}: 块注释未结束", location(source, start)),
+            }
+            TokenKind::Comment
+        } else if rest.starts_with("//") {
+            pos += rest.find('\n').unwrap_or(rest.len());
+            TokenKind::Comment
+        } else if c == '"' {
+            pos += string_len(rest)
+                .ok_or_else(|| anyhow::anyhow!("{}: 字符串未结束", location(source, start)))?;
+            TokenKind::String
+        } else {
+            pos += word_len(rest);
+            match &source[start..pos] {
+                "/begin" => TokenKind::Begin,
+                "/end" => TokenKind::End,
+                _ => TokenKind::Word,
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            span: Span::new(start, pos),
+        });
+    }
+
+    Ok(tokens)
+}
+
+/// 字符串长度（含引号），支持 `\"` 与 `""` 两种转义
+fn string_len(rest: &str) -> Option<usize> {
+    let bytes = rest.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' if bytes.get(i + 1) == Some(&b'"') => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// 普通单词在空白、引号或注释起始处结束
+fn word_len(rest: &str) -> usize {
+    let mut iter = rest.char_indices().peekable();
+    while let Some((i, c)) = iter.next() {
+        if i > 0 && (c.is_whitespace() || c == '"') {
+            return i;
+        }
+        if i > 0 && c == '/' && matches!(iter.peek(), Some((_, '*' | '/'))) {
+            return i;
+        }
+    }
+    rest.len()
+}
+
+/// 偏移量对应的 `第 L 行第 C 列` 描述
+fn location(source: &str, offset: usize) -> String {
+    let (line, col) = line_col(source, offset);
+    format!("第 {} 行第 {} 列", line, col)
+}
+
+fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let col = before
+        .rfind('\n')
+        .map(|i| before[i + 1..].chars().count())
+        .unwrap_or_else(|| before.chars().count())
+        + 1;
+    (line, col)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Word,
+    String,
+}
+
+/// 块内的一个参数值（标识符、数字、关键字或字符串）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Value<'a> {
+    pub kind: ValueKind,
+    /// 源文本，字符串包含引号
+    pub text: &'a str,
+    pub span: Span,
+}
+
+impl Value<'_> {
+    pub fn is_string(&self) -> bool {
+        self.kind == ValueKind::String
+    }
+
+    /// 字符串去除引号并还原转义，其他值原样返回
+    pub fn unquoted(&self) -> String {
+        if !self.is_string() {
+            return self.text.to_string();
+        }
+        let inner = &self.text[1..self.text.len() - 1];
+        let mut out = String::with_capacity(inner.len());
+        let mut chars = inner.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some(next) = chars.next() {
+                        out.push(match next {
+                            'n' => '\n',
+                            't' => '\t',
+                            other => other,
+                        });
+                    }
+                }
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    out.push('"');
+                }
+                _ => out.push(c),
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node<'a> {
+    Block(Block<'a>),
+    Value(Value<'a>),
+}
+
+/// `/begin KEYWORD ... /end KEYWORD` 块
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block<'a> {
+    pub keyword: Value<'a>,
+    /// `/begin` 单词的位置
+    pub begin: Span,
+    /// 从 `/end` 到结束关键字的位置
+    pub end: Span,
+    pub items: Vec<Node<'a>>,
+}
+
+impl<'a> Block<'a> {
+    /// 从 `/begin` 到 `/end KEYWORD` 的完整区间
+    pub fn span(&self) -> Span {
+        Span::new(self.begin.start, self.end.end)
+    }
+
+    pub fn keyword(&self) -> &'a str {
+        self.keyword.text
+    }
+
+    /// 块内直接包含的参数值，按出现顺序
+    pub fn values(&self) -> impl Iterator<Item = &Value<'a>> {
+        self.items.iter().filter_map(|n| match n {
+            Node::Value(v) => Some(v),
+            Node::Block(_) => None,
+        })
+    }
+
+    /// 第 `index` 个参数值
+    pub fn value(&self, index: usize) -> Option<&Value<'a>> {
+        self.values().nth(index)
+    }
+
+    /// 块名，即第一个参数值
+    pub fn name(&self) -> Option<&'a str> {
+        self.value(0).map(|v| v.text)
+    }
+
+    /// 直接包含的子块
+    pub fn blocks(&self) -> impl Iterator<Item = &Block<'a>> {
+        self.items.iter().filter_map(|n| match n {
+            Node::Block(b) => Some(b),
+            Node::Value(_) => None,
+        })
+    }
+
+    pub fn block(&self, keyword: &str) -> Option<&Block<'a>> {
+        self.blocks().find(|b| b.keyword() == keyword)
+    }
+
+    /// 跳过前 `skip` 个位置参数后查找可选关键字，返回其后的 `count` 个参数值
+    pub fn keyword_args(
+        &self,
+        keyword: &str,
+        skip: usize,
+        count: usize,
+    ) -> Option<Vec<&Value<'a>>> {
+        let values: Vec<&Value<'a>> = self.values().collect();
+        let pos = values
+            .iter()
+            .skip(skip)
+            .position(|v| v.kind == ValueKind::Word && v.text == keyword)?
+            + skip;
+        let args = values.get(pos + 1..pos + 1 + count)?;
+        Some(args.to_vec())
+    }
+
+    /// 可选关键字本身的位置（参见 `keyword_args`）
+    pub fn keyword_value(&self, keyword: &str, skip: usize) -> Option<&Value<'a>> {
+        self.values()
+            .skip(skip)
+            .find(|v| v.kind == ValueKind::Word && v.text == keyword)
+    }
+
+    /// 深度优先遍历所有后代块（不含自身）
+    pub fn descendants(&self) -> Vec<&Block<'a>> {
+        let mut out = Vec::new();
+        collect_blocks(&self.items, &mut out);
+        out
+    }
+}
+
+fn collect_blocks<'b, 'a>(items: &'b [Node<'a>], out: &mut Vec<&'b Block<'a>>) {
+    for node in items {
+        if let Node::Block(block) = node {
+            out.push(block);
+            collect_blocks(&block.items, out);
+        }
+    }
+}
+
+/// 解析中的块：块关键字与 `/begin` 位置（文档顶层为 `None`），以及已解析的子节点
+type Frame<'a> = (Option<(Value<'a>, Span)>, Vec<Node<'a>>);
+
+/// 无损的 A2L 语法树：节点只记录字节区间，空白与注释保留在词法单元中，
+/// 对源文本的修改通过 `TextEdits` 按区间替换完成
+#[derive(Debug, Clone)]
+pub struct A2lDocument<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    items: Vec<Node<'a>>,
+}
+
+impl<'a> A2lDocument<'a> {
+    pub fn parse(source: &'a str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+
+        // 栈底为文档顶层，其余为尚未结束的块
+        let mut stack: Vec<Frame<'a>> = vec![(None, Vec::new())];
+        let mut iter = tokens.iter().filter(|t| !t.kind.is_trivia()).peekable();
+
+        while let Some(token) = iter.next() {
+            let text = token.span.text(source);
+            match token.kind {
+                TokenKind::Begin | TokenKind::End => {
+                    let keyword = match iter.next() {
+                        Some(t) if t.kind == TokenKind::Word => Value {
+                            kind: ValueKind::Word,
+                            text: t.span.text(source),
+                            span: t.span,
+                        },
+                        _ => bail!(
+                            "{}: {} 后缺少块关键字",
+                            location(source, token.span.start),
+                            text
+                        ),
+                    };
+
+                    if token.kind == TokenKind::Begin {
+                        stack.push((Some((keyword, token.span)), Vec::new()));
+                        continue;
+                    }
+
+                    let (header, items) = stack.pop().unwrap_or_default();
+                    let (open, begin) = match header {
+                        Some(h) => h,
+                        None => bail!(
+                            "{}: 多余的 /end {}",
+                            location(source, token.span.start),
+                            keyword.text
+                        ),
+                    };
+                    if open.text != keyword.text {
+                        bail!(
+                            "{}: /end {} 与{}的 /begin {} 不匹配",
+                            location(source, token.span.start),
+                            keyword.text,
+                            location(source, begin.start),
+                            open.text
+                        );
+                    }
+                    let block = Block {
+                        keyword: open,
+                        begin,
+                        end: Span::new(token.span.start, keyword.span.end),
+                        items,
+                    };
+                    if let Some((_, parent)) = stack.last_mut() {
+                        parent.push(Node::Block(block));
+                    }
+                }
+                TokenKind::String | TokenKind::Word => {
+                    let kind = if token.kind == TokenKind::String {
+                        ValueKind::String
+                    } else {
+                        ValueKind::Word
+                    };
+                    if let Some((_, items)) = stack.last_mut() {
+                        items.push(Node::Value(Value {
+                            kind,
+                            text,
+                            span: token.span,
+                        }));
+                    }
+                }
+                TokenKind::Whitespace | TokenKind::Comment => {}
+            }
+        }
+
+        if stack.len() > 1 {
+            let (header, _) = stack.pop().unwrap_or_default();
+            if let Some((open, begin)) = header {
+                bail!(
+                    "{}: /begin {} 缺少对应的 /end",
+                    location(source, begin.start),
+                    open.text
+                );
+            }
+        }
+
+        let items = stack.pop().map(|(_, items)| items).unwrap_or_default();
+        Ok(Self {
+            source,
+            tokens,
+            items,
+        })
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// 全部词法单元（含空白与注释），首尾相接覆盖整个源文本
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn comments(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Comment)
+            .map(|t| t.span.text(self.source))
+    }
+
+    /// 顶层节点
+    pub fn items(&self) -> &[Node<'a>] {
+        &self.items
+    }
+
+    /// 深度优先遍历所有块
+    pub fn blocks(&self) -> Vec<&Block<'a>> {
+        let mut out = Vec::new();
+        collect_blocks(&self.items, &mut out);
+        out
+    }
+
+    /// 指定关键字的所有块，按文档顺序
+    pub fn find_blocks(&self, keyword: &str) -> Vec<&Block<'a>> {
+        self.blocks()
+            .into_iter()
+            .filter(|b| b.keyword() == keyword)
+            .collect()
+    }
+
+    /// 指定关键字和名称的第一个块
+    pub fn find_block(&self, keyword: &str, name: &str) -> Option<&Block<'a>> {
+        self.blocks()
+            .into_iter()
+            .find(|b| b.keyword() == keyword && b.name() == Some(name))
+    }
+
+    /// 偏移量所在的行号和列号（均从 1 开始）
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        line_col(self.source, offset)
+    }
+
+    /// 偏移量所在行的起始位置
+    pub fn line_start(&self, offset: usize) -> usize {
+        self.source[..offset]
+            .rfind('\n')
+            .map(|i| i + 1)
+            .unwrap_or(0)
+    }
+
+    /// 偏移量所在行换行符之后的位置
+    pub fn line_end(&self, offset: usize) -> usize {
+        self.source[offset..]
+            .find('\n')
+            .map(|i| offset + i + 1)
+            .unwrap_or(self.source.len())
+    }
+
+    /// 偏移量所在行的前导缩进
+    pub fn indent_at(&self, offset: usize) -> &'a str {
+        let start = self.line_start(offset);
+        let line = &self.source[start..];
+        let len = line
+            .find(|c: char| !matches!(c, ' ' | '\t'))
+            .unwrap_or(line.len());
+        &line[..len]
+    }
+
+    /// 区间独占若干整行时扩展为包含缩进和行尾换行的整行区间，否则原样返回
+    pub fn line_extent(&self, span: Span) -> Span {
+        let start = self.line_start(span.start);
+        let end = self.line_end(span.end);
+        let before = &self.source[start..span.start];
+        let after = &self.source[span.end..end];
+        if before.trim().is_empty() && after.trim().is_empty() {
+            Span::new(start, end)
+        } else {
+            span
+        }
+    }
+}
+
+/// 一组基于原始区间的文本替换，一次性应用以保持其余内容不变
+#[derive(Debug, Clone, Default)]
+pub struct TextEdits {
+    edits: Vec<(Span, String)>,
+}
+
+impl TextEdits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn replace(&mut self, span: Span, text: impl Into<String>) {
+        self.edits.push((span, text.into()));
+    }
+
+    pub fn insert(&mut self, offset: usize, text: impl Into<String>) {
+        self.edits.push((Span::new(offset, offset), text.into()));
+    }
+
+    pub fn delete(&mut self, span: Span) {
+        self.edits.push((span, String::new()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// 按位置顺序应用全部编辑，同一位置的插入保持添加顺序
+    pub fn apply(mut self, source: &str) -> Result<String> {
+        self.edits.sort_by_key(|(span, _)| (span.start, span.end));
+
+        let mut out = String::with_capacity(source.len());
+        let mut pos = 0;
+        for (span, text) in &self.edits {
+            if span.start < pos || span.end > source.len() {
+                bail!("编辑区域重叠或越界: {}..{}", span.start, span.end);
+            }
+            out.push_str(&source[pos..span.start]);
+            out.push_str(text);
+            pos = span.end;
+        }
+        out.push_str(&source[pos..]);
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "/* /begin MEASUREMENT fake */\n\
+        /begin PROJECT p \"a project\"\n\
+        \x20 /begin MODULE m \"\" // /end MODULE\n\
+        \x20   /begin\n\
+        \x20     MEASUREMENT speed \"vehicle \\\"speed\\\"\"\n\
+        \x20     UWORD NO_COMPU_METHOD 0 0 0 65535\n\
+        \x20     ECU_ADDRESS 0x1000\n\
+        \x20     /begin IF_DATA XCP /begin DAQ /end DAQ /end IF_DATA\n\
+        \x20   /end MEASUREMENT\n\
+        \x20 /end MODULE\n\
+        /end PROJECT\n";
+
+    #[test]
+    fn test_tokens_cover_source() {
+        let tokens = tokenize(SAMPLE).unwrap();
+        let joined: String = tokens.iter().map(|t| t.span.text(SAMPLE)).collect();
+        assert_eq!(joined, SAMPLE);
+    }
+
+    #[test]
+    fn test_parse_structure() {
+        let doc = A2lDocument::parse(SAMPLE).unwrap();
+        let measurements = doc.find_blocks("MEASUREMENT");
+        assert_eq!(measurements.len(), 1);
+
+        let m = measurements[0];
+        assert_eq!(m.name(), Some("speed"));
+        assert_eq!(m.value(1).unwrap().unquoted(), "vehicle \"speed\"");
+        assert_eq!(m.value(2).unwrap().text, "UWORD");
+        assert_eq!(
+            m.keyword_args("ECU_ADDRESS", 8, 1).unwrap()[0].text,
+            "0x1000"
+        );
+        assert_eq!(m.descendants().len(), 2);
+        assert_eq!(doc.comments().count(), 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(A2lDocument::parse("/begin A x /end B").is_err());
+        assert!(A2lDocument::parse("/begin A x").is_err());
+        assert!(A2lDocument::parse("/end A").is_err());
+        assert!(A2lDocument::parse("/begin A \"x /end A").is_err());
+    }
+
+    #[test]
+    fn test_edits_are_lossless() {
+        let doc = A2lDocument::parse(SAMPLE).unwrap();
+        let m = doc.find_block("MEASUREMENT", "speed").unwrap();
+        let mut edits = TextEdits::new();
+        edits.replace(m.value(0).unwrap().span, "velocity");
+        let out = edits.apply(SAMPLE).unwrap();
+        assert_eq!(out, SAMPLE.replacen("speed \"", "velocity \"", 1));
+
+        let mut edits = TextEdits::new();
+        edits.delete(doc.line_extent(m.span()));
+        let out = edits.apply(SAMPLE).unwrap();
+        assert!(A2lDocument::parse(&out)
+            .unwrap()
+            .find_blocks("MEASUREMENT")
+            .is_empty());
+        assert!(out.contains("// /end MODULE\n  /end MODULE"));
+    }
+}
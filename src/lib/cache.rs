@@ -1,4 +1,5 @@
-use crate::types::{A2lEntry, A2lEntryStore, CacheEntry, TypeInfo, Variable};
+use crate::data_package::DataPackage;
+use crate::types::{A2lEntry, A2lEntryStore, CacheEntry, EnumType, TypeInfo, Variable};
 use anyhow::{Context, Result};
 use rusqlite::{params, Connection};
 use std::path::PathBuf;
@@ -53,6 +54,9 @@ impl Cache {
                 bit_offset INTEGER,
                 bit_size INTEGER,
                 array_index TEXT,
+                array_dims TEXT,
+                enum_type TEXT,
+                source_file TEXT,
                 FOREIGN KEY (file_hash) REFERENCES cache_entries(file_hash)
             );
             
@@ -64,6 +68,19 @@ impl Cache {
         )
         .context("无法创建缓存表")?;
 
+        if !DataPackage::has_column(&db, "a2l_entries", "array_dims")? {
+            db.execute_batch("ALTER TABLE a2l_entries ADD COLUMN array_dims TEXT")
+                .context("无法升级缓存表")?;
+        }
+        if !DataPackage::has_column(&db, "a2l_entries", "enum_type")? {
+            db.execute_batch("ALTER TABLE a2l_entries ADD COLUMN enum_type TEXT")
+                .context("无法升级缓存表")?;
+        }
+        if !DataPackage::has_column(&db, "a2l_entries", "source_file")? {
+            db.execute_batch("ALTER TABLE a2l_entries ADD COLUMN source_file TEXT")
+                .context("无法升级缓存表")?;
+        }
+
         Ok(Self { db, cache_dir })
     }
 
@@ -306,7 +323,7 @@ impl Cache {
         let mut stmt = self
             .db
             .prepare(
-                "SELECT full_name, address, size, a2l_type, type_name, bit_offset, bit_size, array_index 
+                "SELECT full_name, address, size, a2l_type, type_name, bit_offset, bit_size, array_index, array_dims, enum_type, source_file 
                  FROM a2l_entries WHERE file_hash = ?1 ORDER BY full_name",
             )
             .context("无法准备 A2L 条目查询")?;
@@ -321,9 +338,14 @@ impl Cache {
                 let bit_offset: Option<usize> = row.get(5)?;
                 let bit_size: Option<usize> = row.get(6)?;
                 let array_index_str: Option<String> = row.get(7)?;
+                let array_dims_str: Option<String> = row.get(8)?;
+                let enum_type_str: Option<String> = row.get(9)?;
+                let source_file: Option<String> = row.get(10)?;
 
                 let array_index =
                     array_index_str.and_then(|s| serde_json::from_str::<Vec<usize>>(&s).ok());
+                let array_dims =
+                    array_dims_str.and_then(|s| serde_json::from_str::<Vec<usize>>(&s).ok());
 
                 let mut entry = A2lEntry::new(full_name, address, size, a2l_type, type_name);
                 if let (Some(bo), Some(bs)) = (bit_offset, bit_size) {
@@ -334,6 +356,14 @@ impl Cache {
                         entry = entry.with_array_index(idx);
                     }
                 }
+                if let Some(dims) = array_dims {
+                    entry = entry.with_array_dims(dims);
+                }
+                entry = entry
+                    .with_enum_type(
+                        enum_type_str.and_then(|s| serde_json::from_str::<EnumType>(&s).ok()),
+                    )
+                    .with_source_file(source_file);
 
                 Ok(entry)
             })
@@ -375,8 +405,8 @@ impl Cache {
             let mut stmt = tx
                 .prepare(
                     "INSERT INTO a2l_entries 
-                     (file_hash, full_name, address, size, a2l_type, type_name, bit_offset, bit_size, array_index)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                     (file_hash, full_name, address, size, a2l_type, type_name, bit_offset, bit_size, array_index, array_dims, enum_type, source_file)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
                 )
                 .context("无法准备 A2L 条目插入语句")?;
 
@@ -386,6 +416,14 @@ impl Cache {
                     .as_ref()
                     .filter(|v| !v.is_empty())
                     .map(|v| serde_json::to_string(v).unwrap_or_default());
+                let array_dims_str = entry
+                    .array_dims
+                    .as_ref()
+                    .map(|v| serde_json::to_string(v).unwrap_or_default());
+                let enum_type_str = entry
+                    .enum_type
+                    .as_ref()
+                    .map(|v| serde_json::to_string(v).unwrap_or_default());
 
                 stmt.execute(params![
                     hash,
@@ -397,6 +435,9 @@ impl Cache {
                     entry.bit_offset,
                     entry.bit_size,
                     array_index_str,
+                    array_dims_str,
+                    enum_type_str,
+                    entry.source_file,
                 ])
                 .context("无法插入 A2L 条目")?;
             }
@@ -0,0 +1,512 @@
+use crate::a2l::{insert_after_last, VARIABLE_KEYWORDS};
+use crate::a2l_syntax::{A2lDocument, Block, Value};
+use crate::types::EnumType;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// 不需要定义的保留转换名（ASAP2 1.6 起）
+pub const NO_COMPU_METHOD: &str = "NO_COMPU_METHOD";
+
+const DEFAULT_FORMAT: &str = "%8.3";
+const ENUM_FORMAT: &str = "%.0";
+
+/// 文件中已有的 COMPU_METHOD
+#[derive(Debug, Clone, Serialize)]
+pub struct CompuMethod {
+    pub name: String,
+    pub long_identifier: String,
+    /// IDENTICAL、FORM、LINEAR、RAT_FUNC、TAB_INTP、TAB_NOINTP、TAB_VERB
+    pub conversion_type: String,
+    pub format: String,
+    pub unit: String,
+    /// LINEAR 为 COEFFS_LINEAR 的 a b，RAT_FUNC 为 COEFFS 的 a..f
+    pub coeffs: Option<Vec<f64>>,
+    pub compu_tab_ref: Option<String>,
+}
+
+/// 文件中已有的 COMPU_TAB / COMPU_VTAB
+#[derive(Debug, Clone, Serialize)]
+pub struct CompuTable {
+    /// COMPU_TAB 或 COMPU_VTAB
+    pub keyword: String,
+    pub name: String,
+    pub long_identifier: String,
+    pub conversion_type: String,
+    /// 原始值与转换结果，COMPU_VTAB 的结果为去除引号的文本
+    pub pairs: Vec<(String, String)>,
+    pub default_value: Option<String>,
+}
+
+/// A2L 文件中定义的全部转换
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Conversions {
+    pub methods: Vec<CompuMethod>,
+    pub tables: Vec<CompuTable>,
+}
+
+impl Conversions {
+    pub fn parse(content: &str) -> Result<Self> {
+        let doc = A2lDocument::parse(content)?;
+        Ok(Self::from_document(&doc))
+    }
+
+    pub fn from_document(doc: &A2lDocument) -> Self {
+        let mut conversions = Self::default();
+        for block in doc.blocks() {
+            match block.keyword() {
+                "COMPU_METHOD" => conversions.methods.push(parse_method(block)),
+                "COMPU_TAB" | "COMPU_VTAB" => conversions.tables.push(parse_table(block)),
+                _ => {}
+            }
+        }
+        conversions
+    }
+
+    pub fn method(&self, name: &str) -> Option<&CompuMethod> {
+        self.methods.iter().find(|m| m.name == name)
+    }
+
+    pub fn table(&self, name: &str) -> Option<&CompuTable> {
+        self.tables.iter().find(|t| t.name == name)
+    }
+
+    /// 名称是否已被转换方法或转换表占用
+    pub fn contains(&self, name: &str) -> bool {
+        self.method(name).is_some() || self.table(name).is_some()
+    }
+}
+
+fn text(value: Option<&Value>) -> String {
+    value.map(|v| v.unquoted()).unwrap_or_default()
+}
+
+fn parse_method(block: &Block) -> CompuMethod {
+    // name long_id conversion_type format unit
+    const POSITIONAL: usize = 5;
+    let numbers = |keyword: &str, count: usize| {
+        block
+            .keyword_args(keyword, POSITIONAL, count)
+            .and_then(|args| args.iter().map(|v| v.text.parse().ok()).collect())
+    };
+
+    CompuMethod {
+        name: text(block.value(0)),
+        long_identifier: text(block.value(1)),
+        conversion_type: text(block.value(2)),
+        format: text(block.value(3)),
+        unit: text(block.value(4)),
+        coeffs: numbers("COEFFS_LINEAR", 2).or_else(|| numbers("COEFFS", 6)),
+        compu_tab_ref: block
+            .keyword_args("COMPU_TAB_REF", POSITIONAL, 1)
+            .map(|args| args[0].text.to_string()),
+    }
+}
+
+fn parse_table(block: &Block) -> CompuTable {
+    // name long_id conversion_type number_value_pairs
+    let values: Vec<&Value> = block.values().collect();
+    let count: usize = values.get(3).and_then(|v| v.text.parse().ok()).unwrap_or(0);
+    let pairs = values
+        .get(4..)
+        .unwrap_or_default()
+        .chunks_exact(2)
+        .take(count)
+        .map(|pair| (pair[0].text.to_string(), pair[1].unquoted()))
+        .collect();
+    let rest = values.get(4 + count * 2..).unwrap_or_default();
+    let default_value = rest
+        .iter()
+        .position(|v| matches!(v.text, "DEFAULT_VALUE" | "DEFAULT_VALUE_NUMERIC"))
+        .and_then(|pos| rest.get(pos + 1))
+        .map(|v| v.unquoted());
+
+    CompuTable {
+        keyword: block.keyword().to_string(),
+        name: text(values.first().copied()),
+        long_identifier: text(values.get(1).copied()),
+        conversion_type: text(values.get(2).copied()),
+        pairs,
+        default_value,
+    }
+}
+
+/// 新建转换的计算方式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConversionKind {
+    Identical,
+    /// 物理值 = factor * 原始值 + offset
+    Linear {
+        factor: f64,
+        offset: f64,
+    },
+    /// 原始值 = (a*x² + b*x + c) / (d*x² + e*x + f)，x 为物理值
+    RatFunc {
+        coeffs: [f64; 6],
+    },
+    /// 原始值到物理值的插值表
+    TabIntp {
+        pairs: Vec<(f64, f64)>,
+    },
+    /// 原始值到文本的映射
+    TabVerb {
+        pairs: Vec<(i64, String)>,
+        #[serde(default)]
+        default_value: Option<String>,
+    },
+}
+
+impl ConversionKind {
+    pub fn conversion_type(&self) -> &'static str {
+        match self {
+            ConversionKind::Identical => "IDENTICAL",
+            ConversionKind::Linear { .. } => "LINEAR",
+            ConversionKind::RatFunc { .. } => "RAT_FUNC",
+            ConversionKind::TabIntp { .. } => "TAB_INTP",
+            ConversionKind::TabVerb { .. } => "TAB_VERB",
+        }
+    }
+}
+
+/// 待创建的 COMPU_METHOD，表格类转换同时生成引用的 COMPU_TAB / COMPU_VTAB
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompuMethodSpec {
+    pub name: String,
+    #[serde(default)]
+    pub long_identifier: String,
+    pub kind: ConversionKind,
+    #[serde(default = "default_format")]
+    pub format: String,
+    #[serde(default)]
+    pub unit: String,
+}
+
+fn default_format() -> String {
+    DEFAULT_FORMAT.to_string()
+}
+
+impl CompuMethodSpec {
+    pub fn new(name: &str, kind: ConversionKind) -> Self {
+        Self {
+            name: name.to_string(),
+            long_identifier: String::new(),
+            kind,
+            format: default_format(),
+            unit: String::new(),
+        }
+    }
+
+    pub fn identical(name: &str) -> Self {
+        Self::new(name, ConversionKind::Identical)
+    }
+
+    pub fn linear(name: &str, factor: f64, offset: f64) -> Self {
+        Self::new(name, ConversionKind::Linear { factor, offset })
+    }
+
+    pub fn rat_func(name: &str, coeffs: [f64; 6]) -> Self {
+        Self::new(name, ConversionKind::RatFunc { coeffs })
+    }
+
+    pub fn tab_intp(name: &str, pairs: Vec<(f64, f64)>) -> Self {
+        Self::new(name, ConversionKind::TabIntp { pairs })
+    }
+
+    pub fn tab_verb(name: &str, pairs: Vec<(i64, String)>) -> Self {
+        Self::new(
+            name,
+            ConversionKind::TabVerb {
+                pairs,
+                default_value: None,
+            },
+        )
+    }
+
+    pub fn with_long_identifier(mut self, long_identifier: &str) -> Self {
+        self.long_identifier = long_identifier.to_string();
+        self
+    }
+
+    pub fn with_format(mut self, format: &str) -> Self {
+        self.format = format.to_string();
+        self
+    }
+
+    pub fn with_unit(mut self, unit: &str) -> Self {
+        self.unit = unit.to_string();
+        self
+    }
+
+    /// 表格类转换引用的 COMPU_TAB / COMPU_VTAB 名称
+    pub fn table_name(&self) -> Option<String> {
+        match self.kind {
+            ConversionKind::TabIntp { .. } => Some(format!("{}_TAB", self.name)),
+            ConversionKind::TabVerb { .. } => Some(format!("{}_VTAB", self.name)),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() || self.name == NO_COMPU_METHOD {
+            anyhow::bail!("无效的转换方法名: {:?}", self.name);
+        }
+        match &self.kind {
+            ConversionKind::Linear { factor, .. } if *factor == 0.0 => {
+                anyhow::bail!("LINEAR 转换 {} 的系数不能为 0", self.name)
+            }
+            ConversionKind::RatFunc { coeffs } if coeffs[3..].iter().all(|c| *c == 0.0) => {
+                anyhow::bail!("RAT_FUNC 转换 {} 的分母系数不能全为 0", self.name)
+            }
+            ConversionKind::TabIntp { pairs } if pairs.is_empty() => {
+                anyhow::bail!("转换表 {} 至少需要一组数值", self.name)
+            }
+            ConversionKind::TabVerb { pairs, .. } if pairs.is_empty() => {
+                anyhow::bail!("转换表 {} 至少需要一组数值", self.name)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 生成 COMPU_METHOD 及其转换表的 A2L 文本
+    pub fn to_a2l(&self) -> String {
+        let mut output = String::new();
+
+        output.push_str(&format!(
+            "    /begin COMPU_METHOD {} {}\n",
+            self.name,
+            quote(&self.long_identifier)
+        ));
+        output.push_str(&format!(
+            "      {} {} {}\n",
+            self.kind.conversion_type(),
+            quote(&self.format),
+            quote(&self.unit)
+        ));
+        match &self.kind {
+            ConversionKind::Identical => {}
+            ConversionKind::Linear { factor, offset } => {
+                output.push_str(&format!("      COEFFS_LINEAR {} {}\n", factor, offset));
+            }
+            ConversionKind::RatFunc { coeffs } => {
+                let coeffs: Vec<String> = coeffs.iter().map(|c| c.to_string()).collect();
+                output.push_str(&format!("      COEFFS {}\n", coeffs.join(" ")));
+            }
+            ConversionKind::TabIntp { .. } | ConversionKind::TabVerb { .. } => {
+                if let Some(table) = self.table_name() {
+                    output.push_str(&format!("      COMPU_TAB_REF {}\n", table));
+                }
+            }
+        }
+        output.push_str("    /end COMPU_METHOD\n\n");
+
+        match &self.kind {
+            ConversionKind::TabIntp { pairs } => {
+                output.push_str(&format!(
+                    "    /begin COMPU_TAB {} {} TAB_INTP {}\n",
+                    self.table_name().unwrap_or_default(),
+                    quote(&self.long_identifier),
+                    pairs.len()
+                ));
+                for (raw, phys) in pairs {
+                    output.push_str(&format!("      {} {}\n", raw, phys));
+                }
+                output.push_str("    /end COMPU_TAB\n\n");
+            }
+            ConversionKind::TabVerb {
+                pairs,
+                default_value,
+            } => {
+                output.push_str(&format!(
+                    "    /begin COMPU_VTAB {} {} TAB_VERB {}\n",
+                    self.table_name().unwrap_or_default(),
+                    quote(&self.long_identifier),
+                    pairs.len()
+                ));
+                for (raw, label) in pairs {
+                    output.push_str(&format!("      {} {}\n", raw, quote(label)));
+                }
+                if let Some(default) = default_value {
+                    output.push_str(&format!("      DEFAULT_VALUE {}\n", quote(default)));
+                }
+                output.push_str("    /end COMPU_VTAB\n\n");
+            }
+            _ => {}
+        }
+
+        output
+    }
+}
+
+pub(crate) fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// 枚举类型对应的转换方法名，即清理后的类型名；匿名枚举返回 `None`
+pub fn enum_conversion_name(type_name: &str) -> Option<String> {
+    let name = type_name.strip_prefix("enum ").unwrap_or(type_name).trim();
+    if name.is_empty() || name.starts_with('<') {
+        return None;
+    }
+    let mut name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    Some(name)
+}
+
+/// 枚举类型的 TAB_VERB 转换，COMPU_VTAB 列出全部枚举值
+pub fn enum_conversion(enum_type: &EnumType) -> Option<CompuMethodSpec> {
+    let name = enum_conversion_name(&enum_type.name)?;
+    let pairs = enum_type
+        .variants
+        .iter()
+        .map(|v| (v.value, v.name.clone()))
+        .collect();
+    Some(
+        CompuMethodSpec::tab_verb(&name, pairs)
+            .with_long_identifier(&enum_type.name)
+            .with_format(ENUM_FORMAT),
+    )
+}
+
+/// 插入文件中尚不存在的转换，同名转换沿用已有定义
+pub fn ensure_conversions(content: &str, specs: &[CompuMethodSpec]) -> Result<String> {
+    if specs.is_empty() {
+        return Ok(content.to_string());
+    }
+    let existing = Conversions::parse(content)?;
+    let mut missing: Vec<CompuMethodSpec> = Vec::new();
+    for spec in specs {
+        if existing.method(&spec.name).is_none() && !missing.iter().any(|s| s.name == spec.name) {
+            missing.push(spec.clone());
+        }
+    }
+    if missing.is_empty() {
+        return Ok(content.to_string());
+    }
+    insert_conversions(content, &missing)
+}
+
+/// 把新转换插入到 MODULE 中：最后一个转换块之后，否则最后一个变量块之后，
+/// 否则 MODULE 结束之前。名称冲突时报错
+pub fn insert_conversions(content: &str, specs: &[CompuMethodSpec]) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let existing = Conversions::from_document(&doc);
+
+    let mut blocks = String::new();
+    let mut names = Vec::new();
+    for spec in specs {
+        spec.validate()?;
+        for name in std::iter::once(spec.name.clone()).chain(spec.table_name()) {
+            if existing.contains(&name) || names.contains(&name) {
+                anyhow::bail!("已存在同名转换: {}", name);
+            }
+            names.push(name);
+        }
+        blocks.push_str(&spec.to_a2l());
+    }
+
+    insert_after_last(
+        &doc,
+        &[
+            &[
+                "COMPU_METHOD",
+                "COMPU_TAB",
+                "COMPU_VTAB",
+                "COMPU_VTAB_RANGE",
+            ],
+            VARIABLE_KEYWORDS,
+        ],
+        &blocks,
+    )
+    .context("无法找到转换的插入位置")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+/begin PROJECT P \"\"
+  /begin MODULE M \"\"
+    /begin MEASUREMENT speed \"\"
+      UWORD CM_speed 0 0 0 65535
+    /end MEASUREMENT
+
+    /begin COMPU_METHOD CM_speed \"km/h\"
+      LINEAR \"%6.2\" \"km/h\"
+      COEFFS_LINEAR 0.01 0
+    /end COMPU_METHOD
+
+    /begin COMPU_VTAB VT_gear \"\" TAB_VERB 2
+      0 \"P\" 1 \"R\"
+      DEFAULT_VALUE \"?\"
+    /end COMPU_VTAB
+  /end MODULE
+/end PROJECT
+";
+
+    #[test]
+    fn test_parse_conversions() {
+        let conversions = Conversions::parse(SAMPLE).unwrap();
+        let method = conversions.method("CM_speed").unwrap();
+        assert_eq!(method.conversion_type, "LINEAR");
+        assert_eq!(method.unit, "km/h");
+        assert_eq!(method.coeffs, Some(vec![0.01, 0.0]));
+
+        let table = conversions.table("VT_gear").unwrap();
+        assert_eq!(table.pairs.len(), 2);
+        assert_eq!(table.pairs[1], ("1".to_string(), "R".to_string()));
+        assert_eq!(table.default_value.as_deref(), Some("?"));
+    }
+
+    #[test]
+    fn test_insert_conversions() {
+        let specs = [
+            CompuMethodSpec::tab_verb("CM_mode", vec![(0, "OFF".into()), (1, "ON".into())]),
+            CompuMethodSpec::linear("CM_temp", 0.5, -40.0).with_unit("degC"),
+        ];
+        let out = insert_conversions(SAMPLE, &specs).unwrap();
+        let conversions = Conversions::parse(&out).unwrap();
+        assert_eq!(conversions.methods.len(), 3);
+        assert_eq!(conversions.table("CM_mode_VTAB").unwrap().pairs.len(), 2);
+        assert!(out.find("CM_temp").unwrap() > out.find("VT_gear").unwrap());
+
+        assert!(insert_conversions(&out, &[CompuMethodSpec::identical("CM_speed")]).is_err());
+    }
+
+    #[test]
+    fn test_enum_conversion() {
+        use crate::types::EnumVariant;
+
+        assert_eq!(enum_conversion_name("enum Gear").as_deref(), Some("Gear"));
+        assert_eq!(
+            enum_conversion_name("ns::Mode").as_deref(),
+            Some("ns__Mode")
+        );
+        assert_eq!(enum_conversion_name("<anonymous@0x2a>"), None);
+
+        let gear = EnumType {
+            name: "Gear".to_string(),
+            variants: vec![
+                EnumVariant::new("P".to_string(), -1),
+                EnumVariant::new("D".to_string(), 3),
+            ],
+        };
+        let spec = enum_conversion(&gear).unwrap();
+        let out = ensure_conversions(SAMPLE, &[spec.clone(), spec.clone()]).unwrap();
+        let out = ensure_conversions(&out, &[spec]).unwrap();
+        let conversions = Conversions::parse(&out).unwrap();
+        assert_eq!(conversions.methods.len(), 2);
+        assert_eq!(conversions.table("Gear_VTAB").unwrap().pairs[0].0, "-1");
+    }
+}
@@ -0,0 +1,265 @@
+//! MEASUREMENT 与 CHARACTERISTIC 互相转换：改写块关键字与位置参数，
+//! 去掉目标类型不支持的可选关键字，位掩码、符号链接、注释等其余内容原样保留
+
+use crate::a2l::{positional_count, variable_blocks, A2lGenerator, A2lParser, A2L_DATA_TYPES};
+use crate::a2l_syntax::{A2lDocument, Block, Span, TextEdits};
+use crate::group::move_variable_membership;
+use crate::record_layout::complete_record_layouts;
+use crate::version::{document_version, Asap2Version};
+use anyhow::{Context, Result};
+
+/// 只能出现在 MEASUREMENT 中的可选关键字及其参数个数，ECU_ADDRESS 与 ARRAY_SIZE 另行处理
+const MEASUREMENT_ONLY: &[(&str, usize)] = &[("READ_WRITE", 0), ("ERROR_MASK", 1), ("LAYOUT", 1)];
+const MEASUREMENT_ONLY_BLOCKS: &[&str] = &["VIRTUAL", "BIT_OPERATION"];
+
+/// 只能出现在 CHARACTERISTIC 中的可选关键字及其参数个数，NUMBER 另行处理
+const CHARACTERISTIC_ONLY: &[(&str, usize)] = &[
+    ("READ_ONLY", 0),
+    ("GUARD_RAILS", 0),
+    ("CALIBRATION_ACCESS", 1),
+    ("COMPARISON_QUANTITY", 1),
+    ("STEP_SIZE", 1),
+    ("ENCODING", 1),
+    ("EXTENDED_LIMITS", 2),
+];
+const CHARACTERISTIC_ONLY_BLOCKS: &[&str] = &[
+    "MAP_LIST",
+    "DEPENDENT_CHARACTERISTIC",
+    "VIRTUAL_CHARACTERISTIC",
+];
+
+/// 把变量转换为 `kind`（MEASUREMENT 或 CHARACTERISTIC），已是该类型时原样返回。
+/// 标定量的记录布局按数据类型取标准布局并补全定义，分组中的引用移到对应类别的列表；
+/// 可写标志取目标类型的默认值
+pub fn convert_variable(content: &str, name: &str, kind: &str) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let block = variable_blocks(&doc)
+        .find(|b| b.name() == Some(name))
+        .with_context(|| format!("A2L 中没有变量: {}", name))?;
+    let from = block.keyword();
+    if from == kind {
+        return Ok(content.to_string());
+    }
+
+    let version = document_version(&doc).unwrap_or_default();
+    let mut edits = TextEdits::new();
+    let to_characteristic = match (from, kind) {
+        ("MEASUREMENT", "CHARACTERISTIC") => {
+            to_characteristic(&doc, block, version, &mut edits)?;
+            true
+        }
+        ("CHARACTERISTIC", "MEASUREMENT") => {
+            to_measurement(&doc, block, version, &mut edits)?;
+            false
+        }
+        _ => anyhow::bail!("不支持把 {} 转换为 {}", from, kind),
+    };
+    edits.replace(block.keyword.span, kind);
+    edits.replace(Span::new(block.end.end - from.len(), block.end.end), kind);
+
+    let content = edits.apply(content)?;
+    let content = complete_record_layouts(&content)?;
+    move_variable_membership(&content, name, to_characteristic)
+}
+
+fn to_characteristic(
+    doc: &A2lDocument,
+    block: &Block,
+    version: Asap2Version,
+    edits: &mut TextEdits,
+) -> Result<()> {
+    let name = block.name().unwrap_or_default();
+    let skip = positional_count("MEASUREMENT");
+    let address = block
+        .keyword_args("ECU_ADDRESS", skip, 1)
+        .with_context(|| format!("{} 没有 ECU_ADDRESS，无法转换为 CHARACTERISTIC", name))?[0];
+    // name long_id datatype conversion resolution accuracy lower upper
+    let (Some(data_type), Some(conversion), Some(accuracy)) =
+        (block.value(2), block.value(3), block.value(5))
+    else {
+        anyhow::bail!("MEASUREMENT {} 的参数不完整", name);
+    };
+    if !A2L_DATA_TYPES.contains(&data_type.text) {
+        anyhow::bail!("{} 的数据类型 {} 没有对应的记录布局", name, data_type.text);
+    }
+
+    // 数组转为 VAL_BLK，其余为 VALUE：type address deposit max_diff conversion lower upper
+    let array = ["MATRIX_DIM", "ARRAY_SIZE"]
+        .iter()
+        .any(|kw| block.keyword_value(kw, skip).is_some());
+    edits.replace(
+        data_type.span,
+        format!(
+            "{} {} {} 0",
+            if array { "VAL_BLK" } else { "VALUE" },
+            address.text,
+            A2lGenerator::get_record_layout(data_type.text)
+        ),
+    );
+    edits.delete(Span::new(conversion.span.end, accuracy.span.end));
+
+    A2lGenerator::set_optional_keyword(doc, block, "ECU_ADDRESS", 1, None, edits);
+    to_matrix_dim(doc, block, "ARRAY_SIZE", version, edits);
+    remove_keywords(doc, block, MEASUREMENT_ONLY, MEASUREMENT_ONLY_BLOCKS, edits);
+    Ok(())
+}
+
+fn to_measurement(
+    doc: &A2lDocument,
+    block: &Block,
+    version: Asap2Version,
+    edits: &mut TextEdits,
+) -> Result<()> {
+    let variable = A2lParser::parse_variable_block(doc, block);
+    let kind = variable.kind.as_deref().unwrap_or_default();
+    if !matches!(kind, "VALUE" | "VAL_BLK") {
+        anyhow::bail!(
+            "{} 是 {} 类型，只有 VALUE 与 VAL_BLK 可以转换为 MEASUREMENT",
+            variable.name,
+            kind
+        );
+    }
+    if variable.data_type.is_empty() {
+        anyhow::bail!(
+            "无法从记录布局 {} 确定 {} 的数据类型",
+            variable.record_layout.as_deref().unwrap_or_default(),
+            variable.name
+        );
+    }
+    // name long_id type address deposit max_diff conversion lower upper
+    let (Some(kind), Some(address), Some(max_diff), Some(conversion)) = (
+        block.value(2),
+        block.value(3),
+        block.value(5),
+        block.value(6),
+    ) else {
+        anyhow::bail!("CHARACTERISTIC {} 的参数不完整", variable.name);
+    };
+
+    // datatype conversion resolution accuracy lower upper，地址移到 ECU_ADDRESS
+    edits.replace(
+        Span::new(kind.span.start, max_diff.span.end),
+        variable.data_type.as_str(),
+    );
+    edits.insert(conversion.span.end, " 0 0");
+    A2lGenerator::set_optional_keyword(
+        doc,
+        block,
+        "ECU_ADDRESS",
+        1,
+        Some(vec![address.text.to_string()]),
+        edits,
+    );
+
+    to_matrix_dim(doc, block, "NUMBER", version, edits);
+    remove_keywords(
+        doc,
+        block,
+        CHARACTERISTIC_ONLY,
+        CHARACTERISTIC_ONLY_BLOCKS,
+        edits,
+    );
+    Ok(())
+}
+
+/// `ARRAY_SIZE n`、`NUMBER n` 改写为目标版本的 MATRIX_DIM，已有 MATRIX_DIM 时直接删除
+fn to_matrix_dim(
+    doc: &A2lDocument,
+    block: &Block,
+    keyword: &str,
+    version: Asap2Version,
+    edits: &mut TextEdits,
+) {
+    let skip = positional_count(block.keyword());
+    let (Some(kw), Some(args)) = (
+        block.keyword_value(keyword, skip),
+        block.keyword_args(keyword, skip, 1),
+    ) else {
+        return;
+    };
+    match args[0].text.parse::<usize>() {
+        Ok(n) if block.keyword_value("MATRIX_DIM", skip).is_none() => {
+            let dims: Vec<String> = version
+                .matrix_dim(&[n])
+                .iter()
+                .map(|d| d.to_string())
+                .collect();
+            edits.replace(
+                Span::new(kw.span.start, args[0].span.end),
+                format!("MATRIX_DIM {}", dims.join(" ")),
+            );
+        }
+        _ => A2lGenerator::set_optional_keyword(doc, block, keyword, 1, None, edits),
+    }
+}
+
+fn remove_keywords(
+    doc: &A2lDocument,
+    block: &Block,
+    keywords: &[(&str, usize)],
+    blocks: &[&str],
+    edits: &mut TextEdits,
+) {
+    for &(keyword, count) in keywords {
+        A2lGenerator::set_optional_keyword(doc, block, keyword, count, None, edits);
+    }
+    for child in block.blocks().filter(|b| blocks.contains(&b.keyword())) {
+        edits.delete(doc.line_extent(child.span()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"ASAP2_VERSION 1 71
+/begin PROJECT P ""
+  /begin MODULE M ""
+    /begin MEASUREMENT speed "vehicle speed" UWORD CM_speed 1 0 0 250
+      /* 车速 */
+      BIT_MASK 0x0FFF
+      ECU_ADDRESS 0x1000
+      READ_WRITE
+      SYMBOL_LINK "speed" 0
+    /end MEASUREMENT
+    /begin GROUP G ""
+      /begin REF_MEASUREMENT speed other /end REF_MEASUREMENT
+    /end GROUP
+  /end MODULE
+/end PROJECT
+"#;
+
+    #[test]
+    fn test_convert_round_trip() {
+        let characteristic = convert_variable(SAMPLE, "speed", "CHARACTERISTIC").unwrap();
+        let variable = &A2lParser::parse_all_variables(&characteristic).unwrap()[0];
+        assert_eq!(variable.var_type, "CHARACTERISTIC");
+        assert_eq!(variable.kind.as_deref(), Some("VALUE"));
+        assert_eq!(variable.address.as_deref(), Some("0x1000"));
+        assert_eq!(variable.data_type, "UWORD");
+        assert_eq!(variable.bit_mask.as_deref(), Some("0x0FFF"));
+        assert_eq!(variable.compu_method.as_deref(), Some("CM_speed"));
+        assert_eq!(variable.upper_limit.as_deref(), Some("250"));
+        assert!(variable.read_write);
+        assert!(characteristic.contains("/* 车速 */"));
+        assert!(characteristic.contains("SYMBOL_LINK \"speed\" 0"));
+        assert!(characteristic.contains("/begin RECORD_LAYOUT __UWord_Value"));
+        assert!(!characteristic.contains("READ_WRITE"));
+        let group = &crate::group::list_groups(&characteristic).unwrap()[0];
+        assert_eq!(group.measurements, ["other"]);
+        assert_eq!(group.characteristics, ["speed"]);
+
+        let measurement = convert_variable(&characteristic, "speed", "MEASUREMENT").unwrap();
+        let variable = &A2lParser::parse_all_variables(&measurement).unwrap()[0];
+        assert_eq!(variable.var_type, "MEASUREMENT");
+        assert_eq!(variable.address.as_deref(), Some("0x1000"));
+        assert_eq!(variable.data_type, "UWORD");
+        assert_eq!(variable.lower_limit.as_deref(), Some("0"));
+        let group = &crate::group::list_groups(&measurement).unwrap()[0];
+        assert_eq!(group.measurements, ["other", "speed"]);
+        assert!(group.characteristics.is_empty());
+
+        let axis = "/begin CHARACTERISTIC c \"\" CURVE 0x0 __UWord_Value 0 NO_COMPU_METHOD 0 1\n/end CHARACTERISTIC\n";
+        assert!(convert_variable(axis, "c", "MEASUREMENT").is_err());
+    }
+}
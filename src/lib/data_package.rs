@@ -1,8 +1,106 @@
-use crate::types::{A2lEntry, A2lEntryStore};
+use crate::elf::ElfParser;
+use crate::naming::NamingPolicy;
+use crate::progress::{ProgressPhase, ProgressReporter};
+use crate::tree::EntryTree;
+use crate::types::{A2lEntry, A2lEntryStore, Endianness, EnumType, TypeInfo, Variable};
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+const SCHEMA: &str = r#"
+    CREATE TABLE IF NOT EXISTS meta (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        file_name TEXT,
+        elf_path TEXT,
+        entry_count INTEGER DEFAULT 0,
+        created_at INTEGER
+    );
+
+    CREATE TABLE IF NOT EXISTS a2l_entries (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        full_name TEXT NOT NULL,
+        address INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        a2l_type TEXT NOT NULL,
+        type_name TEXT NOT NULL,
+        bit_offset INTEGER,
+        bit_size INTEGER,
+        array_index TEXT,
+        array_dims TEXT,
+        enum_type TEXT,
+        source_file TEXT
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_a2l_entries_name ON a2l_entries(full_name);
+
+    CREATE TABLE IF NOT EXISTS settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS variables (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        address INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        type_name TEXT NOT NULL,
+        section TEXT NOT NULL,
+        type_info BLOB
+    );
+
+    CREATE TABLE IF NOT EXISTS types (
+        offset INTEGER PRIMARY KEY,
+        info BLOB NOT NULL
+    );
+"#;
+
+const NAMING_POLICY_KEY: &str = "naming_policy";
+const ELF_ENDIANNESS_KEY: &str = "elf_endianness";
+const ENDIANNESS_OVERRIDE_KEY: &str = "endianness_override";
+
+/// 写入数据包的内容。变量和类型表用于按需展开条目树，可以省略
+pub struct PackageContents<'a> {
+    pub store: &'a A2lEntryStore,
+    pub variables: &'a [Variable],
+    pub type_cache: Option<&'a HashMap<u64, TypeInfo>>,
+    pub naming: Option<&'a NamingPolicy>,
+    pub endianness: Option<Endianness>,
+}
+
+impl<'a> PackageContents<'a> {
+    pub fn new(store: &'a A2lEntryStore) -> Self {
+        Self {
+            store,
+            variables: &[],
+            type_cache: None,
+            naming: None,
+            endianness: None,
+        }
+    }
+
+    pub fn with_variables(mut self, variables: &'a [Variable]) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    pub fn with_type_cache(mut self, type_cache: &'a HashMap<u64, TypeInfo>) -> Self {
+        self.type_cache = Some(type_cache);
+        self
+    }
+
+    pub fn with_naming_policy(mut self, naming: &'a NamingPolicy) -> Self {
+        self.naming = Some(naming);
+        self
+    }
+
+    /// ELF 文件头的字节序
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = Some(endianness);
+        self
+    }
+}
+
 pub struct DataPackage {
     db: Connection,
     path: PathBuf,
@@ -34,32 +132,7 @@ impl DataPackage {
     pub fn open_path(path: &Path) -> Result<Self> {
         let db = Connection::open(path).context("无法打开数据包")?;
 
-        db.execute_batch(
-            r#"
-            CREATE TABLE IF NOT EXISTS meta (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
-                file_name TEXT,
-                elf_path TEXT,
-                entry_count INTEGER DEFAULT 0,
-                created_at INTEGER
-            );
-            
-            CREATE TABLE IF NOT EXISTS a2l_entries (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                full_name TEXT NOT NULL,
-                address INTEGER NOT NULL,
-                size INTEGER NOT NULL,
-                a2l_type TEXT NOT NULL,
-                type_name TEXT NOT NULL,
-                bit_offset INTEGER,
-                bit_size INTEGER,
-                array_index TEXT
-            );
-            
-            CREATE INDEX IF NOT EXISTS idx_a2l_entries_name ON a2l_entries(full_name);
-            "#,
-        )
-        .context("无法创建数据包表")?;
+        Self::init_schema(&db).context("无法创建数据包表")?;
 
         Ok(Self {
             db,
@@ -76,35 +149,7 @@ impl DataPackage {
 
         let db = Connection::open(&package_path).context("无法创建数据包")?;
 
-        db.execute_batch(
-            r#"
-            CREATE TABLE IF NOT EXISTS meta (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
-                file_name TEXT,
-                elf_path TEXT,
-                entry_count INTEGER DEFAULT 0,
-                created_at INTEGER
-            );
-            
-            CREATE TABLE IF NOT EXISTS a2l_entries (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                full_name TEXT NOT NULL,
-                address INTEGER NOT NULL,
-                size INTEGER NOT NULL,
-                a2l_type TEXT NOT NULL,
-                type_name TEXT NOT NULL,
-                bit_offset INTEGER,
-                bit_size INTEGER,
-                array_index TEXT
-            );
-            
-            CREATE INDEX IF NOT EXISTS idx_a2l_entries_name ON a2l_entries(full_name);
-            
-            INSERT OR REPLACE INTO meta (id, file_name, elf_path, created_at)
-            VALUES (1, ?1, ?2, ?3);
-            "#,
-        )
-        .context("无法初始化数据包")?;
+        Self::init_schema(&db).context("无法初始化数据包")?;
 
         let created_at = chrono::Utc::now().timestamp();
         db.execute(
@@ -118,6 +163,29 @@ impl DataPackage {
         })
     }
 
+    fn init_schema(db: &Connection) -> Result<()> {
+        db.execute_batch(SCHEMA)?;
+        // 旧版本数据包没有 array_dims 列
+        if !Self::has_column(db, "a2l_entries", "array_dims")? {
+            db.execute_batch("ALTER TABLE a2l_entries ADD COLUMN array_dims TEXT")?;
+        }
+        if !Self::has_column(db, "a2l_entries", "enum_type")? {
+            db.execute_batch("ALTER TABLE a2l_entries ADD COLUMN enum_type TEXT")?;
+        }
+        if !Self::has_column(db, "a2l_entries", "source_file")? {
+            db.execute_batch("ALTER TABLE a2l_entries ADD COLUMN source_file TEXT")?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn has_column(db: &Connection, table: &str, column: &str) -> Result<bool> {
+        let mut stmt = db.prepare(&format!("PRAGMA table_info({})", table))?;
+        let names = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(names.iter().any(|n| n == column))
+    }
+
     pub fn create_at(path: &Path, elf_path: &Path) -> Result<Self> {
         let file_name = elf_path
             .file_name()
@@ -126,32 +194,7 @@ impl DataPackage {
 
         let db = Connection::open(path).context("无法创建数据包")?;
 
-        db.execute_batch(
-            r#"
-            CREATE TABLE IF NOT EXISTS meta (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
-                file_name TEXT,
-                elf_path TEXT,
-                entry_count INTEGER DEFAULT 0,
-                created_at INTEGER
-            );
-            
-            CREATE TABLE IF NOT EXISTS a2l_entries (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                full_name TEXT NOT NULL,
-                address INTEGER NOT NULL,
-                size INTEGER NOT NULL,
-                a2l_type TEXT NOT NULL,
-                type_name TEXT NOT NULL,
-                bit_offset INTEGER,
-                bit_size INTEGER,
-                array_index TEXT
-            );
-            
-            CREATE INDEX IF NOT EXISTS idx_a2l_entries_name ON a2l_entries(full_name);
-            "#,
-        )
-        .context("无法初始化数据包")?;
+        Self::init_schema(&db).context("无法初始化数据包")?;
 
         let created_at = chrono::Utc::now().timestamp();
         db.execute(
@@ -165,6 +208,61 @@ impl DataPackage {
         })
     }
 
+    /// 原子地生成数据包：先写入临时文件，全部条目提交后再重命名为目标路径。
+    /// 取消或出错时删除临时文件，不会留下写了一半的 `.a2ldata`。
+    pub fn create_with_entries(
+        path: Option<&Path>,
+        elf_path: &Path,
+        contents: &PackageContents,
+        reporter: ProgressReporter,
+    ) -> Result<Self> {
+        let store = contents.store;
+        let final_path = path
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| Self::get_package_path(elf_path));
+        let tmp_path = Self::temp_path(&final_path);
+        Self::remove_temp_files(&tmp_path);
+
+        // 临时数据包的连接在此作用域结束时关闭，之后才能安全重命名
+        let written = Self::create_at(&tmp_path, elf_path)
+            .and_then(|mut pkg| {
+                pkg.save_entries_with_progress(store, reporter)?;
+                reporter.check_cancelled()?;
+                pkg.save_tree_source(contents.variables, contents.type_cache)?;
+                if let Some(naming) = contents.naming {
+                    pkg.set_naming_policy(naming)?;
+                }
+                if let Some(endianness) = contents.endianness {
+                    pkg.set_setting(ELF_ENDIANNESS_KEY, endianness.as_str())?;
+                }
+                Ok(())
+            })
+            .and_then(|_| reporter.check_cancelled());
+
+        if let Err(e) = written {
+            Self::remove_temp_files(&tmp_path);
+            return Err(e);
+        }
+
+        std::fs::rename(&tmp_path, &final_path).context("无法移动数据包到目标路径")?;
+        reporter.report(ProgressPhase::Done, store.len(), store.len(), store.len());
+
+        Self::open_path(&final_path)
+    }
+
+    fn temp_path(final_path: &Path) -> PathBuf {
+        let mut name = final_path.as_os_str().to_os_string();
+        name.push(".tmp");
+        PathBuf::from(name)
+    }
+
+    fn remove_temp_files(tmp_path: &Path) {
+        let _ = std::fs::remove_file(tmp_path);
+        let mut journal = tmp_path.as_os_str().to_os_string();
+        journal.push("-journal");
+        let _ = std::fs::remove_file(PathBuf::from(journal));
+    }
+
     pub fn path(&self) -> &Path {
         &self.path
     }
@@ -190,6 +288,17 @@ impl DataPackage {
     }
 
     pub fn save_entries(&mut self, store: &A2lEntryStore) -> Result<()> {
+        self.save_entries_with_progress(store, ProgressReporter::new())
+    }
+
+    /// 保存条目，取消时事务回滚，已有内容保持不变
+    pub fn save_entries_with_progress(
+        &mut self,
+        store: &A2lEntryStore,
+        reporter: ProgressReporter,
+    ) -> Result<()> {
+        const REPORT_INTERVAL: usize = 4096;
+
         let entry_count = store.len();
 
         let tx = self.db.transaction().context("无法开始事务")?;
@@ -201,17 +310,30 @@ impl DataPackage {
             let mut stmt = tx
                 .prepare(
                     "INSERT INTO a2l_entries 
-                     (full_name, address, size, a2l_type, type_name, bit_offset, bit_size, array_index)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                     (full_name, address, size, a2l_type, type_name, bit_offset, bit_size, array_index, array_dims, enum_type, source_file)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
                 )
                 .context("无法准备插入语句")?;
 
-            for entry in &store.entries {
+            for (i, entry) in store.entries.iter().enumerate() {
+                if i % REPORT_INTERVAL == 0 {
+                    reporter.check_cancelled()?;
+                    reporter.report(ProgressPhase::Save, i, entry_count, entry_count);
+                }
+
                 let array_index_str = entry
                     .array_index
                     .as_ref()
                     .filter(|v| !v.is_empty())
                     .map(|v| serde_json::to_string(v).unwrap_or_default());
+                let array_dims_str = entry
+                    .array_dims
+                    .as_ref()
+                    .map(|v| serde_json::to_string(v).unwrap_or_default());
+                let enum_type_str = entry
+                    .enum_type
+                    .as_ref()
+                    .map(|v| serde_json::to_string(v).unwrap_or_default());
 
                 stmt.execute(params![
                     entry.full_name,
@@ -222,6 +344,9 @@ impl DataPackage {
                     entry.bit_offset,
                     entry.bit_size,
                     array_index_str,
+                    array_dims_str,
+                    enum_type_str,
+                    entry.source_file,
                 ])
                 .context("无法插入条目")?;
             }
@@ -232,14 +357,16 @@ impl DataPackage {
             params![entry_count as i64],
         )?;
 
+        reporter.check_cancelled()?;
         tx.commit().context("无法提交事务")?;
+        reporter.report(ProgressPhase::Save, entry_count, entry_count, entry_count);
 
         Ok(())
     }
 
     pub fn load_entries(&self) -> Result<A2lEntryStore> {
         let mut stmt = self.db.prepare(
-            "SELECT full_name, address, size, a2l_type, type_name, bit_offset, bit_size, array_index 
+            "SELECT full_name, address, size, a2l_type, type_name, bit_offset, bit_size, array_index, array_dims, enum_type, source_file 
              FROM a2l_entries ORDER BY full_name"
         ).context("无法准备查询")?;
 
@@ -253,9 +380,14 @@ impl DataPackage {
                 let bit_offset: Option<usize> = row.get(5)?;
                 let bit_size: Option<usize> = row.get(6)?;
                 let array_index_str: Option<String> = row.get(7)?;
+                let array_dims_str: Option<String> = row.get(8)?;
+                let enum_type_str: Option<String> = row.get(9)?;
+                let source_file: Option<String> = row.get(10)?;
 
                 let array_index =
                     array_index_str.and_then(|s| serde_json::from_str::<Vec<usize>>(&s).ok());
+                let array_dims =
+                    array_dims_str.and_then(|s| serde_json::from_str::<Vec<usize>>(&s).ok());
 
                 let mut entry = A2lEntry::new(full_name, address, size, a2l_type, type_name);
                 if let (Some(bo), Some(bs)) = (bit_offset, bit_size) {
@@ -266,6 +398,14 @@ impl DataPackage {
                         entry = entry.with_array_index(idx);
                     }
                 }
+                if let Some(dims) = array_dims {
+                    entry = entry.with_array_dims(dims);
+                }
+                entry = entry
+                    .with_enum_type(
+                        enum_type_str.and_then(|s| serde_json::from_str::<EnumType>(&s).ok()),
+                    )
+                    .with_source_file(source_file);
 
                 Ok(entry)
             })
@@ -291,4 +431,306 @@ impl DataPackage {
 
         Ok(count as usize)
     }
+
+    /// 保存条目树所需的变量和类型表
+    pub fn save_tree_source(
+        &mut self,
+        variables: &[Variable],
+        type_cache: Option<&HashMap<u64, TypeInfo>>,
+    ) -> Result<()> {
+        let tx = self.db.transaction().context("无法开始事务")?;
+
+        tx.execute("DELETE FROM variables", [])
+            .context("无法清除旧变量")?;
+        tx.execute("DELETE FROM types", [])
+            .context("无法清除旧类型")?;
+
+        {
+            let mut stmt = tx
+                .prepare(
+                    "INSERT INTO variables (name, address, size, type_name, section, type_info)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )
+                .context("无法准备变量插入语句")?;
+
+            for var in variables {
+                let type_info_blob = var
+                    .type_info
+                    .as_ref()
+                    .and_then(|t| bincode::serialize(t).ok());
+
+                stmt.execute(params![
+                    var.name,
+                    var.address,
+                    var.size,
+                    var.type_name,
+                    var.section,
+                    type_info_blob,
+                ])
+                .context("无法插入变量")?;
+            }
+        }
+
+        if let Some(type_cache) = type_cache {
+            let mut stmt = tx
+                .prepare("INSERT INTO types (offset, info) VALUES (?1, ?2)")
+                .context("无法准备类型插入语句")?;
+
+            for (offset, info) in type_cache {
+                let blob = bincode::serialize(info).context("无法序列化类型")?;
+                stmt.execute(params![*offset as i64, blob])
+                    .context("无法插入类型")?;
+            }
+        }
+
+        tx.commit().context("无法提交事务")?;
+
+        Ok(())
+    }
+
+    /// 旧版本数据包没有保存变量，无法构建条目树
+    pub fn has_tree_source(&self) -> bool {
+        self.db
+            .query_row("SELECT COUNT(*) FROM variables", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|n| n > 0)
+            .unwrap_or(false)
+    }
+
+    pub fn load_variables(&self) -> Result<Vec<Variable>> {
+        let mut stmt = self
+            .db
+            .prepare("SELECT name, address, size, type_name, section, type_info FROM variables")
+            .context("无法准备变量查询")?;
+
+        let variables = stmt
+            .query_map([], |row| {
+                let type_info_blob: Option<Vec<u8>> = row.get(5)?;
+                let mut var = Variable::new(
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                );
+                var.type_info = type_info_blob.and_then(|b| bincode::deserialize(&b).ok());
+                Ok(var)
+            })
+            .context("无法查询变量")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("无法解析变量")?;
+
+        Ok(variables)
+    }
+
+    pub fn load_type_cache(&self) -> Result<HashMap<u64, TypeInfo>> {
+        let mut stmt = self
+            .db
+            .prepare("SELECT offset, info FROM types")
+            .context("无法准备类型查询")?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)? as u64, row.get::<_, Vec<u8>>(1)?))
+            })
+            .context("无法查询类型")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("无法读取类型")?;
+
+        let mut type_cache = HashMap::with_capacity(rows.len());
+        for (offset, blob) in rows {
+            if let Ok(info) = bincode::deserialize::<TypeInfo>(&blob) {
+                type_cache.insert(offset, info);
+            }
+        }
+
+        Ok(type_cache)
+    }
+
+    pub fn load_tree(&self) -> Result<EntryTree> {
+        if !self.has_tree_source() {
+            anyhow::bail!("数据包未包含变量信息，请重新生成数据包");
+        }
+        Ok(
+            EntryTree::new(self.load_variables()?, self.load_type_cache()?)
+                .with_naming_policy(self.naming_policy()?),
+        )
+    }
+
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let value = self
+            .db
+            .query_row(
+                "SELECT value FROM settings WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("无法读取数据包设置")?;
+        Ok(value)
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.db
+            .execute(
+                "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )
+            .context("无法写入数据包设置")?;
+        Ok(())
+    }
+
+    /// 生成条目时使用的命名策略，旧数据包没有记录时返回默认策略
+    pub fn naming_policy(&self) -> Result<NamingPolicy> {
+        match self.get_setting(NAMING_POLICY_KEY)? {
+            Some(json) => NamingPolicy::from_json(&json).context("无法解析命名策略"),
+            None => Ok(NamingPolicy::default()),
+        }
+    }
+
+    pub fn set_naming_policy(&self, policy: &NamingPolicy) -> Result<()> {
+        self.set_setting(NAMING_POLICY_KEY, &policy.to_json())
+    }
+
+    /// 生成 A2L 时使用的字节序：手动覆盖优先，其次是生成时记录的 ELF 字节序，
+    /// 旧数据包没有记录时读取 ELF 文件头，ELF 不可用时按小端
+    pub fn endianness(&self) -> Result<Endianness> {
+        if let Some(endianness) = self.endianness_override()? {
+            return Ok(endianness);
+        }
+        if let Some(value) = self.get_setting(ELF_ENDIANNESS_KEY)? {
+            return value.parse();
+        }
+        let elf_path = self.get_meta()?.elf_path.map(PathBuf::from);
+        Ok(elf_path
+            .filter(|p| p.exists())
+            .and_then(|p| ElfParser::read_endianness(&p).ok())
+            .unwrap_or_default())
+    }
+
+    pub fn endianness_override(&self) -> Result<Option<Endianness>> {
+        self.get_setting(ENDIANNESS_OVERRIDE_KEY)?
+            .map(|value| value.parse())
+            .transpose()
+    }
+
+    /// 设置或清除（`None`）手动指定的字节序
+    pub fn set_endianness_override(&self, endianness: Option<Endianness>) -> Result<()> {
+        match endianness {
+            Some(endianness) => self.set_setting(ENDIANNESS_OVERRIDE_KEY, endianness.as_str()),
+            None => {
+                self.db
+                    .execute(
+                        "DELETE FROM settings WHERE key = ?1",
+                        params![ENDIANNESS_OVERRIDE_KEY],
+                    )
+                    .context("无法写入数据包设置")?;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::{is_cancelled_error, CancelToken, Progress};
+    use std::sync::{Arc, Mutex};
+
+    /// 条目数超过两个上报间隔，保存阶段会多次上报和检查取消
+    fn store() -> A2lEntryStore {
+        let mut store = A2lEntryStore::new();
+        for i in 0..10_000u64 {
+            store.add(A2lEntry::new(
+                format!("var_{}", i),
+                0x1000 + i * 2,
+                2,
+                "UWORD".to_string(),
+                "uint16_t".to_string(),
+            ));
+        }
+        store
+    }
+
+    fn package_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("a2l_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_cancel_leaves_no_files() {
+        let dir = package_dir("package_cancel");
+        let elf_path = dir.join("app.elf");
+        let final_path = DataPackage::get_package_path(&elf_path);
+        let tmp_path = DataPackage::temp_path(&final_path);
+
+        let token = CancelToken::new();
+        let cancel = token.clone();
+        let callback = move |progress: &Progress| {
+            if progress.phase == ProgressPhase::Save && progress.processed > 0 {
+                cancel.cancel();
+            }
+        };
+        let reporter = ProgressReporter::new()
+            .with_callback(&callback)
+            .with_cancel(&token);
+
+        let store = store();
+        let err = DataPackage::create_with_entries(
+            None,
+            &elf_path,
+            &PackageContents::new(&store),
+            reporter,
+        )
+        .err()
+        .unwrap();
+        assert!(is_cancelled_error(&err));
+        assert!(token.is_cancelled());
+        assert!(!tmp_path.exists());
+        assert!(!final_path.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_progress_is_monotonic() {
+        let dir = package_dir("package_progress");
+        let elf_path = dir.join("app.elf");
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let log = reports.clone();
+        let callback = move |progress: &Progress| log.lock().unwrap().push(progress.clone());
+        let store = store();
+        let package = DataPackage::create_with_entries(
+            None,
+            &elf_path,
+            &PackageContents::new(&store),
+            ProgressReporter::new().with_callback(&callback),
+        )
+        .unwrap();
+        assert_eq!(package.get_meta().unwrap().entry_count, store.len());
+        drop(package);
+
+        let reports = reports.lock().unwrap().clone();
+        let phases: Vec<ProgressPhase> = reports.iter().map(|p| p.phase).collect();
+        assert_eq!(phases.first(), Some(&ProgressPhase::Save));
+        assert_eq!(phases.last(), Some(&ProgressPhase::Done));
+        // 阶段不会回退，同一阶段内已处理数单调递增且不超过总数
+        for pair in reports.windows(2) {
+            assert!(pair[0].phase as u8 <= pair[1].phase as u8);
+            if pair[0].phase == pair[1].phase {
+                assert!(pair[0].processed < pair[1].processed);
+            }
+        }
+        for progress in &reports {
+            assert!(progress.processed <= progress.total);
+            assert_eq!(progress.total, store.len());
+        }
+        let saves = phases.iter().filter(|&&p| p == ProgressPhase::Save).count();
+        assert_eq!(saves, 4);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,407 @@
+use crate::a2l::{symbol_link, variable_blocks, A2lParser};
+use crate::a2l_syntax::A2lDocument;
+use crate::group::{list_groups, A2lGroup};
+use crate::validate::parse_address;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// 参与比较的字段，按输出顺序
+const FIELDS: &[&str] = &[
+    "kind",
+    "address",
+    "data_type",
+    "lower_limit",
+    "upper_limit",
+    "conversion",
+    "bit_mask",
+    "groups",
+];
+
+/// 按数值比较的字段，`255` 与 `255.0`、`0x10` 与 `0x0010` 视为相同
+const NUMERIC_FIELDS: &[&str] = &["address", "lower_limit", "upper_limit", "bit_mask"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// 只存在于一侧的对象
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffObject {
+    pub name: String,
+    /// MEASUREMENT / CHARACTERISTIC / AXIS_PTS
+    pub keyword: String,
+    pub address: Option<String>,
+}
+
+/// 两侧都存在的对象，改名时 `old_name` 与 `name` 不同
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ObjectChange {
+    pub old_name: String,
+    pub name: String,
+    pub keyword: String,
+    pub changes: Vec<FieldChange>,
+}
+
+impl ObjectChange {
+    pub fn is_rename(&self) -> bool {
+        self.old_name != self.name
+    }
+}
+
+/// 两个 A2L 文件按对象比较的结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct A2lDiff {
+    pub added: Vec<DiffObject>,
+    pub removed: Vec<DiffObject>,
+    /// 按地址或 SYMBOL_LINK 匹配到的改名对象
+    pub renamed: Vec<ObjectChange>,
+    /// 同名且有字段变化的对象
+    pub changed: Vec<ObjectChange>,
+}
+
+impl A2lDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+impl std::fmt::Display for A2lDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+        let write_changes = |f: &mut std::fmt::Formatter<'_>, changes: &[FieldChange]| {
+            for change in changes {
+                writeln!(
+                    f,
+                    "    {}: {} -> {}",
+                    change.field,
+                    value(&change.old),
+                    value(&change.new)
+                )?;
+            }
+            Ok(())
+        };
+
+        for object in &self.added {
+            writeln!(
+                f,
+                "+ {} {} {}",
+                object.keyword,
+                object.name,
+                value(&object.address)
+            )?;
+        }
+        for object in &self.removed {
+            writeln!(
+                f,
+                "- {} {} {}",
+                object.keyword,
+                object.name,
+                value(&object.address)
+            )?;
+        }
+        for object in &self.renamed {
+            writeln!(
+                f,
+                "R {} {} -> {}",
+                object.keyword, object.old_name, object.name
+            )?;
+            write_changes(f, &object.changes)?;
+        }
+        for object in &self.changed {
+            writeln!(f, "M {} {}", object.keyword, object.name)?;
+            write_changes(f, &object.changes)?;
+        }
+        write!(
+            f,
+            "新增 {}，删除 {}，改名 {}，修改 {}",
+            self.added.len(),
+            self.removed.len(),
+            self.renamed.len(),
+            self.changed.len()
+        )
+    }
+}
+
+/// 一侧文件中的变量对象
+struct Snapshot {
+    name: String,
+    keyword: String,
+    symbol: Option<String>,
+    fields: BTreeMap<&'static str, String>,
+}
+
+impl Snapshot {
+    fn field(&self, field: &str) -> Option<&String> {
+        self.fields.get(field)
+    }
+
+    fn object(&self) -> DiffObject {
+        DiffObject {
+            name: self.name.clone(),
+            keyword: self.keyword.clone(),
+            address: self.field("address").cloned(),
+        }
+    }
+
+    /// 用于改名匹配的地址键，位域按 BIT_MASK 区分
+    fn address_key(&self) -> Option<(String, u64, Option<u64>)> {
+        let address = self.field("address").and_then(|a| parse_address(a))?;
+        let mask = self.field("bit_mask").and_then(|m| parse_address(m));
+        Some((self.keyword.clone(), address, mask))
+    }
+}
+
+fn snapshots(content: &str) -> Result<Vec<Snapshot>> {
+    let doc = A2lDocument::parse(content)?;
+    let memberships = group_memberships(&list_groups(content)?);
+
+    Ok(variable_blocks(&doc)
+        .map(|block| {
+            let variable = A2lParser::parse_variable_block(&doc, block);
+            let mut fields = BTreeMap::new();
+            let kind = match variable.kind {
+                Some(kind) => format!("{} {}", block.keyword(), kind),
+                None => block.keyword().to_string(),
+            };
+            fields.insert("kind", kind);
+            for (field, value) in [
+                ("address", variable.address),
+                ("data_type", Some(variable.data_type)),
+                ("lower_limit", variable.lower_limit),
+                ("upper_limit", variable.upper_limit),
+                ("conversion", variable.compu_method),
+                ("bit_mask", variable.bit_mask),
+                (
+                    "groups",
+                    memberships.get(&variable.name).map(|g| g.join(", ")),
+                ),
+            ] {
+                if let Some(value) = value.filter(|v| !v.is_empty()) {
+                    fields.insert(field, value);
+                }
+            }
+            Snapshot {
+                symbol: symbol_link(block).map(|(symbol, _)| symbol),
+                name: variable.name,
+                keyword: block.keyword().to_string(),
+                fields,
+            }
+        })
+        .collect())
+}
+
+/// 变量名到其所在分组，分组按名称排序
+fn group_memberships(groups: &[A2lGroup]) -> HashMap<String, Vec<String>> {
+    let mut memberships: HashMap<String, Vec<String>> = HashMap::new();
+    for group in groups {
+        for name in group.measurements.iter().chain(&group.characteristics) {
+            memberships.entry(name.clone()).or_default().push(format!(
+                "{} {}",
+                group.kind.keyword(),
+                group.name
+            ));
+        }
+    }
+    for names in memberships.values_mut() {
+        names.sort();
+        names.dedup();
+    }
+    memberships
+}
+
+fn same_value(field: &str, old: &str, new: &str) -> bool {
+    if old == new {
+        return true;
+    }
+    if !NUMERIC_FIELDS.contains(&field) {
+        return false;
+    }
+    let number = |text: &str| {
+        parse_address(text)
+            .map(|v| v as f64)
+            .or_else(|| text.parse::<f64>().ok())
+    };
+    matches!((number(old), number(new)), (Some(a), Some(b)) if a == b)
+}
+
+fn compare(old: &Snapshot, new: &Snapshot) -> ObjectChange {
+    let changes = FIELDS
+        .iter()
+        .filter_map(|&field| {
+            let (a, b) = (old.field(field), new.field(field));
+            let same = match (a, b) {
+                (Some(a), Some(b)) => same_value(field, a, b),
+                (None, None) => true,
+                _ => false,
+            };
+            (!same).then(|| FieldChange {
+                field: field.to_string(),
+                old: a.cloned(),
+                new: b.cloned(),
+            })
+        })
+        .collect();
+    ObjectChange {
+        old_name: old.name.clone(),
+        name: new.name.clone(),
+        keyword: new.keyword.clone(),
+        changes,
+    }
+}
+
+/// 比较两个 A2L 文件中的 MEASUREMENT、CHARACTERISTIC 与 AXIS_PTS。
+/// 先按名称匹配，剩余对象按 SYMBOL_LINK、再按地址（位域含 BIT_MASK）唯一匹配为改名
+pub fn diff_a2l(old: &str, new: &str) -> Result<A2lDiff> {
+    let old = snapshots(old)?;
+    let new = snapshots(new)?;
+
+    let new_by_name: HashMap<&str, usize> = new
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.name.as_str(), i))
+        .collect();
+    let mut matched_new: HashSet<usize> = HashSet::new();
+    let mut diff = A2lDiff::default();
+    let mut unmatched_old: Vec<&Snapshot> = Vec::new();
+
+    for snapshot in &old {
+        match new_by_name.get(snapshot.name.as_str()) {
+            Some(&i) if matched_new.insert(i) => {
+                let change = compare(snapshot, &new[i]);
+                if !change.changes.is_empty() {
+                    diff.changed.push(change);
+                }
+            }
+            _ => unmatched_old.push(snapshot),
+        }
+    }
+
+    let remaining: Vec<usize> = (0..new.len())
+        .filter(|i| !matched_new.contains(i))
+        .collect();
+    let mut by_symbol: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut by_address: HashMap<(String, u64, Option<u64>), Vec<usize>> = HashMap::new();
+    for &i in &remaining {
+        if let Some(symbol) = &new[i].symbol {
+            by_symbol.entry(symbol).or_default().push(i);
+        }
+        if let Some(key) = new[i].address_key() {
+            by_address.entry(key).or_default().push(i);
+        }
+    }
+
+    for snapshot in unmatched_old {
+        let unique = |candidates: Option<&Vec<usize>>| match candidates.map(|c| c.as_slice()) {
+            Some(&[i]) => Some(i),
+            _ => None,
+        };
+        let candidate = snapshot
+            .symbol
+            .as_deref()
+            .and_then(|s| unique(by_symbol.get(s)))
+            .or_else(|| unique(by_address.get(&snapshot.address_key()?)))
+            .filter(|i| !matched_new.contains(i));
+        match candidate {
+            Some(i) => {
+                matched_new.insert(i);
+                diff.renamed.push(compare(snapshot, &new[i]));
+            }
+            None => diff.removed.push(snapshot.object()),
+        }
+    }
+
+    diff.added = remaining
+        .into_iter()
+        .filter(|i| !matched_new.contains(i))
+        .map(|i| new[i].object())
+        .collect();
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "\
+/begin PROJECT P \"\"
+  /begin MODULE M \"\"
+    /begin MEASUREMENT speed \"\"
+      UWORD NO_COMPU_METHOD 0 0 0 65535
+      ECU_ADDRESS 0x1000
+    /end MEASUREMENT
+    /begin MEASUREMENT rpm \"\"
+      UWORD NO_COMPU_METHOD 0 0 0 8000
+      ECU_ADDRESS 0x1002
+      SYMBOL_LINK \"engine.rpm\" 0
+    /end MEASUREMENT
+    /begin MEASUREMENT temp \"\"
+      SBYTE NO_COMPU_METHOD 0 0 -40 120
+      ECU_ADDRESS 0x1004
+    /end MEASUREMENT
+    /begin MEASUREMENT old \"\"
+      UBYTE NO_COMPU_METHOD 0 0 0 255
+      ECU_ADDRESS 0x2000
+    /end MEASUREMENT
+  /end MODULE
+/end PROJECT
+";
+
+    const NEW: &str = "\
+/begin PROJECT P \"\"
+  /begin MODULE M \"\"
+    /begin MEASUREMENT speed \"\"
+      UWORD NO_COMPU_METHOD 0 0 0.0 65535.0
+      ECU_ADDRESS 0x00001010
+    /end MEASUREMENT
+    /begin MEASUREMENT engine_rpm \"\"
+      UWORD NO_COMPU_METHOD 0 0 0 8000
+      ECU_ADDRESS 0x1012
+      SYMBOL_LINK \"engine.rpm\" 0
+    /end MEASUREMENT
+    /begin MEASUREMENT temperature \"\"
+      SWORD NO_COMPU_METHOD 0 0 -40 120
+      ECU_ADDRESS 0x1004
+    /end MEASUREMENT
+    /begin MEASUREMENT fresh \"\"
+      UBYTE NO_COMPU_METHOD 0 0 0 255
+      ECU_ADDRESS 0x3000
+    /end MEASUREMENT
+    /begin GROUP G \"\"
+      /begin REF_MEASUREMENT speed /end REF_MEASUREMENT
+    /end GROUP
+  /end MODULE
+/end PROJECT
+";
+
+    #[test]
+    fn test_diff_a2l() {
+        let diff = diff_a2l(OLD, NEW).unwrap();
+
+        let fields: Vec<&str> = diff.changed[0]
+            .changes
+            .iter()
+            .map(|c| c.field.as_str())
+            .collect();
+        assert_eq!(diff.changed[0].name, "speed");
+        assert_eq!(fields, ["address", "groups"]);
+
+        let renames: Vec<(&str, &str)> = diff
+            .renamed
+            .iter()
+            .map(|r| (r.old_name.as_str(), r.name.as_str()))
+            .collect();
+        assert_eq!(renames, [("rpm", "engine_rpm"), ("temp", "temperature")]);
+        assert_eq!(diff.renamed[1].changes[0].field, "data_type");
+
+        assert_eq!(diff.removed[0].name, "old");
+        assert_eq!(diff.added[0].name, "fresh");
+        assert!(diff_a2l(OLD, OLD).unwrap().is_empty());
+    }
+}
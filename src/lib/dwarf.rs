@@ -1,3 +1,5 @@
+use crate::progress::{ProgressPhase, ProgressReporter};
+use crate::type_rules::{default_type_rules, TypeGuessRecord, TypeGuessReport, TypeRules};
 use crate::types::{StructMember, TypeEncoding, TypeInfo, TypeKind, Variable};
 use anyhow::{Context, Result};
 use gimli::{EndianSlice, LittleEndian};
@@ -10,6 +12,11 @@ pub struct DwarfParser {
     type_cache: HashMap<u64, TypeInfo>,
     struct_map: HashMap<String, TypeInfo>,
     variable_types: HashMap<String, u64>,
+    /// 变量所在编译单元的源文件名（不含目录）
+    variable_files: HashMap<String, String>,
+    /// 编译单元名可能存放在 .debug_str / .debug_line_str 中
+    debug_str: gimli::DebugStr<DwarfReader>,
+    debug_line_str: gimli::DebugLineStr<DwarfReader>,
     array_elem_offsets: HashMap<u64, u64>,
     type_refs: HashMap<u64, u64>,
     stats: DwarfStats,
@@ -29,12 +36,21 @@ pub struct DwarfStats {
     pub enum_values: usize,
 }
 
+impl Default for DwarfParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DwarfParser {
     pub fn new() -> Self {
         Self {
             type_cache: HashMap::new(),
             struct_map: HashMap::new(),
             variable_types: HashMap::new(),
+            variable_files: HashMap::new(),
+            debug_str: gimli::DebugStr::default(),
+            debug_line_str: gimli::DebugLineStr::default(),
             array_elem_offsets: HashMap::new(),
             type_refs: HashMap::new(),
             stats: DwarfStats::default(),
@@ -42,6 +58,10 @@ impl DwarfParser {
     }
 
     pub fn parse(elf_data: &[u8]) -> Result<Self> {
+        Self::parse_with_progress(elf_data, ProgressReporter::new())
+    }
+
+    pub fn parse_with_progress(elf_data: &[u8], reporter: ProgressReporter) -> Result<Self> {
         let mut parser = Self::new();
 
         let obj = object::File::parse(elf_data).context("无法解析 ELF 文件")?;
@@ -64,7 +84,15 @@ impl DwarfParser {
         let debug_info: DwarfReader = EndianSlice::new(debug_info_data, endian);
         let debug_abbrev: DwarfReader = EndianSlice::new(debug_abbrev_data, endian);
 
-        parser.parse_dwarf_sections(debug_info, debug_abbrev)?;
+        if let Some(section) = obj.section_by_name(".debug_str") {
+            parser.debug_str = EndianSlice::new(Self::get_section_bytes(&section), endian).into();
+        }
+        if let Some(section) = obj.section_by_name(".debug_line_str") {
+            parser.debug_line_str =
+                EndianSlice::new(Self::get_section_bytes(&section), endian).into();
+        }
+
+        parser.parse_dwarf_sections(debug_info, debug_abbrev, reporter)?;
 
         Ok(parser)
     }
@@ -81,10 +109,7 @@ impl DwarfParser {
 
     fn get_section_bytes(section: &object::Section) -> &'static [u8] {
         match section.data() {
-            Ok(data) => {
-                let slice = data.as_ref();
-                unsafe { std::slice::from_raw_parts(slice.as_ptr(), slice.len()) }
-            }
+            Ok(data) => unsafe { std::slice::from_raw_parts(data.as_ptr(), data.len()) },
             Err(_) => &[],
         }
     }
@@ -93,19 +118,30 @@ impl DwarfParser {
         &mut self,
         debug_info: DwarfReader,
         debug_abbrev: DwarfReader,
+        reporter: ProgressReporter,
     ) -> Result<()> {
         let debug_info = gimli::DebugInfo::from(debug_info);
         let debug_abbrev = gimli::DebugAbbrev::from(debug_abbrev);
 
+        // 先统计单元数，仅读取单元头，开销很小
+        let mut total_units = 0;
+        let mut count_iter = debug_info.units();
+        while let Ok(Some(_)) = count_iter.next() {
+            total_units += 1;
+        }
+
         let mut iter = debug_info.units();
+        let mut processed_units = 0;
 
         while let Some(header) = iter.next().context("遍历 DWARF 单元失败")? {
-            match header.abbreviations(&debug_abbrev) {
-                Ok(abbrevs) => {
-                    self.parse_unit_types(&header, &abbrevs)?;
-                }
-                Err(_) => continue,
+            reporter.check_cancelled()?;
+
+            if let Ok(abbrevs) = header.abbreviations(&debug_abbrev) {
+                self.parse_unit_types(&header, &abbrevs)?;
             }
+
+            processed_units += 1;
+            reporter.report(ProgressPhase::Dwarf, processed_units, total_units, 0);
         }
 
         self.resolve_all_member_types();
@@ -196,11 +232,15 @@ impl DwarfParser {
             .unwrap_or(0);
 
         let mut cursor = header.entries(abbrevs);
+        let mut unit_file: Option<String> = None;
 
         while let Some((_, entry)) = cursor.next_dfs().context("遍历 DIE 失败")? {
             let global_offset = unit_offset + entry.offset().0;
 
             match entry.tag() {
+                gimli::constants::DW_TAG_compile_unit => {
+                    unit_file = self.unit_file_name(entry);
+                }
                 gimli::constants::DW_TAG_base_type => {
                     self.parse_base_type_with_offset(entry, global_offset);
                 }
@@ -229,7 +269,7 @@ impl DwarfParser {
                     self.parse_volatile_type_with_offset(entry, global_offset);
                 }
                 gimli::constants::DW_TAG_variable => {
-                    self.parse_variable(entry);
+                    self.parse_variable(entry, unit_file.as_deref());
                 }
                 gimli::constants::DW_TAG_member => {
                     self.stats.struct_members += 1;
@@ -483,13 +523,13 @@ impl DwarfParser {
 
         let variants = Self::parse_enum_variants(header, abbrevs, entry);
 
-        if let Some(type_name) = name {
-            let mut type_info =
-                TypeInfo::enum_type(type_name, size, encoding, variants, global_offset as u64);
-            type_info.offset = global_offset as u64;
-            self.type_cache.insert(global_offset as u64, type_info);
-            self.stats.enums += 1;
-        }
+        // 匿名枚举同样缓存，`typedef enum { ... } T` 解析 typedef 时才能拿到枚举值
+        let type_name = name.unwrap_or_else(|| format!("<anonymous@0x{:x}>", global_offset));
+        let mut type_info =
+            TypeInfo::enum_type(type_name, size, encoding, variants, global_offset as u64);
+        type_info.offset = global_offset as u64;
+        self.type_cache.insert(global_offset as u64, type_info);
+        self.stats.enums += 1;
     }
 
     fn parse_enum_variants(
@@ -710,8 +750,7 @@ impl DwarfParser {
             type_info.offset = global_offset as u64;
 
             if target_offset > 0 {
-                self.type_refs
-                    .insert(global_offset as u64, target_offset as u64);
+                self.type_refs.insert(global_offset as u64, target_offset);
             }
 
             self.type_cache.insert(global_offset as u64, type_info);
@@ -732,8 +771,7 @@ impl DwarfParser {
         type_info.offset = global_offset as u64;
 
         if target_offset > 0 {
-            self.type_refs
-                .insert(global_offset as u64, target_offset as u64);
+            self.type_refs.insert(global_offset as u64, target_offset);
         }
 
         self.type_cache.insert(global_offset as u64, type_info);
@@ -752,17 +790,35 @@ impl DwarfParser {
         type_info.offset = global_offset as u64;
 
         if target_offset > 0 {
-            self.type_refs
-                .insert(global_offset as u64, target_offset as u64);
+            self.type_refs.insert(global_offset as u64, target_offset);
         }
 
         self.type_cache.insert(global_offset as u64, type_info);
     }
 
-    fn parse_variable(&mut self, entry: &gimli::DebuggingInformationEntry<DwarfReader>) {
+    fn parse_variable(
+        &mut self,
+        entry: &gimli::DebuggingInformationEntry<DwarfReader>,
+        unit_file: Option<&str>,
+    ) {
         self.stats.variables += 1;
 
         if let Some(name) = Self::get_name_static(entry) {
+            if let Some(file) = unit_file {
+                // extern 声明出现在引用它的每个单元中，以定义所在的单元为准
+                let is_declaration = entry
+                    .attr(gimli::constants::DW_AT_declaration)
+                    .ok()
+                    .flatten()
+                    .is_some();
+                if is_declaration {
+                    self.variable_files
+                        .entry(name.clone())
+                        .or_insert_with(|| file.to_string());
+                } else {
+                    self.variable_files.insert(name.clone(), file.to_string());
+                }
+            }
             let type_offset = Self::get_type_offset_static(entry);
             if type_offset > 0 {
                 self.variable_types.insert(name, type_offset);
@@ -770,6 +826,33 @@ impl DwarfParser {
         }
     }
 
+    /// 编译单元的源文件名，去掉目录部分
+    fn unit_file_name(
+        &self,
+        entry: &gimli::DebuggingInformationEntry<DwarfReader>,
+    ) -> Option<String> {
+        let value = entry
+            .attr_value(gimli::constants::DW_AT_name)
+            .ok()
+            .flatten()?;
+        let name = match value {
+            gimli::AttributeValue::String(s) => s,
+            gimli::AttributeValue::DebugStrRef(offset) => self.debug_str.get_str(offset).ok()?,
+            gimli::AttributeValue::DebugLineStrRef(offset) => {
+                self.debug_line_str.get_str(offset).ok()?
+            }
+            _ => return None,
+        };
+        let path = String::from_utf8_lossy(&name).to_string();
+        let file = path.rsplit(['/', '\\']).next().unwrap_or(&path);
+        (!file.is_empty()).then(|| file.to_string())
+    }
+
+    /// 变量定义所在的源文件名
+    pub fn variable_file(&self, name: &str) -> Option<&str> {
+        self.variable_files.get(name).map(|s| s.as_str())
+    }
+
     pub fn get_variable_count(&self) -> usize {
         self.variable_types.len()
     }
@@ -1086,16 +1169,15 @@ impl DwarfParser {
     pub fn list_variables_with_types(&self) -> Vec<(String, String)> {
         self.variable_types
             .iter()
-            .filter_map(|(name, &type_offset)| {
-                if let Some(type_info) = self.type_cache.get(&type_offset) {
-                    Some((name.clone(), type_info.name.clone()))
-                } else {
-                    Some((
+            .map(
+                |(name, &type_offset)| match self.type_cache.get(&type_offset) {
+                    Some(type_info) => (name.clone(), type_info.name.clone()),
+                    None => (
                         name.clone(),
                         format!("unknown (offset: 0x{:x})", type_offset),
-                    ))
-                }
-            })
+                    ),
+                },
+            )
             .collect()
     }
 
@@ -1113,6 +1195,39 @@ impl DwarfParser {
             .collect()
     }
 
+    /// 为变量填充 DWARF 类型信息，找不到时按规则表推断，返回所有经推断的变量。
+    /// 没有规则命中时类型信息保持为空，并在报告中标记为未知
+    pub fn apply_to_variables(
+        &self,
+        variables: &mut [Variable],
+        rules: &TypeRules,
+    ) -> TypeGuessReport {
+        let mut report = TypeGuessReport::new();
+
+        for var in variables.iter_mut() {
+            var.source_file = self.variable_file(&var.name).map(|s| s.to_string());
+            if let Some(&type_offset) = self.variable_types.get(&var.name) {
+                if let Some(type_info) = self.type_cache.get(&type_offset) {
+                    var.type_info = Some(type_info.clone());
+                    continue;
+                }
+            }
+
+            // 符号表中的类型名由大小合成，不参与推断
+            let guess = rules.infer(None, &var.name, var.size);
+            var.type_info = guess.as_ref().map(|g| g.to_type_info(var.size));
+            report.push(TypeGuessRecord {
+                variable: var.name.clone(),
+                address: var.address,
+                size: var.size,
+                a2l_type: guess.as_ref().map(|g| g.a2l_type.to_string()),
+                rule: guess.map(|g| g.pattern),
+            });
+        }
+
+        report
+    }
+
     pub fn debug_type_resolution(&self) -> (usize, usize, usize) {
         let total = self.variable_types.len();
         let mut resolved = 0;
@@ -1132,100 +1247,6 @@ impl DwarfParser {
 
 pub fn analyze_variables_with_dwarf(variables: &mut [Variable], elf_data: &[u8]) -> Result<bool> {
     let parser = DwarfParser::parse(elf_data)?;
-
-    let has_dwarf = parser.has_dwarf_info();
-
-    for var in variables.iter_mut() {
-        if let Some(&type_offset) = parser.variable_types.get(&var.name) {
-            if let Some(type_info) = parser.type_cache.get(&type_offset) {
-                var.type_info = Some(type_info.clone());
-                continue;
-            }
-        }
-
-        let type_info = infer_type_from_name(&var.name, var.size);
-        var.type_info = Some(type_info);
-    }
-
-    Ok(has_dwarf)
-}
-
-fn infer_type_from_name(name: &str, size: usize) -> TypeInfo {
-    let lower = name.to_lowercase();
-
-    let (encoding, type_name) = if lower.contains("_u8")
-        || lower.contains("_uint8")
-        || lower.ends_with("_u8")
-        || lower.contains("uint8_t")
-    {
-        (TypeEncoding::Unsigned, "uint8_t".to_string())
-    } else if lower.contains("_u16")
-        || lower.contains("_uint16")
-        || lower.ends_with("_u16")
-        || lower.contains("uint16_t")
-    {
-        (TypeEncoding::Unsigned, "uint16_t".to_string())
-    } else if lower.contains("_u32")
-        || lower.contains("_uint32")
-        || lower.ends_with("_u32")
-        || lower.contains("uint32_t")
-    {
-        (TypeEncoding::Unsigned, "uint32_t".to_string())
-    } else if lower.contains("_u64")
-        || lower.contains("_uint64")
-        || lower.ends_with("_u64")
-        || lower.contains("uint64_t")
-    {
-        (TypeEncoding::Unsigned, "uint64_t".to_string())
-    } else if lower.contains("_s8")
-        || lower.contains("_int8")
-        || lower.ends_with("_s8")
-        || lower.contains("int8_t")
-    {
-        (TypeEncoding::Signed, "int8_t".to_string())
-    } else if lower.contains("_s16")
-        || lower.contains("_int16")
-        || lower.ends_with("_s16")
-        || lower.contains("int16_t")
-    {
-        (TypeEncoding::Signed, "int16_t".to_string())
-    } else if lower.contains("_s32")
-        || lower.contains("_int32")
-        || lower.ends_with("_s32")
-        || lower.contains("int32_t")
-        || lower.contains("_int")
-        || lower.ends_with("_i")
-    {
-        (TypeEncoding::Signed, "int32_t".to_string())
-    } else if lower.contains("_s64")
-        || lower.contains("_int64")
-        || lower.ends_with("_s64")
-        || lower.contains("int64_t")
-    {
-        (TypeEncoding::Signed, "int64_t".to_string())
-    } else if lower.contains("_f32")
-        || lower.contains("_float")
-        || lower.ends_with("_f32")
-        || lower.contains("float32")
-    {
-        (TypeEncoding::Float, "float".to_string())
-    } else if lower.contains("_f64")
-        || lower.contains("_double")
-        || lower.ends_with("_f64")
-        || lower.contains("float64")
-    {
-        (TypeEncoding::Float, "double".to_string())
-    } else if lower.contains("_bool") || lower.ends_with("_b") || lower.contains("boolean") {
-        (TypeEncoding::Unsigned, "bool".to_string())
-    } else {
-        match size {
-            1 => (TypeEncoding::Unsigned, "uint8_t".to_string()),
-            2 => (TypeEncoding::Unsigned, "uint16_t".to_string()),
-            4 => (TypeEncoding::Unsigned, "uint32_t".to_string()),
-            8 => (TypeEncoding::Unsigned, "uint64_t".to_string()),
-            _ => (TypeEncoding::Unsigned, format!("uint8_t[{}]", size)),
-        }
-    };
-
-    TypeInfo::primitive(type_name, size, encoding)
+    parser.apply_to_variables(variables, default_type_rules());
+    Ok(parser.has_dwarf_info())
 }
@@ -1,7 +1,10 @@
-use crate::dwarf::{analyze_variables_with_dwarf, DwarfParser};
+use crate::dwarf::DwarfParser;
+use crate::naming::NamingPolicy;
+use crate::progress::{is_cancelled_error, ProgressPhase, ProgressReporter};
+use crate::type_rules::{TypeGuessReport, TypeRules};
 use crate::types::{
-    infer_a2l_type_from_encoding, A2lEntry, A2lEntryStore, TypeInfo, TypeKind, Variable,
-    MAX_ARRAY_EXPAND, MAX_NESTING_DEPTH,
+    infer_a2l_type_from_encoding, A2lEntry, A2lEntryStore, Endianness, EnumType, ExpandConfig,
+    ExpandLimits, TypeInfo, TypeKind, Variable,
 };
 use anyhow::{Context, Result};
 use memmap2::Mmap;
@@ -10,14 +13,61 @@ use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::path::Path;
 
+/// 展开过程中共享的状态
+struct ExpandContext<'a> {
+    type_cache: &'a HashMap<u64, TypeInfo>,
+    config: &'a ExpandConfig,
+    naming: &'a NamingPolicy,
+    store: A2lEntryStore,
+    visited: HashSet<u64>,
+}
+
+/// 多维数组逐元素展开时各层不变的参数
+struct ArrayElement<'t> {
+    elem_type: &'t TypeInfo,
+    elem_size: usize,
+    depth: usize,
+    limits: ExpandLimits,
+}
+
+/// 深度解析选项
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub expand: ExpandConfig,
+    pub naming: NamingPolicy,
+    pub type_rules: TypeRules,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_expand_config(mut self, expand: ExpandConfig) -> Self {
+        self.expand = expand;
+        self
+    }
+
+    pub fn with_naming_policy(mut self, naming: NamingPolicy) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    pub fn with_type_rules(mut self, type_rules: TypeRules) -> Self {
+        self.type_rules = type_rules;
+        self
+    }
+}
+
 pub struct ElfParser {
     variables: Vec<Variable>,
     file_size: u64,
+    endianness: Endianness,
     has_dwarf: bool,
     dwarf_stats: Option<DwarfStats>,
     a2l_entries: Option<A2lEntryStore>,
-    #[allow(dead_code)]
     type_cache: Option<HashMap<u64, TypeInfo>>,
+    type_guesses: Option<TypeGuessReport>,
 }
 
 #[derive(Clone)]
@@ -44,6 +94,27 @@ impl ElfParser {
     }
 
     pub fn parse_with_depth(path: &Path, deep: bool) -> Result<Self> {
+        Self::parse_with_progress(path, deep, ProgressReporter::new())
+    }
+
+    pub fn parse_deep_with_progress(path: &Path, reporter: ProgressReporter) -> Result<Self> {
+        Self::parse_with_progress(path, true, reporter)
+    }
+
+    pub fn parse_with_progress(
+        path: &Path,
+        deep: bool,
+        reporter: ProgressReporter,
+    ) -> Result<Self> {
+        Self::parse_with_options(path, deep, &ParseOptions::default(), reporter)
+    }
+
+    pub fn parse_with_options(
+        path: &Path,
+        deep: bool,
+        options: &ParseOptions,
+        reporter: ProgressReporter,
+    ) -> Result<Self> {
         let file = File::open(path).context("无法打开 ELF 文件")?;
         let metadata = file.metadata().context("无法读取文件元数据")?;
         let file_size = metadata.len();
@@ -51,6 +122,7 @@ impl ElfParser {
         let mmap = unsafe { Mmap::map(&file).context("无法创建内存映射")? };
 
         let obj = object::File::parse(&*mmap).context("无法解析 ELF 文件")?;
+        let endianness = Self::object_endianness(&obj);
 
         let mut variables = Vec::new();
         let mut seen = HashSet::new();
@@ -114,9 +186,15 @@ impl ElfParser {
         }
 
         variables.sort_by(|a, b| a.name.cmp(&b.name));
-
-        let (has_dwarf, dwarf_stats, type_cache, a2l_entries) = if deep {
-            let parser = DwarfParser::parse(&mmap).unwrap_or_else(|_| DwarfParser::new());
+        reporter.report(ProgressPhase::Symbols, variables.len(), variables.len(), 0);
+        reporter.check_cancelled()?;
+
+        let (has_dwarf, dwarf_stats, type_cache, a2l_entries, type_guesses) = if deep {
+            let parser = match DwarfParser::parse_with_progress(&mmap, reporter) {
+                Ok(parser) => parser,
+                Err(e) if is_cancelled_error(&e) => return Err(e),
+                Err(_) => DwarfParser::new(),
+            };
             let has_dwarf = parser.has_dwarf_info();
 
             let stats = if has_dwarf {
@@ -148,23 +226,25 @@ impl ElfParser {
                 None
             };
 
-            analyze_variables_with_dwarf(&mut variables, &mmap).ok();
+            let guesses = parser.apply_to_variables(&mut variables, &options.type_rules);
 
             let tc = parser.type_cache().clone();
-            let entries = Self::expand_all_entries(&variables, &tc);
+            let entries = Self::expand_all_entries(&variables, &tc, options, reporter)?;
 
-            (has_dwarf, stats, Some(tc), Some(entries))
+            (has_dwarf, stats, Some(tc), Some(entries), Some(guesses))
         } else {
-            (false, None, None, None)
+            (false, None, None, None, None)
         };
 
         Ok(Self {
             variables,
             file_size,
+            endianness,
             has_dwarf,
             dwarf_stats,
             type_cache,
             a2l_entries,
+            type_guesses,
         })
     }
 
@@ -190,6 +270,27 @@ impl ElfParser {
         self.file_size
     }
 
+    /// ELF 文件头声明的字节序
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// 只读取文件头得到字节序，不解析符号
+    pub fn read_endianness(path: &Path) -> Result<Endianness> {
+        let file = File::open(path).context("无法打开 ELF 文件")?;
+        let mmap = unsafe { Mmap::map(&file).context("无法创建内存映射")? };
+        let obj = object::File::parse(&*mmap).context("无法解析 ELF 文件")?;
+        Ok(Self::object_endianness(&obj))
+    }
+
+    fn object_endianness(obj: &object::File) -> Endianness {
+        if obj.is_little_endian() {
+            Endianness::Little
+        } else {
+            Endianness::Big
+        }
+    }
+
     pub fn has_dwarf(&self) -> bool {
         self.has_dwarf
     }
@@ -210,6 +311,16 @@ impl ElfParser {
         self.variables.iter().find(|v| v.name == name)
     }
 
+    /// 深度解析得到的 DWARF 类型表，按 DIE 偏移索引
+    pub fn type_cache(&self) -> Option<&HashMap<u64, TypeInfo>> {
+        self.type_cache.as_ref()
+    }
+
+    /// 深度解析时缺少 DWARF 类型、经规则推断的变量
+    pub fn type_guesses(&self) -> Option<&TypeGuessReport> {
+        self.type_guesses.as_ref()
+    }
+
     pub fn a2l_entries(&self) -> Option<&A2lEntryStore> {
         self.a2l_entries.as_ref()
     }
@@ -225,43 +336,67 @@ impl ElfParser {
     fn expand_all_entries(
         variables: &[Variable],
         type_cache: &HashMap<u64, TypeInfo>,
-    ) -> A2lEntryStore {
-        let mut store = A2lEntryStore::new();
+        options: &ParseOptions,
+        reporter: ProgressReporter,
+    ) -> Result<A2lEntryStore> {
+        const REPORT_INTERVAL: usize = 256;
+
+        let mut ctx = ExpandContext {
+            type_cache,
+            config: &options.expand,
+            naming: &options.naming,
+            store: A2lEntryStore::new(),
+            visited: HashSet::new(),
+        };
+        let total = variables.len();
 
-        for var in variables {
-            Self::expand_variable(var, type_cache, &mut store);
+        for (i, var) in variables.iter().enumerate() {
+            if i % REPORT_INTERVAL == 0 {
+                reporter.check_cancelled()?;
+                reporter.report(ProgressPhase::Expand, i, total, ctx.store.len());
+            }
+            Self::expand_variable(var, &mut ctx);
         }
 
-        store
+        reporter.report(ProgressPhase::Expand, total, total, ctx.store.len());
+
+        Ok(ctx.store)
     }
 
-    fn expand_variable(
-        var: &Variable,
-        type_cache: &HashMap<u64, TypeInfo>,
-        store: &mut A2lEntryStore,
-    ) {
-        let mut visited: HashSet<u64> = HashSet::new();
+    fn expand_variable(var: &Variable, ctx: &mut ExpandContext) {
+        ctx.visited.clear();
+        let root_name = ctx.naming.root_name(&var.name);
+        let first = ctx.store.len();
 
         if let Some(ref type_info) = var.type_info {
             Self::expand_recursive(
-                &var.name,
+                &root_name,
                 var.address,
                 type_info,
                 0,
-                &mut visited,
-                type_cache,
-                store,
+                ctx,
                 None,
+                ctx.config.limits,
             );
         } else {
-            let a2l_type = infer_a2l_type_from_encoding(var.size, Default::default());
-            store.add(A2lEntry::new(
-                var.name.clone(),
+            // 类型未知：按原始字节块输出，不猜测数值类型
+            let mut entry = A2lEntry::new(
+                ctx.naming.finalize(&root_name),
                 var.address,
                 var.size,
-                a2l_type.to_string(),
+                "UBYTE".to_string(),
                 var.type_name.clone(),
-            ));
+            );
+            if var.size > 1 {
+                entry = entry.with_array_dims(vec![var.size]);
+            }
+            ctx.store.add(entry);
+        }
+
+        if var.source_file.is_some() {
+            for entry in &mut ctx.store.entries[first..] {
+                entry.source_file = var.source_file.clone();
+            }
         }
     }
 
@@ -270,136 +405,202 @@ impl ElfParser {
         base_addr: u64,
         type_info: &TypeInfo,
         depth: usize,
-        visited: &mut HashSet<u64>,
-        type_cache: &HashMap<u64, TypeInfo>,
-        store: &mut A2lEntryStore,
+        ctx: &mut ExpandContext,
         array_index: Option<Vec<usize>>,
+        inherited: ExpandLimits,
     ) {
-        if depth > MAX_NESTING_DEPTH {
+        let elem_name = type_info
+            .pointer_target
+            .as_ref()
+            .filter(|_| type_info.kind == TypeKind::Array)
+            .map(|t| t.name.as_str())
+            .unwrap_or("");
+        let limits = ctx
+            .config
+            .resolve(prefix, &[type_info.name.as_str(), elem_name], inherited);
+
+        if depth > limits.max_nesting_depth {
             return;
         }
 
-        if type_info.offset > 0 && visited.contains(&type_info.offset) {
+        if type_info.offset > 0 && ctx.visited.contains(&type_info.offset) {
+            return;
+        }
+        ctx.visited.insert(type_info.offset);
+
+        if type_info.kind == TypeKind::Array {
+            Self::expand_array(
+                prefix,
+                base_addr,
+                type_info,
+                depth,
+                ctx,
+                array_index,
+                limits,
+            );
+            ctx.visited.remove(&type_info.offset);
             return;
         }
-        visited.insert(type_info.offset);
 
         let a2l_type = infer_a2l_type_from_encoding(type_info.size, type_info.encoding);
-        store.add(
+        ctx.store.add(
             A2lEntry::new(
-                prefix.to_string(),
+                ctx.naming.finalize(prefix),
                 base_addr,
                 type_info.size,
                 a2l_type.to_string(),
                 type_info.name.clone(),
             )
-            .with_array_index(array_index.clone().unwrap_or_default()),
+            .with_array_index(array_index.unwrap_or_default())
+            .with_enum_type(EnumType::from_type(type_info)),
         );
 
-        match type_info.kind {
-            TypeKind::Struct | TypeKind::Union => {
-                for member in &type_info.members {
-                    let member_full_name = format!("{}.{}", prefix, member.name);
-                    let member_addr = base_addr + member.offset as u64;
-
-                    if member.is_bitfield() {
-                        let member_a2l_type =
-                            infer_a2l_type_from_encoding(member.type_size, type_info.encoding);
-                        store.add(
-                            A2lEntry::new(
-                                member_full_name,
+        if matches!(type_info.kind, TypeKind::Struct | TypeKind::Union) {
+            for member in &type_info.members {
+                let member_full_name = ctx.naming.member_name(prefix, &member.name);
+                let member_addr = base_addr + member.offset as u64;
+
+                if member.is_bitfield() {
+                    let member_a2l_type =
+                        infer_a2l_type_from_encoding(member.type_size, type_info.encoding);
+                    let enum_type = member
+                        .type_offset
+                        .and_then(|offset| ctx.type_cache.get(&offset))
+                        .and_then(EnumType::from_type);
+                    ctx.store.add(
+                        A2lEntry::new(
+                            ctx.naming.finalize(&member_full_name),
+                            member_addr,
+                            member.type_size,
+                            member_a2l_type.to_string(),
+                            member.type_name.clone(),
+                        )
+                        .with_bitfield(member.bit_offset.unwrap_or(0), member.bit_size.unwrap_or(0))
+                        .with_enum_type(enum_type),
+                    );
+                } else if let Some(type_offset) = member.type_offset {
+                    if type_offset > 0 {
+                        if let Some(member_type) = ctx.type_cache.get(&type_offset) {
+                            Self::expand_recursive(
+                                &member_full_name,
                                 member_addr,
-                                member.type_size,
-                                member_a2l_type.to_string(),
-                                member.type_name.clone(),
-                            )
-                            .with_bitfield(
-                                member.bit_offset.unwrap_or(0),
-                                member.bit_size.unwrap_or(0),
-                            ),
-                        );
-                    } else if let Some(type_offset) = member.type_offset {
-                        if type_offset > 0 {
-                            if let Some(member_type) = type_cache.get(&type_offset) {
-                                Self::expand_recursive(
-                                    &member_full_name,
-                                    member_addr,
-                                    member_type,
-                                    depth + 1,
-                                    visited,
-                                    type_cache,
-                                    store,
-                                    None,
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-            TypeKind::Array => {
-                let (effective_dims, final_elem_type, final_elem_size) =
-                    Self::flatten_array_type(type_info, 0);
-
-                let total_elements: usize = effective_dims.iter().product();
-                let original_total: usize = type_info.array_dims.iter().product();
-
-                if original_total <= MAX_ARRAY_EXPAND && original_total > 0 {
-                    if let Some(ref elem_type) = final_elem_type {
-                        let base_idx = array_index.clone().unwrap_or_default();
-                        Self::expand_multi_dim_array(
-                            prefix,
-                            base_addr,
-                            elem_type,
-                            &effective_dims,
-                            final_elem_size,
-                            depth,
-                            visited,
-                            type_cache,
-                            store,
-                            &base_idx,
-                        );
-                    } else if total_elements > 0 {
-                        for i in 0..total_elements {
-                            let multi_idx = Self::flat_to_multi_index(i, &effective_dims);
-                            let idx: Vec<usize> = array_index
-                                .clone()
-                                .unwrap_or_default()
-                                .into_iter()
-                                .chain(multi_idx.into_iter())
-                                .collect();
-                            let elem_name = Self::format_array_element_name(prefix, &idx);
-                            let elem_addr = base_addr + (i * final_elem_size) as u64;
-                            let elem_a2l_type =
-                                infer_a2l_type_from_encoding(final_elem_size, type_info.encoding);
-                            store.add(
-                                A2lEntry::new(
-                                    elem_name,
-                                    elem_addr,
-                                    final_elem_size,
-                                    elem_a2l_type.to_string(),
-                                    type_info.name.clone(),
-                                )
-                                .with_array_index(idx),
+                                member_type,
+                                depth + 1,
+                                ctx,
+                                None,
+                                limits,
                             );
                         }
                     }
                 }
             }
-            _ => {}
         }
 
-        visited.remove(&type_info.offset);
+        ctx.visited.remove(&type_info.offset);
     }
 
-    fn format_array_element_name(prefix: &str, indices: &[usize]) -> String {
-        if indices.is_empty() {
-            return prefix.to_string();
+    /// 元素数不超过限制时逐个展开元素，否则整个数组作为一个带维度的条目保留
+    fn expand_array(
+        prefix: &str,
+        base_addr: u64,
+        type_info: &TypeInfo,
+        depth: usize,
+        ctx: &mut ExpandContext,
+        array_index: Option<Vec<usize>>,
+        limits: ExpandLimits,
+    ) {
+        let (effective_dims, final_elem_type, final_elem_size) =
+            Self::flatten_array_type(type_info, 0);
+
+        let total_elements: usize = effective_dims.iter().product();
+        let original_total: usize = type_info.array_dims.iter().product();
+
+        let expanded = original_total > 0
+            && original_total <= limits.max_array_expand
+            && (final_elem_type.is_some() || total_elements > 0);
+        if !expanded {
+            // 未展开的数组作为一个带维度的条目保留
+            let is_scalar_elem = final_elem_type.as_ref().is_none_or(|t| {
+                !matches!(t.kind, TypeKind::Struct | TypeKind::Union | TypeKind::Array)
+            });
+            let (block_type, block_dims) = if is_scalar_elem && final_elem_size > 0 {
+                let encoding = final_elem_type
+                    .as_ref()
+                    .map(|t| t.encoding)
+                    .unwrap_or(type_info.encoding);
+                (
+                    infer_a2l_type_from_encoding(final_elem_size, encoding),
+                    effective_dims.clone(),
+                )
+            } else {
+                // 结构体数组无法表示为单一数据类型的块，按字节块保留
+                ("UBYTE", vec![type_info.size])
+            };
+
+            let mut entry = A2lEntry::new(
+                ctx.naming.finalize(prefix),
+                base_addr,
+                type_info.size,
+                block_type.to_string(),
+                type_info.name.clone(),
+            )
+            .with_array_index(array_index.unwrap_or_default());
+            if is_scalar_elem {
+                entry =
+                    entry.with_enum_type(final_elem_type.as_ref().and_then(EnumType::from_type));
+            }
+            if !block_dims.is_empty() && block_dims.iter().product::<usize>() > 1 {
+                entry = entry.with_array_dims(block_dims);
+            }
+            ctx.store.add(entry);
+            return;
+        }
+
+        if let Some(ref elem_type) = final_elem_type {
+            let element = ArrayElement {
+                elem_type,
+                elem_size: final_elem_size,
+                depth,
+                limits,
+            };
+            Self::expand_multi_dim_array(
+                prefix,
+                base_addr,
+                &effective_dims,
+                &array_index.unwrap_or_default(),
+                &element,
+                ctx,
+            );
+        } else if total_elements > 0 {
+            for i in 0..total_elements {
+                let multi_idx = Self::flat_to_multi_index(i, &effective_dims);
+                let elem_name = ctx
+                    .naming
+                    .finalize(&ctx.naming.element_name(prefix, &multi_idx));
+                let idx: Vec<usize> = array_index
+                    .clone()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .chain(multi_idx)
+                    .collect();
+                let elem_addr = base_addr + (i * final_elem_size) as u64;
+                let elem_a2l_type =
+                    infer_a2l_type_from_encoding(final_elem_size, type_info.encoding);
+                ctx.store.add(
+                    A2lEntry::new(
+                        elem_name,
+                        elem_addr,
+                        final_elem_size,
+                        elem_a2l_type.to_string(),
+                        type_info.name.clone(),
+                    )
+                    .with_array_index(idx),
+                );
+            }
         }
-        let idx_str: Vec<String> = indices.iter().map(|i| format!("._{}_", i)).collect();
-        format!("{}{}", prefix, idx_str.join(""))
     }
 
-    fn flatten_array_type(
+    pub(crate) fn flatten_array_type(
         type_info: &TypeInfo,
         base_elem_size: usize,
     ) -> (Vec<usize>, Option<TypeInfo>, usize) {
@@ -412,11 +613,7 @@ impl ElfParser {
 
         let mut current_size = if type_info.size > 0 {
             let total: usize = type_info.array_dims.iter().product();
-            if total > 0 {
-                type_info.size / total
-            } else {
-                base_elem_size
-            }
+            type_info.size.checked_div(total).unwrap_or(base_elem_size)
         } else {
             base_elem_size
         };
@@ -461,57 +658,234 @@ impl ElfParser {
     fn expand_multi_dim_array(
         prefix: &str,
         base_addr: u64,
-        elem_type: &TypeInfo,
         dims: &[usize],
-        elem_size: usize,
-        depth: usize,
-        visited: &mut HashSet<u64>,
-        type_cache: &HashMap<u64, TypeInfo>,
-        store: &mut A2lEntryStore,
         base_idx: &[usize],
+        element: &ArrayElement,
+        ctx: &mut ExpandContext,
     ) {
         if dims.is_empty() {
-            let fixed_elem_type = if elem_type.size == 0 {
-                let mut t = elem_type.clone();
-                t.size = elem_size;
+            let fixed_elem_type = if element.elem_type.size == 0 {
+                let mut t = element.elem_type.clone();
+                t.size = element.elem_size;
                 t
             } else {
-                elem_type.clone()
+                element.elem_type.clone()
             };
             Self::expand_recursive(
                 prefix,
                 base_addr,
                 &fixed_elem_type,
-                depth,
-                visited,
-                type_cache,
-                store,
+                element.depth,
+                ctx,
                 Some(base_idx.to_vec()),
+                element.limits,
             );
             return;
         }
 
         let current_dim = dims[0];
         let remaining_dims = &dims[1..];
-        let stride: usize = remaining_dims.iter().product::<usize>() * elem_size;
+        let stride: usize = remaining_dims.iter().product::<usize>() * element.elem_size;
 
         for i in 0..current_dim {
             let mut full_idx = base_idx.to_vec();
             full_idx.push(i);
-            let elem_name = format!("{}._{}_", prefix, i);
+            let elem_name = ctx.naming.element_name(prefix, &[i]);
             let elem_addr = base_addr + (i * stride) as u64;
             Self::expand_multi_dim_array(
                 &elem_name,
                 elem_addr,
-                elem_type,
                 remaining_dims,
-                elem_size,
-                depth,
-                visited,
-                type_cache,
-                store,
                 &full_idx,
+                element,
+                ctx,
             );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{StructMember, TypeEncoding};
+
+    /// `struct cfg_t { uint16_t gain[4]; inner_t inner; }`，`inner_t` 只有一个 `uint8_t x`
+    fn type_cache() -> HashMap<u64, TypeInfo> {
+        let u8_type = TypeInfo::primitive("uint8_t".to_string(), 1, TypeEncoding::Unsigned);
+        let u16_type = TypeInfo::primitive("uint16_t".to_string(), 2, TypeEncoding::Unsigned);
+        let gain = TypeInfo::array_type("uint16_t[4]".to_string(), 8, u16_type, vec![4], 0x20);
+        let inner = TypeInfo::struct_type(
+            "inner_t".to_string(),
+            1,
+            vec![
+                StructMember::new("x".to_string(), 0, "uint8_t".to_string(), 1)
+                    .with_type_offset(0x30),
+            ],
+            0x40,
+        );
+        let cfg = TypeInfo::struct_type(
+            "cfg_t".to_string(),
+            10,
+            vec![
+                StructMember::new("gain".to_string(), 0, "uint16_t[4]".to_string(), 8)
+                    .with_type_offset(0x20),
+                StructMember::new("inner".to_string(), 8, "inner_t".to_string(), 1)
+                    .with_type_offset(0x40),
+            ],
+            0x50,
+        );
+        HashMap::from([(0x20, gain), (0x30, u8_type), (0x40, inner), (0x50, cfg)])
+    }
+
+    fn expand(
+        variable: &Variable,
+        cache: &HashMap<u64, TypeInfo>,
+        expand: ExpandConfig,
+    ) -> Vec<String> {
+        let options = ParseOptions::new().with_expand_config(expand);
+        ElfParser::expand_all_entries(
+            std::slice::from_ref(variable),
+            cache,
+            &options,
+            ProgressReporter::new(),
+        )
+        .unwrap()
+        .entries
+        .into_iter()
+        .map(|e| e.full_name)
+        .collect()
+    }
+
+    fn variable(cache: &HashMap<u64, TypeInfo>) -> Variable {
+        let mut variable = Variable::new(
+            "cfg".to_string(),
+            0x1000,
+            10,
+            "cfg_t".to_string(),
+            ".data".to_string(),
+        );
+        variable.type_info = cache.get(&0x50).cloned();
+        variable
+    }
+
+    fn limits(max_array_expand: usize, max_nesting_depth: usize) -> ExpandLimits {
+        ExpandLimits {
+            max_array_expand,
+            max_nesting_depth,
+        }
+    }
+
+    #[test]
+    fn test_expand_limits_and_overrides() {
+        let cache = type_cache();
+        let variable = variable(&cache);
+        let gain_elements = [
+            "cfg.gain._0_",
+            "cfg.gain._1_",
+            "cfg.gain._2_",
+            "cfg.gain._3_",
+        ];
+
+        let all = expand(&variable, &cache, ExpandConfig::default());
+        assert!(gain_elements.iter().all(|e| all.iter().any(|n| n == e)));
+        assert!(!all.iter().any(|n| n == "cfg.gain"));
+        assert!(all.iter().any(|n| n == "cfg.inner.x"));
+
+        // 运行时限制：数组只保留整体条目，嵌套只到第一层成员
+        let limited = ExpandConfig::new(limits(2, 1));
+        assert_eq!(
+            expand(&variable, &cache, limited.clone()),
+            ["cfg", "cfg.gain", "cfg.inner"]
+        );
+
+        // 变量路径覆盖只作用于匹配的节点及其子节点
+        let by_path = limited
+            .clone()
+            .with_variable_override("cfg.gain", limits(8, 2));
+        let names = expand(&variable, &cache, by_path);
+        assert!(gain_elements.iter().all(|e| names.iter().any(|n| n == e)));
+        assert!(!names.iter().any(|n| n == "cfg.inner.x"));
+
+        // 类型名覆盖，变量路径优先
+        let by_type = limited
+            .with_type_override("inner_t", limits(2, 5))
+            .with_type_override("uint16_t", limits(8, 5))
+            .with_variable_override("cfg.gain", limits(2, 5));
+        let names = expand(&variable, &cache, by_type);
+        assert!(names.iter().any(|n| n == "cfg.inner.x"));
+        assert!(names.iter().any(|n| n == "cfg.gain"));
+        assert!(!names.iter().any(|n| n == "cfg.gain._0_"));
+    }
+
+    #[test]
+    fn test_collapsed_array_dims() {
+        let i16_type = TypeInfo::primitive("int16_t".to_string(), 2, TypeEncoding::Signed);
+        let row = TypeInfo::array_type("int16_t[3]".to_string(), 6, i16_type, vec![3], 0x60);
+        let table = TypeInfo::array_type("int16_t[2][3]".to_string(), 12, row, vec![2], 0x70);
+        let mut variable = Variable::new(
+            "table".to_string(),
+            0x2000,
+            12,
+            "int16_t[2][3]".to_string(),
+            ".data".to_string(),
+        );
+        variable.type_info = Some(table);
+        let cache = HashMap::new();
+        let expand = |limits: ExpandLimits| {
+            let options = ParseOptions::new().with_expand_config(ExpandConfig::new(limits));
+            ElfParser::expand_all_entries(
+                std::slice::from_ref(&variable),
+                &cache,
+                &options,
+                ProgressReporter::new(),
+            )
+            .unwrap()
+        };
+
+        // 展开的小数组只有元素条目
+        let store = expand(ExpandLimits::default());
+        assert_eq!(store.entries.len(), 6);
+        assert!(store.entries.iter().all(|e| e.array_dims.is_none()));
+        let last = store.entries.last().unwrap();
+        assert_eq!(last.full_name, "table._1_._2_");
+        assert_eq!(last.address, 0x2000 + 5 * 2);
+        assert_eq!(last.array_index.as_deref(), Some(&[1, 2][..]));
+
+        // 限制按最外层维度计算，超过时只保留一个带维度的条目
+        let store = expand(limits(1, 5));
+        assert_eq!(store.entries.len(), 1);
+        let block = &store.entries[0];
+        assert_eq!(block.full_name, "table");
+        assert_eq!(block.a2l_type, "SWORD");
+        assert_eq!(block.array_dims.as_deref(), Some(&[2, 3][..]));
+    }
+
+    #[test]
+    fn test_expand_progress_and_cancel() {
+        use crate::progress::{CancelToken, Progress};
+        use std::sync::{Arc, Mutex};
+
+        let cache = type_cache();
+        let variables: Vec<Variable> = (0..600).map(|_| variable(&cache)).collect();
+        let options = ParseOptions::new();
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let log = reports.clone();
+        let callback = move |progress: &Progress| log.lock().unwrap().push(progress.clone());
+        let reporter = ProgressReporter::new().with_callback(&callback);
+        ElfParser::expand_all_entries(&variables, &cache, &options, reporter).unwrap();
+        let reports = reports.lock().unwrap().clone();
+        let processed: Vec<usize> = reports.iter().map(|p| p.processed).collect();
+        assert_eq!(processed, [0, 256, 512, 600]);
+        assert!(reports.iter().all(|p| p.phase == ProgressPhase::Expand));
+        assert!(reports.windows(2).all(|w| w[0].entries <= w[1].entries));
+
+        let token = CancelToken::new();
+        token.cancel();
+        let reporter = ProgressReporter::new().with_cancel(&token);
+        let err = ElfParser::expand_all_entries(&variables, &cache, &options, reporter)
+            .err()
+            .unwrap();
+        assert!(is_cancelled_error(&err));
+    }
+}
@@ -0,0 +1,281 @@
+//! A2L 文件的字符编码与换行风格：读取时识别并统一为 UTF-8 与 `\n`，
+//! 写回时还原为原文件的编码、BOM、换行符与末尾换行
+
+use anyhow::{Context, Result};
+use encoding_rs::{Encoding, GBK, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// 在文件开头查找编码声明的范围
+const DECLARATION_WINDOW: usize = 4096;
+
+/// 文件的字符编码，按 WHATWG 标签解析，如 `utf-8`、`gbk`、`latin1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextEncoding(&'static Encoding);
+
+impl TextEncoding {
+    pub const UTF8: TextEncoding = TextEncoding(UTF_8);
+
+    fn is_utf16(self) -> bool {
+        self.0 == UTF_16LE || self.0 == UTF_16BE
+    }
+}
+
+impl Default for TextEncoding {
+    fn default() -> Self {
+        Self::UTF8
+    }
+}
+
+impl std::fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0.name())
+    }
+}
+
+impl std::str::FromStr for TextEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Encoding::for_label(s.trim().as_bytes())
+            .map(TextEncoding)
+            .ok_or_else(|| anyhow::anyhow!("不支持的字符编码: {}（如 utf-8/gbk/latin1）", s))
+    }
+}
+
+/// 换行符风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl std::fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+        })
+    }
+}
+
+/// 文件的编码与换行格式，写回时按原样还原
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextFormat {
+    pub encoding: TextEncoding,
+    /// 原文件带有字节顺序标记
+    pub bom: bool,
+    pub line_ending: LineEnding,
+    /// 原文件以换行结尾
+    pub trailing_newline: bool,
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        Self {
+            encoding: TextEncoding::UTF8,
+            bom: false,
+            line_ending: LineEnding::Lf,
+            trailing_newline: true,
+        }
+    }
+}
+
+impl TextFormat {
+    /// 读取文件并转换为 UTF-8、`\n` 换行的内容
+    pub fn read(path: &Path, encoding: Option<TextEncoding>) -> Result<(String, TextFormat)> {
+        let bytes =
+            std::fs::read(path).with_context(|| format!("无法读取文件: {}", path.display()))?;
+        Self::decode(&bytes, encoding).with_context(|| format!("无法解码文件: {}", path.display()))
+    }
+
+    /// 识别编码并解码：指定的编码优先，其次是 BOM、文件开头的编码声明，
+    /// 都没有时有效的 UTF-8 按 UTF-8，能按 GBK 解出中文的按 GBK，其余按 Latin-1
+    pub fn decode(bytes: &[u8], encoding: Option<TextEncoding>) -> Result<(String, TextFormat)> {
+        let bom =
+            Encoding::for_bom(bytes).filter(|(found, _)| encoding.is_none_or(|e| e.0 == *found));
+        let (encoding, bom, body) = match bom {
+            Some((found, len)) => (TextEncoding(found), true, &bytes[len..]),
+            None => {
+                let encoding = encoding
+                    .or_else(|| declared_encoding(bytes))
+                    .unwrap_or_else(|| guess_encoding(bytes));
+                (encoding, false, bytes)
+            }
+        };
+        let text = encoding
+            .0
+            .decode_without_bom_handling_and_without_replacement(body)
+            .with_context(|| format!("文件不是有效的 {} 编码", encoding))?;
+
+        let lines = text.matches('\n').count();
+        let crlf = text.matches("\r\n").count();
+        let format = TextFormat {
+            encoding,
+            bom,
+            line_ending: if lines > 0 && crlf * 2 >= lines {
+                LineEnding::CrLf
+            } else {
+                LineEnding::Lf
+            },
+            trailing_newline: text.is_empty() || text.ends_with('\n'),
+        };
+        Ok((text.replace("\r\n", "\n"), format))
+    }
+
+    /// 按原格式编码后写入文件
+    pub fn write(&self, path: &Path, content: &str) -> Result<()> {
+        let bytes = self
+            .encode(content)
+            .with_context(|| format!("无法写入文件: {}", path.display()))?;
+        std::fs::write(path, bytes).with_context(|| format!("无法写入文件: {}", path.display()))
+    }
+
+    /// `\n` 换行的内容还原为原文件的换行符、末尾换行、编码与 BOM
+    pub fn encode(&self, content: &str) -> Result<Vec<u8>> {
+        let mut text = content.to_string();
+        if self.trailing_newline {
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+        } else {
+            while text.ends_with('\n') {
+                text.pop();
+            }
+        }
+        if self.line_ending == LineEnding::CrLf {
+            text = text.replace('\n', "\r\n");
+        }
+
+        let encoding = self.encoding.0;
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+        if self.encoding.is_utf16() {
+            if self.bom {
+                bytes.extend(encode_utf16(encoding, "\u{feff}"));
+            }
+            bytes.extend(encode_utf16(encoding, &text));
+            return Ok(bytes);
+        }
+        if self.bom {
+            bytes.extend_from_slice("\u{feff}".as_bytes());
+        }
+        let (encoded, _, unmappable) = encoding.encode(&text);
+        if unmappable {
+            let ch = text
+                .chars()
+                .find(|c| encoding.encode(c.encode_utf8(&mut [0; 4])).2)
+                .unwrap_or_default();
+            anyhow::bail!("内容包含 {} 编码无法表示的字符 '{}'", self.encoding, ch);
+        }
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+}
+
+/// 文件开头注释里的编码声明，如 `/* encoding: GBK */`、`-*- coding: latin1 -*-`
+fn declared_encoding(bytes: &[u8]) -> Option<TextEncoding> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)\b(?:en)?coding\s*[:=]\s*|\bcharset\s*=\s*").expect("编码声明正则无效")
+    });
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(DECLARATION_WINDOW)]);
+    let found = pattern.find(&head)?;
+    let label: String = head[found.end()..]
+        .trim_start_matches('"')
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+        .collect();
+    label
+        .parse()
+        .ok()
+        .filter(|encoding: &TextEncoding| !encoding.is_utf16())
+}
+
+fn guess_encoding(bytes: &[u8]) -> TextEncoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        return TextEncoding::UTF8;
+    }
+    let decodes = GBK
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .is_some();
+    if decodes && gb2312_pairs(bytes) {
+        return TextEncoding(GBK);
+    }
+    TextEncoding(WINDOWS_1252)
+}
+
+/// 非 ASCII 字节大多组成 GB2312 区的双字节（首尾字节都不低于 0xA1）。
+/// Latin-1 的重音字母后面通常是 ASCII 字母，虽然也是合法的 GBK 却落在区外
+fn gb2312_pairs(bytes: &[u8]) -> bool {
+    let (mut pairs, mut other) = (0, 0);
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] < 0x80 {
+            i += 1;
+            continue;
+        }
+        match bytes.get(i + 1) {
+            Some(0xa1..=0xfe) if (0xa1..=0xf7).contains(&bytes[i]) => {
+                pairs += 1;
+                i += 2;
+            }
+            _ => {
+                other += 1;
+                i += 1;
+            }
+        }
+    }
+    pairs > other
+}
+
+fn encode_utf16(encoding: &'static Encoding, text: &str) -> Vec<u8> {
+    text.encode_utf16()
+        .flat_map(|unit| {
+            if encoding == UTF_16LE {
+                unit.to_le_bytes()
+            } else {
+                unit.to_be_bytes()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let (gbk, _, _) = GBK.encode("/* 发动机转速 */\r\nASAP2_VERSION 1 71\r\n");
+        let (text, format) = TextFormat::decode(&gbk, None).unwrap();
+        assert_eq!(text, "/* 发动机转速 */\nASAP2_VERSION 1 71\n");
+        assert_eq!(format.encoding.to_string(), "GBK");
+        assert_eq!(format.line_ending, LineEnding::CrLf);
+        assert_eq!(format.encode(&text).unwrap(), gbk.as_ref());
+
+        let latin1 = b"/* R\xe9gime moteur */\nASAP2_VERSION 1 71";
+        let (text, format) = TextFormat::decode(latin1, None).unwrap();
+        assert!(text.contains("Régime"));
+        assert!(!format.trailing_newline);
+        assert_eq!(format.encode(&(text + "\n")).unwrap(), latin1);
+        assert!(format.encode("/* 中文 */").is_err());
+
+        let bom = "\u{feff}/* ok */\n".as_bytes();
+        let (text, format) = TextFormat::decode(bom, None).unwrap();
+        assert_eq!(text, "/* ok */\n");
+        assert_eq!(format.encode(&text).unwrap(), bom);
+    }
+
+    #[test]
+    fn test_declared_and_override() {
+        let declared = b"/* encoding: latin1 */\n/* \xc4\xe3 */\n";
+        let (text, _) = TextFormat::decode(declared, None).unwrap();
+        assert!(text.contains("Äã"));
+        let gbk: TextEncoding = "gbk".parse().unwrap();
+        let (text, _) = TextFormat::decode(declared, Some(gbk)).unwrap();
+        assert!(text.contains("你"));
+        assert!("klingon".parse::<TextEncoding>().is_err());
+    }
+}
@@ -0,0 +1,700 @@
+use crate::a2l::{insert_after_last, ExportKind};
+use crate::a2l_syntax::{A2lDocument, Block, Span, TextEdits, Value};
+use crate::compu::quote;
+use crate::naming::{IndexStyle, NamingPolicy};
+use crate::types::A2lEntry;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// GROUP 与 FUNCTION 中引用变量的列表
+const VARIABLE_LISTS: &[&str] = &[
+    "REF_MEASUREMENT",
+    "REF_CHARACTERISTIC",
+    "DEF_CHARACTERISTIC",
+    "IN_MEASUREMENT",
+    "OUT_MEASUREMENT",
+    "LOC_MEASUREMENT",
+];
+
+const MEASUREMENT_LISTS: &[&str] = &[
+    "REF_MEASUREMENT",
+    "IN_MEASUREMENT",
+    "OUT_MEASUREMENT",
+    "LOC_MEASUREMENT",
+];
+
+const CHARACTERISTIC_LISTS: &[&str] = &["REF_CHARACTERISTIC", "DEF_CHARACTERISTIC"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GroupKind {
+    #[default]
+    Group,
+    Function,
+}
+
+impl GroupKind {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            GroupKind::Group => "GROUP",
+            GroupKind::Function => "FUNCTION",
+        }
+    }
+
+    /// 测量量列表，FUNCTION 的 IN/OUT_MEASUREMENT 不在此编辑
+    fn measurement_list(self) -> &'static str {
+        match self {
+            GroupKind::Group => "REF_MEASUREMENT",
+            GroupKind::Function => "LOC_MEASUREMENT",
+        }
+    }
+
+    /// 标定量列表，AXIS_PTS 也放在这里
+    fn characteristic_list(self) -> &'static str {
+        match self {
+            GroupKind::Group => "REF_CHARACTERISTIC",
+            GroupKind::Function => "DEF_CHARACTERISTIC",
+        }
+    }
+
+    fn sub_list(self) -> &'static str {
+        match self {
+            GroupKind::Group => "SUB_GROUP",
+            GroupKind::Function => "SUB_FUNCTION",
+        }
+    }
+
+    /// 引用该类块名称的列表：子分组列表，FUNCTION 还会出现在变量和分组的 FUNCTION_LIST 中
+    fn reference_lists(self) -> &'static [&'static str] {
+        match self {
+            GroupKind::Group => &["SUB_GROUP"],
+            GroupKind::Function => &["SUB_FUNCTION", "FUNCTION_LIST"],
+        }
+    }
+}
+
+impl std::str::FromStr for GroupKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "group" | "GROUP" => Ok(GroupKind::Group),
+            "function" | "FUNCTION" => Ok(GroupKind::Function),
+            _ => anyhow::bail!("无效的分组类型: {}（可选 group/function）", s),
+        }
+    }
+}
+
+/// GROUP 或 FUNCTION 块
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct A2lGroup {
+    pub kind: GroupKind,
+    pub name: String,
+    pub long_identifier: String,
+    /// 顶层分组，仅 GROUP 有效
+    pub root: bool,
+    /// GROUP 的 REF_MEASUREMENT，FUNCTION 的 LOC_MEASUREMENT
+    pub measurements: Vec<String>,
+    /// GROUP 的 REF_CHARACTERISTIC，FUNCTION 的 DEF_CHARACTERISTIC
+    pub characteristics: Vec<String>,
+    /// SUB_GROUP 或 SUB_FUNCTION
+    pub sub_groups: Vec<String>,
+}
+
+impl A2lGroup {
+    pub fn new(kind: GroupKind, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_long_identifier(mut self, long_identifier: &str) -> Self {
+        self.long_identifier = long_identifier.to_string();
+        self
+    }
+
+    pub fn with_root(mut self, root: bool) -> Self {
+        self.root = root;
+        self
+    }
+
+    fn from_block(kind: GroupKind, block: &Block) -> Self {
+        let list = |keyword: &str| -> Vec<String> {
+            block
+                .block(keyword)
+                .map(|b| b.values().map(|v| v.text.to_string()).collect())
+                .unwrap_or_default()
+        };
+        Self {
+            kind,
+            name: block.name().unwrap_or_default().to_string(),
+            long_identifier: block.value(1).map(|v| v.unquoted()).unwrap_or_default(),
+            root: root_value(block).is_some(),
+            measurements: list(kind.measurement_list()),
+            characteristics: list(kind.characteristic_list()),
+            sub_groups: list(kind.sub_list()),
+        }
+    }
+
+    fn lists(&self) -> [(&'static str, &[String]); 3] {
+        [
+            (self.kind.characteristic_list(), &self.characteristics),
+            (self.kind.measurement_list(), &self.measurements),
+            (self.kind.sub_list(), &self.sub_groups),
+        ]
+    }
+
+    pub fn to_a2l(&self) -> String {
+        let mut output = format!(
+            "    /begin {} {} {}\n",
+            self.kind.keyword(),
+            self.name,
+            quote(&self.long_identifier)
+        );
+        if self.root && self.kind == GroupKind::Group {
+            output.push_str("      ROOT\n");
+        }
+        for (keyword, names) in self.lists() {
+            if !names.is_empty() {
+                output.push_str(&format_list(keyword, names, "      "));
+            }
+        }
+        output.push_str(&format!("    /end {}\n\n", self.kind.keyword()));
+        output
+    }
+
+    /// 把另一个同名分组的成员并入，已有的名称不重复
+    fn merge(&mut self, other: &A2lGroup) {
+        self.root |= other.root;
+        for (mine, theirs) in [
+            (&mut self.measurements, &other.measurements),
+            (&mut self.characteristics, &other.characteristics),
+            (&mut self.sub_groups, &other.sub_groups),
+        ] {
+            for name in theirs {
+                if !mine.contains(name) {
+                    mine.push(name.clone());
+                }
+            }
+        }
+    }
+}
+
+/// 块头之后的 ROOT 关键字
+fn root_value<'d, 'a>(block: &'d Block<'a>) -> Option<&'d Value<'a>> {
+    block.keyword_value("ROOT", 2)
+}
+
+fn format_list(keyword: &str, names: &[String], indent: &str) -> String {
+    let mut output = format!("{}/begin {}\n", indent, keyword);
+    for name in names {
+        output.push_str(&format!("{}  {}\n", indent, name));
+    }
+    output.push_str(&format!("{}/end {}\n", indent, keyword));
+    output
+}
+
+fn find_group<'d, 'a>(
+    doc: &'d A2lDocument<'a>,
+    kind: GroupKind,
+    name: &str,
+) -> Option<&'d Block<'a>> {
+    doc.find_block(kind.keyword(), name)
+}
+
+/// 文件中全部 GROUP 与 FUNCTION，按文档顺序
+pub fn list_groups(content: &str) -> Result<Vec<A2lGroup>> {
+    let doc = A2lDocument::parse(content)?;
+    Ok(doc
+        .blocks()
+        .into_iter()
+        .filter_map(|b| {
+            let kind = b.keyword().parse::<GroupKind>().ok()?;
+            Some(A2lGroup::from_block(kind, b))
+        })
+        .collect())
+}
+
+/// 新建分组，插入到最后一个 GROUP/FUNCTION 之后，否则 MODULE 结束之前。同名时报错
+pub fn create_groups(content: &str, groups: &[A2lGroup]) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let mut blocks = String::new();
+    let mut names: Vec<(GroupKind, &str)> = Vec::new();
+    for group in groups {
+        if group.name.is_empty() {
+            anyhow::bail!("分组名称不能为空");
+        }
+        if find_group(&doc, group.kind, &group.name).is_some()
+            || names.contains(&(group.kind, group.name.as_str()))
+        {
+            anyhow::bail!("已存在同名分组: {}", group.name);
+        }
+        names.push((group.kind, &group.name));
+        blocks.push_str(&group.to_a2l());
+    }
+    if blocks.is_empty() {
+        return Ok(content.to_string());
+    }
+    insert_after_last(&doc, &[&["GROUP", "FUNCTION"]], &blocks).context("无法找到分组的插入位置")?
+}
+
+/// 修改分组的说明、ROOT 与各引用列表；名称改变时同步更新其他块中的引用
+pub fn update_group(
+    content: &str,
+    kind: GroupKind,
+    original_name: &str,
+    group: &A2lGroup,
+) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let block = find_group(&doc, kind, original_name)
+        .with_context(|| format!("未找到分组: {}", original_name))?;
+    let renamed = !group.name.is_empty() && group.name != original_name;
+    if renamed && find_group(&doc, kind, &group.name).is_some() {
+        anyhow::bail!("已存在同名分组: {}", group.name);
+    }
+
+    let mut edits = TextEdits::new();
+    let target = A2lGroup {
+        kind,
+        ..group.clone()
+    };
+    set_group(&doc, block, &target, &mut edits);
+    if renamed {
+        if let Some(name) = block.value(0) {
+            edits.replace(name.span, group.name.as_str());
+        }
+    }
+    let content = edits.apply(content)?;
+
+    if renamed {
+        rename_references(&content, kind.reference_lists(), original_name, &group.name)
+    } else {
+        Ok(content)
+    }
+}
+
+/// 删除分组，并从其他块的子分组列表中移除对它的引用
+pub fn delete_group(content: &str, kind: GroupKind, name: &str) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let block = find_group(&doc, kind, name).with_context(|| format!("未找到分组: {}", name))?;
+    let mut edits = TextEdits::new();
+    edits.delete(doc.line_extent(block.span()));
+    let content = edits.apply(content)?;
+
+    let names = HashSet::from([name]);
+    remove_references(&content, kind.reference_lists(), &names)
+}
+
+/// 把分组并入文件：已存在的分组追加缺少的成员，不存在的新建
+pub fn merge_groups(content: &str, groups: &[A2lGroup]) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let mut edits = TextEdits::new();
+    let mut created: Vec<A2lGroup> = Vec::new();
+    let mut merged: Vec<A2lGroup> = Vec::new();
+
+    for group in groups {
+        if let Some(existing) = created
+            .iter_mut()
+            .chain(merged.iter_mut())
+            .find(|g| g.kind == group.kind && g.name == group.name)
+        {
+            existing.merge(group);
+            continue;
+        }
+        match find_group(&doc, group.kind, &group.name) {
+            Some(block) => {
+                let mut existing = A2lGroup::from_block(group.kind, block);
+                existing.merge(group);
+                merged.push(existing);
+            }
+            None => created.push(group.clone()),
+        }
+    }
+
+    for group in &merged {
+        if let Some(block) = find_group(&doc, group.kind, &group.name) {
+            set_group(&doc, block, group, &mut edits);
+        }
+    }
+    let content = edits.apply(content)?;
+    create_groups(&content, &created)
+}
+
+/// 删除变量后，从所有分组的变量列表中移除这些名称
+pub fn remove_variable_references(content: &str, names: &[String]) -> Result<String> {
+    let names: HashSet<&str> = names.iter().map(|s| s.as_str()).collect();
+    remove_references(content, VARIABLE_LISTS, &names)
+}
+
+/// 变量在 MEASUREMENT 与 CHARACTERISTIC 之间转换后，把它在各分组中移到新类别的列表：
+/// 转为标定量时从 REF/LOC/IN/OUT_MEASUREMENT 移入 REF_CHARACTERISTIC（FUNCTION 为 DEF_CHARACTERISTIC），
+/// 转为测量量时从 REF/DEF_CHARACTERISTIC 移入 REF_MEASUREMENT（FUNCTION 为 LOC_MEASUREMENT）
+pub fn move_variable_membership(
+    content: &str,
+    name: &str,
+    to_characteristic: bool,
+) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let mut edits = TextEdits::new();
+    for block in doc.blocks() {
+        let Ok(kind) = block.keyword().parse::<GroupKind>() else {
+            continue;
+        };
+        let (sources, target): (&[&str], &str) = if to_characteristic {
+            (MEASUREMENT_LISTS, kind.characteristic_list())
+        } else {
+            (CHARACTERISTIC_LISTS, kind.measurement_list())
+        };
+        let mut moved = false;
+        for list in block.blocks().filter(|b| sources.contains(&b.keyword())) {
+            if list.values().any(|v| v.text == name) {
+                let kept: Vec<String> = list
+                    .values()
+                    .filter(|v| v.text != name)
+                    .map(|v| v.text.to_string())
+                    .collect();
+                replace_list(&doc, list, &kept, &mut edits);
+                moved = true;
+            }
+        }
+        if !moved {
+            continue;
+        }
+        let names = vec![name.to_string()];
+        match block.block(target) {
+            Some(list) if list.values().any(|v| v.text == name) => {}
+            Some(list) => {
+                let mut names: Vec<String> = list.values().map(|v| v.text.to_string()).collect();
+                names.push(name.to_string());
+                replace_list(&doc, list, &names, &mut edits);
+            }
+            None => {
+                let indent = format!("{}  ", doc.indent_at(block.begin.start));
+                edits.insert(
+                    doc.line_start(block.end.start),
+                    format_list(target, &names, &indent),
+                );
+            }
+        }
+    }
+    edits.apply(content)
+}
+
+fn rename_references(content: &str, lists: &[&str], old: &str, new: &str) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let mut edits = TextEdits::new();
+    for list in doc
+        .blocks()
+        .into_iter()
+        .filter(|b| lists.contains(&b.keyword()))
+    {
+        for value in list.values().filter(|v| v.text == old) {
+            edits.replace(value.span, new);
+        }
+    }
+    edits.apply(content)
+}
+
+fn remove_references(content: &str, lists: &[&str], names: &HashSet<&str>) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let mut edits = TextEdits::new();
+    for list in doc
+        .blocks()
+        .into_iter()
+        .filter(|b| lists.contains(&b.keyword()))
+    {
+        if list.values().any(|v| names.contains(v.text)) {
+            let kept: Vec<String> = list
+                .values()
+                .filter(|v| !names.contains(v.text))
+                .map(|v| v.text.to_string())
+                .collect();
+            replace_list(&doc, list, &kept, &mut edits);
+        }
+    }
+    edits.apply(content)
+}
+
+/// 按目标分组改写已有块的说明、ROOT 与三个引用列表，块中的其他内容保持不变
+fn set_group(doc: &A2lDocument, block: &Block, group: &A2lGroup, edits: &mut TextEdits) {
+    if let Some(long_id) = block.value(1) {
+        if long_id.unquoted() != group.long_identifier {
+            edits.replace(long_id.span, quote(&group.long_identifier));
+        }
+    }
+
+    let root = root_value(block);
+    let want_root = group.root && group.kind == GroupKind::Group;
+    let child_indent = format!("{}  ", doc.indent_at(block.begin.start));
+    let insert_at = doc.line_start(block.end.start);
+    match (root, want_root) {
+        (Some(value), false) => edits.delete(doc.line_extent(value.span)),
+        (None, true) => {
+            let after_header = block
+                .value(1)
+                .map(|v| doc.line_end(v.span.end))
+                .unwrap_or(insert_at);
+            edits.insert(after_header, format!("{}ROOT\n", child_indent));
+        }
+        _ => {}
+    }
+
+    for (keyword, names) in group.lists() {
+        match block.block(keyword) {
+            Some(list) => {
+                let current: Vec<&str> = list.values().map(|v| v.text).collect();
+                if current != names.iter().map(|s| s.as_str()).collect::<Vec<_>>() {
+                    replace_list(doc, list, names, edits);
+                }
+            }
+            None if !names.is_empty() => {
+                edits.insert(insert_at, format_list(keyword, names, &child_indent));
+            }
+            None => {}
+        }
+    }
+}
+
+/// 改写列表块的内容，为空时删除整个列表块
+fn replace_list(doc: &A2lDocument, list: &Block, names: &[String], edits: &mut TextEdits) {
+    if names.is_empty() {
+        edits.delete(doc.line_extent(list.span()));
+        return;
+    }
+    let indent = doc.indent_at(list.begin.start);
+    let mut text = String::from("\n");
+    for name in names {
+        text.push_str(&format!("{}  {}\n", indent, name));
+    }
+    text.push_str(indent);
+    edits.replace(Span::new(list.keyword.span.end, list.end.start), text);
+}
+
+/// 导出时自动创建分组的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GroupBy {
+    #[default]
+    None,
+    /// 按结构体层级，每个结构体或数组路径一个分组
+    Struct,
+    /// 按变量定义所在的源文件
+    SourceFile,
+}
+
+impl std::str::FromStr for GroupBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" | "none" => Ok(GroupBy::None),
+            "struct" => Ok(GroupBy::Struct),
+            "source_file" => Ok(GroupBy::SourceFile),
+            _ => anyhow::bail!("无效的分组方式: {}（可选 none/struct/source_file）", s),
+        }
+    }
+}
+
+/// 为导出的条目生成分组：结构体层级按命名策略的分隔符和下标拆分路径，
+/// 源文件分组以文件名为分组名，没有源文件信息的条目不分组
+pub fn groups_for_entries<'e>(
+    entries: impl IntoIterator<Item = &'e A2lEntry>,
+    kind: ExportKind,
+    group_by: GroupBy,
+    naming: &NamingPolicy,
+) -> Vec<A2lGroup> {
+    if group_by == GroupBy::None {
+        return Vec::new();
+    }
+    let mut groups: Vec<A2lGroup> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut group = |name: &str| -> usize {
+        *index.entry(name.to_string()).or_insert_with(|| {
+            groups.push(A2lGroup::new(GroupKind::Group, &naming.identifier(name)));
+            groups.len() - 1
+        })
+    };
+    let mut members: Vec<(usize, String)> = Vec::new();
+    let mut children: Vec<(usize, String)> = Vec::new();
+    let mut roots: Vec<usize> = Vec::new();
+
+    for entry in entries {
+        let parent = match group_by {
+            GroupBy::None => continue,
+            GroupBy::Struct => match parent_path(&entry.full_name, naming) {
+                Some(parent) => parent.to_string(),
+                None => continue,
+            },
+            GroupBy::SourceFile => match &entry.source_file {
+                Some(file) => group_identifier(file),
+                None => continue,
+            },
+        };
+        members.push((group(&parent), naming.identifier(&entry.full_name)));
+
+        // 结构体路径逐级向上挂到父分组，最外层为 ROOT
+        let mut child = parent;
+        while let Some(up) = parent_path(&child, naming).filter(|_| group_by == GroupBy::Struct) {
+            let up = up.to_string();
+            children.push((group(&up), child));
+            child = up;
+        }
+        roots.push(group(&child));
+    }
+
+    for (i, name) in members {
+        let list = if kind == ExportKind::Measurement {
+            &mut groups[i].measurements
+        } else {
+            &mut groups[i].characteristics
+        };
+        if !list.contains(&name) {
+            list.push(name);
+        }
+    }
+    for (i, child) in children {
+        let child = naming.identifier(&child);
+        if !groups[i].sub_groups.contains(&child) {
+            groups[i].sub_groups.push(child);
+        }
+    }
+    for i in roots {
+        groups[i].root = true;
+    }
+    groups
+}
+
+/// 去掉最后一级成员或下标后的父路径，顶层名称返回 `None`
+fn parent_path<'n>(name: &'n str, naming: &NamingPolicy) -> Option<&'n str> {
+    let member = (!naming.separator.is_empty())
+        .then(|| name.rfind(naming.separator.as_str()))
+        .flatten();
+    let index = (naming.index_style == IndexStyle::Bracket && name.ends_with(']'))
+        .then(|| name.rfind('['))
+        .flatten();
+    let pos = member.max(index).filter(|&p| p > 0)?;
+    Some(&name[..pos])
+}
+
+/// 文件名转为分组名：非标识符字符替换为下划线，数字开头时补下划线
+fn group_identifier(file: &str) -> String {
+    let mut name: String = file
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+/begin PROJECT P \"\"
+  /begin MODULE M \"\"
+    /begin MEASUREMENT speed \"\"
+      UWORD NO_COMPU_METHOD 0 0 0 65535
+    /end MEASUREMENT
+
+    /begin GROUP Engine \"engine\"
+      ROOT
+      /begin REF_MEASUREMENT
+        speed rpm
+      /end REF_MEASUREMENT
+      /begin SUB_GROUP
+        Idle
+      /end SUB_GROUP
+    /end GROUP
+
+    /begin GROUP Idle \"\"
+      /begin REF_MEASUREMENT speed /end REF_MEASUREMENT
+    /end GROUP
+  /end MODULE
+/end PROJECT
+";
+
+    #[test]
+    fn test_edit_groups() {
+        let groups = list_groups(SAMPLE).unwrap();
+        assert_eq!(groups.len(), 2);
+        assert!(groups[0].root);
+        assert_eq!(groups[0].measurements, ["speed", "rpm"]);
+        assert_eq!(groups[0].sub_groups, ["Idle"]);
+
+        // 改名同步子分组引用，删除变量同步列表
+        let mut idle = groups[1].clone();
+        idle.name = "IdleCtl".to_string();
+        idle.root = true;
+        let output = update_group(SAMPLE, GroupKind::Group, "Idle", &idle).unwrap();
+        let output = remove_variable_references(&output, &["speed".to_string()]).unwrap();
+        let groups = list_groups(&output).unwrap();
+        assert_eq!(groups[0].sub_groups, ["IdleCtl"]);
+        assert_eq!(groups[0].measurements, ["rpm"]);
+        assert_eq!(groups[1].name, "IdleCtl");
+        assert!(groups[1].root);
+        assert!(groups[1].measurements.is_empty());
+
+        let output = delete_group(&output, GroupKind::Group, "IdleCtl").unwrap();
+        let groups = list_groups(&output).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert!(groups[0].sub_groups.is_empty());
+    }
+
+    #[test]
+    fn test_groups_for_entries() {
+        let naming = NamingPolicy::default();
+        let entry = |name: &str, file: &str| {
+            A2lEntry::new(name.to_string(), 0, 1, "UBYTE".to_string(), String::new())
+                .with_source_file(Some(file.to_string()))
+        };
+        let entries = [
+            entry("cfg.limits.max", "cfg.c"),
+            entry("cfg.limits.min", "cfg.c"),
+            entry("cfg.mode", "cfg.c"),
+            entry("counter", "main.c"),
+        ];
+
+        let groups = groups_for_entries(
+            &entries,
+            ExportKind::Characteristic,
+            GroupBy::Struct,
+            &naming,
+        );
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].name, "cfg.limits");
+        assert_eq!(
+            groups[0].characteristics,
+            ["cfg.limits.max", "cfg.limits.min"]
+        );
+        assert_eq!(groups[1].name, "cfg");
+        assert!(groups[1].root);
+        assert_eq!(groups[1].sub_groups, ["cfg.limits"]);
+        assert_eq!(groups[1].characteristics, ["cfg.mode"]);
+
+        let groups = groups_for_entries(
+            &entries,
+            ExportKind::Measurement,
+            GroupBy::SourceFile,
+            &naming,
+        );
+        let output = merge_groups(SAMPLE, &groups).unwrap();
+        let output = merge_groups(&output, &groups).unwrap();
+        let groups = list_groups(&output).unwrap();
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[2].name, "cfg.c");
+        assert_eq!(groups[2].measurements.len(), 3);
+        assert_eq!(groups[3].measurements, ["counter"]);
+    }
+}
@@ -0,0 +1,615 @@
+//! `/include` 指令支持：把主 A2L 及其递归包含的文件展开为一份内容，
+//! 编辑完成后按标记拆分，写回对象实际所在的文件
+
+use crate::a2l::{variable_blocks, A2lParser, A2lVariable};
+use crate::a2l_syntax::{tokenize, A2lDocument, Span, TokenKind};
+use crate::encoding::{TextEncoding, TextFormat};
+use crate::rename::{self, Reference};
+use crate::validate::{validate, Diagnostic};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// 展开内容中包围被包含文件的注释标记：`/*@include-begin N*/` 与 `/*@include-end N*/`
+const MARKER_PREFIX: &str = "/*@include-";
+
+/// 主 A2L 与其包含的文件
+#[derive(Debug, Clone)]
+pub struct A2lProject {
+    /// 按展开顺序排列，第一个为主文件
+    files: Vec<SourceFile>,
+    /// 展开后的内容，被包含文件原样嵌入在标记之间
+    content: String,
+}
+
+#[derive(Debug, Clone)]
+struct SourceFile {
+    path: PathBuf,
+    /// 规范化路径，用于识别被多次包含的同一文件
+    canonical: PathBuf,
+    content: String,
+    /// 父文件中的 `/include` 指令原文，主文件为空
+    directive: String,
+    /// 写回时还原的编码与换行
+    format: TextFormat,
+}
+
+/// 被包含文件在展开内容中的范围
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    parent: usize,
+    /// 含首尾标记
+    outer: Span,
+    /// 文件内容本身
+    body: Span,
+}
+
+impl A2lProject {
+    /// 读取主文件并递归展开 `/include`，路径相对于包含它的文件
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::load_with_encoding(path, None)
+    }
+
+    /// 同 [`load`](Self::load)，`encoding` 指定时不再自动识别各文件的编码
+    pub fn load_with_encoding(path: &Path, encoding: Option<TextEncoding>) -> Result<Self> {
+        let mut project = A2lProject {
+            files: Vec::new(),
+            content: String::new(),
+        };
+        project.content = project.expand(path, String::new(), encoding, &mut Vec::new())?;
+        Ok(project)
+    }
+
+    fn expand(
+        &mut self,
+        path: &Path,
+        directive: String,
+        encoding: Option<TextEncoding>,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<String> {
+        let canonical = path
+            .canonicalize()
+            .with_context(|| format!("无法读取文件: {}", path.display()))?;
+        if stack.contains(&canonical) {
+            anyhow::bail!("/include 循环包含: {}", path.display());
+        }
+        let (content, format) = TextFormat::read(path, encoding)?;
+        let includes =
+            find_includes(&content).with_context(|| format!("无法解析文件: {}", path.display()))?;
+
+        self.files.push(SourceFile {
+            path: path.to_path_buf(),
+            canonical: canonical.clone(),
+            content: content.clone(),
+            directive,
+            format,
+        });
+        stack.push(canonical);
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut expanded = String::with_capacity(content.len());
+        let mut last = 0;
+        for (span, target) in includes {
+            expanded.push_str(&content[last..span.start]);
+            let index = self.files.len();
+            let body = self.expand(
+                &dir.join(&target),
+                span.text(&content).to_string(),
+                encoding,
+                stack,
+            )?;
+            expanded.push_str(&format!(
+                "{}begin {}*/\n{}\n{}end {}*/",
+                MARKER_PREFIX, index, body, MARKER_PREFIX, index
+            ));
+            last = span.end;
+        }
+        expanded.push_str(&content[last..]);
+
+        stack.pop();
+        Ok(expanded)
+    }
+
+    /// 主文件路径
+    pub fn path(&self) -> &Path {
+        &self.files[0].path
+    }
+
+    /// 主文件的编码与换行格式
+    pub fn format(&self) -> TextFormat {
+        self.files[0].format
+    }
+
+    /// 展开后的内容，可直接交给解析与编辑函数
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// 主文件及所有被包含文件的路径
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|f| f.path.as_path())
+    }
+
+    /// 解析全部变量，并标注来自被包含文件的变量所在文件
+    pub fn variables(&self) -> Result<Vec<A2lVariable>> {
+        let doc = A2lDocument::parse(&self.content)?;
+        let regions = self.regions();
+        Ok(variable_blocks(&doc)
+            .map(|block| {
+                let mut variable = A2lParser::parse_variable_block(&doc, block);
+                variable.file = self.label(file_at(&regions, block.span().start));
+                variable
+            })
+            .collect())
+    }
+
+    /// 校验展开后的内容，行号换算为问题所在文件中的行号
+    pub fn validate(&self) -> Result<Vec<Diagnostic>> {
+        let mut diagnostics = validate(&self.content)?;
+        if self.files.len() > 1 {
+            let locate = self.locator();
+            for diagnostic in &mut diagnostics {
+                let (file, line) = locate(diagnostic.line);
+                diagnostic.line = line;
+                diagnostic.file = self.label(file);
+                if let Some(first) = diagnostic.first_line {
+                    let (file, line) = locate(first);
+                    diagnostic.first_line = Some(line);
+                    diagnostic.first_file = self.label(file);
+                }
+            }
+        }
+        Ok(diagnostics)
+    }
+
+    /// 变量的声明与全部引用，行号换算为所在文件中的行号
+    pub fn find_references(&self, name: &str) -> Result<Vec<Reference>> {
+        let mut references = rename::find_references(&self.content, name)?;
+        if self.files.len() > 1 {
+            let locate = self.locator();
+            for reference in &mut references {
+                let (file, line) = locate(reference.line);
+                reference.line = line;
+                reference.file = self.label(file);
+            }
+        }
+        Ok(references)
+    }
+
+    /// 展开内容中的行号 → (文件下标, 该文件中的行号)
+    fn locator(&self) -> impl Fn(usize) -> (usize, usize) + '_ {
+        let regions = self.regions();
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(self.content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        move |line: usize| {
+            let offset = line_starts[line.clamp(1, line_starts.len()) - 1];
+            let file = file_at(&regions, offset);
+            let local = self.local_offset(&regions, file, offset);
+            (
+                file,
+                self.files[file].content[..local].matches('\n').count() + 1,
+            )
+        }
+    }
+
+    /// 把编辑后的展开内容拆分写回各文件，只写入有变化的文件，返回写入的路径。
+    /// 同一文件被包含多次且各处内容不一致时返回错误，不写入任何文件
+    pub fn write(&mut self, content: &str) -> Result<Vec<PathBuf>> {
+        let parts = self.split(content)?;
+        for (i, file) in self.files.iter().enumerate() {
+            let conflict = self.files[..i]
+                .iter()
+                .zip(&parts)
+                .any(|(other, part)| other.canonical == file.canonical && *part != parts[i]);
+            if conflict {
+                anyhow::bail!(
+                    "文件 {} 被包含多次，各处的修改不一致，无法写回",
+                    file.path.display()
+                );
+            }
+        }
+        let mut written = Vec::new();
+        for (file, part) in self.files.iter_mut().zip(parts) {
+            if file.content != part {
+                file.format.write(&file.path, &part)?;
+                written.push(file.path.clone());
+                file.content = part;
+            }
+        }
+        self.content = content.to_string();
+        Ok(written)
+    }
+
+    /// 去掉包含标记，得到内联了全部被包含文件的单个 A2L
+    pub fn flatten(content: &str) -> Result<String> {
+        let mut flat = String::with_capacity(content.len());
+        let mut last = 0;
+        for (span, begin, _) in markers(content)? {
+            flat.push_str(&content[last..span.start]);
+            last = span.end;
+            if begin {
+                last += usize::from(content[last..].starts_with('\n'));
+            } else if flat.ends_with('\n') {
+                flat.pop();
+            }
+        }
+        flat.push_str(&content[last..]);
+        Ok(flat)
+    }
+
+    /// 按标记把展开内容还原为各文件的内容，嵌套的部分换回 `/include` 指令
+    fn split(&self, content: &str) -> Result<Vec<String>> {
+        let mut parts = vec![String::new(); self.files.len()];
+        let mut stack = vec![0];
+        let mut last = 0;
+        for (span, begin, index) in markers(content)? {
+            let top = *stack.last().unwrap_or(&0);
+            parts[top].push_str(&content[last..span.start]);
+            last = span.end;
+            if begin {
+                if index == 0 || index >= self.files.len() {
+                    anyhow::bail!("无效的包含标记: {}", index);
+                }
+                parts[top].push_str(&self.files[index].directive);
+                stack.push(index);
+                last += usize::from(content[last..].starts_with('\n'));
+            } else {
+                if stack.len() < 2 || top != index {
+                    anyhow::bail!("包含标记不匹配: {}", index);
+                }
+                stack.pop();
+                if parts[index].ends_with('\n') {
+                    parts[index].pop();
+                }
+            }
+        }
+        if stack.len() != 1 {
+            anyhow::bail!("包含标记未闭合");
+        }
+        parts[0].push_str(&content[last..]);
+        Ok(parts)
+    }
+
+    /// 各文件在当前展开内容中的范围，下标与 `files` 一致
+    fn regions(&self) -> Vec<Region> {
+        let whole = Span::new(0, self.content.len());
+        let mut regions = vec![
+            Region {
+                parent: 0,
+                outer: whole,
+                body: whole,
+            };
+            self.files.len()
+        ];
+        let mut stack = vec![0];
+        for (span, begin, index) in markers(&self.content).unwrap_or_default() {
+            let Some(region) = regions.get_mut(index) else {
+                continue;
+            };
+            if begin {
+                region.parent = *stack.last().unwrap_or(&0);
+                region.outer.start = span.start;
+                region.body.start = span.end + 1;
+                stack.push(index);
+            } else {
+                region.outer.end = span.end;
+                region.body.end = span.start - 1;
+                stack.pop();
+            }
+        }
+        regions
+    }
+
+    /// 展开内容中的偏移量在 `file` 原文中的偏移量
+    fn local_offset(&self, regions: &[Region], file: usize, offset: usize) -> usize {
+        let nested: usize = regions
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(i, r)| *i != file && r.parent == file && r.outer.end <= offset)
+            .map(|(i, r)| r.outer.len() - self.files[i].directive.len())
+            .sum();
+        offset - regions[file].body.start - nested
+    }
+
+    /// 被包含文件相对主文件目录的路径，主文件返回 `None`
+    fn label(&self, file: usize) -> Option<String> {
+        if file == 0 {
+            return None;
+        }
+        let path = &self.files[file].path;
+        let dir = self.path().parent().unwrap_or_else(|| Path::new(""));
+        Some(path.strip_prefix(dir).unwrap_or(path).display().to_string())
+    }
+}
+
+/// 偏移量所在的最内层文件
+fn file_at(regions: &[Region], offset: usize) -> usize {
+    regions
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.body.start <= offset && offset <= r.body.end)
+        .max_by_key(|(_, r)| r.body.start)
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// 查找 `/include` 指令及其文件名，文件名可带引号
+fn find_includes(content: &str) -> Result<Vec<(Span, String)>> {
+    let tokens = tokenize(content)?;
+    let mut iter = tokens.iter().filter(|t| !t.kind.is_trivia());
+    let mut includes = Vec::new();
+    while let Some(token) = iter.next() {
+        if token.kind != TokenKind::Word || token.span.text(content) != "/include" {
+            continue;
+        }
+        match iter.next() {
+            Some(file) if matches!(file.kind, TokenKind::Word | TokenKind::String) => {
+                let name = file.span.text(content).trim_matches('"').to_string();
+                includes.push((Span::new(token.span.start, file.span.end), name));
+            }
+            _ => anyhow::bail!("/include 后缺少文件名"),
+        }
+    }
+    Ok(includes)
+}
+
+/// 展开内容中被包含文件所占的范围（含标记，嵌套的只取最外层）。
+/// 新对象插入到这些范围之外，写回时落在主文件中
+pub(crate) fn included_spans(content: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (span, begin, _) in markers(content).unwrap_or_default() {
+        if begin {
+            if depth == 0 {
+                start = span.start;
+            }
+            depth += 1;
+        } else if depth > 0 {
+            depth -= 1;
+            if depth == 0 {
+                spans.push(Span::new(start, span.end));
+            }
+        }
+    }
+    spans
+}
+
+/// 展开内容中的包含标记：(范围, 是否为开始标记, 文件下标)
+fn markers(content: &str) -> Result<Vec<(Span, bool, usize)>> {
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while let Some(i) = content[pos..].find(MARKER_PREFIX) {
+        let start = pos + i;
+        let end = content[start..]
+            .find("*/")
+            .map(|j| start + j + 2)
+            .context("包含标记未结束")?;
+        let marker = &content[start + MARKER_PREFIX.len()..end - 2];
+        let (kind, index) = marker
+            .split_once(' ')
+            .with_context(|| format!("无效的包含标记: {}", marker))?;
+        let index = index
+            .parse()
+            .with_context(|| format!("无效的包含标记: {}", marker))?;
+        let begin = match kind {
+            "begin" => true,
+            "end" => false,
+            _ => anyhow::bail!("无效的包含标记: {}", marker),
+        };
+        found.push((Span::new(start, end), begin, index));
+        pos = end;
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a2l::A2lGenerator;
+
+    const MASTER: &str = r#"ASAP2_VERSION 1 71
+/begin PROJECT P ""
+  /begin MODULE M ""
+    /include "parts/meas.a2l"
+    /begin MEASUREMENT top "" UBYTE NO_COMPU_METHOD 0 0 0 255
+      ECU_ADDRESS 0x1000
+    /end MEASUREMENT
+  /end MODULE
+/end PROJECT
+"#;
+
+    const PART: &str = r#"/begin MEASUREMENT inner "" UWORD NO_COMPU_METHOD 0 0 0 65535
+  ECU_ADDRESS 0x2000
+/end MEASUREMENT
+/include nested.a2l
+"#;
+
+    const NESTED: &str = r#"/begin MEASUREMENT deep "" SLONG NO_COMPU_METHOD 0 0 -100 100
+  ECU_ADDRESS 0x3000
+/end MEASUREMENT
+"#;
+
+    #[test]
+    fn test_edits_are_written_to_owning_file() {
+        let dir = std::env::temp_dir().join(format!("a2l_include_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        let master = dir.join("master.a2l");
+        std::fs::write(&master, MASTER).unwrap();
+        std::fs::write(dir.join("parts/meas.a2l"), PART).unwrap();
+        std::fs::write(dir.join("parts/nested.a2l"), NESTED).unwrap();
+
+        let mut project = A2lProject::load(&master).unwrap();
+        let files: Vec<Option<String>> = project
+            .variables()
+            .unwrap()
+            .into_iter()
+            .map(|v| v.file)
+            .collect();
+        assert_eq!(
+            files,
+            [
+                Some("parts/meas.a2l".to_string()),
+                Some("parts/nested.a2l".to_string()),
+                None
+            ]
+        );
+        assert_eq!(
+            A2lProject::flatten(project.content())
+                .unwrap()
+                .matches("/begin MEASUREMENT")
+                .count(),
+            3
+        );
+
+        let content =
+            A2lGenerator::remove_variables(project.content(), &["deep".to_string()]).unwrap();
+        let written = project.write(&content).unwrap();
+        assert_eq!(written, [dir.join("parts/nested.a2l")]);
+        assert_eq!(std::fs::read_to_string(&master).unwrap(), MASTER);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("parts/meas.a2l")).unwrap(),
+            PART
+        );
+        assert!(!std::fs::read_to_string(dir.join("parts/nested.a2l"))
+            .unwrap()
+            .contains("deep"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_diagnostics_point_into_included_file() {
+        let dir = std::env::temp_dir().join(format!("a2l_include_diag_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        let master = dir.join("master.a2l");
+        std::fs::write(&master, MASTER).unwrap();
+        std::fs::write(dir.join("parts/meas.a2l"), PART.replace("inner", "top")).unwrap();
+        std::fs::write(dir.join("parts/nested.a2l"), NESTED.replace("deep", "top")).unwrap();
+
+        let project = A2lProject::load(&master).unwrap();
+        let duplicates: Vec<_> = project
+            .validate()
+            .unwrap()
+            .into_iter()
+            .filter(|d| d.code == "duplicate-name")
+            .map(|d| (d.file, d.line, d.first_file, d.first_line))
+            .collect();
+        let part = Some("parts/meas.a2l".to_string());
+        assert_eq!(
+            duplicates,
+            [
+                (
+                    Some("parts/nested.a2l".to_string()),
+                    1,
+                    part.clone(),
+                    Some(1)
+                ),
+                (None, 5, part, Some(1))
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_repeated_include_conflict() {
+        let dir = std::env::temp_dir().join(format!("a2l_include_twice_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let master = dir.join("master.a2l");
+        std::fs::write(
+            &master,
+            MASTER.replace(
+                "/include \"parts/meas.a2l\"",
+                "/include a.a2l\n    /include a.a2l",
+            ),
+        )
+        .unwrap();
+        std::fs::write(dir.join("a.a2l"), NESTED).unwrap();
+
+        let mut project = A2lProject::load(&master).unwrap();
+        assert_eq!(project.files().count(), 3);
+
+        // 两处内容相同时照常写回
+        let content = project
+            .content()
+            .replace("ECU_ADDRESS 0x1000", "ECU_ADDRESS 0x1100");
+        assert_eq!(
+            project.write(&content).unwrap(),
+            std::slice::from_ref(&master)
+        );
+
+        // 只修改其中一处时报错，文件保持不变
+        let content = project.content().replacen("0x3000", "0x3100", 1);
+        assert!(project.write(&content).is_err());
+        assert_eq!(std::fs::read_to_string(dir.join("a.a2l")).unwrap(), NESTED);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_new_blocks_stay_in_main_file() {
+        use crate::a2l::ExportKind;
+        use crate::group::GroupBy;
+        use crate::naming::NamingPolicy;
+        use crate::types::{A2lEntry, Endianness};
+
+        let dir = std::env::temp_dir().join(format!("a2l_include_append_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        let master = dir.join("master.a2l");
+        // 包含指令位于 MODULE 末尾，最后一个变量块来自被包含文件
+        let trailing = MASTER
+            .replace("    /include \"parts/meas.a2l\"\n", "")
+            .replace(
+                "    /end MEASUREMENT\n",
+                "    /end MEASUREMENT\n    /include \"parts/meas.a2l\"\n",
+            );
+        std::fs::write(&master, &trailing).unwrap();
+        std::fs::write(dir.join("parts/meas.a2l"), PART).unwrap();
+        std::fs::write(dir.join("parts/nested.a2l"), NESTED).unwrap();
+
+        let entries = [A2lEntry::new(
+            "added".to_string(),
+            0x5000,
+            2,
+            "UWORD".to_string(),
+            "uint16_t".to_string(),
+        )];
+        for (existing, kind) in [
+            (3, ExportKind::Measurement),
+            (4, ExportKind::Characteristic),
+        ] {
+            let mut entries = entries.clone();
+            entries[0].full_name = format!("added_{:?}", kind);
+            let result = A2lGenerator::append_to_file(
+                &entries,
+                &master,
+                kind,
+                &NamingPolicy::new(),
+                GroupBy::None,
+                Endianness::Little,
+                None,
+            )
+            .unwrap();
+            assert_eq!((result.added, result.existing), (1, existing));
+        }
+
+        let main = std::fs::read_to_string(&master).unwrap();
+        assert!(main.contains("/begin MEASUREMENT added_Measurement"));
+        assert!(main.contains("/begin CHARACTERISTIC added_Characteristic"));
+        assert!(main.contains("/begin RECORD_LAYOUT __UWord_Value"));
+        let include = main.find("/include").unwrap();
+        assert!(main.find("added_Measurement").unwrap() < include);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("parts/meas.a2l")).unwrap(),
+            PART
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("parts/nested.a2l")).unwrap(),
+            NESTED
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,481 @@
+use crate::a2l::{insert_after_last, A2lGenerator, BlockLayout, VARIABLE_KEYWORDS};
+use crate::a2l_syntax::{A2lDocument, Block, TextEdits, Value};
+use crate::validate::REFERENCE_LISTS;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+const CONVERSION_KEYWORDS: &[&str] = &[
+    "COMPU_METHOD",
+    "COMPU_TAB",
+    "COMPU_VTAB",
+    "COMPU_VTAB_RANGE",
+];
+
+/// 目标文件中已有同名变量、GROUP 或 FUNCTION 时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// 保留目标文件中的块
+    #[default]
+    Skip,
+    /// 用来源文件中的块替换
+    Overwrite,
+    /// 来源块加前缀后导入
+    Rename,
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "rename" => Ok(Self::Rename),
+            _ => anyhow::bail!("无效的冲突策略: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MergeOptions {
+    pub policy: ConflictPolicy,
+    /// 改名时添加的前缀，为空时改为追加 `_2`、`_3` 等后缀
+    pub prefix: String,
+}
+
+impl MergeOptions {
+    pub fn new(policy: ConflictPolicy) -> Self {
+        Self {
+            policy,
+            prefix: String::new(),
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeAction {
+    Imported,
+    /// 目标已有同名块，按策略跳过
+    Skipped,
+    Overwritten,
+    /// 改名后导入，引用随之更新
+    Renamed,
+    /// 目标已有内容相同的转换或记录布局，直接引用
+    Reused,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeItem {
+    pub keyword: String,
+    pub name: String,
+    pub action: MergeAction,
+    /// 改名后的名称
+    pub new_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MergeReport {
+    pub items: Vec<MergeItem>,
+}
+
+impl MergeReport {
+    pub fn count(&self, action: MergeAction) -> usize {
+        self.items.iter().filter(|i| i.action == action).count()
+    }
+}
+
+/// 名称各自独立的块类别，按依赖顺序排列：被引用的类别先处理，
+/// 改名结果才能参与后续类别的内容比较
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Namespace {
+    Table,
+    RecordLayout,
+    Method,
+    Variable,
+    Group,
+    Function,
+}
+
+impl Namespace {
+    const ORDER: [Namespace; 6] = [
+        Self::Table,
+        Self::RecordLayout,
+        Self::Method,
+        Self::Variable,
+        Self::Group,
+        Self::Function,
+    ];
+
+    fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Self::Table => &["COMPU_TAB", "COMPU_VTAB", "COMPU_VTAB_RANGE"],
+            Self::RecordLayout => &["RECORD_LAYOUT"],
+            Self::Method => &["COMPU_METHOD"],
+            Self::Variable => VARIABLE_KEYWORDS,
+            Self::Group => &["GROUP"],
+            Self::Function => &["FUNCTION"],
+        }
+    }
+
+    fn of(keyword: &str) -> Option<Self> {
+        Self::ORDER
+            .into_iter()
+            .find(|ns| ns.keywords().contains(&keyword))
+    }
+
+    /// 转换与记录布局同名但内容不同时总是改名，内容相同时复用
+    fn reusable(self) -> bool {
+        matches!(self, Self::Table | Self::RecordLayout | Self::Method)
+    }
+}
+
+type Renames = HashMap<(Namespace, String), String>;
+
+fn blocks_in<'d, 'a>(doc: &'d A2lDocument<'a>, ns: Namespace) -> Vec<&'d Block<'a>> {
+    doc.blocks()
+        .into_iter()
+        .filter(|b| ns.keywords().contains(&b.keyword()))
+        .collect()
+}
+
+fn find_in<'d, 'a>(doc: &'d A2lDocument<'a>, ns: Namespace, name: &str) -> Option<&'d Block<'a>> {
+    ns.keywords().iter().find_map(|k| doc.find_block(k, name))
+}
+
+/// 块名之后的全部内容，空白统一为单个空格，用于判断转换是否等价
+fn signature(doc: &A2lDocument, block: &Block) -> String {
+    let start = block
+        .value(0)
+        .map_or(block.keyword.span.end, |v| v.span.end);
+    doc.source()[start..block.end.start]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 按改名表改写块名及其全部引用：变量的转换、记录布局与轴点引用，
+/// COMPU_METHOD 的转换表引用，以及分组列表
+fn apply_renames(content: &str, renames: &Renames) -> Result<String> {
+    if renames.is_empty() {
+        return Ok(content.to_string());
+    }
+    let doc = A2lDocument::parse(content)?;
+    let mut edits = TextEdits::new();
+    let mut rename = |ns: Namespace, value: Option<&Value>| {
+        if let Some(value) = value {
+            if let Some(new_name) = renames.get(&(ns, value.text.to_string())) {
+                edits.replace(value.span, new_name.clone());
+            }
+        }
+    };
+
+    for block in doc.blocks() {
+        let keyword = block.keyword();
+        if let Some(ns) = Namespace::of(keyword) {
+            rename(ns, block.value(0));
+        }
+        if VARIABLE_KEYWORDS.contains(&keyword) {
+            let layout = BlockLayout::of(keyword);
+            rename(Namespace::Method, block.value(layout.conversion));
+            rename(
+                Namespace::RecordLayout,
+                layout.deposit.and_then(|i| block.value(i)),
+            );
+            for axis in block.blocks().filter(|b| b.keyword() == "AXIS_DESCR") {
+                // attribute input_quantity conversion max_axis_points lower upper
+                rename(Namespace::Method, axis.value(2));
+                if let Some(args) = axis.keyword_args("AXIS_PTS_REF", 6, 1) {
+                    rename(Namespace::Variable, Some(args[0]));
+                }
+            }
+        }
+        if keyword == "COMPU_METHOD" {
+            if let Some(args) = block.keyword_args("COMPU_TAB_REF", 5, 1) {
+                rename(Namespace::Table, Some(args[0]));
+            }
+        }
+        if let Some(&(_, targets)) = REFERENCE_LISTS.iter().find(|(list, _)| *list == keyword) {
+            if let Some(ns) = Namespace::of(targets[0]) {
+                for value in block.values() {
+                    rename(ns, Some(value));
+                }
+            }
+        }
+    }
+
+    edits.apply(content)
+}
+
+/// 把一个或多个来源 A2L 的变量、转换、转换表、记录布局、GROUP 与 FUNCTION 合并到目标 MODULE。
+/// 转换类块同名时内容相同则复用，否则改名；变量与分组同名时按 `options.policy` 处理。
+/// 改名后来源块中的引用一并更新
+pub fn merge_a2l(
+    target: &str,
+    sources: &[&str],
+    options: &MergeOptions,
+) -> Result<(String, MergeReport)> {
+    let mut content = target.to_string();
+    let mut report = MergeReport::default();
+    for source in sources {
+        content = merge_source(&content, source, options, &mut report)?;
+    }
+    Ok((content, report))
+}
+
+fn merge_source(
+    target: &str,
+    source: &str,
+    options: &MergeOptions,
+    report: &mut MergeReport,
+) -> Result<String> {
+    let target_doc = A2lDocument::parse(target)?;
+    let mut renames = Renames::new();
+    let mut excluded: HashSet<(Namespace, String)> = HashSet::new();
+    let mut overwritten: Vec<(Namespace, String)> = Vec::new();
+
+    for ns in Namespace::ORDER {
+        // 已确定的改名先应用到来源，转换的内容比较才能反映改名后的引用
+        let renamed = apply_renames(source, &renames)?;
+        let source_doc = A2lDocument::parse(&renamed)?;
+        let mut taken: HashSet<String> = blocks_in(&target_doc, ns)
+            .iter()
+            .chain(&blocks_in(&source_doc, ns))
+            .filter_map(|b| b.name())
+            .map(|n| n.to_string())
+            .collect();
+
+        for block in blocks_in(&source_doc, ns) {
+            let Some(name) = block.name() else {
+                continue;
+            };
+            let mut item = MergeItem {
+                keyword: block.keyword().to_string(),
+                name: name.to_string(),
+                action: MergeAction::Imported,
+                new_name: None,
+            };
+            if let Some(existing) = find_in(&target_doc, ns, name) {
+                let equivalent = ns.reusable()
+                    && existing.keyword() == block.keyword()
+                    && signature(&target_doc, existing) == signature(&source_doc, block);
+                let policy = if ns.reusable() {
+                    ConflictPolicy::Rename
+                } else {
+                    options.policy
+                };
+                item.action = match policy {
+                    _ if equivalent => MergeAction::Reused,
+                    ConflictPolicy::Skip => MergeAction::Skipped,
+                    ConflictPolicy::Overwrite => {
+                        overwritten.push((ns, name.to_string()));
+                        MergeAction::Overwritten
+                    }
+                    ConflictPolicy::Rename => {
+                        let new_name = unique_name(name, &options.prefix, &taken);
+                        taken.insert(new_name.clone());
+                        renames.insert((ns, name.to_string()), new_name.clone());
+                        item.new_name = Some(new_name);
+                        MergeAction::Renamed
+                    }
+                };
+                if matches!(item.action, MergeAction::Reused | MergeAction::Skipped) {
+                    excluded.insert((ns, name.to_string()));
+                }
+            }
+            report.items.push(item);
+        }
+    }
+
+    // 提取改名后的来源块
+    let renamed = apply_renames(source, &renames)?;
+    let source_doc = A2lDocument::parse(&renamed)?;
+    let originals: HashMap<(Namespace, &str), &str> = renames
+        .iter()
+        .map(|((ns, old), new)| ((*ns, new.as_str()), old.as_str()))
+        .collect();
+    let mut texts: HashMap<Namespace, String> = HashMap::new();
+    for ns in Namespace::ORDER {
+        for block in blocks_in(&source_doc, ns) {
+            let name = block.name().unwrap_or_default();
+            let original = originals.get(&(ns, name)).copied().unwrap_or(name);
+            if excluded.contains(&(ns, original.to_string())) {
+                continue;
+            }
+            let text = texts.entry(ns).or_default();
+            text.push_str(text_of(&renamed, &source_doc, block));
+            text.push('\n');
+        }
+    }
+
+    // 删除被覆盖的目标块后按类别插入
+    let mut edits = TextEdits::new();
+    for (ns, name) in &overwritten {
+        if let Some(block) = find_in(&target_doc, *ns, name) {
+            edits.delete(target_doc.line_extent(block.span()));
+        }
+    }
+    let mut content = edits.apply(target)?;
+
+    let conversions: String = [Namespace::Table, Namespace::Method]
+        .iter()
+        .filter_map(|ns| texts.get(ns))
+        .cloned()
+        .collect();
+    let insertions: [(String, &[&[&str]]); 3] = [
+        (
+            conversions,
+            &[CONVERSION_KEYWORDS, &["RECORD_LAYOUT"], VARIABLE_KEYWORDS],
+        ),
+        (
+            texts.remove(&Namespace::RecordLayout).unwrap_or_default(),
+            &[&["RECORD_LAYOUT"], CONVERSION_KEYWORDS, VARIABLE_KEYWORDS],
+        ),
+        (
+            [Namespace::Group, Namespace::Function]
+                .iter()
+                .filter_map(|ns| texts.get(ns))
+                .cloned()
+                .collect(),
+            &[&["GROUP", "FUNCTION"]],
+        ),
+    ];
+    for (blocks, anchors) in insertions {
+        if blocks.is_empty() {
+            continue;
+        }
+        let doc = A2lDocument::parse(&content)?;
+        content =
+            insert_after_last(&doc, anchors, &blocks).context("无法找到合并块的插入位置")??;
+    }
+    if let Some(variables) = texts.get(&Namespace::Variable) {
+        content = A2lGenerator::insert_blocks(&content, variables)?;
+    }
+
+    Ok(content)
+}
+
+/// 块所在的完整行
+fn text_of<'s>(content: &'s str, doc: &A2lDocument, block: &Block) -> &'s str {
+    let extent = doc.line_extent(block.span());
+    &content[extent.start..extent.end]
+}
+
+fn unique_name(name: &str, prefix: &str, taken: &HashSet<String>) -> String {
+    let base = format!("{}{}", prefix, name);
+    if !prefix.is_empty() && !taken.contains(&base) {
+        return base;
+    }
+    (2..)
+        .map(|i| format!("{}_{}", base, i))
+        .find(|n| !taken.contains(n))
+        .expect("后缀序号不会耗尽")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: &str = "\
+/begin PROJECT P \"\"
+  /begin MODULE M \"\"
+    /begin MEASUREMENT speed \"\"
+      UWORD CM_speed 0 0 0 655
+      ECU_ADDRESS 0x1000
+    /end MEASUREMENT
+
+    /begin COMPU_METHOD CM_speed \"\"
+      LINEAR \"%6.2\" \"km/h\"
+      COEFFS_LINEAR 0.01 0
+    /end COMPU_METHOD
+
+    /begin COMPU_METHOD CM_ident \"\"
+      IDENTICAL \"%6.2\" \"\"
+    /end COMPU_METHOD
+  /end MODULE
+/end PROJECT
+";
+
+    const SOURCE: &str = "\
+/begin PROJECT S \"\"
+  /begin MODULE S \"\"
+    /begin MEASUREMENT speed \"\"
+      UWORD CM_speed 0 0 0 6553
+      ECU_ADDRESS 0x2000
+    /end MEASUREMENT
+
+    /begin MEASUREMENT rpm \"\"
+      UWORD CM_ident 0 0 0 8000
+      ECU_ADDRESS 0x2002
+    /end MEASUREMENT
+
+    /begin COMPU_METHOD CM_speed \"\"
+      LINEAR \"%6.2\" \"km/h\"
+      COEFFS_LINEAR 0.1 0
+    /end COMPU_METHOD
+
+    /begin COMPU_METHOD CM_ident \"\"
+      IDENTICAL \"%6.2\" \"\"
+    /end COMPU_METHOD
+
+    /begin GROUP Engine \"\"
+      /begin REF_MEASUREMENT speed rpm /end REF_MEASUREMENT
+    /end GROUP
+  /end MODULE
+/end PROJECT
+";
+
+    #[test]
+    fn test_merge_rename() {
+        let options = MergeOptions::new(ConflictPolicy::Rename).with_prefix("S_");
+        let (output, report) = merge_a2l(TARGET, &[SOURCE], &options).unwrap();
+        let doc = A2lDocument::parse(&output).unwrap();
+
+        // 内容不同的同名转换改名，相同的复用
+        assert_eq!(report.count(MergeAction::Reused), 1);
+        assert!(doc.find_block("COMPU_METHOD", "S_CM_speed").is_some());
+        let speed = doc.find_block("MEASUREMENT", "S_speed").unwrap();
+        assert_eq!(speed.value(3).unwrap().text, "S_CM_speed");
+        let rpm = doc.find_block("MEASUREMENT", "rpm").unwrap();
+        assert_eq!(rpm.value(3).unwrap().text, "CM_ident");
+
+        let group = doc.find_block("GROUP", "Engine").unwrap();
+        let members: Vec<&str> = group
+            .block("REF_MEASUREMENT")
+            .unwrap()
+            .values()
+            .map(|v| v.text)
+            .collect();
+        assert_eq!(members, ["S_speed", "rpm"]);
+        assert!(crate::validate::validate(&output)
+            .unwrap()
+            .iter()
+            .all(|d| !d.is_error()));
+    }
+
+    #[test]
+    fn test_merge_skip_and_overwrite() {
+        let options = MergeOptions::new(ConflictPolicy::Skip);
+        let (output, report) = merge_a2l(TARGET, &[SOURCE], &options).unwrap();
+        let doc = A2lDocument::parse(&output).unwrap();
+        assert_eq!(report.count(MergeAction::Skipped), 1);
+        assert_eq!(doc.find_blocks("MEASUREMENT").len(), 2);
+        let speed = doc.find_block("MEASUREMENT", "speed").unwrap();
+        assert_eq!(speed.value(7).unwrap().text, "655");
+
+        let options = MergeOptions::new(ConflictPolicy::Overwrite).with_prefix("S_");
+        let (output, _) = merge_a2l(TARGET, &[SOURCE], &options).unwrap();
+        let doc = A2lDocument::parse(&output).unwrap();
+        assert_eq!(doc.find_blocks("MEASUREMENT").len(), 2);
+        let speed = doc.find_block("MEASUREMENT", "speed").unwrap();
+        assert_eq!(speed.value(3).unwrap().text, "S_CM_speed");
+    }
+}
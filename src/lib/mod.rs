@@ -1,22 +1,82 @@
 pub mod a2l;
+pub mod a2l_syntax;
 pub mod cache;
+pub mod compu;
+pub mod convert;
 pub mod data_package;
+pub mod diff;
 pub mod dwarf;
 pub mod elf;
+pub mod encoding;
+pub mod group;
 pub mod hash;
+pub mod include;
+pub mod merge;
+pub mod modpar;
+pub mod naming;
+pub mod progress;
+pub mod record_layout;
+pub mod rename;
+pub mod tree;
+pub mod type_rules;
 pub mod types;
+pub mod updater;
+pub mod validate;
+pub mod version;
+pub mod xcp;
 
 pub use a2l::{
-    A2lEntryInfo, A2lGenerator, A2lParser, A2lVariable, AppendResult, ExportKind, SaveResult,
-    VariableChanges, VariableEdit,
+    A2lEntryInfo, A2lGenerator, A2lParser, A2lVariable, AppendResult, ByteOrder, ExportKind,
+    SaveResult, VariableChanges, VariableEdit,
 };
+pub use a2l_syntax::{A2lDocument, Span, TextEdits};
 pub use cache::Cache;
-pub use data_package::{DataPackage, PackageMeta};
+pub use compu::{
+    ensure_conversions, enum_conversion, insert_conversions, CompuMethod, CompuMethodSpec,
+    CompuTable, ConversionKind, Conversions,
+};
+pub use convert::convert_variable;
+pub use data_package::{DataPackage, PackageContents, PackageMeta};
+pub use diff::{diff_a2l, A2lDiff, DiffObject, FieldChange, ObjectChange};
 pub use dwarf::{analyze_variables_with_dwarf, DwarfParser};
-pub use elf::{DwarfStats, ElfParser};
+pub use elf::{DwarfStats, ElfParser, ParseOptions};
+pub use encoding::{LineEnding, TextEncoding, TextFormat};
+pub use group::{
+    create_groups, delete_group, groups_for_entries, list_groups, merge_groups,
+    move_variable_membership, remove_variable_references, update_group, A2lGroup, GroupBy,
+    GroupKind,
+};
 pub use hash::{compute_file_hash, format_file_size};
+pub use include::A2lProject;
+pub use merge::{merge_a2l, ConflictPolicy, MergeAction, MergeItem, MergeOptions, MergeReport};
+pub use modpar::{
+    apply_module_params, propose_module_params, read_module_params, Alignments, MemorySegment,
+    ModuleParams,
+};
+pub use naming::{IndexStyle, NamingPolicy};
+pub use progress::{
+    is_cancelled_error, CancelToken, Cancelled, Progress, ProgressCallback, ProgressPhase,
+    ProgressReporter,
+};
+pub use record_layout::{complete_record_layouts, LayoutRole, StandardLayout};
+pub use rename::{find_references, rename_variable, rename_variable_references, Reference};
+pub use tree::{EntryTree, TreeNode, TreeNodeKind};
+pub use type_rules::{
+    default_type_rules, RuleTarget, TypeGuess, TypeGuessRecord, TypeGuessReport, TypeRule,
+    TypeRules,
+};
 pub use types::{
     infer_a2l_type, infer_a2l_type_from_encoding, A2lEntry, A2lEntryStore, CacheEntry, Endianness,
-    EnumVariant, StructMember, TypeEncoding, TypeInfo, TypeKind, Variable, MAX_ARRAY_EXPAND,
-    MAX_NESTING_DEPTH,
+    EnumType, EnumVariant, ExpandConfig, ExpandLimits, StructMember, TypeEncoding, TypeInfo,
+    TypeKind, Variable, MAX_ARRAY_EXPAND, MAX_NESTING_DEPTH,
+};
+pub use updater::{update_addresses, UpdateItem, UpdateReport, UpdateStatus};
+pub use validate::{
+    is_valid_identifier, new_errors, new_warnings, validate, validate_document, Diagnostic,
+    Severity,
+};
+pub use version::{read_asap2_version, Asap2Version};
+pub use xcp::{
+    read_xcp_config, set_xcp_if_data, xcp_if_data, DaqConfig, DaqConfigType, DaqList, EventChannel,
+    EventDirection, IpProtocol, ProtocolLayer, Transport, XcpConfig,
 };
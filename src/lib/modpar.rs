@@ -0,0 +1,738 @@
+//! 模块级参数：`MOD_COMMON` 的字节序与对齐、`MOD_PAR` 的 EPK、ECU 与存储段
+//!
+//! 读取与无损写回都基于语法树，只改动与目标值不同的参数；
+//! 建议值从 ELF 的程序头、节、DWARF 结构体布局和 EPK 符号推断。
+
+use crate::a2l::A2lGenerator;
+use crate::a2l_syntax::{A2lDocument, Block, TextEdits, Value};
+use crate::compu::quote;
+use crate::dwarf::DwarfParser;
+use crate::types::{Endianness, TypeEncoding, TypeInfo, TypeKind};
+use crate::validate::parse_address;
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+use object::{
+    Architecture, Object, ObjectSection, ObjectSegment, ObjectSymbol, SectionFlags, SectionKind,
+    SegmentFlags,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs::File;
+use std::path::Path;
+
+/// 未给出符号大小时读取 EPK 的最大字节数
+const MAX_EPK_LEN: u64 = 256;
+
+/// ELF 程序头标志
+const PF_X: u32 = 0x1;
+const PF_W: u32 = 0x2;
+
+/// ELF 节标志：占用目标内存
+const SHF_ALLOC: u64 = 0x2;
+
+/// 模块级参数，`None` 表示保持文件中的现状
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ModuleParams {
+    pub byte_order: Option<Endianness>,
+    pub alignments: Alignments,
+    pub epk: Option<String>,
+    pub addr_epk: Option<u64>,
+    pub ecu: Option<String>,
+    /// 给出时按名称同步：更新已有段、删除多余段、补充缺少的段
+    pub memory_segments: Option<Vec<MemorySegment>>,
+}
+
+/// `ALIGNMENT_*` 关键字的取值（字节）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Alignments {
+    pub byte: Option<u32>,
+    pub word: Option<u32>,
+    pub long: Option<u32>,
+    pub int64: Option<u32>,
+    pub float32: Option<u32>,
+    pub float64: Option<u32>,
+}
+
+impl Alignments {
+    /// 按自然对齐给出各类型的对齐，不超过 `max`
+    fn natural(max: u32) -> Self {
+        Alignments {
+            byte: Some(1),
+            word: Some(2.min(max)),
+            long: Some(4.min(max)),
+            int64: Some(8.min(max)),
+            float32: Some(4.min(max)),
+            float64: Some(8.min(max)),
+        }
+    }
+
+    fn entries(&self) -> [(&'static str, Option<u32>); 6] {
+        [
+            ("ALIGNMENT_BYTE", self.byte),
+            ("ALIGNMENT_WORD", self.word),
+            ("ALIGNMENT_LONG", self.long),
+            ("ALIGNMENT_INT64", self.int64),
+            ("ALIGNMENT_FLOAT32_IEEE", self.float32),
+            ("ALIGNMENT_FLOAT64_IEEE", self.float64),
+        ]
+    }
+
+    fn slot(&mut self, size: usize, float: bool) -> Option<&mut Option<u32>> {
+        match (size, float) {
+            (2, false) => Some(&mut self.word),
+            (4, false) => Some(&mut self.long),
+            (8, false) => Some(&mut self.int64),
+            (4, true) => Some(&mut self.float32),
+            (8, true) => Some(&mut self.float64),
+            _ => None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries().iter().all(|(_, value)| value.is_none())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemorySegment {
+    pub name: String,
+    #[serde(default)]
+    pub long_identifier: String,
+    /// `CODE`、`DATA`、`VARIABLES` 等
+    pub prg_type: String,
+    /// `FLASH`、`RAM`、`EEPROM` 等
+    pub memory_type: String,
+    /// `INTERN` 或 `EXTERN`
+    #[serde(default = "default_attribute")]
+    pub attribute: String,
+    pub address: u64,
+    pub size: u64,
+}
+
+fn default_attribute() -> String {
+    "INTERN".to_string()
+}
+
+impl ModuleParams {
+    fn has_mod_par(&self) -> bool {
+        self.epk.is_some()
+            || self.addr_epk.is_some()
+            || self.ecu.is_some()
+            || self.memory_segments.as_ref().is_some_and(|s| !s.is_empty())
+    }
+
+    fn has_mod_common(&self) -> bool {
+        self.byte_order.is_some() || !self.alignments.is_empty()
+    }
+}
+
+/// 读取第一个 MODULE 的 `MOD_COMMON` 与 `MOD_PAR` 参数
+pub fn read_module_params(content: &str) -> Result<ModuleParams> {
+    let doc = A2lDocument::parse(content)?;
+    let mut params = ModuleParams::default();
+    let Some(module) = doc.find_blocks("MODULE").into_iter().next() else {
+        return Ok(params);
+    };
+
+    if let Some(common) = module.block("MOD_COMMON") {
+        params.byte_order = keyword_arg(common, "BYTE_ORDER")
+            .and_then(|v| Endianness::from_byte_order_keyword(v.text));
+        let number = |keyword| {
+            keyword_arg(common, keyword)
+                .and_then(|v| parse_address(v.text))
+                .map(|n| n as u32)
+        };
+        params.alignments = Alignments {
+            byte: number("ALIGNMENT_BYTE"),
+            word: number("ALIGNMENT_WORD"),
+            long: number("ALIGNMENT_LONG"),
+            int64: number("ALIGNMENT_INT64"),
+            float32: number("ALIGNMENT_FLOAT32_IEEE"),
+            float64: number("ALIGNMENT_FLOAT64_IEEE"),
+        };
+    }
+
+    if let Some(par) = module.block("MOD_PAR") {
+        params.epk = keyword_arg(par, "EPK").map(|v| v.unquoted());
+        params.addr_epk = keyword_arg(par, "ADDR_EPK").and_then(|v| parse_address(v.text));
+        params.ecu = keyword_arg(par, "ECU").map(|v| v.unquoted());
+        params.memory_segments = Some(
+            par.blocks()
+                .filter(|b| b.keyword() == "MEMORY_SEGMENT")
+                .map(read_segment)
+                .collect(),
+        );
+    }
+
+    Ok(params)
+}
+
+/// 把参数写回第一个 MODULE，缺少的 `MOD_PAR`/`MOD_COMMON` 会新建
+pub fn apply_module_params(content: &str, params: &ModuleParams) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let module = doc
+        .find_blocks("MODULE")
+        .into_iter()
+        .next()
+        .context("无法找到 MODULE")?;
+    let indent = format!("{}  ", doc.indent_at(module.begin.start));
+
+    let mut edits = TextEdits::new();
+    let mut created = String::new();
+
+    match module.block("MOD_PAR") {
+        Some(par) => update_mod_par(&doc, par, params, &mut edits),
+        None if params.has_mod_par() => created.push_str(&mod_par_block(params, &indent)),
+        None => {}
+    }
+    match module.block("MOD_COMMON") {
+        Some(common) => update_mod_common(&doc, common, params, &mut edits),
+        None if params.has_mod_common() => created.push_str(&mod_common_block(params, &indent)),
+        None => {}
+    }
+
+    // 新块放在 A2ML/MOD_PAR/MOD_COMMON 之后，没有时紧跟 MODULE 头所在行，
+    // 避免落进 /include 引入的文件
+    if !created.is_empty() {
+        let header = module
+            .value(1)
+            .or_else(|| module.value(0))
+            .unwrap_or(&module.keyword)
+            .span
+            .end;
+        let pos = module
+            .blocks()
+            .filter(|b| matches!(b.keyword(), "A2ML" | "MOD_PAR" | "MOD_COMMON"))
+            .last()
+            .map(|b| doc.line_extent(b.span()).end)
+            .unwrap_or_else(|| doc.line_end(header));
+        insert_lines(doc.source(), pos, created, &mut edits);
+    }
+
+    edits.apply(content)
+}
+
+/// 从 ELF 推断模块参数：存储段来自程序头（没有时用节），字节序来自文件头，
+/// 对齐按架构给出并用 DWARF 结构体布局修正；给出 `epk_symbol` 时读取其字符串与地址
+pub fn propose_module_params(elf_path: &Path, epk_symbol: Option<&str>) -> Result<ModuleParams> {
+    let file = File::open(elf_path).context("无法打开 ELF 文件")?;
+    let mmap = unsafe { Mmap::map(&file).context("无法创建内存映射")? };
+    let obj = object::File::parse(&*mmap).context("无法解析 ELF 文件")?;
+
+    let mut alignments = Alignments::natural(max_alignment(obj.architecture()));
+    if let Ok(dwarf) = DwarfParser::parse(&mmap) {
+        refine_alignments(&mut alignments, dwarf.type_cache());
+    }
+
+    let (epk, addr_epk) = match epk_symbol {
+        Some(symbol) => {
+            let (address, epk) = read_epk(&obj, symbol)?;
+            (Some(epk), Some(address))
+        }
+        None => (None, None),
+    };
+
+    Ok(ModuleParams {
+        byte_order: Some(if obj.is_little_endian() {
+            Endianness::Little
+        } else {
+            Endianness::Big
+        }),
+        alignments,
+        epk,
+        addr_epk,
+        ecu: None,
+        memory_segments: Some(memory_segments(&obj)),
+    })
+}
+
+/// 在行首位置插入若干行，位置前没有换行符时（最后一行）先补换行
+fn insert_lines(source: &str, pos: usize, lines: String, edits: &mut TextEdits) {
+    if pos > 0 && !source[..pos].ends_with('\n') {
+        edits.insert(pos, format!("\n{}", lines));
+    } else {
+        edits.insert(pos, lines);
+    }
+}
+
+fn keyword_arg<'d, 'a>(block: &'d Block<'a>, keyword: &str) -> Option<&'d Value<'a>> {
+    // 跳过块的说明字符串
+    block.keyword_args(keyword, 1, 1).map(|args| args[0])
+}
+
+fn read_segment(block: &Block) -> MemorySegment {
+    let text = |i| {
+        block
+            .value(i)
+            .map(|v| v.text.to_string())
+            .unwrap_or_default()
+    };
+    let number = |i| {
+        block
+            .value(i)
+            .and_then(|v| parse_address(v.text))
+            .unwrap_or(0)
+    };
+    MemorySegment {
+        name: text(0),
+        long_identifier: block.value(1).map(|v| v.unquoted()).unwrap_or_default(),
+        prg_type: text(2),
+        memory_type: text(3),
+        attribute: text(4),
+        address: number(5),
+        size: number(6),
+    }
+}
+
+fn hex(n: u64) -> String {
+    format!("0x{:X}", n)
+}
+
+fn same_number(value: &Value, n: u64) -> bool {
+    parse_address(value.text) == Some(n)
+}
+
+fn same_string(value: &Value, s: &str) -> bool {
+    value.unquoted() == s
+}
+
+/// 设置块中单参数关键字：已有且不同则替换参数，没有则在最后一个参数所在行之后插入
+fn set_keyword(
+    doc: &A2lDocument,
+    block: &Block,
+    keyword: &str,
+    text: String,
+    same: impl Fn(&Value) -> bool,
+    edits: &mut TextEdits,
+) {
+    match keyword_arg(block, keyword) {
+        Some(value) if same(value) => {}
+        Some(value) => edits.replace(value.span, text),
+        None => {
+            let anchor = block.values().last().unwrap_or(&block.keyword);
+            let indent = format!("{}  ", doc.indent_at(block.begin.start));
+            A2lGenerator::insert_line(
+                doc,
+                block,
+                anchor.span.end,
+                &indent,
+                &format!("{} {}", keyword, text),
+                edits,
+            );
+        }
+    }
+}
+
+fn update_mod_common(
+    doc: &A2lDocument,
+    common: &Block,
+    params: &ModuleParams,
+    edits: &mut TextEdits,
+) {
+    if let Some(order) = params.byte_order {
+        let keyword = order.byte_order_keyword();
+        let same = |v: &Value| Endianness::from_byte_order_keyword(v.text) == Some(order);
+        set_keyword(doc, common, "BYTE_ORDER", keyword.to_string(), same, edits);
+    }
+    for (keyword, value) in params.alignments.entries() {
+        if let Some(value) = value {
+            let same = |v: &Value| same_number(v, value as u64);
+            set_keyword(doc, common, keyword, value.to_string(), same, edits);
+        }
+    }
+}
+
+fn update_mod_par(doc: &A2lDocument, par: &Block, params: &ModuleParams, edits: &mut TextEdits) {
+    if let Some(ecu) = &params.ecu {
+        set_keyword(doc, par, "ECU", quote(ecu), |v| same_string(v, ecu), edits);
+    }
+    if let Some(addr) = params.addr_epk {
+        set_keyword(
+            doc,
+            par,
+            "ADDR_EPK",
+            hex(addr),
+            |v| same_number(v, addr),
+            edits,
+        );
+    }
+    if let Some(epk) = &params.epk {
+        set_keyword(doc, par, "EPK", quote(epk), |v| same_string(v, epk), edits);
+    }
+
+    let Some(segments) = &params.memory_segments else {
+        return;
+    };
+    let existing: Vec<&Block> = par
+        .blocks()
+        .filter(|b| b.keyword() == "MEMORY_SEGMENT")
+        .collect();
+    for block in &existing {
+        match segments
+            .iter()
+            .find(|s| block.name() == Some(s.name.as_str()))
+        {
+            Some(segment) => update_segment(block, segment, edits),
+            None => edits.delete(doc.line_extent(block.span())),
+        }
+    }
+
+    let indent = existing
+        .first()
+        .map(|b| doc.indent_at(b.begin.start).to_string())
+        .unwrap_or_else(|| format!("{}  ", doc.indent_at(par.begin.start)));
+    let added: String = segments
+        .iter()
+        .filter(|s| !existing.iter().any(|b| b.name() == Some(s.name.as_str())))
+        .map(|s| segment_block(s, &indent))
+        .collect();
+    if !added.is_empty() {
+        let pos = existing
+            .last()
+            .map(|b| doc.line_extent(b.span()).end)
+            .unwrap_or_else(|| doc.line_start(par.end.start));
+        edits.insert(pos, added);
+    }
+}
+
+/// 只替换不同的位置参数，段内的 IF_DATA 等子块保持不变
+fn update_segment(block: &Block, segment: &MemorySegment, edits: &mut TextEdits) {
+    let mut set = |index: usize, text: String, same: bool| {
+        if let Some(value) = block.value(index) {
+            if !same {
+                edits.replace(value.span, text);
+            }
+        }
+    };
+    let value = |i| block.value(i);
+    set(
+        1,
+        quote(&segment.long_identifier),
+        value(1).is_some_and(|v| same_string(v, &segment.long_identifier)),
+    );
+    set(
+        2,
+        segment.prg_type.clone(),
+        value(2).is_some_and(|v| v.text == segment.prg_type),
+    );
+    set(
+        3,
+        segment.memory_type.clone(),
+        value(3).is_some_and(|v| v.text == segment.memory_type),
+    );
+    set(
+        4,
+        segment.attribute.clone(),
+        value(4).is_some_and(|v| v.text == segment.attribute),
+    );
+    set(
+        5,
+        format!("0x{:08X}", segment.address),
+        value(5).is_some_and(|v| same_number(v, segment.address)),
+    );
+    set(
+        6,
+        hex(segment.size),
+        value(6).is_some_and(|v| same_number(v, segment.size)),
+    );
+}
+
+fn segment_block(segment: &MemorySegment, indent: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{}/begin MEMORY_SEGMENT {} {} {} {} {} 0x{:08X} {} -1 -1 -1 -1 -1",
+        indent,
+        segment.name,
+        quote(&segment.long_identifier),
+        segment.prg_type,
+        segment.memory_type,
+        segment.attribute,
+        segment.address,
+        hex(segment.size)
+    );
+    let _ = writeln!(out, "{}/end MEMORY_SEGMENT", indent);
+    out
+}
+
+fn mod_par_block(params: &ModuleParams, indent: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{}/begin MOD_PAR \"\"", indent);
+    if let Some(ecu) = &params.ecu {
+        let _ = writeln!(out, "{}  ECU {}", indent, quote(ecu));
+    }
+    if let Some(addr) = params.addr_epk {
+        let _ = writeln!(out, "{}  ADDR_EPK {}", indent, hex(addr));
+    }
+    if let Some(epk) = &params.epk {
+        let _ = writeln!(out, "{}  EPK {}", indent, quote(epk));
+    }
+    for segment in params.memory_segments.iter().flatten() {
+        out.push_str(&segment_block(segment, &format!("{}  ", indent)));
+    }
+    let _ = writeln!(out, "{}/end MOD_PAR", indent);
+    out
+}
+
+fn mod_common_block(params: &ModuleParams, indent: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{}/begin MOD_COMMON \"\"", indent);
+    if let Some(order) = params.byte_order {
+        let _ = writeln!(out, "{}  BYTE_ORDER {}", indent, order.byte_order_keyword());
+    }
+    for (keyword, value) in params.alignments.entries() {
+        if let Some(value) = value {
+            let _ = writeln!(out, "{}  {} {}", indent, keyword, value);
+        }
+    }
+    let _ = writeln!(out, "{}/end MOD_COMMON", indent);
+    out
+}
+
+/// 架构 ABI 中基本类型的最大对齐
+fn max_alignment(arch: Architecture) -> u32 {
+    match arch {
+        Architecture::I386 => 4,
+        Architecture::M68k | Architecture::Msp430 => 2,
+        Architecture::Avr => 1,
+        _ => 8,
+    }
+}
+
+/// 用结构体成员偏移修正对齐：成员偏移能被整除的最大 2 的幂不小于该类型的对齐，
+/// 取观察到的最小值；存在奇数偏移多字节成员的结构体视为 packed，不参与推断
+fn refine_alignments(alignments: &mut Alignments, types: &HashMap<u64, TypeInfo>) {
+    for ty in types.values().filter(|t| t.kind == TypeKind::Struct) {
+        let members: Vec<_> = ty
+            .members
+            .iter()
+            .filter(|m| m.bit_size.is_none() && m.type_size > 1)
+            .collect();
+        if members.iter().any(|m| m.offset % 2 == 1) {
+            continue;
+        }
+        for member in members.into_iter().filter(|m| m.offset > 0) {
+            let Some(member_type) = member.type_offset.and_then(|o| types.get(&o)) else {
+                continue;
+            };
+            if !matches!(member_type.kind, TypeKind::Primitive | TypeKind::Enum) {
+                continue;
+            }
+            let float = member_type.encoding == TypeEncoding::Float;
+            let observed = 1u32 << member.offset.trailing_zeros().min(3);
+            if let Some(Some(current)) = alignments.slot(member.type_size, float) {
+                if observed < *current {
+                    *current = observed;
+                }
+            }
+        }
+    }
+}
+
+/// 程序头中的加载段（没有时用占内存的节）转换为存储段，名称取段内第一个节
+fn memory_segments(obj: &object::File) -> Vec<MemorySegment> {
+    let sections: Vec<(String, u64, u64, SectionKind)> = obj
+        .sections()
+        .filter(|s| s.size() > 0)
+        .filter(|s| match s.flags() {
+            SectionFlags::Elf { sh_flags } => sh_flags & SHF_ALLOC != 0,
+            _ => true,
+        })
+        .filter_map(|s| {
+            let name = s.name().ok()?.to_string();
+            Some((name, s.address(), s.size(), s.kind()))
+        })
+        .collect();
+
+    let mut ranges: Vec<(u64, u64, &str, &str)> = obj
+        .segments()
+        .filter(|s| s.size() > 0)
+        .map(|s| {
+            let flags = match s.flags() {
+                SegmentFlags::Elf { p_flags } => p_flags,
+                _ => 0,
+            };
+            let (prg_type, memory_type) = if flags & PF_X != 0 {
+                ("CODE", "FLASH")
+            } else if flags & PF_W != 0 {
+                ("VARIABLES", "RAM")
+            } else {
+                ("DATA", "FLASH")
+            };
+            (s.address(), s.size(), prg_type, memory_type)
+        })
+        .collect();
+    if ranges.is_empty() {
+        ranges = sections
+            .iter()
+            .filter_map(|(_, address, size, kind)| {
+                let (prg_type, memory_type) = match kind {
+                    SectionKind::Text => ("CODE", "FLASH"),
+                    SectionKind::Data
+                    | SectionKind::UninitializedData
+                    | SectionKind::Common
+                    | SectionKind::Tls
+                    | SectionKind::UninitializedTls => ("VARIABLES", "RAM"),
+                    SectionKind::ReadOnlyData
+                    | SectionKind::ReadOnlyDataWithRel
+                    | SectionKind::ReadOnlyString => ("DATA", "FLASH"),
+                    _ => return None,
+                };
+                Some((*address, *size, prg_type, memory_type))
+            })
+            .collect();
+    }
+    ranges.sort_by_key(|r| r.0);
+
+    let mut used = HashSet::new();
+    ranges
+        .into_iter()
+        .enumerate()
+        .map(|(i, (address, size, prg_type, memory_type))| {
+            let contained: Vec<&str> = sections
+                .iter()
+                .filter(|s| s.1 >= address && s.1 < address + size)
+                .map(|s| s.0.as_str())
+                .collect();
+            let base = contained
+                .first()
+                .map(|name| segment_name(name))
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| format!("SEG_{}", i));
+            let mut name = base.clone();
+            let mut n = 1;
+            while !used.insert(name.clone()) {
+                name = format!("{}_{}", base, n);
+                n += 1;
+            }
+            MemorySegment {
+                name,
+                long_identifier: contained.join(" "),
+                prg_type: prg_type.to_string(),
+                memory_type: memory_type.to_string(),
+                attribute: default_attribute(),
+                address,
+                size,
+            }
+        })
+        .collect()
+}
+
+/// 节名转换为 A2L 标识符，如 `.text` → `text`
+fn segment_name(section: &str) -> String {
+    let name: String = section
+        .trim_start_matches('.')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+/// 读取 EPK 符号的地址与字符串内容（到第一个 NUL 为止）
+fn read_epk(obj: &object::File, symbol: &str) -> Result<(u64, String)> {
+    let sym = obj
+        .symbol_by_name(symbol)
+        .with_context(|| format!("ELF 中找不到 EPK 符号: {}", symbol))?;
+    let section = sym
+        .section_index()
+        .and_then(|index| obj.section_by_index(index).ok())
+        .with_context(|| format!("EPK 符号 {} 不属于任何节", symbol))?;
+    let len = if sym.size() > 0 {
+        sym.size()
+    } else {
+        (section.address() + section.size())
+            .saturating_sub(sym.address())
+            .min(MAX_EPK_LEN)
+    };
+    let data = section
+        .data_range(sym.address(), len)
+        .ok()
+        .flatten()
+        .with_context(|| format!("无法读取 EPK 符号 {} 的内容", symbol))?;
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    Ok((
+        sym.address(),
+        String::from_utf8_lossy(&data[..end]).into_owned(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "/begin PROJECT p \"\"\n\
+        \x20 /begin MODULE m \"\"\n\
+        \x20   /begin MOD_PAR \"\"\n\
+        \x20     EPK \"old\"\n\
+        \x20     /begin MEMORY_SEGMENT text \"\" CODE FLASH INTERN 0x8000 0x100 -1 -1 -1 -1 -1\n\
+        \x20       /begin IF_DATA XCP /begin SEGMENT 0 1 0 0 0 /end SEGMENT /end IF_DATA\n\
+        \x20     /end MEMORY_SEGMENT\n\
+        \x20     /begin MEMORY_SEGMENT stale \"\" DATA FLASH INTERN 0x9000 0x10 -1 -1 -1 -1 -1\n\
+        \x20     /end MEMORY_SEGMENT\n\
+        \x20   /end MOD_PAR\n\
+        \x20   /begin MEASUREMENT speed \"\" UWORD NO_COMPU_METHOD 0 0 0 65535\n\
+        \x20   /end MEASUREMENT\n\
+        \x20 /end MODULE\n\
+        /end PROJECT\n";
+
+    #[test]
+    fn test_applies_params_losslessly() {
+        let segment = |name: &str, address| MemorySegment {
+            name: name.to_string(),
+            long_identifier: String::new(),
+            prg_type: "CODE".to_string(),
+            memory_type: "FLASH".to_string(),
+            attribute: "INTERN".to_string(),
+            address,
+            size: 0x100,
+        };
+        let params = ModuleParams {
+            byte_order: Some(Endianness::Big),
+            alignments: Alignments::natural(4),
+            epk: Some("v2".to_string()),
+            addr_epk: Some(0x8010),
+            memory_segments: Some(vec![segment("text", 0x8000), segment("boot", 0)]),
+            ..Default::default()
+        };
+
+        let out = apply_module_params(SAMPLE, &params).unwrap();
+        assert!(out.contains("EPK \"v2\""));
+        assert!(out.contains("ADDR_EPK 0x8010"));
+        assert!(out.contains("/begin SEGMENT 0 1 0 0 0 /end SEGMENT"));
+        assert!(!out.contains("stale"));
+        assert!(out.contains("/begin MEMORY_SEGMENT boot"));
+        assert!(out.find("/begin MOD_COMMON").unwrap() < out.find("MEASUREMENT").unwrap());
+
+        let read = read_module_params(&out).unwrap();
+        assert_eq!(read, params);
+        assert_eq!(apply_module_params(&out, &params).unwrap(), out);
+    }
+
+    #[test]
+    fn test_refines_alignment_from_struct_layout() {
+        let mut types = HashMap::new();
+        types.insert(
+            1,
+            TypeInfo::new("double".to_string(), 8, TypeEncoding::Float),
+        );
+        let mut s = TypeInfo::new("s".to_string(), 12, TypeEncoding::Unsigned);
+        s.kind = TypeKind::Struct;
+        let mut member =
+            crate::types::StructMember::new("d".to_string(), 4, "double".to_string(), 8);
+        member.type_offset = Some(1);
+        s.members.push(member);
+        types.insert(2, s);
+
+        let mut alignments = Alignments::natural(8);
+        refine_alignments(&mut alignments, &types);
+        assert_eq!(alignments.float64, Some(4));
+        assert_eq!(alignments.int64, Some(8));
+    }
+}
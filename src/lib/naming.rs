@@ -0,0 +1,412 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::validate::{is_valid_identifier, MAX_IDENTIFIER_LEN};
+
+/// 数组元素下标的命名风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexStyle {
+    /// `var._3_._1_`
+    #[default]
+    Underscore,
+    /// `var[3][1]`
+    Bracket,
+    /// `var_3_1`
+    Flat,
+    /// `var.3.1`
+    Dotted,
+}
+
+impl std::str::FromStr for IndexStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "underscore" => Ok(IndexStyle::Underscore),
+            "bracket" => Ok(IndexStyle::Bracket),
+            "flat" => Ok(IndexStyle::Flat),
+            "dotted" => Ok(IndexStyle::Dotted),
+            _ => anyhow::bail!(
+                "未知的下标风格: {}（可选 underscore/bracket/flat/dotted）",
+                s
+            ),
+        }
+    }
+}
+
+/// 条目命名策略，展开、数据包、导出和与已有 A2L 的名称匹配都使用同一策略
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NamingPolicy {
+    pub index_style: IndexStyle,
+    /// 结构体成员路径分隔符
+    pub separator: String,
+    /// 从变量名开头去除的前缀，按顺序匹配第一个
+    pub strip_prefixes: Vec<String>,
+    /// 标识符最大长度，超长时截断并追加哈希后缀
+    pub max_length: Option<usize>,
+}
+
+impl Default for NamingPolicy {
+    fn default() -> Self {
+        Self {
+            index_style: IndexStyle::Underscore,
+            separator: ".".to_string(),
+            strip_prefixes: Vec::new(),
+            max_length: None,
+        }
+    }
+}
+
+/// 截断后追加的哈希长度（十六进制字符数）
+const HASH_LEN: usize = 8;
+
+impl NamingPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_index_style(mut self, style: IndexStyle) -> Self {
+        self.index_style = style;
+        self
+    }
+
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    pub fn with_strip_prefix(mut self, prefix: &str) -> Self {
+        self.strip_prefixes.push(prefix.to_string());
+        self
+    }
+
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// 顶层变量名，去除配置的前缀（去除后为空时保留原名）
+    pub fn root_name(&self, name: &str) -> String {
+        self.strip_prefix(name).to_string()
+    }
+
+    fn strip_prefix<'a>(&self, name: &'a str) -> &'a str {
+        self.strip_prefixes
+            .iter()
+            .filter(|p| !p.is_empty())
+            .find_map(|p| {
+                name.strip_prefix(p.as_str())
+                    .filter(|rest| !rest.is_empty())
+            })
+            .unwrap_or(name)
+    }
+
+    pub fn member_name(&self, parent: &str, member: &str) -> String {
+        format!("{}{}{}", parent, self.separator, member)
+    }
+
+    pub fn element_name(&self, parent: &str, indices: &[usize]) -> String {
+        let mut name = parent.to_string();
+        for i in indices {
+            match self.index_style {
+                IndexStyle::Underscore => name.push_str(&format!("{}_{}_", self.separator, i)),
+                IndexStyle::Bracket => name.push_str(&format!("[{}]", i)),
+                IndexStyle::Flat => name.push_str(&format!("_{}", i)),
+                IndexStyle::Dotted => name.push_str(&format!("{}{}", self.separator, i)),
+            }
+        }
+        name
+    }
+
+    /// 写入条目前的最终名称：超过最大长度时截断，并以完整名称的哈希保证唯一且可复现
+    pub fn finalize(&self, name: &str) -> String {
+        let max = match self.max_length {
+            Some(max) if name.chars().count() > max => max,
+            _ => return name.to_string(),
+        };
+
+        let hash = name_hash(name);
+        let keep = max.saturating_sub(HASH_LEN + 1);
+        let head: String = name.chars().take(keep).collect();
+        if head.is_empty() {
+            // 上限不足以容纳哈希时截断哈希，结果仍不超过上限
+            hash[..max.min(HASH_LEN)].to_string()
+        } else {
+            format!("{}_{}", head, hash)
+        }
+    }
+
+    /// 写入 A2L 块名的 ASAP2 标识符：合法且不超长的名称原样保留，
+    /// 否则替换非法字符并追加原名的哈希，不同名称得到的标识符互不相同且可复现
+    pub fn identifier(&self, name: &str) -> String {
+        let max = self.identifier_length();
+        if is_valid_identifier(name) && name.chars().count() <= max {
+            return name.to_string();
+        }
+
+        let readable = readable_identifier(name).replace(['[', ']'], "_");
+        let keep = max.saturating_sub(HASH_LEN + 1).min(readable.len());
+        let head = readable[..keep].trim_end_matches('.');
+        let hash = name_hash(name);
+        if head.is_empty() {
+            format!("_{}", &hash[..max.saturating_sub(1).min(HASH_LEN)])
+        } else {
+            format!("{}_{}", head, hash)
+        }
+    }
+
+    /// 写入 DISPLAY_IDENTIFIER 的可读名称，`identifier` 保留原名时不需要，返回 `None`
+    pub fn display_identifier(&self, name: &str) -> Option<String> {
+        if self.identifier(name) == name {
+            return None;
+        }
+        let readable = readable_identifier(name);
+        let truncated: String = readable.chars().take(self.identifier_length()).collect();
+        let truncated = truncated.trim_end_matches('.');
+        // 截断可能切开下标
+        Some(if is_valid_identifier(truncated) {
+            truncated.to_string()
+        } else {
+            truncated.replace(['[', ']'], "_")
+        })
+    }
+
+    /// 标识符长度上限，取命名策略与 ASAP2 限制中较小者
+    fn identifier_length(&self) -> usize {
+        self.max_length
+            .map_or(MAX_IDENTIFIER_LEN, |max| max.min(MAX_IDENTIFIER_LEN))
+    }
+
+    /// 与命名风格无关的匹配键：成员路径按分隔符拆开，`._3_`、`[3]`、`.3` 形式的下标统一写成 `[3]`。
+    /// 成员与下标的位置都一致才得到相同的键，因此 `var._3_._1_`、`var[3][1]`、`var.3.1` 相同，
+    /// 而 `motor.speed` 与 `motor_speed` 不同。末尾的 `_3` 可能是普通名称的一部分，不视为下标，
+    /// 数组元素见 [`element_match_key`](Self::element_match_key)
+    pub fn match_key(&self, name: &str) -> String {
+        self.key(name, false)
+    }
+
+    /// 数组元素的匹配键：策略为 Flat 时末尾的 `_3` 也视为下标，其余风格与 `match_key` 相同
+    pub fn element_match_key(&self, name: &str) -> String {
+        self.key(name, self.index_style == IndexStyle::Flat)
+    }
+
+    fn key(&self, name: &str, flat: bool) -> String {
+        let name = self.strip_prefix(name);
+        let name = if self.separator.is_empty() || self.separator == "." {
+            name.to_string()
+        } else {
+            name.replace(self.separator.as_str(), ".")
+        };
+
+        let mut key = String::with_capacity(name.len());
+        for (i, part) in name.split('.').enumerate() {
+            let (base, indices) = split_indices(part, flat);
+            // 整段为下标时直接接在前一段之后
+            if i > 0 && (!base.is_empty() || indices.is_empty()) {
+                key.push('.');
+            }
+            key.push_str(base);
+            for index in indices {
+                key.push_str(&format!("[{}]", index));
+            }
+        }
+        key
+    }
+}
+
+/// 路径中的一段拆成名称与下标：`_3_`、`3` 整段为下标，`name[3][1]` 取方括号中的下标，
+/// `flat` 时 `name_3_1` 取末尾的数字。无法识别时整段作为名称
+fn split_indices(part: &str, flat: bool) -> (&str, Vec<&str>) {
+    let is_index = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if let Some(index) = part
+        .strip_prefix('_')
+        .and_then(|p| p.strip_suffix('_'))
+        .filter(|p| is_index(p))
+    {
+        return ("", vec![index]);
+    }
+    if is_index(part) {
+        return ("", vec![part]);
+    }
+    if let Some(pos) = part.find('[') {
+        let indices: Option<Vec<&str>> = part[pos..]
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+            .map(|inner| inner.split("][").collect());
+        if let Some(indices) = indices.filter(|ids| ids.iter().all(|i| is_index(i))) {
+            return (&part[..pos], indices);
+        }
+        return (part, Vec::new());
+    }
+    if flat {
+        let mut base = part;
+        let mut indices = Vec::new();
+        while let Some((head, tail)) = base.rsplit_once('_') {
+            if head.is_empty() || !is_index(tail) {
+                break;
+            }
+            indices.insert(0, tail);
+            base = head;
+        }
+        return (base, indices);
+    }
+    (part, Vec::new())
+}
+
+/// 名称的短哈希，用于截断或合法化后保持唯一
+fn name_hash(name: &str) -> String {
+    let hash = format!("{:x}", Sha256::digest(name.as_bytes()));
+    hash[..HASH_LEN].to_string()
+}
+
+/// 尽量保留原样的合法标识符：`::` 视为层级分隔，其余非法字符替换为 `_`，
+/// 以数字开头或为空的部分补 `_`，不合法的下标展平
+fn readable_identifier(name: &str) -> String {
+    name.replace("::", ".")
+        .split('.')
+        .map(|part| {
+            let mut part: String = part
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || matches!(c, '_' | '[' | ']') {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            if !is_valid_identifier(&part) {
+                part = part.replace(['[', ']'], "_");
+            }
+            if !part.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                part.insert(0, '_');
+            }
+            part
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_element_name_styles() {
+        let idx = [3, 1];
+        let policy = NamingPolicy::new();
+        assert_eq!(policy.element_name("var", &idx), "var._3_._1_");
+        let policy = policy.with_index_style(IndexStyle::Bracket);
+        assert_eq!(policy.element_name("var", &idx), "var[3][1]");
+        let policy = policy.with_index_style(IndexStyle::Flat);
+        assert_eq!(policy.element_name("var", &idx), "var_3_1");
+        let policy = policy.with_index_style(IndexStyle::Dotted);
+        assert_eq!(policy.element_name("var", &idx), "var.3.1");
+    }
+
+    #[test]
+    fn test_match_key_across_styles() {
+        let policy = NamingPolicy::new();
+        let key = policy.match_key("var._3_._1_");
+        assert_eq!(key, "var[3][1]");
+        assert_eq!(policy.match_key("var[3][1]"), key);
+        assert_eq!(policy.match_key("var.3.1"), key);
+        assert_eq!(
+            policy.match_key("cfg._2_.gain"),
+            policy.match_key("cfg[2].gain")
+        );
+        assert_eq!(policy.match_key("__start"), "__start");
+        // 下划线风格之外，`_3_1` 只在 Flat 策略下的数组元素中视为下标
+        assert_ne!(policy.match_key("var_3_1"), key);
+        assert_ne!(policy.element_match_key("var_3_1"), key);
+        let flat = NamingPolicy::new().with_index_style(IndexStyle::Flat);
+        assert_eq!(flat.element_match_key("var_3_1"), key);
+        assert_eq!(flat.element_match_key("_3"), "_3");
+    }
+
+    #[test]
+    fn test_flat_scalar_is_not_element() {
+        // 名为 sensor_1 的普通变量与 sensor[1] 不同
+        let flat = NamingPolicy::new().with_index_style(IndexStyle::Flat);
+        assert_eq!(flat.match_key("sensor_1"), "sensor_1");
+        assert_ne!(flat.match_key("sensor_1"), flat.match_key("sensor[1]"));
+        assert_eq!(
+            flat.element_match_key("sensor_1"),
+            flat.match_key("sensor[1]")
+        );
+    }
+
+    #[test]
+    fn test_match_key_keeps_members_apart() {
+        let policy = NamingPolicy::new();
+        assert_ne!(
+            policy.match_key("motor.speed"),
+            policy.match_key("motor_speed")
+        );
+        assert_ne!(policy.match_key("a.b"), policy.match_key("a_b"));
+        assert_ne!(policy.match_key("a.b[1]"), policy.match_key("a_b[1]"));
+        assert_ne!(policy.match_key("sensor._1_"), policy.match_key("sensor_1"));
+    }
+
+    #[test]
+    fn test_strip_prefix() {
+        let policy = NamingPolicy::new().with_strip_prefix("g_");
+        assert_eq!(policy.root_name("g_speed"), "speed");
+        assert_eq!(policy.root_name("g_"), "g_");
+        assert_eq!(policy.match_key("g_speed"), policy.match_key("speed"));
+    }
+
+    #[test]
+    fn test_finalize_is_deterministic() {
+        let policy = NamingPolicy::new().with_max_length(20);
+        let long = "very_long_structure_name.member[10]";
+        let a = policy.finalize(long);
+        assert_eq!(a, policy.finalize(long));
+        assert_eq!(a.chars().count(), 20);
+        assert_ne!(a, policy.finalize("very_long_structure_name.member[11]"));
+        assert_eq!(policy.finalize("short"), "short");
+
+        // 上限小于哈希长度时同样不超长
+        for max in [1, 4, HASH_LEN, HASH_LEN + 1] {
+            let policy = NamingPolicy::new().with_max_length(max);
+            assert!(policy.finalize(long).chars().count() <= max);
+            let identifier = policy.identifier(long);
+            assert!(is_valid_identifier(&identifier));
+            assert!(identifier.chars().count() <= max.max(1));
+        }
+    }
+
+    #[test]
+    fn test_identifier_sanitisation() {
+        let policy = NamingPolicy::new();
+        assert_eq!(policy.identifier("cfg.table._3_.val"), "cfg.table._3_.val");
+        assert_eq!(policy.identifier("var[3][1]"), "var[3][1]");
+        assert_eq!(policy.display_identifier("var[3][1]"), None);
+
+        let cpp = policy.identifier("ns::T<int>::x");
+        assert!(is_valid_identifier(&cpp));
+        assert!(cpp.starts_with("ns.T_int_.x_"));
+        assert_eq!(cpp, policy.identifier("ns::T<int>::x"));
+        assert_ne!(cpp, policy.identifier("ns::T<int>.x"));
+        assert_eq!(
+            policy.display_identifier("ns::T<int>::x").as_deref(),
+            Some("ns.T_int_.x")
+        );
+        assert!(is_valid_identifier(&policy.identifier("var.3.1")));
+
+        let policy = policy.with_max_length(16);
+        let long = policy.identifier("very_long_structure_name.member[10]");
+        assert!(is_valid_identifier(&long));
+        assert_eq!(long.chars().count(), 16);
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// 解析/打包所处的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressPhase {
+    Symbols,
+    Dwarf,
+    Expand,
+    Save,
+    Done,
+}
+
+impl std::fmt::Display for ProgressPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgressPhase::Symbols => write!(f, "符号表"),
+            ProgressPhase::Dwarf => write!(f, "DWARF"),
+            ProgressPhase::Expand => write!(f, "条目展开"),
+            ProgressPhase::Save => write!(f, "保存"),
+            ProgressPhase::Done => write!(f, "完成"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress {
+    pub phase: ProgressPhase,
+    /// 当前阶段已处理的单元数（DWARF 编译单元 / 变量 / 条目）
+    pub processed: usize,
+    /// 当前阶段的单元总数，未知时为 0
+    pub total: usize,
+    /// 已展开的 A2L 条目数
+    pub entries: usize,
+}
+
+/// 取消令牌，可跨线程共享，在处理单元之间检查
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// 取消时返回的错误，调用方可通过 `downcast_ref::<Cancelled>()` 识别
+#[derive(Debug, Clone, Copy)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "操作已取消")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+pub fn is_cancelled_error(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<Cancelled>().is_some()
+}
+
+pub type ProgressCallback = dyn Fn(&Progress) + Send + Sync;
+
+/// 进度回调与取消令牌的组合，未设置时所有操作均为空操作
+#[derive(Clone, Copy, Default)]
+pub struct ProgressReporter<'a> {
+    callback: Option<&'a ProgressCallback>,
+    cancel: Option<&'a CancelToken>,
+}
+
+impl<'a> ProgressReporter<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_callback(mut self, callback: &'a ProgressCallback) -> Self {
+        self.callback = Some(callback);
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: &'a CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn report(&self, phase: ProgressPhase, processed: usize, total: usize, entries: usize) {
+        if let Some(callback) = self.callback {
+            callback(&Progress {
+                phase,
+                processed,
+                total,
+                entries,
+            });
+        }
+    }
+
+    pub fn check_cancelled(&self) -> Result<()> {
+        match self.cancel {
+            Some(token) if token.is_cancelled() => Err(Cancelled.into()),
+            _ => Ok(()),
+        }
+    }
+}
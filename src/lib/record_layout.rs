@@ -0,0 +1,194 @@
+use crate::a2l::{insert_after_last, A2lGenerator, BlockLayout, A2L_DATA_TYPES, VARIABLE_KEYWORDS};
+use crate::a2l_syntax::{A2lDocument, Block, TextEdits};
+use anyhow::{Context, Result};
+
+/// 标准记录布局存放的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutRole {
+    /// 特性值，`FNC_VALUES`
+    Values,
+    /// 轴点，`AXIS_PTS_X`
+    AxisPoints,
+}
+
+/// 生成器使用的标准 RECORD_LAYOUT，如 `__UWord_Value`、`__UWord_Axis`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StandardLayout {
+    pub role: LayoutRole,
+    pub data_type: &'static str,
+}
+
+impl StandardLayout {
+    /// 按标准命名反查，非标准名称返回 `None`
+    pub fn from_name(name: &str) -> Option<Self> {
+        A2L_DATA_TYPES.iter().find_map(|t| {
+            if A2lGenerator::get_record_layout(t) == name {
+                Some(Self {
+                    role: LayoutRole::Values,
+                    data_type: t,
+                })
+            } else if A2lGenerator::get_axis_layout(t) == name {
+                Some(Self {
+                    role: LayoutRole::AxisPoints,
+                    data_type: t,
+                })
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.role {
+            LayoutRole::Values => A2lGenerator::get_record_layout(self.data_type),
+            LayoutRole::AxisPoints => A2lGenerator::get_axis_layout(self.data_type),
+        }
+    }
+
+    /// 布局内容，单值、VAL_BLK 与多维特性共用按行存放的 FNC_VALUES
+    pub fn content(&self) -> String {
+        match self.role {
+            // FNC_VALUES position datatype index_mode addressing
+            LayoutRole::Values => format!("FNC_VALUES 1 {} ROW_DIR DIRECT", self.data_type),
+            // AXIS_PTS_X position datatype index_incr addressing
+            LayoutRole::AxisPoints => format!("AXIS_PTS_X 1 {} INDEX_INCR DIRECT", self.data_type),
+        }
+    }
+
+    pub fn to_a2l(&self) -> String {
+        format!(
+            "    /begin RECORD_LAYOUT {}\n      {}\n    /end RECORD_LAYOUT\n\n",
+            self.name(),
+            self.content()
+        )
+    }
+}
+
+/// RECORD_LAYOUT 名称之后的全部参数，空白统一为单个空格，用于比较布局是否等价
+fn layout_signature(block: &Block) -> String {
+    block
+        .values()
+        .skip(1)
+        .map(|v| v.text)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 补全 CHARACTERISTIC 与 AXIS_PTS 引用但文件中未定义的标准记录布局：
+/// 已有内容相同的布局时改为引用它，否则插入标准定义。非标准名称保持不变
+pub fn complete_record_layouts(content: &str) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let layouts = doc.find_blocks("RECORD_LAYOUT");
+
+    let mut edits = TextEdits::new();
+    let mut missing: Vec<StandardLayout> = Vec::new();
+    for block in doc.blocks() {
+        if !VARIABLE_KEYWORDS.contains(&block.keyword()) {
+            continue;
+        }
+        let Some(deposit) = BlockLayout::of(block.keyword())
+            .deposit
+            .and_then(|i| block.value(i))
+        else {
+            continue;
+        };
+        if layouts.iter().any(|l| l.name() == Some(deposit.text)) {
+            continue;
+        }
+        let Some(standard) = StandardLayout::from_name(deposit.text) else {
+            continue;
+        };
+        let signature = standard.content();
+        match layouts
+            .iter()
+            .find(|l| layout_signature(l) == signature)
+            .and_then(|l| l.name())
+        {
+            Some(equivalent) => edits.replace(deposit.span, equivalent),
+            None if !missing.contains(&standard) => missing.push(standard),
+            None => {}
+        }
+    }
+
+    let content = edits.apply(content)?;
+    if missing.is_empty() {
+        return Ok(content);
+    }
+
+    let definitions: String = missing.iter().map(|l| l.to_a2l()).collect();
+    let doc = A2lDocument::parse(&content)?;
+    insert_after_last(
+        &doc,
+        &[
+            &["RECORD_LAYOUT"],
+            &[
+                "COMPU_METHOD",
+                "COMPU_TAB",
+                "COMPU_VTAB",
+                "COMPU_VTAB_RANGE",
+            ],
+            VARIABLE_KEYWORDS,
+        ],
+        &definitions,
+    )
+    .context("无法找到记录布局的插入位置")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+/begin PROJECT P \"\"
+  /begin MODULE M \"\"
+    /begin CHARACTERISTIC gain \"\"
+      VALUE 0x1000 __UWord_Value 0 NO_COMPU_METHOD 0 65535
+    /end CHARACTERISTIC
+
+    /begin CHARACTERISTIC table \"\"
+      VAL_BLK 0x2000 __Float32_Value 0 NO_COMPU_METHOD -3.4E38 3.4E38
+      MATRIX_DIM 4
+    /end CHARACTERISTIC
+
+    /begin AXIS_PTS axis \"\"
+      0x3000 NO_INPUT_QUANTITY __Float32_Axis 0 NO_COMPU_METHOD 8 0 100
+    /end AXIS_PTS
+
+    /begin CHARACTERISTIC other \"\"
+      VALUE 0x4000 RL_CUSTOM 0 NO_COMPU_METHOD 0 255
+    /end CHARACTERISTIC
+
+    /begin RECORD_LAYOUT RL_WORD
+      FNC_VALUES  1 UWORD ROW_DIR DIRECT
+    /end RECORD_LAYOUT
+  /end MODULE
+/end PROJECT
+";
+
+    #[test]
+    fn test_complete_record_layouts() {
+        let output = complete_record_layouts(SAMPLE).unwrap();
+        let doc = A2lDocument::parse(&output).unwrap();
+
+        // 内容相同的已有布局被复用
+        let gain = doc.find_block("CHARACTERISTIC", "gain").unwrap();
+        assert_eq!(gain.value(4).unwrap().text, "RL_WORD");
+        assert!(doc.find_block("RECORD_LAYOUT", "__UWord_Value").is_none());
+
+        // 缺少的标准布局按引用插入，VAL_BLK 与轴点各一个
+        let values = doc.find_block("RECORD_LAYOUT", "__Float32_Value").unwrap();
+        assert_eq!(
+            layout_signature(values),
+            "FNC_VALUES 1 FLOAT32_IEEE ROW_DIR DIRECT"
+        );
+        let axis = doc.find_block("RECORD_LAYOUT", "__Float32_Axis").unwrap();
+        assert_eq!(
+            layout_signature(axis),
+            "AXIS_PTS_X 1 FLOAT32_IEEE INDEX_INCR DIRECT"
+        );
+
+        // 非标准名称不处理，再次补全不产生变化
+        assert!(doc.find_block("RECORD_LAYOUT", "RL_CUSTOM").is_none());
+        assert_eq!(complete_record_layouts(&output).unwrap(), output);
+    }
+}
@@ -0,0 +1,195 @@
+//! 变量改名：块名连同模块中对它的全部引用一起更新，
+//! 包括分组与函数的变量列表、轴的输入量与轴点引用、比较量、映射列表与虚拟量公式
+
+use crate::a2l::{positional_count, VARIABLE_KEYWORDS};
+use crate::a2l_syntax::{A2lDocument, Block, Span, TextEdits, Value};
+use crate::validate::is_valid_identifier;
+use anyhow::Result;
+use serde::Serialize;
+
+/// 每个值都是变量名的列表块
+const NAME_LISTS: &[&str] = &[
+    "REF_MEASUREMENT",
+    "REF_CHARACTERISTIC",
+    "DEF_CHARACTERISTIC",
+    "IN_MEASUREMENT",
+    "OUT_MEASUREMENT",
+    "LOC_MEASUREMENT",
+    "MAP_LIST",
+    "VIRTUAL",
+];
+
+/// 第一个参数为公式、其后为变量名的块
+const FORMULA_LISTS: &[&str] = &["DEPENDENT_CHARACTERISTIC", "VIRTUAL_CHARACTERISTIC"];
+
+/// 变量名出现的一处位置，行列号从 1 开始
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reference {
+    /// 名称所在的关键字：声明处为块关键字，引用处如 `REF_MEASUREMENT`、`AXIS_PTS_REF`
+    pub keyword: String,
+    /// 包含该位置的对象，如引用变量的 GROUP 或 CHARACTERISTIC
+    pub owner: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    /// 位于被包含文件时的文件名，见 [`A2lProject::find_references`](crate::A2lProject::find_references)
+    pub file: Option<String>,
+    #[serde(skip)]
+    pub span: Span,
+}
+
+/// 变量的声明与全部引用，按文档顺序排列
+pub fn find_references(content: &str, name: &str) -> Result<Vec<Reference>> {
+    let doc = A2lDocument::parse(content)?;
+    let mut references: Vec<Reference> = doc
+        .blocks()
+        .into_iter()
+        .flat_map(|block| name_positions(block, name))
+        .map(|(keyword, owner, span)| {
+            let (line, column) = doc.line_col(span.start);
+            Reference {
+                keyword: keyword.to_string(),
+                owner: owner.map(|o| o.to_string()),
+                line,
+                column,
+                file: None,
+                span,
+            }
+        })
+        .collect();
+    references.sort_by_key(|r| r.span.start);
+    Ok(references)
+}
+
+/// 改名并更新全部引用，返回新内容与改动的位置（位置对应改名前的内容）
+pub fn rename_variable(content: &str, old: &str, new: &str) -> Result<(String, Vec<Reference>)> {
+    if !is_valid_identifier(new) {
+        anyhow::bail!("不是合法的 ASAP2 标识符: {}", new);
+    }
+    let references = find_references(content, old)?;
+    if !references.iter().any(is_declaration) {
+        anyhow::bail!("A2L 中没有变量: {}", old);
+    }
+    if old != new && find_references(content, new)?.iter().any(is_declaration) {
+        anyhow::bail!("已存在同名变量: {}", new);
+    }
+
+    let mut edits = TextEdits::new();
+    for reference in &references {
+        edits.replace(reference.span, new);
+    }
+    Ok((edits.apply(content)?, references))
+}
+
+/// 块名已经改好后，更新其余位置对旧名称的引用
+pub fn rename_variable_references(content: &str, old: &str, new: &str) -> Result<String> {
+    let mut edits = TextEdits::new();
+    for reference in find_references(content, old)?
+        .iter()
+        .filter(|r| !is_declaration(r))
+    {
+        edits.replace(reference.span, new);
+    }
+    edits.apply(content)
+}
+
+fn is_declaration(reference: &Reference) -> bool {
+    VARIABLE_KEYWORDS.contains(&reference.keyword.as_str()) && reference.owner.is_none()
+}
+
+/// 块自身及其直接子块中名称等于 `name` 的位置：(关键字, 所属对象, 范围)。
+/// 嵌套块在遍历到父块时处理，以便记录所属对象
+fn name_positions<'a>(block: &Block<'a>, name: &str) -> Vec<(&'a str, Option<&'a str>, Span)> {
+    let keyword = block.keyword();
+    let owner = block.name();
+    let mut found = Vec::new();
+    let mut check = |keyword: &'a str, owner: Option<&'a str>, value: Option<&Value>| {
+        if let Some(value) = value.filter(|v| v.text == name) {
+            found.push((keyword, owner, value.span));
+        }
+    };
+
+    if VARIABLE_KEYWORDS.contains(&keyword) {
+        check(keyword, None, block.value(0));
+        let skip = positional_count(keyword);
+        if keyword == "AXIS_PTS" {
+            check("INPUT_QUANTITY", owner, block.value(3));
+        }
+        if let Some(args) = block.keyword_args("COMPARISON_QUANTITY", skip, 1) {
+            check("COMPARISON_QUANTITY", owner, Some(args[0]));
+        }
+    }
+
+    for child in block.blocks() {
+        let list = child.keyword();
+        if list == "AXIS_DESCR" {
+            // attribute input_quantity conversion max_axis_points lower upper
+            check("INPUT_QUANTITY", owner, child.value(1));
+            for reference in ["AXIS_PTS_REF", "CURVE_AXIS_REF"] {
+                if let Some(args) = child.keyword_args(reference, 6, 1) {
+                    check(reference, owner, Some(args[0]));
+                }
+            }
+        } else if NAME_LISTS.contains(&list) {
+            for value in child.values() {
+                check(list, owner, Some(value));
+            }
+        } else if FORMULA_LISTS.contains(&list) {
+            for value in child.values().skip(1) {
+                check(list, owner, Some(value));
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"/begin PROJECT P ""
+  /begin MODULE M ""
+    /begin MEASUREMENT speed "" UWORD NO_COMPU_METHOD 0 0 0 65535
+      ECU_ADDRESS 0x1000
+    /end MEASUREMENT
+    /begin AXIS_PTS speed_axis "" 0x2000 speed RL_AXIS 0 NO_COMPU_METHOD 8 0 100
+    /end AXIS_PTS
+    /begin CHARACTERISTIC gain "" CURVE 0x3000 RL_CURVE 0 NO_COMPU_METHOD 0 10
+      /begin AXIS_DESCR COM_AXIS speed NO_COMPU_METHOD 8 0 100
+        AXIS_PTS_REF speed_axis
+      /end AXIS_DESCR
+      COMPARISON_QUANTITY speed
+    /end CHARACTERISTIC
+    /begin GROUP G ""
+      /begin REF_MEASUREMENT speed speed_raw /end REF_MEASUREMENT
+    /end GROUP
+  /end MODULE
+/end PROJECT
+"#;
+
+    #[test]
+    fn test_rename_references() {
+        let keywords: Vec<(String, Option<String>)> = find_references(SAMPLE, "speed")
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.keyword, r.owner))
+            .collect();
+        let owned = |k: &str, o: &str| (k.to_string(), Some(o.to_string()));
+        assert_eq!(
+            keywords,
+            [
+                ("MEASUREMENT".to_string(), None),
+                owned("INPUT_QUANTITY", "speed_axis"),
+                owned("INPUT_QUANTITY", "gain"),
+                owned("COMPARISON_QUANTITY", "gain"),
+                owned("REF_MEASUREMENT", "G"),
+            ]
+        );
+
+        let (renamed, references) = rename_variable(SAMPLE, "speed", "vehicle_speed").unwrap();
+        assert_eq!(references.len(), 5);
+        assert!(find_references(&renamed, "speed").unwrap().is_empty());
+        assert!(renamed.contains("speed_raw"));
+        assert!(rename_variable(SAMPLE, "speed", "gain").is_err());
+        assert!(rename_variable(SAMPLE, "missing", "other").is_err());
+    }
+}
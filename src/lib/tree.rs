@@ -0,0 +1,466 @@
+use crate::elf::ElfParser;
+use crate::naming::NamingPolicy;
+use crate::types::{infer_a2l_type_from_encoding, TypeEncoding, TypeInfo, TypeKind, Variable};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TreeNodeKind {
+    Variable,
+    Member,
+    Element,
+}
+
+/// 条目树节点。`id` 在同一数据包内稳定：顶层为变量名（重名时追加 `@地址`），
+/// 之后每一级追加 `/m<成员序号>` 或 `/e<下标>`
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode {
+    pub id: String,
+    pub label: String,
+    pub full_name: String,
+    pub kind: TreeNodeKind,
+    pub address: u64,
+    pub size: usize,
+    pub a2l_type: String,
+    pub type_name: String,
+    pub bit_offset: Option<usize>,
+    pub bit_size: Option<usize>,
+    pub array_dims: Option<Vec<usize>>,
+    /// 直接子节点数，不需要展开即可得到
+    pub child_count: usize,
+}
+
+/// 节点的可展开形态
+enum Shape {
+    /// 结构体 / 联合体 / 标量
+    Type(TypeInfo),
+    /// 数组视图，`dims` 为尚未展开的维度
+    Array {
+        dims: Vec<usize>,
+        elem: Option<TypeInfo>,
+        elem_size: usize,
+        encoding: TypeEncoding,
+    },
+    /// 位域或没有类型信息的叶子
+    Leaf,
+}
+
+struct Resolved {
+    id: String,
+    label: String,
+    /// 未截断的完整路径，子节点名称在此基础上生成
+    path: String,
+    kind: TreeNodeKind,
+    address: u64,
+    size: usize,
+    type_name: String,
+    bitfield: Option<(usize, usize)>,
+    shape: Shape,
+}
+
+enum Segment {
+    Member(usize),
+    Element(usize),
+}
+
+/// 按需展开的条目树：变量 → 成员 → 数组元素，只在请求某个节点的子节点时才计算
+pub struct EntryTree {
+    variables: Vec<Variable>,
+    root_ids: Vec<String>,
+    roots: HashMap<String, usize>,
+    type_cache: HashMap<u64, TypeInfo>,
+    naming: NamingPolicy,
+}
+
+impl EntryTree {
+    pub fn new(mut variables: Vec<Variable>, type_cache: HashMap<u64, TypeInfo>) -> Self {
+        variables.sort_by(|a, b| a.name.cmp(&b.name).then(a.address.cmp(&b.address)));
+
+        let mut root_ids = Vec::with_capacity(variables.len());
+        let mut roots = HashMap::with_capacity(variables.len());
+        for (i, var) in variables.iter().enumerate() {
+            let id = if roots.contains_key(&var.name) {
+                format!("{}@{:x}", var.name, var.address)
+            } else {
+                var.name.clone()
+            };
+            roots.insert(id.clone(), i);
+            root_ids.push(id);
+        }
+
+        Self {
+            variables,
+            root_ids,
+            roots,
+            type_cache,
+            naming: NamingPolicy::default(),
+        }
+    }
+
+    pub fn with_naming_policy(mut self, naming: NamingPolicy) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    pub fn root_count(&self) -> usize {
+        self.variables.len()
+    }
+
+    pub fn roots(&self, offset: usize, limit: usize) -> Vec<TreeNode> {
+        (offset..self.variables.len().min(offset.saturating_add(limit)))
+            .map(|i| self.to_node(&self.resolve_root(i)))
+            .collect()
+    }
+
+    pub fn node(&self, id: &str) -> Result<TreeNode> {
+        Ok(self.to_node(&self.resolve(id)?))
+    }
+
+    pub fn child_count(&self, id: &str) -> Result<usize> {
+        Ok(Self::count_children(&self.resolve(id)?))
+    }
+
+    pub fn children(&self, id: &str, offset: usize, limit: usize) -> Result<Vec<TreeNode>> {
+        let parent = self.resolve(id)?;
+        let count = Self::count_children(&parent);
+        let end = count.min(offset.saturating_add(limit));
+
+        (offset..end)
+            .map(|i| {
+                let segment = match parent.shape {
+                    Shape::Array { .. } => Segment::Element(i),
+                    _ => Segment::Member(i),
+                };
+                self.child(&parent, &segment).map(|c| self.to_node(&c))
+            })
+            .collect()
+    }
+
+    fn resolve(&self, id: &str) -> Result<Resolved> {
+        let mut parts = id.split('/');
+        let root_id = parts.next().unwrap_or("");
+        let index = *self
+            .roots
+            .get(root_id)
+            .with_context(|| format!("未找到变量: {}", root_id))?;
+
+        let mut node = self.resolve_root(index);
+        for part in parts {
+            let segment =
+                Self::parse_segment(part).with_context(|| format!("无效的节点 ID: {}", id))?;
+            node = self.child(&node, &segment)?;
+        }
+        Ok(node)
+    }
+
+    fn parse_segment(part: &str) -> Option<Segment> {
+        if let Some(i) = part.strip_prefix('m') {
+            i.parse().ok().map(Segment::Member)
+        } else if let Some(i) = part.strip_prefix('e') {
+            i.parse().ok().map(Segment::Element)
+        } else {
+            None
+        }
+    }
+
+    fn resolve_root(&self, index: usize) -> Resolved {
+        let var = &self.variables[index];
+        let path = self.naming.root_name(&var.name);
+        let id = self.root_ids[index].clone();
+
+        match var.type_info {
+            Some(ref type_info) => Self::from_type(
+                id,
+                var.name.clone(),
+                path,
+                TreeNodeKind::Variable,
+                var.address,
+                type_info,
+            ),
+            None => Resolved {
+                id,
+                label: var.name.clone(),
+                path,
+                kind: TreeNodeKind::Variable,
+                address: var.address,
+                size: var.size,
+                type_name: var.type_name.clone(),
+                bitfield: None,
+                shape: Shape::Leaf,
+            },
+        }
+    }
+
+    fn from_type(
+        id: String,
+        label: String,
+        path: String,
+        kind: TreeNodeKind,
+        address: u64,
+        type_info: &TypeInfo,
+    ) -> Resolved {
+        let shape = if type_info.kind == TypeKind::Array {
+            let (dims, elem, elem_size) = ElfParser::flatten_array_type(type_info, 0);
+            let encoding = elem
+                .as_ref()
+                .map(|t| t.encoding)
+                .unwrap_or(type_info.encoding);
+            Shape::Array {
+                dims,
+                elem,
+                elem_size,
+                encoding,
+            }
+        } else {
+            Shape::Type(type_info.clone())
+        };
+
+        Resolved {
+            id,
+            label,
+            path,
+            kind,
+            address,
+            size: type_info.size,
+            type_name: type_info.name.clone(),
+            bitfield: None,
+            shape,
+        }
+    }
+
+    fn child(&self, parent: &Resolved, segment: &Segment) -> Result<Resolved> {
+        match (segment, &parent.shape) {
+            (Segment::Member(i), Shape::Type(type_info))
+                if matches!(type_info.kind, TypeKind::Struct | TypeKind::Union) =>
+            {
+                let member = type_info
+                    .members
+                    .get(*i)
+                    .with_context(|| format!("成员序号越界: {}", i))?;
+                let id = format!("{}/m{}", parent.id, i);
+                let path = self.naming.member_name(&parent.path, &member.name);
+                let address = parent.address + member.offset as u64;
+
+                let member_type = member
+                    .type_offset
+                    .filter(|&o| o > 0)
+                    .and_then(|o| self.type_cache.get(&o));
+
+                match member_type {
+                    Some(t) if !member.is_bitfield() => Ok(Self::from_type(
+                        id,
+                        member.name.clone(),
+                        path,
+                        TreeNodeKind::Member,
+                        address,
+                        t,
+                    )),
+                    _ => Ok(Resolved {
+                        id,
+                        label: member.name.clone(),
+                        path,
+                        kind: TreeNodeKind::Member,
+                        address,
+                        size: member.type_size,
+                        type_name: member.type_name.clone(),
+                        bitfield: member.is_bitfield().then(|| {
+                            (member.bit_offset.unwrap_or(0), member.bit_size.unwrap_or(0))
+                        }),
+                        shape: Shape::Leaf,
+                    }),
+                }
+            }
+            (
+                Segment::Element(i),
+                Shape::Array {
+                    dims,
+                    elem,
+                    elem_size,
+                    encoding,
+                },
+            ) => {
+                let current = dims.first().copied().unwrap_or(0);
+                if *i >= current {
+                    bail!("数组下标越界: {} (长度 {})", i, current);
+                }
+                let remaining = &dims[1..];
+                let stride = remaining.iter().product::<usize>() * elem_size;
+                let id = format!("{}/e{}", parent.id, i);
+                let path = self.naming.element_name(&parent.path, &[*i]);
+                let address = parent.address + (i * stride) as u64;
+                let label = format!("[{}]", i);
+
+                if !remaining.is_empty() {
+                    return Ok(Resolved {
+                        id,
+                        label,
+                        path,
+                        kind: TreeNodeKind::Element,
+                        address,
+                        size: stride,
+                        type_name: parent.type_name.clone(),
+                        bitfield: None,
+                        shape: Shape::Array {
+                            dims: remaining.to_vec(),
+                            elem: elem.clone(),
+                            elem_size: *elem_size,
+                            encoding: *encoding,
+                        },
+                    });
+                }
+
+                match elem {
+                    Some(elem_type) => {
+                        let mut elem_type = elem_type.clone();
+                        if elem_type.size == 0 {
+                            elem_type.size = *elem_size;
+                        }
+                        Ok(Self::from_type(
+                            id,
+                            label,
+                            path,
+                            TreeNodeKind::Element,
+                            address,
+                            &elem_type,
+                        ))
+                    }
+                    None => Ok(Resolved {
+                        id,
+                        label,
+                        path,
+                        kind: TreeNodeKind::Element,
+                        address,
+                        size: *elem_size,
+                        type_name: parent.type_name.clone(),
+                        bitfield: None,
+                        shape: Shape::Leaf,
+                    }),
+                }
+            }
+            _ => bail!("节点 {} 没有该子节点", parent.id),
+        }
+    }
+
+    fn count_children(node: &Resolved) -> usize {
+        match node.shape {
+            Shape::Type(ref t) if matches!(t.kind, TypeKind::Struct | TypeKind::Union) => {
+                t.members.len()
+            }
+            Shape::Array { ref dims, .. } => dims.first().copied().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn to_node(&self, node: &Resolved) -> TreeNode {
+        let (a2l_type, array_dims) = match node.shape {
+            Shape::Array {
+                ref dims,
+                ref elem,
+                elem_size,
+                encoding,
+            } => {
+                let is_scalar_elem = elem.as_ref().is_none_or(|t| {
+                    !matches!(t.kind, TypeKind::Struct | TypeKind::Union | TypeKind::Array)
+                });
+                if is_scalar_elem && elem_size > 0 {
+                    (
+                        infer_a2l_type_from_encoding(elem_size, encoding),
+                        Some(dims.clone()),
+                    )
+                } else {
+                    ("UBYTE", Some(vec![node.size]))
+                }
+            }
+            Shape::Type(ref t) => (infer_a2l_type_from_encoding(node.size, t.encoding), None),
+            // 类型未知的变量按原始字节块显示，与展开结果一致
+            Shape::Leaf if node.kind == TreeNodeKind::Variable => ("UBYTE", Some(vec![node.size])),
+            Shape::Leaf => (
+                infer_a2l_type_from_encoding(node.size, TypeEncoding::default()),
+                None,
+            ),
+        };
+
+        TreeNode {
+            id: node.id.clone(),
+            label: node.label.clone(),
+            full_name: self.naming.finalize(&node.path),
+            kind: node.kind,
+            address: node.address,
+            size: node.size,
+            a2l_type: a2l_type.to_string(),
+            type_name: node.type_name.clone(),
+            bit_offset: node.bitfield.map(|(o, _)| o),
+            bit_size: node.bitfield.map(|(_, s)| s),
+            array_dims: array_dims.filter(|d| d.iter().product::<usize>() > 1),
+            child_count: Self::count_children(node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::StructMember;
+
+    fn sample_tree() -> EntryTree {
+        let float = TypeInfo::primitive("float".to_string(), 4, TypeEncoding::Float);
+        let mut gain = TypeInfo::new("float[4]".to_string(), 16, TypeEncoding::Float);
+        gain.kind = TypeKind::Array;
+        gain.array_dims = vec![4];
+        gain.pointer_target = Some(Box::new(float));
+        gain.offset = 0x20;
+
+        let mut member = StructMember::new("gain".to_string(), 4, "float[4]".to_string(), 16);
+        member.type_offset = Some(0x20);
+        let mut cfg = TypeInfo::new("Cfg".to_string(), 20, TypeEncoding::Unsigned);
+        cfg.kind = TypeKind::Struct;
+        cfg.members = vec![
+            StructMember::new("a".to_string(), 0, "int".to_string(), 4),
+            member,
+        ];
+
+        let var = Variable::new(
+            "g_cfg".to_string(),
+            0x1000,
+            20,
+            "Cfg".to_string(),
+            ".bss".to_string(),
+        )
+        .with_type_info(cfg);
+
+        let mut type_cache = HashMap::new();
+        type_cache.insert(0x20, gain);
+        EntryTree::new(vec![var], type_cache)
+    }
+
+    #[test]
+    fn test_lazy_children() {
+        let tree = sample_tree();
+        let roots = tree.roots(0, 10);
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].child_count, 2);
+
+        let members = tree.children("g_cfg", 0, 10).unwrap();
+        assert_eq!(members[1].id, "g_cfg/m1");
+        assert_eq!(members[1].full_name, "g_cfg.gain");
+        assert_eq!(members[1].child_count, 4);
+        assert_eq!(members[1].array_dims, Some(vec![4]));
+
+        let elems = tree.children("g_cfg/m1", 2, 10).unwrap();
+        assert_eq!(elems.len(), 2);
+        assert_eq!(elems[0].id, "g_cfg/m1/e2");
+        assert_eq!(elems[0].full_name, "g_cfg.gain._2_");
+        assert_eq!(elems[0].address, 0x1000 + 4 + 8);
+        assert_eq!(elems[0].a2l_type, "FLOAT32_IEEE");
+    }
+
+    #[test]
+    fn test_invalid_id() {
+        let tree = sample_tree();
+        assert!(tree.node("missing").is_err());
+        assert!(tree.node("g_cfg/m9").is_err());
+        assert!(tree.node("g_cfg/x1").is_err());
+    }
+}
@@ -0,0 +1,359 @@
+use crate::types::{TypeEncoding, TypeInfo};
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// 规则匹配的对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleTarget {
+    /// C 类型名（如 `uint16_t`、`unsigned short`）
+    TypeName,
+    /// 变量名（如 `g_speed_u16`）
+    VariableName,
+}
+
+/// 可配置的类型推断规则，按顺序匹配，第一条命中的规则生效
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeRule {
+    pub target: RuleTarget,
+    /// 正则表达式，需要完整匹配时请自行加 `^`/`$`
+    pub pattern: String,
+    /// 要求的字节数，未设置时任意大小均可
+    #[serde(default)]
+    pub size: Option<usize>,
+    /// 推断出的 A2L 数据类型
+    pub a2l_type: String,
+}
+
+impl TypeRule {
+    pub fn new(target: RuleTarget, pattern: &str, size: Option<usize>, a2l_type: &str) -> Self {
+        Self {
+            target,
+            pattern: pattern.to_string(),
+            size,
+            a2l_type: a2l_type.to_string(),
+        }
+    }
+}
+
+/// 支持的 A2L 数据类型及其对应的 C 类型、字节数和编码
+const A2L_TYPES: &[(&str, &str, usize, TypeEncoding)] = &[
+    ("UBYTE", "uint8_t", 1, TypeEncoding::Unsigned),
+    ("SBYTE", "int8_t", 1, TypeEncoding::Signed),
+    ("UWORD", "uint16_t", 2, TypeEncoding::Unsigned),
+    ("SWORD", "int16_t", 2, TypeEncoding::Signed),
+    ("ULONG", "uint32_t", 4, TypeEncoding::Unsigned),
+    ("SLONG", "int32_t", 4, TypeEncoding::Signed),
+    ("A_UINT64", "uint64_t", 8, TypeEncoding::Unsigned),
+    ("A_INT64", "int64_t", 8, TypeEncoding::Signed),
+    ("FLOAT32_IEEE", "float", 4, TypeEncoding::Float),
+    ("FLOAT64_IEEE", "double", 8, TypeEncoding::Float),
+];
+
+/// 默认按 C 类型名精确匹配的规则
+const DEFAULT_TYPE_NAME_RULES: &[(&str, usize, &str)] = &[
+    (r"^(float|float32_t|f32|real32)$", 4, "FLOAT32_IEEE"),
+    (r"^(double|float64_t|f64|real64)$", 8, "FLOAT64_IEEE"),
+    (r"^(_Bool|bool|boolean(_t)?)$", 1, "UBYTE"),
+    (r"^(char|unsigned char|uint8(_t)?|u8)$", 1, "UBYTE"),
+    (r"^(signed char|int8(_t)?|s8|i8|sint8)$", 1, "SBYTE"),
+    (
+        r"^(unsigned short( int)?|short unsigned int|uint16(_t)?|u16)$",
+        2,
+        "UWORD",
+    ),
+    (
+        r"^((signed )?short( int)?|int16(_t)?|s16|i16|sint16)$",
+        2,
+        "SWORD",
+    ),
+    (
+        r"^(unsigned( int)?|unsigned long( int)?|long unsigned int|uint32(_t)?|u32)$",
+        4,
+        "ULONG",
+    ),
+    (
+        r"^((signed )?int|signed|(signed )?long( int)?|int32(_t)?|s32|i32|sint32)$",
+        4,
+        "SLONG",
+    ),
+    (
+        r"^(unsigned long( long)?( int)?|long( long)? unsigned int|uint64(_t)?|u64)$",
+        8,
+        "A_UINT64",
+    ),
+    (
+        r"^((signed )?long( long)?( int)?|int64(_t)?|s64|i64|sint64)$",
+        8,
+        "A_INT64",
+    ),
+];
+
+/// 默认按变量名中独立的类型标记（`_u16`、`f32_` 等）匹配的规则，
+/// 标记必须以下划线或首尾分隔，避免 `print_count` 之类的子串误判
+const DEFAULT_VARIABLE_NAME_RULES: &[(&str, usize, &str)] = &[
+    (r"(?i)(^|_)(f32|float32|float)(_|$)", 4, "FLOAT32_IEEE"),
+    (r"(?i)(^|_)(f64|float64|double)(_|$)", 8, "FLOAT64_IEEE"),
+    (r"(?i)(^|_)(b|bool|boolean|flag)(_|$)", 1, "UBYTE"),
+    (r"(?i)(^|_)(u8|uint8)(_|$)", 1, "UBYTE"),
+    (r"(?i)(^|_)(s8|i8|int8|sint8)(_|$)", 1, "SBYTE"),
+    (r"(?i)(^|_)(u16|uint16)(_|$)", 2, "UWORD"),
+    (r"(?i)(^|_)(s16|i16|int16|sint16)(_|$)", 2, "SWORD"),
+    (r"(?i)(^|_)(u32|uint32)(_|$)", 4, "ULONG"),
+    (r"(?i)(^|_)(s32|i32|int32|sint32)(_|$)", 4, "SLONG"),
+    (r"(?i)(^|_)(u64|uint64)(_|$)", 8, "A_UINT64"),
+    (r"(?i)(^|_)(s64|i64|int64|sint64)(_|$)", 8, "A_INT64"),
+];
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    rule: TypeRule,
+    regex: Regex,
+    a2l_type: &'static str,
+    c_type: &'static str,
+    encoding: TypeEncoding,
+}
+
+/// 有序的类型推断规则表，在缺少 DWARF 类型信息时使用
+#[derive(Debug, Clone)]
+pub struct TypeRules {
+    rules: Vec<CompiledRule>,
+}
+
+impl Default for TypeRules {
+    fn default() -> Self {
+        // 先类型名后变量名，所有规则都要求大小一致，不含兜底规则
+        let type_name_rules = DEFAULT_TYPE_NAME_RULES
+            .iter()
+            .map(|&(pattern, size, a2l_type)| (RuleTarget::TypeName, pattern, size, a2l_type));
+        let variable_name_rules = DEFAULT_VARIABLE_NAME_RULES
+            .iter()
+            .map(|&(pattern, size, a2l_type)| (RuleTarget::VariableName, pattern, size, a2l_type));
+        let rules = type_name_rules
+            .chain(variable_name_rules)
+            .map(|(target, pattern, size, a2l_type)| {
+                TypeRule::new(target, pattern, Some(size), a2l_type)
+            })
+            .collect();
+        Self::from_rules(rules).expect("默认类型规则无效")
+    }
+}
+
+impl TypeRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 空规则表，所有推断结果均为未知
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    pub fn from_rules(rules: Vec<TypeRule>) -> Result<Self> {
+        let rules = rules
+            .into_iter()
+            .map(Self::compile)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    /// 从 JSON 数组加载规则，替换默认规则
+    pub fn from_json(json: &str) -> Result<Self> {
+        let rules: Vec<TypeRule> = serde_json::from_str(json).context("无法解析类型规则")?;
+        Self::from_rules(rules)
+    }
+
+    /// 在末尾追加一条规则
+    pub fn with_rule(mut self, rule: TypeRule) -> Result<Self> {
+        self.rules.push(Self::compile(rule)?);
+        Ok(self)
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &TypeRule> {
+        self.rules.iter().map(|r| &r.rule)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    fn compile(rule: TypeRule) -> Result<CompiledRule> {
+        let regex = Regex::new(&rule.pattern)
+            .with_context(|| format!("无效的类型规则正则: {}", rule.pattern))?;
+        let &(a2l_type, c_type, type_size, encoding) = A2L_TYPES
+            .iter()
+            .find(|(name, ..)| *name == rule.a2l_type)
+            .with_context(|| format!("未知的 A2L 数据类型: {}", rule.a2l_type))?;
+        if let Some(size) = rule.size {
+            if size != type_size {
+                anyhow::bail!(
+                    "类型规则 {} 的大小 {} 与 {} 不一致",
+                    rule.pattern,
+                    size,
+                    a2l_type
+                );
+            }
+        }
+        Ok(CompiledRule {
+            rule,
+            regex,
+            a2l_type,
+            c_type,
+            encoding,
+        })
+    }
+
+    /// 按顺序匹配规则，没有规则命中时返回 `None`
+    pub fn infer(
+        &self,
+        type_name: Option<&str>,
+        variable_name: &str,
+        size: usize,
+    ) -> Option<TypeGuess> {
+        self.rules
+            .iter()
+            .enumerate()
+            .find(|(_, r)| {
+                if r.rule.size.is_some_and(|s| s != size) {
+                    return false;
+                }
+                match r.rule.target {
+                    RuleTarget::TypeName => type_name.is_some_and(|t| r.regex.is_match(t.trim())),
+                    RuleTarget::VariableName => r.regex.is_match(variable_name),
+                }
+            })
+            .map(|(index, r)| TypeGuess {
+                rule_index: index,
+                pattern: r.rule.pattern.clone(),
+                a2l_type: r.a2l_type,
+                c_type: r.c_type,
+                encoding: r.encoding,
+            })
+    }
+}
+
+/// 默认规则表的共享实例
+pub fn default_type_rules() -> &'static TypeRules {
+    static RULES: OnceLock<TypeRules> = OnceLock::new();
+    RULES.get_or_init(TypeRules::default)
+}
+
+/// 规则命中的推断结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeGuess {
+    pub rule_index: usize,
+    pub pattern: String,
+    pub a2l_type: &'static str,
+    pub c_type: &'static str,
+    pub encoding: TypeEncoding,
+}
+
+impl TypeGuess {
+    pub fn to_type_info(&self, size: usize) -> TypeInfo {
+        TypeInfo::primitive(self.c_type.to_string(), size, self.encoding)
+    }
+}
+
+/// 一个缺少 DWARF 类型、经规则推断的变量
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeGuessRecord {
+    pub variable: String,
+    pub address: u64,
+    pub size: usize,
+    /// 推断出的 A2L 类型，未知时为 `None`
+    pub a2l_type: Option<String>,
+    /// 命中的规则正则
+    pub rule: Option<String>,
+}
+
+impl TypeGuessRecord {
+    pub fn is_unknown(&self) -> bool {
+        self.a2l_type.is_none()
+    }
+}
+
+/// 所有经规则推断类型的变量
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TypeGuessReport {
+    pub records: Vec<TypeGuessRecord>,
+}
+
+impl TypeGuessReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, record: TypeGuessRecord) {
+        self.records.push(record);
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn guessed(&self) -> impl Iterator<Item = &TypeGuessRecord> {
+        self.records.iter().filter(|r| !r.is_unknown())
+    }
+
+    pub fn unknown(&self) -> impl Iterator<Item = &TypeGuessRecord> {
+        self.records.iter().filter(|r| r.is_unknown())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substring_does_not_match() {
+        let rules = TypeRules::default();
+        assert_eq!(rules.infer(None, "print_count", 4), None);
+        assert_eq!(rules.infer(None, "char_count", 2), None);
+        assert_eq!(rules.infer(None, "mu8_state", 1), None);
+    }
+
+    #[test]
+    fn test_name_and_size() {
+        let rules = TypeRules::default();
+        let guess = rules.infer(None, "g_speed_u16", 2).unwrap();
+        assert_eq!(guess.a2l_type, "UWORD");
+        // 大小不符时不采用名称标记
+        assert_eq!(rules.infer(None, "g_speed_u16", 4), None);
+        let guess = rules.infer(None, "f32_gain", 4).unwrap();
+        assert_eq!(guess.a2l_type, "FLOAT32_IEEE");
+        let guess = rules.infer(Some("unsigned short"), "x", 2).unwrap();
+        assert_eq!(guess.a2l_type, "UWORD");
+    }
+
+    #[test]
+    fn test_infer_falls_back_to_size() {
+        use crate::types::infer_a2l_type;
+        assert_eq!(infer_a2l_type(4, "mystery_t"), "ULONG");
+        assert_eq!(infer_a2l_type(8, "mystery_t"), "A_UINT64");
+        assert_eq!(infer_a2l_type(2, "mystery_t"), "UWORD");
+        assert_eq!(infer_a2l_type(4, "float"), "FLOAT32_IEEE");
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let json = r#"[{"target":"variable_name","pattern":"^cnt","a2l_type":"SLONG"}]"#;
+        let rules = TypeRules::from_json(json).unwrap();
+        assert_eq!(rules.infer(None, "cnt_a", 4).unwrap().a2l_type, "SLONG");
+        assert_eq!(rules.infer(None, "speed_u16", 2), None);
+
+        let bad = r#"[{"target":"type_name","pattern":"(","a2l_type":"UBYTE"}]"#;
+        assert!(TypeRules::from_json(bad).is_err());
+        let bad = r#"[{"target":"type_name","pattern":"x","size":2,"a2l_type":"UBYTE"}]"#;
+        assert!(TypeRules::from_json(bad).is_err());
+    }
+}
@@ -4,6 +4,73 @@ use std::collections::HashMap;
 pub const MAX_ARRAY_EXPAND: usize = 1000;
 pub const MAX_NESTING_DEPTH: usize = 50;
 
+/// 条目展开限制：超过 `max_array_expand` 个元素的数组只保留为一个带维度的条目
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExpandLimits {
+    pub max_array_expand: usize,
+    pub max_nesting_depth: usize,
+}
+
+impl Default for ExpandLimits {
+    fn default() -> Self {
+        Self {
+            max_array_expand: MAX_ARRAY_EXPAND,
+            max_nesting_depth: MAX_NESTING_DEPTH,
+        }
+    }
+}
+
+/// 运行时展开配置，可按变量路径或类型名覆盖默认限制。
+/// 覆盖对匹配节点及其所有子节点生效，变量路径优先于类型名。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExpandConfig {
+    pub limits: ExpandLimits,
+    pub variable_overrides: HashMap<String, ExpandLimits>,
+    pub type_overrides: HashMap<String, ExpandLimits>,
+}
+
+impl ExpandConfig {
+    pub fn new(limits: ExpandLimits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_variable_override(mut self, path: &str, limits: ExpandLimits) -> Self {
+        self.variable_overrides.insert(path.to_string(), limits);
+        self
+    }
+
+    pub fn with_type_override(mut self, type_name: &str, limits: ExpandLimits) -> Self {
+        self.type_overrides.insert(type_name.to_string(), limits);
+        self
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// 计算某个节点实际生效的限制，`inherited` 为父节点的限制
+    pub fn resolve(
+        &self,
+        path: &str,
+        type_names: &[&str],
+        inherited: ExpandLimits,
+    ) -> ExpandLimits {
+        if let Some(limits) = self.variable_overrides.get(path) {
+            return *limits;
+        }
+        type_names
+            .iter()
+            .find_map(|name| self.type_overrides.get(*name))
+            .copied()
+            .unwrap_or(inherited)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Variable {
     pub name: String,
@@ -12,6 +79,9 @@ pub struct Variable {
     pub type_name: String,
     pub section: String,
     pub type_info: Option<TypeInfo>,
+    /// DWARF 中定义该变量的编译单元源文件名
+    #[serde(default)]
+    pub source_file: Option<String>,
 }
 
 impl Variable {
@@ -29,6 +99,7 @@ impl Variable {
             type_name,
             section,
             type_info: None,
+            source_file: None,
         }
     }
 
@@ -188,15 +259,70 @@ impl std::fmt::Display for TypeKind {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Endianness {
+    #[default]
     Little,
     Big,
 }
 
-impl Default for Endianness {
-    fn default() -> Self {
-        Endianness::Little
+impl Endianness {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Endianness::Little => "little",
+            Endianness::Big => "big",
+        }
+    }
+
+    /// A2L 中 `BYTE_ORDER` 的取值
+    pub fn byte_order_keyword(self) -> &'static str {
+        match self {
+            Endianness::Little => "MSB_LAST",
+            Endianness::Big => "MSB_FIRST",
+        }
+    }
+
+    /// 解析 `BYTE_ORDER` 的取值，兼容旧版的 `LITTLE_ENDIAN`/`BIG_ENDIAN`
+    pub fn from_byte_order_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "MSB_LAST" | "LITTLE_ENDIAN" => Some(Endianness::Little),
+            "MSB_FIRST" | "BIG_ENDIAN" => Some(Endianness::Big),
+            _ => None,
+        }
+    }
+
+    /// 位域在容器整数中从最低位起算的偏移
+    ///
+    /// `bit_offset` 按内存顺序从容器起始处计数（DWARF `DW_AT_data_bit_offset` 的约定），
+    /// 小端时即为最低位偏移，大端时从最高位开始计数。
+    pub fn lsb_bit_offset(
+        self,
+        bit_offset: usize,
+        bit_size: usize,
+        container_bits: usize,
+    ) -> usize {
+        match self {
+            Endianness::Little => bit_offset,
+            Endianness::Big => container_bits.saturating_sub(bit_offset + bit_size),
+        }
+    }
+}
+
+impl std::fmt::Display for Endianness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for Endianness {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "little" | "Little" => Ok(Endianness::Little),
+            "big" | "Big" => Ok(Endianness::Big),
+            _ => anyhow::bail!("无效的字节序: {}（可选 little/big）", s),
+        }
     }
 }
 
@@ -263,13 +389,7 @@ impl StructMember {
         container_size_bits: usize,
     ) -> Option<usize> {
         let raw_offset = self.bit_offset?;
-
-        let effective = match endianness {
-            Endianness::Little => container_size_bits - raw_offset - self.bit_size.unwrap_or(0),
-            Endianness::Big => raw_offset,
-        };
-
-        Some(effective)
+        Some(endianness.lsb_bit_offset(raw_offset, self.bit_size.unwrap_or(0), container_size_bits))
     }
 }
 
@@ -285,6 +405,33 @@ impl EnumVariant {
     }
 }
 
+/// 条目引用的枚举类型，导出时生成同名的 TAB_VERB 转换
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnumType {
+    pub name: String,
+    pub variants: Vec<EnumVariant>,
+}
+
+impl EnumType {
+    /// 非枚举类型返回 `None`
+    pub fn from_type(type_info: &TypeInfo) -> Option<Self> {
+        if type_info.kind != TypeKind::Enum || type_info.variants.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name: type_info.name.clone(),
+            variants: type_info.variants.clone(),
+        })
+    }
+
+    /// 枚举值的最小值和最大值
+    pub fn range(&self) -> Option<(i64, i64)> {
+        let min = self.variants.iter().map(|v| v.value).min()?;
+        let max = self.variants.iter().map(|v| v.value).max()?;
+        Some((min, max))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheEntry {
     pub file_hash: String,
@@ -320,50 +467,18 @@ impl CacheEntry {
     }
 }
 
+/// 按默认规则表由 C 类型名推断 A2L 类型，无规则命中时按大小取无符号类型
 pub fn infer_a2l_type(size: usize, type_name: &str) -> &'static str {
-    let lower = type_name.to_lowercase();
-
-    if lower.contains("float") || lower.contains("double") {
-        return match size {
-            4 => "FLOAT32_IEEE",
-            8 => "FLOAT64_IEEE",
-            _ => "FLOAT64_IEEE",
-        };
-    }
-
-    if lower.contains("u8") || lower.contains("uint8") || lower.contains("char") {
-        return "UBYTE";
-    }
-    if lower.contains("u16") || lower.contains("uint16") || lower.contains("wchar") {
-        return "UWORD";
-    }
-    if lower.contains("u32") || lower.contains("uint32") {
-        return "ULONG";
-    }
-    if lower.contains("u64") || lower.contains("uint64") {
-        return "A_UINT64";
-    }
-
-    if lower.contains("i8") || lower.contains("int8") || lower.contains("sbyte") {
-        return "SBYTE";
-    }
-    if lower.contains("i16") || lower.contains("int16") || lower.contains("short") {
-        return "SWORD";
-    }
-    if lower.contains("i32") || lower.contains("int32") || lower.contains("int") {
-        return "SLONG";
-    }
-    if lower.contains("i64") || lower.contains("int64") {
-        return "A_INT64";
-    }
-
-    match size {
-        1 => "UBYTE",
-        2 => "UWORD",
-        4 => "ULONG",
-        8 => "A_UINT64",
-        _ => "UBYTE",
-    }
+    crate::type_rules::default_type_rules()
+        .infer(Some(type_name), "", size)
+        .map(|g| g.a2l_type)
+        .unwrap_or(match size {
+            1 => "UBYTE",
+            2 => "UWORD",
+            4 => "ULONG",
+            8 => "A_UINT64",
+            _ => "UBYTE",
+        })
 }
 
 pub fn infer_a2l_type_from_encoding(size: usize, encoding: TypeEncoding) -> &'static str {
@@ -392,6 +507,15 @@ pub struct A2lEntry {
     pub bit_offset: Option<usize>,
     pub bit_size: Option<usize>,
     pub array_index: Option<Vec<usize>>,
+    /// 数组条目的维度，`a2l_type` 此时为元素类型
+    #[serde(default)]
+    pub array_dims: Option<Vec<usize>>,
+    /// 枚举类型（数组条目为元素的枚举类型）
+    #[serde(default)]
+    pub enum_type: Option<EnumType>,
+    /// 所属变量定义所在的源文件名
+    #[serde(default)]
+    pub source_file: Option<String>,
 }
 
 impl A2lEntry {
@@ -411,6 +535,9 @@ impl A2lEntry {
             bit_offset: None,
             bit_size: None,
             array_index: None,
+            array_dims: None,
+            enum_type: None,
+            source_file: None,
         }
     }
 
@@ -424,10 +551,53 @@ impl A2lEntry {
         self.bit_size.is_some()
     }
 
+    /// 位域的 BIT_MASK，容器为条目本身的大小
+    pub fn bit_mask(&self, endianness: Endianness) -> Option<u64> {
+        let size = self.bit_size?;
+        let offset = endianness.lsb_bit_offset(self.bit_offset.unwrap_or(0), size, self.size * 8);
+        let bits = if size >= 64 {
+            u64::MAX
+        } else {
+            (1u64 << size) - 1
+        };
+        Some(bits.checked_shl(offset as u32).unwrap_or(0))
+    }
+
     pub fn with_array_index(mut self, index: Vec<usize>) -> Self {
         self.array_index = Some(index);
         self
     }
+
+    pub fn with_array_dims(mut self, dims: Vec<usize>) -> Self {
+        self.array_dims = Some(dims);
+        self
+    }
+
+    pub fn with_enum_type(mut self, enum_type: Option<EnumType>) -> Self {
+        self.enum_type = enum_type;
+        self
+    }
+
+    pub fn with_source_file(mut self, source_file: Option<String>) -> Self {
+        self.source_file = source_file;
+        self
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.array_dims.as_ref().is_some_and(|d| !d.is_empty())
+    }
+
+    /// 条目是数组元素或位于数组元素之中，名称带有下标
+    pub fn is_array_element(&self) -> bool {
+        self.array_index.as_ref().is_some_and(|i| !i.is_empty())
+    }
+
+    pub fn element_count(&self) -> usize {
+        self.array_dims
+            .as_ref()
+            .map(|d| d.iter().product())
+            .unwrap_or(1)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,287 @@
+use crate::a2l::{
+    positional_count, symbol_link, variable_blocks, A2lGenerator, A2lParser, BlockLayout,
+    VariableChanges,
+};
+use crate::a2l_syntax::{A2lDocument, TextEdits};
+use crate::naming::NamingPolicy;
+use crate::types::{A2lEntry, A2lEntryStore, Endianness};
+use crate::validate::parse_address;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 单个对象的更新结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateStatus {
+    /// 地址或 BIT_MASK 已改写
+    Updated,
+    Unchanged,
+    /// 新 ELF 中找不到对应符号，保持原样
+    Missing,
+    /// 数据类型与新 ELF 不一致，地址仍会更新，类型需人工确认
+    TypeChanged,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateItem {
+    pub name: String,
+    /// MEASUREMENT / CHARACTERISTIC / AXIS_PTS
+    pub keyword: String,
+    /// 用于查找的符号，来自 SYMBOL_LINK，没有时为块名
+    pub symbol: String,
+    pub status: UpdateStatus,
+    pub old_address: Option<String>,
+    pub new_address: Option<String>,
+    pub old_type: Option<String>,
+    pub new_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UpdateReport {
+    pub items: Vec<UpdateItem>,
+}
+
+impl UpdateReport {
+    pub fn count(&self, status: UpdateStatus) -> usize {
+        self.items.iter().filter(|i| i.status == status).count()
+    }
+
+    pub fn with_status(&self, status: UpdateStatus) -> impl Iterator<Item = &UpdateItem> {
+        self.items.iter().filter(move |i| i.status == status)
+    }
+}
+
+/// 按新 ELF 的条目刷新 A2L 中变量的地址与 BIT_MASK。
+/// 每个 MEASUREMENT/CHARACTERISTIC/AXIS_PTS 先按 SYMBOL_LINK（加偏移）查找，
+/// 没有 SYMBOL_LINK 时按块名查找，名称比较遵循命名策略
+pub fn update_addresses(
+    content: &str,
+    store: &A2lEntryStore,
+    naming: &NamingPolicy,
+    endianness: Endianness,
+) -> Result<(String, UpdateReport)> {
+    let doc = A2lDocument::parse(content)?;
+    // 只有数组元素按元素的匹配键比较，Flat 风格下名为 `x_1` 的普通变量不会当作 `x[1]`
+    let match_keys: HashMap<String, &A2lEntry> = store
+        .entries
+        .iter()
+        .map(|e| {
+            let key = if e.is_array_element() {
+                naming.element_match_key(&e.full_name)
+            } else {
+                naming.match_key(&e.full_name)
+            };
+            (key, e)
+        })
+        .collect();
+    let lookup = |symbol: &str| {
+        store.get_by_name(symbol).or_else(|| {
+            match_keys
+                .get(&naming.match_key(symbol))
+                .or_else(|| match_keys.get(&naming.element_match_key(symbol)))
+                .copied()
+        })
+    };
+
+    let mut edits = TextEdits::new();
+    let mut report = UpdateReport::default();
+    for block in variable_blocks(&doc) {
+        let variable = A2lParser::parse_variable_block(&doc, block);
+        let skip = positional_count(block.keyword());
+
+        let (symbol, offset) = symbol_link(block).unwrap_or((variable.name.clone(), 0));
+        let mut item = UpdateItem {
+            name: variable.name.clone(),
+            keyword: block.keyword().to_string(),
+            symbol,
+            status: UpdateStatus::Missing,
+            old_address: variable.address.clone(),
+            new_address: None,
+            old_type: Some(variable.data_type.clone()).filter(|t| !t.is_empty()),
+            new_type: None,
+        };
+        let Some(entry) = lookup(&item.symbol) else {
+            report.items.push(item);
+            continue;
+        };
+
+        // 没有地址参数的块（如 MEASUREMENT 没有 ECU_ADDRESS）不补写地址
+        let has_address = BlockLayout::of(block.keyword()).address.is_some()
+            || block.keyword_args("ECU_ADDRESS", skip, 1).is_some();
+        let address = entry.address.wrapping_add_signed(offset);
+        let mut changes = VariableChanges::default();
+        if has_address && variable.address.as_deref().and_then(parse_address) != Some(address) {
+            changes.address = Some(format_address(variable.address.as_deref(), address));
+        }
+        if let Some(mask) = entry.bit_mask(endianness) {
+            if variable.bit_mask.as_deref().and_then(parse_address) != Some(mask) {
+                changes.bit_mask = Some(format!("0x{:X}", mask));
+            }
+        }
+        let updated = changes.address.is_some() || changes.bit_mask.is_some();
+        if updated {
+            A2lGenerator::apply_changes_to_block(&doc, block, &changes, &mut edits)?;
+        }
+
+        item.new_address = Some(format_address(variable.address.as_deref(), address));
+        item.new_type = Some(entry.a2l_type.clone());
+        item.status = if item.old_type.as_ref().is_some_and(|t| *t != entry.a2l_type) {
+            UpdateStatus::TypeChanged
+        } else if updated {
+            UpdateStatus::Updated
+        } else {
+            UpdateStatus::Unchanged
+        };
+        report.items.push(item);
+    }
+
+    Ok((edits.apply(content)?, report))
+}
+
+/// 十六进制地址沿用原地址的位数，其他情况按 8 位输出
+fn format_address(original: Option<&str>, address: u64) -> String {
+    let width = original
+        .and_then(|a| a.strip_prefix("0x").or_else(|| a.strip_prefix("0X")))
+        .map(|hex| hex.len())
+        .unwrap_or(8);
+    format!("0x{:0width$X}", address, width = width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+/begin PROJECT P \"\"
+  /begin MODULE M \"\"
+    /begin MEASUREMENT speed \"\"
+      UWORD NO_COMPU_METHOD 0 0 0 65535
+      ECU_ADDRESS 0x1000
+      SYMBOL_LINK \"speed\" 0
+    /end MEASUREMENT
+    /begin MEASUREMENT flags.ready \"\"
+      UBYTE NO_COMPU_METHOD 0 0 0 1
+      BIT_MASK 0x1
+      ECU_ADDRESS 0x00002000
+    /end MEASUREMENT
+    /begin CHARACTERISTIC gain \"\"
+      VALUE 0x3000 __UWord_Value 0 NO_COMPU_METHOD 0 65535
+    /end CHARACTERISTIC
+    /begin CHARACTERISTIC gone \"\"
+      VALUE 0x4000 __UWord_Value 0 NO_COMPU_METHOD 0 65535
+    /end CHARACTERISTIC
+    /begin RECORD_LAYOUT __UWord_Value
+      FNC_VALUES 1 UWORD ROW_DIR DIRECT
+    /end RECORD_LAYOUT
+  /end MODULE
+/end PROJECT
+";
+
+    #[test]
+    fn test_update_addresses() {
+        let mut store = A2lEntryStore::new();
+        let entry = |name: &str, address, a2l_type: &str| {
+            A2lEntry::new(name.into(), address, 2, a2l_type.into(), String::new())
+        };
+        store.add(entry("speed", 0x1000, "UWORD"));
+        store.add(entry("flags.ready", 0x2100, "UBYTE").with_bitfield(3, 1));
+        store.add(entry("gain", 0x3100, "ULONG"));
+
+        let (output, report) =
+            update_addresses(SAMPLE, &store, &NamingPolicy::default(), Endianness::Little).unwrap();
+        let statuses: Vec<UpdateStatus> = report.items.iter().map(|i| i.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                UpdateStatus::Unchanged,
+                UpdateStatus::Updated,
+                UpdateStatus::TypeChanged,
+                UpdateStatus::Missing,
+            ]
+        );
+
+        assert!(output.contains("BIT_MASK 0x8\n      ECU_ADDRESS 0x00002100\n"));
+        // 类型变化时地址照常更新，类型保持不变
+        assert!(output.contains("VALUE 0x3100 __UWord_Value"));
+        assert!(output.contains("VALUE 0x4000 __UWord_Value"));
+    }
+
+    #[test]
+    fn test_big_endian_bit_mask() {
+        let mut store = A2lEntryStore::new();
+        store.add(
+            A2lEntry::new(
+                "flags.ready".into(),
+                0x2000,
+                2,
+                "UBYTE".into(),
+                String::new(),
+            )
+            .with_bitfield(3, 1),
+        );
+
+        // 大端时位偏移从容器最高位数起：16 - 3 - 1 = 12
+        let (output, _) =
+            update_addresses(SAMPLE, &store, &NamingPolicy::default(), Endianness::Big).unwrap();
+        assert!(output.contains("BIT_MASK 0x1000\n"));
+    }
+
+    #[test]
+    fn test_flat_scalar_not_matched_as_element() {
+        let content = SAMPLE.replace("flags.ready", "sensor[1]");
+        let flat = NamingPolicy::new().with_index_style(crate::naming::IndexStyle::Flat);
+        let entry = || A2lEntry::new("sensor_1".into(), 0x2200, 1, "UBYTE".into(), String::new());
+
+        // 名为 sensor_1 的普通变量不是 sensor[1]
+        let mut store = A2lEntryStore::new();
+        store.add(entry());
+        let (_, report) = update_addresses(&content, &store, &flat, Endianness::Little).unwrap();
+        assert_eq!(report.items[1].status, UpdateStatus::Missing);
+
+        // Flat 风格的数组元素 sensor_1 即 sensor[1]
+        let mut store = A2lEntryStore::new();
+        store.add(entry().with_array_index(vec![1]));
+        let (output, report) =
+            update_addresses(&content, &store, &flat, Endianness::Little).unwrap();
+        assert_eq!(report.items[1].status, UpdateStatus::Updated);
+        assert!(output.contains("ECU_ADDRESS 0x00002200"));
+    }
+
+    #[test]
+    fn test_measurement_without_address() {
+        let content = SAMPLE.replace("      ECU_ADDRESS 0x00002000\n", "");
+        let mut store = A2lEntryStore::new();
+        store.add(A2lEntry::new(
+            "flags.ready".into(),
+            0x2100,
+            1,
+            "UBYTE".into(),
+            String::new(),
+        ));
+
+        // 地址不补写，BIT_MASK 未变，文件保持原样
+        let (output, report) = update_addresses(
+            &content,
+            &store,
+            &NamingPolicy::default(),
+            Endianness::Little,
+        )
+        .unwrap();
+        assert_eq!(output, content);
+        assert_eq!(report.items[1].status, UpdateStatus::Unchanged);
+
+        // 只有 BIT_MASK 变化时如实报告为已更新
+        store.entries[0] = store.entries[0].clone().with_bitfield(3, 1);
+        let (output, report) = update_addresses(
+            &content,
+            &store,
+            &NamingPolicy::default(),
+            Endianness::Little,
+        )
+        .unwrap();
+        assert!(output.contains("BIT_MASK 0x8\n"));
+        assert!(!output.contains("ECU_ADDRESS 0x00002100"));
+        assert_eq!(report.items[1].status, UpdateStatus::Updated);
+    }
+}
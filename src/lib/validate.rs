@@ -0,0 +1,654 @@
+use crate::a2l::{
+    positional_count, variable_blocks, A2lGenerator, A2lParser, BlockLayout, VARIABLE_KEYWORDS,
+};
+use crate::a2l_syntax::{A2lDocument, Block, Span};
+use crate::compu::{Conversions, NO_COMPU_METHOD};
+use crate::version::document_version;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// ASAP2 标识符的最大长度
+pub(crate) const MAX_IDENTIFIER_LEN: usize = 1024;
+
+/// 名称各自独立的块类别，同一类别内名称必须唯一
+const NAMESPACES: &[&[&str]] = &[
+    VARIABLE_KEYWORDS,
+    &["COMPU_METHOD"],
+    &["COMPU_TAB", "COMPU_VTAB", "COMPU_VTAB_RANGE"],
+    &["RECORD_LAYOUT"],
+    &["GROUP"],
+    &["FUNCTION"],
+];
+
+/// 分组列表及其引用的块类别
+pub(crate) const REFERENCE_LISTS: &[(&str, &[&str])] = &[
+    ("REF_MEASUREMENT", VARIABLE_KEYWORDS),
+    ("REF_CHARACTERISTIC", VARIABLE_KEYWORDS),
+    ("DEF_CHARACTERISTIC", VARIABLE_KEYWORDS),
+    ("IN_MEASUREMENT", VARIABLE_KEYWORDS),
+    ("OUT_MEASUREMENT", VARIABLE_KEYWORDS),
+    ("LOC_MEASUREMENT", VARIABLE_KEYWORDS),
+    ("SUB_GROUP", &["GROUP"]),
+    ("SUB_FUNCTION", &["FUNCTION"]),
+    ("FUNCTION_LIST", &["FUNCTION"]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// 一条校验结果，行列号从 1 开始
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 规则名，如 `duplicate-name`
+    pub code: String,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    /// 出问题的块名
+    pub name: Option<String>,
+    /// 问题位于被包含文件时的文件名，见 [`A2lProject::validate`](crate::A2lProject::validate)
+    pub file: Option<String>,
+    /// 重名时首次定义所在的行
+    pub first_line: Option<usize>,
+    /// 首次定义位于被包含文件时的文件名
+    pub first_file: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}[{}]: {}",
+            self.line, self.column, severity, self.code, self.message
+        )?;
+        if let Some(line) = self.first_line {
+            let place = self.first_file.as_ref().map(|f| f.clone() + " ");
+            write!(
+                f,
+                "（首次定义在 {}第 {} 行）",
+                place.unwrap_or_default(),
+                line
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 校验 A2L 内容，语法错误时返回 `Err`，结果按位置排序
+pub fn validate(content: &str) -> Result<Vec<Diagnostic>> {
+    let doc = A2lDocument::parse(content)?;
+    Ok(validate_document(&doc))
+}
+
+pub fn validate_document(doc: &A2lDocument) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        doc,
+        conversions: Conversions::from_document(doc),
+        diagnostics: Vec::new(),
+    };
+    validator.check_names();
+    validator.check_references();
+    validator.check_limits();
+    validator.check_overlaps();
+    validator.check_version();
+
+    let mut diagnostics = validator.diagnostics;
+    diagnostics.sort_by_key(|d| (d.line, d.column, d.severity));
+    diagnostics
+}
+
+/// 相对于 `before` 新出现的错误，按规则名和消息比较，不受位置变化影响
+pub fn new_errors<'d>(before: &[Diagnostic], after: &'d [Diagnostic]) -> Vec<&'d Diagnostic> {
+    let known: HashSet<(&str, &str)> = before
+        .iter()
+        .map(|d| (d.code.as_str(), d.message.as_str()))
+        .collect();
+    after
+        .iter()
+        .filter(|d| d.is_error() && !known.contains(&(d.code.as_str(), d.message.as_str())))
+        .collect()
+}
+
+/// 相对于 `before` 新出现的警告
+pub fn new_warnings(before: &[Diagnostic], after: &[Diagnostic]) -> Vec<Diagnostic> {
+    let known: HashSet<(&str, &str)> = before
+        .iter()
+        .map(|d| (d.code.as_str(), d.message.as_str()))
+        .collect();
+    after
+        .iter()
+        .filter(|d| !d.is_error() && !known.contains(&(d.code.as_str(), d.message.as_str())))
+        .cloned()
+        .collect()
+}
+
+/// 标识符由 `.` 分隔的若干部分组成，每部分以字母或下划线开头，只含字母、数字和下划线，
+/// 末尾可带 `[数字]` 下标
+pub fn is_valid_identifier(name: &str) -> bool {
+    if name.is_empty() || name.len() > MAX_IDENTIFIER_LEN {
+        return false;
+    }
+    name.split('.').all(|part| {
+        let (base, indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+        let base_ok = base.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && base.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        let indices_ok = indices.is_empty()
+            || indices
+                .strip_prefix('[')
+                .and_then(|s| s.strip_suffix(']'))
+                .is_some_and(|s| {
+                    s.split("][")
+                        .all(|i| !i.is_empty() && i.chars().all(|c| c.is_ascii_digit()))
+                });
+        base_ok && indices_ok
+    })
+}
+
+struct Validator<'d, 'a> {
+    doc: &'d A2lDocument<'a>,
+    conversions: Conversions,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'d, 'a> Validator<'d, 'a> {
+    fn push(
+        &mut self,
+        severity: Severity,
+        code: &str,
+        span: Span,
+        name: Option<&str>,
+        message: String,
+    ) {
+        let (line, column) = self.doc.line_col(span.start);
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code: code.to_string(),
+            message,
+            line,
+            column,
+            name: name.map(|n| n.to_string()),
+            file: None,
+            first_line: None,
+            first_file: None,
+        });
+    }
+
+    fn blocks_in(&self, keywords: &[&str]) -> Vec<&'d Block<'a>> {
+        self.doc
+            .blocks()
+            .into_iter()
+            .filter(|b| keywords.contains(&b.keyword()))
+            .collect()
+    }
+
+    fn defined(&self, keywords: &[&str], name: &str) -> bool {
+        keywords
+            .iter()
+            .any(|k| self.doc.find_block(k, name).is_some())
+    }
+
+    /// 重名与非法标识符
+    fn check_names(&mut self) {
+        for keywords in NAMESPACES {
+            let mut seen: HashMap<&str, usize> = HashMap::new();
+            for block in self.blocks_in(keywords) {
+                let Some(value) = block.value(0) else {
+                    continue;
+                };
+                let name = value.text;
+                if let Some(&first) = seen.get(name) {
+                    let message = format!("重复定义的名称 {}", name);
+                    self.push(
+                        Severity::Error,
+                        "duplicate-name",
+                        value.span,
+                        Some(name),
+                        message,
+                    );
+                    if let Some(diagnostic) = self.diagnostics.last_mut() {
+                        diagnostic.first_line = Some(self.doc.line_col(first).0);
+                    }
+                } else {
+                    seen.insert(name, value.span.start);
+                }
+                if !is_valid_identifier(name) {
+                    let message =
+                        format!("{} {} 的名称不是合法的 ASAP2 标识符", block.keyword(), name);
+                    self.push(
+                        Severity::Error,
+                        "invalid-identifier",
+                        value.span,
+                        Some(name),
+                        message,
+                    );
+                }
+            }
+        }
+    }
+
+    /// 引用未定义的转换、转换表、记录布局、轴点和分组成员
+    fn check_references(&mut self) {
+        for block in self.blocks_in(VARIABLE_KEYWORDS) {
+            let name = block.name();
+            let layout = BlockLayout::of(block.keyword());
+            if let Some(conversion) = block.value(layout.conversion) {
+                self.check_compu_method(conversion.text, conversion.span, name);
+            }
+            if let Some(deposit) = layout.deposit.and_then(|i| block.value(i)) {
+                if self.doc.find_block("RECORD_LAYOUT", deposit.text).is_none() {
+                    let message = format!("引用了未定义的记录布局 {}", deposit.text);
+                    self.push(
+                        Severity::Error,
+                        "undefined-record-layout",
+                        deposit.span,
+                        name,
+                        message,
+                    );
+                }
+            }
+            for axis in block.blocks().filter(|b| b.keyword() == "AXIS_DESCR") {
+                // attribute input_quantity conversion max_axis_points lower upper
+                if let Some(conversion) = axis.value(2) {
+                    self.check_compu_method(conversion.text, conversion.span, name);
+                }
+                if let Some(args) = axis.keyword_args("AXIS_PTS_REF", 6, 1) {
+                    if self.doc.find_block("AXIS_PTS", args[0].text).is_none() {
+                        let message = format!("引用了未定义的轴点 {}", args[0].text);
+                        self.push(
+                            Severity::Error,
+                            "undefined-axis-pts",
+                            args[0].span,
+                            name,
+                            message,
+                        );
+                    }
+                }
+            }
+        }
+
+        for method in self.blocks_in(&["COMPU_METHOD"]) {
+            if let Some(args) = method.keyword_args("COMPU_TAB_REF", 5, 1) {
+                let table = args[0].text;
+                if !self.defined(&["COMPU_TAB", "COMPU_VTAB", "COMPU_VTAB_RANGE"], table) {
+                    let message = format!("引用了未定义的转换表 {}", table);
+                    self.push(
+                        Severity::Error,
+                        "undefined-compu-tab",
+                        args[0].span,
+                        method.name(),
+                        message,
+                    );
+                }
+            }
+        }
+
+        for &(list, targets) in REFERENCE_LISTS {
+            for block in self.blocks_in(&[list]) {
+                for value in block.values() {
+                    if !self.defined(targets, value.text) {
+                        let message = format!("{} 引用了不存在的 {}", list, value.text);
+                        self.push(
+                            Severity::Warning,
+                            "undefined-reference",
+                            value.span,
+                            Some(value.text),
+                            message,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_compu_method(&mut self, method: &str, span: Span, name: Option<&str>) {
+        if method != NO_COMPU_METHOD && self.conversions.method(method).is_none() {
+            let message = format!("引用了未定义的转换方法 {}", method);
+            self.push(
+                Severity::Error,
+                "undefined-compu-method",
+                span,
+                name,
+                message,
+            );
+        }
+    }
+
+    /// 上下限颠倒或超出数据类型（经转换后）的取值范围
+    fn check_limits(&mut self) {
+        let blocks: Vec<&Block> = variable_blocks(self.doc).collect();
+        for block in blocks {
+            let variable = A2lParser::parse_variable_block(self.doc, block);
+            let layout = BlockLayout::of(block.keyword());
+            let Some(lower_value) = block.value(layout.limits) else {
+                continue;
+            };
+            let (Some(lower), Some(upper)) = (
+                variable.lower_limit.as_deref().and_then(parse_number),
+                variable.upper_limit.as_deref().and_then(parse_number),
+            ) else {
+                continue;
+            };
+            let name = Some(variable.name.as_str());
+
+            if lower > upper {
+                let message = format!("{} 的下限 {} 大于上限 {}", variable.name, lower, upper);
+                self.push(
+                    Severity::Error,
+                    "invalid-limits",
+                    lower_value.span,
+                    name,
+                    message,
+                );
+                continue;
+            }
+
+            let Some((raw_min, raw_max)) =
+                raw_range(&variable.data_type, variable.bit_mask.as_deref())
+            else {
+                continue;
+            };
+            let Some((min, max)) =
+                self.physical_range(variable.compu_method.as_deref(), raw_min, raw_max)
+            else {
+                continue;
+            };
+            if lower < min - tolerance(min) || upper > max + tolerance(max) {
+                let message = format!(
+                    "{} 的上下限 [{}, {}] 超出数据类型 {} 的范围 [{}, {}]",
+                    variable.name, lower, upper, variable.data_type, min, max
+                );
+                self.push(
+                    Severity::Warning,
+                    "limits-out-of-range",
+                    lower_value.span,
+                    name,
+                    message,
+                );
+            }
+        }
+    }
+
+    /// 原始值范围经转换后的物理值范围，无法线性换算的转换返回 `None`
+    fn physical_range(&self, method: Option<&str>, min: f64, max: f64) -> Option<(f64, f64)> {
+        let Some(method) = method
+            .filter(|m| *m != NO_COMPU_METHOD)
+            .and_then(|m| self.conversions.method(m))
+        else {
+            return Some((min, max));
+        };
+        match method.conversion_type.as_str() {
+            "IDENTICAL" | "TAB_VERB" => Some((min, max)),
+            "LINEAR" => {
+                let coeffs = method.coeffs.as_ref()?;
+                let (a, b) = (*coeffs.first()?, *coeffs.get(1)?);
+                let (x, y) = (a * min + b, a * max + b);
+                Some((x.min(y), x.max(y)))
+            }
+            _ => None,
+        }
+    }
+
+    /// 文件声明的 ASAP2 版本不支持的关键字与数据类型
+    fn check_version(&mut self) {
+        let Some(version) = document_version(self.doc) else {
+            return;
+        };
+        let mut unsupported: Vec<(Span, Option<&str>, String)> = Vec::new();
+        for block in variable_blocks(self.doc) {
+            let name = block.name();
+            let skip = positional_count(block.keyword());
+            if !version.supports_symbol_link() {
+                if let Some(keyword) = block.keyword_value("SYMBOL_LINK", skip) {
+                    unsupported.push((keyword.span, name, "SYMBOL_LINK".to_string()));
+                }
+            }
+            if version.fixed_matrix_dim() {
+                if let Some(keyword) = block.keyword_value("MATRIX_DIM", skip) {
+                    let count = block
+                        .values()
+                        .skip_while(|v| v.span != keyword.span)
+                        .skip(1)
+                        .take_while(|v| v.text.parse::<usize>().is_ok())
+                        .count();
+                    if count != 3 {
+                        let what = format!("{} 个参数的 MATRIX_DIM", count);
+                        unsupported.push((keyword.span, name, what));
+                    }
+                }
+            }
+        }
+        let typed = self
+            .blocks_in(&["MEASUREMENT"])
+            .into_iter()
+            .filter_map(|b| b.value(2).map(|v| (v, b.name())))
+            .chain(
+                self.blocks_in(&["RECORD_LAYOUT"])
+                    .into_iter()
+                    .flat_map(|b| b.values().skip(1).map(move |v| (v, b.name()))),
+            );
+        for (value, name) in typed {
+            if !version.supports_type(value.text) {
+                unsupported.push((value.span, name, value.text.to_string()));
+            }
+        }
+
+        for (span, name, what) in unsupported {
+            let message = format!(
+                "{} 使用了 ASAP2 {} 不支持的 {}",
+                name.unwrap_or_default(),
+                version,
+                what
+            );
+            self.push(
+                Severity::Warning,
+                "unsupported-keyword",
+                span,
+                name,
+                message,
+            );
+        }
+    }
+
+    /// 非位域变量的地址区间重叠
+    fn check_overlaps(&mut self) {
+        let mut ranges: Vec<(u64, u64, &str, Span)> = Vec::new();
+        let blocks: Vec<&Block> = variable_blocks(self.doc).collect();
+        for block in blocks {
+            let variable = A2lParser::parse_variable_block(self.doc, block);
+            if variable.bit_mask.is_some() {
+                continue;
+            }
+            let (Some(address), Some(size), Some(name)) = (
+                variable.address.as_deref().and_then(parse_address),
+                type_size(&variable.data_type),
+                block.value(0),
+            ) else {
+                continue;
+            };
+            let count: usize = variable.dims.iter().flatten().product();
+            ranges.push((
+                address,
+                address + (size * count.max(1)) as u64,
+                name.text,
+                name.span,
+            ));
+        }
+        ranges.sort_by_key(|r| (r.0, r.1));
+
+        let mut widest: Option<(u64, u64, &str)> = None;
+        for (start, end, name, span) in ranges {
+            if let Some((first_start, first_end, first)) = widest {
+                if start < first_end {
+                    let message = format!(
+                        "{} 的地址范围 0x{:X}..0x{:X} 与 {} 的 0x{:X}..0x{:X} 重叠",
+                        name, start, end, first, first_start, first_end
+                    );
+                    self.push(
+                        Severity::Warning,
+                        "address-overlap",
+                        span,
+                        Some(name),
+                        message,
+                    );
+                }
+                if end <= first_end {
+                    continue;
+                }
+            }
+            widest = Some((start, end, name));
+        }
+    }
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok().map(|v| v as f64),
+        None => text.parse().ok(),
+    }
+}
+
+pub(crate) fn parse_address(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn tolerance(value: f64) -> f64 {
+    value.abs().max(1.0) * 1e-6
+}
+
+fn type_size(a2l_type: &str) -> Option<usize> {
+    match a2l_type {
+        "UBYTE" | "SBYTE" => Some(1),
+        "UWORD" | "SWORD" => Some(2),
+        "ULONG" | "SLONG" | "FLOAT32_IEEE" => Some(4),
+        "A_UINT64" | "A_INT64" | "FLOAT64_IEEE" => Some(8),
+        _ => None,
+    }
+}
+
+/// 数据类型的原始值范围，有 BIT_MASK 时按掩码位数计算
+fn raw_range(a2l_type: &str, bit_mask: Option<&str>) -> Option<(f64, f64)> {
+    type_size(a2l_type)?;
+    let signed = matches!(a2l_type, "SBYTE" | "SWORD" | "SLONG" | "A_INT64");
+    if let Some(mask) = bit_mask.and_then(parse_address).filter(|m| *m != 0) {
+        let bits = (mask >> mask.trailing_zeros()).trailing_ones() as i32;
+        return Some(if signed {
+            (-(2f64.powi(bits - 1)), 2f64.powi(bits - 1) - 1.0)
+        } else {
+            (0.0, 2f64.powi(bits) - 1.0)
+        });
+    }
+    let (min, max) = A2lGenerator::get_min_max(a2l_type);
+    Some((min.parse().ok()?, max.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+/begin PROJECT P \"\"
+  /begin MODULE M \"\"
+    /begin MEASUREMENT speed \"\"
+      UWORD CM_speed 0 0 0 70000
+      ECU_ADDRESS 0x1000
+    /end MEASUREMENT
+    /begin MEASUREMENT speed \"\"
+      UBYTE NO_COMPU_METHOD 0 0 0 255
+      ECU_ADDRESS 0x1001
+    /end MEASUREMENT
+    /begin MEASUREMENT flags \"\"
+      UBYTE NO_COMPU_METHOD 0 0 0 7
+      BIT_MASK 0x7
+      ECU_ADDRESS 0x1000
+    /end MEASUREMENT
+    /begin CHARACTERISTIC ns::gain \"\"
+      VALUE 0x2000 RL_WORD 0 CM_missing 10 0
+    /end CHARACTERISTIC
+    /begin CHARACTERISTIC table.3 \"\"
+      VAL_BLK 0x3000 __UWord_Value 0 NO_COMPU_METHOD 0 100
+      MATRIX_DIM 4
+    /end CHARACTERISTIC
+    /begin COMPU_METHOD CM_speed \"\"
+      LINEAR \"%6.2\" \"km/h\"
+      COEFFS_LINEAR 0.01 0
+    /end COMPU_METHOD
+    /begin GROUP G \"\"
+      /begin REF_MEASUREMENT speed rpm /end REF_MEASUREMENT
+    /end GROUP
+  /end MODULE
+/end PROJECT
+";
+
+    #[test]
+    fn test_identifiers() {
+        assert!(is_valid_identifier("var._3_.x"));
+        assert!(is_valid_identifier("var[3][1].x"));
+        assert!(!is_valid_identifier("var.3"));
+        assert!(!is_valid_identifier("ns::var"));
+        assert!(!is_valid_identifier("var[i]"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let diagnostics = validate(SAMPLE).unwrap();
+        let codes: Vec<(&str, Option<&str>)> = diagnostics
+            .iter()
+            .map(|d| (d.code.as_str(), d.name.as_deref()))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                // LINEAR 0.01 下 UWORD 的物理范围为 0..655.35
+                ("limits-out-of-range", Some("speed")),
+                ("duplicate-name", Some("speed")),
+                ("address-overlap", Some("speed")),
+                ("invalid-identifier", Some("ns::gain")),
+                ("undefined-record-layout", Some("ns::gain")),
+                ("undefined-compu-method", Some("ns::gain")),
+                ("invalid-limits", Some("ns::gain")),
+                ("invalid-identifier", Some("table.3")),
+                ("undefined-record-layout", Some("table.3")),
+                ("undefined-reference", Some("rpm")),
+            ]
+        );
+        assert_eq!(diagnostics.iter().filter(|d| d.is_error()).count(), 7);
+        assert_eq!(diagnostics[1].line, 7);
+    }
+
+    #[test]
+    fn test_version_keywords() {
+        let content = format!("ASAP2_VERSION 1 51\n{}", SAMPLE);
+        // 1.70 之前 MATRIX_DIM 必须写满三个参数
+        let unsupported: Vec<String> = validate(&content)
+            .unwrap()
+            .into_iter()
+            .filter(|d| d.code == "unsupported-keyword")
+            .filter_map(|d| d.name)
+            .collect();
+        assert_eq!(unsupported, ["table.3"]);
+        let current = format!("ASAP2_VERSION 1 71\n{}", SAMPLE);
+        assert!(validate(&current)
+            .unwrap()
+            .iter()
+            .all(|d| d.code != "unsupported-keyword"));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::a2l_syntax::{A2lDocument, Node};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// 生成内容的目标 ASAP2 版本，决定可以使用的关键字与数据类型
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub enum Asap2Version {
+    #[serde(rename = "1.51")]
+    V1_51,
+    #[serde(rename = "1.60")]
+    V1_60,
+    #[serde(rename = "1.61")]
+    V1_61,
+    #[serde(rename = "1.70")]
+    V1_70,
+    #[default]
+    #[serde(rename = "1.71")]
+    V1_71,
+}
+
+impl Asap2Version {
+    pub const ALL: [Asap2Version; 5] = [
+        Asap2Version::V1_51,
+        Asap2Version::V1_60,
+        Asap2Version::V1_61,
+        Asap2Version::V1_70,
+        Asap2Version::V1_71,
+    ];
+
+    /// `ASAP2_VERSION` 的主、次版本号
+    pub fn numbers(self) -> (u32, u32) {
+        match self {
+            Asap2Version::V1_51 => (1, 51),
+            Asap2Version::V1_60 => (1, 60),
+            Asap2Version::V1_61 => (1, 61),
+            Asap2Version::V1_70 => (1, 70),
+            Asap2Version::V1_71 => (1, 71),
+        }
+    }
+
+    /// 按版本号取不高于它的已知版本，如 1.50 以下返回 `None`、1.62 视为 1.61
+    pub fn from_numbers(major: u32, minor: u32) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .rev()
+            .find(|v| v.numbers() <= (major, minor))
+    }
+
+    /// 文件头的 `ASAP2_VERSION` 行
+    pub fn keyword_line(self) -> String {
+        let (major, minor) = self.numbers();
+        format!("ASAP2_VERSION {} {}", major, minor)
+    }
+
+    /// SYMBOL_LINK 自 1.60 起可用
+    pub fn supports_symbol_link(self) -> bool {
+        self >= Asap2Version::V1_60
+    }
+
+    /// A_UINT64/A_INT64 自 1.60 起可用
+    pub fn supports_type(self, a2l_type: &str) -> bool {
+        !matches!(a2l_type, "A_UINT64" | "A_INT64") || self >= Asap2Version::V1_60
+    }
+
+    /// 1.70 之前 MATRIX_DIM 固定为 x y z 三个参数
+    pub fn fixed_matrix_dim(self) -> bool {
+        self < Asap2Version::V1_70
+    }
+
+    /// MATRIX_DIM 的参数：旧版本不足三维时补 1，超过三维时合并到最后一维
+    pub fn matrix_dim(self, dims: &[usize]) -> Vec<usize> {
+        if !self.fixed_matrix_dim() {
+            return dims.to_vec();
+        }
+        let mut fixed: Vec<usize> = dims.iter().copied().take(2).collect();
+        fixed.push(dims.iter().skip(2).product());
+        fixed.resize(3, 1);
+        fixed
+    }
+}
+
+impl std::fmt::Display for Asap2Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (major, minor) = self.numbers();
+        write!(f, "{}.{}", major, minor)
+    }
+}
+
+impl std::str::FromStr for Asap2Version {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|v| v.to_string() == s)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "不支持的 ASAP2 版本: {}（可选 1.51/1.60/1.61/1.70/1.71）",
+                    s
+                )
+            })
+    }
+}
+
+/// 文件声明的 ASAP2 版本，支持 `ASAP2_VERSION 1 71` 与 `/begin ASAP2_VERSION 1 71 /end` 两种写法，
+/// 没有声明时返回 `None`
+pub fn read_asap2_version(content: &str) -> Result<Option<Asap2Version>> {
+    let doc = A2lDocument::parse(content)?;
+    Ok(document_version(&doc))
+}
+
+pub(crate) fn document_version(doc: &A2lDocument) -> Option<Asap2Version> {
+    let items = doc.items();
+    let pos = items.iter().position(|node| match node {
+        Node::Value(v) => v.text == "ASAP2_VERSION",
+        Node::Block(b) => b.keyword() == "ASAP2_VERSION",
+    })?;
+    let numbers: Vec<&str> = match &items[pos] {
+        Node::Block(block) => block.values().map(|v| v.text).collect(),
+        Node::Value(_) => items[pos + 1..]
+            .iter()
+            .take(2)
+            .filter_map(|node| match node {
+                Node::Value(v) => Some(v.text),
+                Node::Block(_) => None,
+            })
+            .collect(),
+    };
+    let major = numbers.first()?.parse().ok()?;
+    let minor = numbers.get(1)?.parse().ok()?;
+    Asap2Version::from_numbers(major, minor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_version() {
+        let keyword = "ASAP2_VERSION 1 60\n/begin PROJECT P \"\"\n/end PROJECT\n";
+        assert_eq!(
+            read_asap2_version(keyword).unwrap(),
+            Some(Asap2Version::V1_60)
+        );
+        let block = "/begin ASAP2_VERSION\n  1 51\n/end ASAP2_VERSION\n";
+        assert_eq!(
+            read_asap2_version(block).unwrap(),
+            Some(Asap2Version::V1_51)
+        );
+        assert_eq!(
+            read_asap2_version("/begin PROJECT P \"\"\n/end PROJECT\n").unwrap(),
+            None
+        );
+        assert_eq!(Asap2Version::from_numbers(1, 62), Some(Asap2Version::V1_61));
+        assert_eq!("1.70".parse::<Asap2Version>().unwrap(), Asap2Version::V1_70);
+    }
+
+    #[test]
+    fn test_matrix_dim() {
+        assert_eq!(Asap2Version::V1_51.matrix_dim(&[4]), [4, 1, 1]);
+        assert_eq!(Asap2Version::V1_60.matrix_dim(&[2, 3, 4, 5]), [2, 3, 20]);
+        assert_eq!(Asap2Version::V1_71.matrix_dim(&[4]), [4]);
+    }
+}
@@ -0,0 +1,581 @@
+//! 模块级 `IF_DATA XCP`：按结构化配置生成协议层、DAQ、事件通道与传输层描述，
+//! 并在 MODULE 中原位插入或替换
+//!
+//! 生成的文本遵循 ASAM XCP 的 A2ML 定义，文件中需有相应的 A2ML 才能被标定工具识别。
+
+use crate::a2l::{insert_after_last, module_end};
+use crate::a2l_syntax::{A2lDocument, Block, TextEdits};
+use crate::types::Endianness;
+use crate::validate::parse_address;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// 生成时使用的 XCP 协议层版本
+const PROTOCOL_VERSION: u16 = 0x0104;
+
+/// 传输层版本
+const TRANSPORT_VERSION: u16 = 0x0104;
+
+/// 扩展 CAN 标识符在 XCP_ON_CAN 中以最高位标记
+const CAN_EXTENDED_FLAG: u32 = 0x8000_0000;
+
+/// XCP 时间单位编码（`TIME_UNIT`）与对应的微秒数，1 µs 以下不使用
+const TIME_UNITS: [(u8, u64); 7] = [
+    (3, 1),
+    (4, 10),
+    (5, 100),
+    (6, 1_000),
+    (7, 10_000),
+    (8, 100_000),
+    (9, 1_000_000),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct XcpConfig {
+    pub protocol: ProtocolLayer,
+    pub daq: DaqConfig,
+    pub events: Vec<EventChannel>,
+    pub transport: Transport,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProtocolLayer {
+    /// 超时 T1..T7（毫秒）
+    pub timeouts: [u16; 7],
+    pub max_cto: u16,
+    pub max_dto: u16,
+    pub byte_order: Endianness,
+    /// 可选命令，如 `GET_COMM_MODE_INFO`
+    pub optional_commands: Vec<String>,
+}
+
+impl Default for ProtocolLayer {
+    fn default() -> Self {
+        ProtocolLayer {
+            timeouts: [1000, 1000, 1000, 1000, 1000, 1000, 2000],
+            max_cto: 8,
+            max_dto: 8,
+            byte_order: Endianness::Little,
+            optional_commands: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DaqConfigType {
+    Static,
+    #[default]
+    Dynamic,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DaqConfig {
+    pub config_type: DaqConfigType,
+    pub max_daq: u16,
+    pub min_daq: u8,
+    pub max_odt_entry_size: u8,
+    /// 静态 DAQ 列表，动态配置时通常为空
+    pub lists: Vec<DaqList>,
+}
+
+impl Default for DaqConfig {
+    fn default() -> Self {
+        DaqConfig {
+            config_type: DaqConfigType::Dynamic,
+            max_daq: 0,
+            min_daq: 0,
+            max_odt_entry_size: 8,
+            lists: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaqList {
+    pub number: u16,
+    pub max_odt: u8,
+    pub max_odt_entries: u8,
+    /// 固定绑定的事件通道
+    #[serde(default)]
+    pub event_fixed: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EventDirection {
+    #[default]
+    Daq,
+    Stim,
+    DaqStim,
+}
+
+impl EventDirection {
+    fn keyword(self) -> &'static str {
+        match self {
+            EventDirection::Daq => "DAQ",
+            EventDirection::Stim => "STIM",
+            EventDirection::DaqStim => "DAQ_STIM",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventChannel {
+    pub name: String,
+    #[serde(default)]
+    pub short_name: String,
+    pub number: u16,
+    #[serde(default)]
+    pub direction: EventDirection,
+    /// 周期（微秒），0 表示非周期事件
+    pub cycle_us: u64,
+    #[serde(default = "default_max_daq_list")]
+    pub max_daq_list: u8,
+    #[serde(default)]
+    pub priority: u8,
+}
+
+fn default_max_daq_list() -> u8 {
+    0xFF
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum IpProtocol {
+    #[default]
+    Udp,
+    Tcp,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Transport {
+    Can {
+        baudrate: u32,
+        /// 主站（标定工具）发送的 CAN ID
+        master_id: u32,
+        /// 从站（ECU）应答的 CAN ID
+        slave_id: u32,
+        #[serde(default)]
+        broadcast_id: Option<u32>,
+        #[serde(default)]
+        extended: bool,
+    },
+    Ethernet {
+        #[serde(default)]
+        protocol: IpProtocol,
+        address: String,
+        port: u16,
+    },
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Transport::Can {
+            baudrate: 500_000,
+            master_id: 0x7E0,
+            slave_id: 0x7E1,
+            broadcast_id: None,
+            extended: false,
+        }
+    }
+}
+
+/// 生成 `IF_DATA XCP` 块，每行带 `indent` 前缀
+pub fn xcp_if_data(config: &XcpConfig, indent: &str) -> Result<String> {
+    let mut lines = vec!["/begin IF_DATA XCP".to_string()];
+    lines.extend(protocol_layer(&config.protocol).into_iter().map(nested));
+    lines.extend(daq(config)?.into_iter().map(nested));
+    lines.extend(transport(&config.transport).into_iter().map(nested));
+    lines.push("/end IF_DATA".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        let _ = writeln!(out, "{}{}", indent, line);
+    }
+    Ok(out)
+}
+
+/// 在第一个 MODULE 中插入或替换 `IF_DATA XCP`，其他 IF_DATA 保持不变
+pub fn set_xcp_if_data(content: &str, config: &XcpConfig) -> Result<String> {
+    let doc = A2lDocument::parse(content)?;
+    let module = doc
+        .find_blocks("MODULE")
+        .into_iter()
+        .next()
+        .context("无法找到 MODULE")?;
+
+    if let Some(existing) = module_xcp(module) {
+        let extent = doc.line_extent(existing.span());
+        let block = xcp_if_data(config, doc.indent_at(existing.begin.start))?;
+        let mut edits = TextEdits::new();
+        edits.replace(extent, block);
+        return edits.apply(content);
+    }
+
+    let indent = module_end(&doc)
+        .map(|end| format!("{}  ", doc.indent_at(end)))
+        .unwrap_or_default();
+    let block = xcp_if_data(config, &indent)?;
+    insert_after_last(&doc, &[&["MOD_PAR"], &["MOD_COMMON"], &["A2ML"]], &block)
+        .context("无法找到 MODULE")?
+}
+
+/// 读取第一个 MODULE 中的 `IF_DATA XCP`，没有时返回 `None`
+///
+/// 只识别本模块会生成的部分，其余可选内容按默认值处理。
+pub fn read_xcp_config(content: &str) -> Result<Option<XcpConfig>> {
+    let doc = A2lDocument::parse(content)?;
+    let Some(block) = doc
+        .find_blocks("MODULE")
+        .into_iter()
+        .next()
+        .and_then(module_xcp)
+    else {
+        return Ok(None);
+    };
+
+    let mut config = XcpConfig::default();
+    if let Some(layer) = block.block("PROTOCOL_LAYER") {
+        let protocol = &mut config.protocol;
+        for (i, timeout) in protocol.timeouts.iter_mut().enumerate() {
+            *timeout = number(layer, i + 1).unwrap_or(*timeout as u64) as u16;
+        }
+        protocol.max_cto = number(layer, 8).unwrap_or(8) as u16;
+        protocol.max_dto = number(layer, 9).unwrap_or(8) as u16;
+        if layer.values().any(|v| v.text == "BYTE_ORDER_MSB_FIRST") {
+            protocol.byte_order = Endianness::Big;
+        }
+        let values: Vec<&str> = layer.values().map(|v| v.text).collect();
+        protocol.optional_commands = values
+            .windows(2)
+            .filter(|w| w[0] == "OPTIONAL_CMD")
+            .map(|w| w[1].to_string())
+            .collect();
+    }
+
+    if let Some(daq) = block.block("DAQ") {
+        config.daq.config_type = match daq.value(0).map(|v| v.text) {
+            Some("STATIC") => DaqConfigType::Static,
+            _ => DaqConfigType::Dynamic,
+        };
+        config.daq.max_daq = number(daq, 1).unwrap_or(0) as u16;
+        config.daq.min_daq = number(daq, 3).unwrap_or(0) as u8;
+        config.daq.max_odt_entry_size = number(daq, 8).unwrap_or(8) as u8;
+        for list in daq.blocks().filter(|b| b.keyword() == "DAQ_LIST") {
+            let arg = |keyword| {
+                list.keyword_args(keyword, 1, 1)
+                    .and_then(|args| parse_address(args[0].text))
+            };
+            config.daq.lists.push(DaqList {
+                number: number(list, 0).unwrap_or(0) as u16,
+                max_odt: arg("MAX_ODT").unwrap_or(0) as u8,
+                max_odt_entries: arg("MAX_ODT_ENTRIES").unwrap_or(0) as u8,
+                event_fixed: arg("EVENT_FIXED").map(|e| e as u16),
+            });
+        }
+        for event in daq.blocks().filter(|b| b.keyword() == "EVENT") {
+            let unit = number(event, 6).unwrap_or(6) as u8;
+            let factor = TIME_UNITS
+                .iter()
+                .find(|(code, _)| *code == unit)
+                .map(|(_, us)| *us)
+                .unwrap_or(1_000);
+            config.events.push(EventChannel {
+                name: event.value(0).map(|v| v.unquoted()).unwrap_or_default(),
+                short_name: event.value(1).map(|v| v.unquoted()).unwrap_or_default(),
+                number: number(event, 2).unwrap_or(0) as u16,
+                direction: match event.value(3).map(|v| v.text) {
+                    Some("STIM") => EventDirection::Stim,
+                    Some("DAQ_STIM") => EventDirection::DaqStim,
+                    _ => EventDirection::Daq,
+                },
+                max_daq_list: number(event, 4).unwrap_or(0xFF) as u8,
+                cycle_us: number(event, 5).unwrap_or(0) * factor,
+                priority: number(event, 7).unwrap_or(0) as u8,
+            });
+        }
+    }
+
+    if let Some(can) = block.block("XCP_ON_CAN") {
+        let arg = |keyword| {
+            can.keyword_args(keyword, 1, 1)
+                .and_then(|args| parse_address(args[0].text))
+                .map(|id| id as u32)
+        };
+        let master_id = arg("CAN_ID_MASTER").unwrap_or(0);
+        config.transport = Transport::Can {
+            baudrate: arg("BAUDRATE").unwrap_or(500_000),
+            master_id: master_id & !CAN_EXTENDED_FLAG,
+            slave_id: arg("CAN_ID_SLAVE").unwrap_or(0) & !CAN_EXTENDED_FLAG,
+            broadcast_id: arg("CAN_ID_BROADCAST").map(|id| id & !CAN_EXTENDED_FLAG),
+            extended: master_id & CAN_EXTENDED_FLAG != 0,
+        };
+    } else if let Some((ip, protocol)) = block
+        .block("XCP_ON_UDP_IP")
+        .map(|b| (b, IpProtocol::Udp))
+        .or_else(|| block.block("XCP_ON_TCP_IP").map(|b| (b, IpProtocol::Tcp)))
+    {
+        config.transport = Transport::Ethernet {
+            protocol,
+            address: ip
+                .keyword_args("ADDRESS", 2, 1)
+                .or_else(|| ip.keyword_args("HOST_NAME", 2, 1))
+                .map(|args| args[0].unquoted())
+                .unwrap_or_default(),
+            port: number(ip, 1).unwrap_or(0) as u16,
+        };
+    }
+
+    Ok(Some(config))
+}
+
+/// MODULE 直属的 `IF_DATA XCP`，不含 MOD_PAR 或对象内部的 IF_DATA
+fn module_xcp<'d, 'a>(module: &'d Block<'a>) -> Option<&'d Block<'a>> {
+    module
+        .blocks()
+        .find(|b| b.keyword() == "IF_DATA" && b.name() == Some("XCP"))
+}
+
+fn number(block: &Block, index: usize) -> Option<u64> {
+    block.value(index).and_then(|v| parse_address(v.text))
+}
+
+fn nested(line: String) -> String {
+    format!("  {}", line)
+}
+
+fn protocol_layer(protocol: &ProtocolLayer) -> Vec<String> {
+    let timeouts: Vec<String> = protocol.timeouts.iter().map(|t| t.to_string()).collect();
+    let mut lines = vec![
+        "/begin PROTOCOL_LAYER".to_string(),
+        format!("  0x{:04X}", PROTOCOL_VERSION),
+        format!("  {}", timeouts.join(" ")),
+        format!("  {} {}", protocol.max_cto, protocol.max_dto),
+        match protocol.byte_order {
+            Endianness::Little => "  BYTE_ORDER_MSB_LAST".to_string(),
+            Endianness::Big => "  BYTE_ORDER_MSB_FIRST".to_string(),
+        },
+        "  ADDRESS_GRANULARITY_BYTE".to_string(),
+    ];
+    lines.extend(
+        protocol
+            .optional_commands
+            .iter()
+            .map(|cmd| format!("  OPTIONAL_CMD {}", cmd)),
+    );
+    lines.push("/end PROTOCOL_LAYER".to_string());
+    lines
+}
+
+fn daq(config: &XcpConfig) -> Result<Vec<String>> {
+    let daq = &config.daq;
+    let mut lines = vec![
+        "/begin DAQ".to_string(),
+        match daq.config_type {
+            DaqConfigType::Static => "  STATIC".to_string(),
+            DaqConfigType::Dynamic => "  DYNAMIC".to_string(),
+        },
+        format!("  0x{:02X}", daq.max_daq),
+        format!("  0x{:02X}", config.events.len()),
+        format!("  0x{:02X}", daq.min_daq),
+        "  OPTIMISATION_TYPE_DEFAULT".to_string(),
+        "  ADDRESS_EXTENSION_FREE".to_string(),
+        "  IDENTIFICATION_FIELD_TYPE_ABSOLUTE".to_string(),
+        "  GRANULARITY_ODT_ENTRY_SIZE_DAQ_BYTE".to_string(),
+        format!("  0x{:02X}", daq.max_odt_entry_size),
+        "  NO_OVERLOAD_INDICATION".to_string(),
+    ];
+
+    for list in &daq.lists {
+        lines.push("  /begin DAQ_LIST".to_string());
+        lines.push(format!("    0x{:02X}", list.number));
+        lines.push("    DAQ_LIST_TYPE DAQ".to_string());
+        lines.push(format!("    MAX_ODT 0x{:02X}", list.max_odt));
+        lines.push(format!(
+            "    MAX_ODT_ENTRIES 0x{:02X}",
+            list.max_odt_entries
+        ));
+        if let Some(event) = list.event_fixed {
+            lines.push(format!("    EVENT_FIXED 0x{:04X}", event));
+        }
+        lines.push("  /end DAQ_LIST".to_string());
+    }
+
+    for event in &config.events {
+        let (cycle, unit) = time_cycle(event.cycle_us).with_context(|| {
+            format!("事件 {} 的周期 {} µs 无法表示", event.name, event.cycle_us)
+        })?;
+        let short_name = if event.short_name.is_empty() {
+            &event.name
+        } else {
+            &event.short_name
+        };
+        lines.push("  /begin EVENT".to_string());
+        lines.push(format!(
+            "    \"{}\" \"{}\" 0x{:04X} {}",
+            event.name,
+            short_name,
+            event.number,
+            event.direction.keyword()
+        ));
+        lines.push(format!(
+            "    0x{:02X} {} {} 0x{:02X}",
+            event.max_daq_list, cycle, unit, event.priority
+        ));
+        lines.push("  /end EVENT".to_string());
+    }
+
+    lines.push("/end DAQ".to_string());
+    Ok(lines)
+}
+
+fn transport(transport: &Transport) -> Vec<String> {
+    match transport {
+        Transport::Can {
+            baudrate,
+            master_id,
+            slave_id,
+            broadcast_id,
+            extended,
+        } => {
+            let id = |id: u32| {
+                if *extended {
+                    format!("0x{:08X}", id | CAN_EXTENDED_FLAG)
+                } else {
+                    format!("0x{:03X}", id)
+                }
+            };
+            let mut lines = vec![
+                "/begin XCP_ON_CAN".to_string(),
+                format!("  0x{:04X}", TRANSPORT_VERSION),
+            ];
+            if let Some(broadcast) = broadcast_id {
+                lines.push(format!("  CAN_ID_BROADCAST {}", id(*broadcast)));
+            }
+            lines.push(format!("  CAN_ID_MASTER {}", id(*master_id)));
+            lines.push(format!("  CAN_ID_SLAVE {}", id(*slave_id)));
+            lines.push(format!("  BAUDRATE {}", baudrate));
+            lines.push("/end XCP_ON_CAN".to_string());
+            lines
+        }
+        Transport::Ethernet {
+            protocol,
+            address,
+            port,
+        } => {
+            let keyword = match protocol {
+                IpProtocol::Udp => "XCP_ON_UDP_IP",
+                IpProtocol::Tcp => "XCP_ON_TCP_IP",
+            };
+            vec![
+                format!("/begin {}", keyword),
+                format!("  0x{:04X}", TRANSPORT_VERSION),
+                format!("  {}", port),
+                format!("  ADDRESS \"{}\"", address),
+                format!("/end {}", keyword),
+            ]
+        }
+    }
+}
+
+/// 周期换算为 (TIME_CYCLE, TIME_UNIT)，取周期不超过 255 的最小单位；非周期事件为 (0, 1 ms)
+fn time_cycle(cycle_us: u64) -> Option<(u8, u8)> {
+    if cycle_us == 0 {
+        return Some((0, 6));
+    }
+    TIME_UNITS
+        .iter()
+        .filter(|(_, us)| cycle_us.is_multiple_of(*us))
+        .find_map(|(code, us)| u8::try_from(cycle_us / us).ok().map(|c| (c, *code)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"ASAP2_VERSION 1 71
+/begin PROJECT P ""
+  /begin MODULE M ""
+    /begin MOD_PAR ""
+      /begin MEMORY_SEGMENT ram "" DATA RAM INTERN 0x1000 0x100 -1 -1 -1 -1 -1
+        /begin IF_DATA XCP /begin SEGMENT 0 1 0 0 0 /end SEGMENT /end IF_DATA
+      /end MEMORY_SEGMENT
+    /end MOD_PAR
+    /begin MEASUREMENT speed "" UWORD NO_COMPU_METHOD 0 0 0 65535
+      ECU_ADDRESS 0x1000
+      /begin IF_DATA CANAPE_EXT 100 /end IF_DATA
+    /end MEASUREMENT
+  /end MODULE
+/end PROJECT
+"#;
+
+    fn config() -> XcpConfig {
+        XcpConfig {
+            events: vec![
+                EventChannel {
+                    name: "10ms".to_string(),
+                    short_name: String::new(),
+                    number: 0,
+                    direction: EventDirection::Daq,
+                    cycle_us: 10_000,
+                    max_daq_list: 0xFF,
+                    priority: 0,
+                },
+                EventChannel {
+                    name: "1s".to_string(),
+                    short_name: "slow".to_string(),
+                    number: 1,
+                    direction: EventDirection::DaqStim,
+                    cycle_us: 1_000_000,
+                    max_daq_list: 2,
+                    priority: 1,
+                },
+            ],
+            ..XcpConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_insert_then_update_in_place() {
+        let inserted = set_xcp_if_data(SAMPLE, &config()).unwrap();
+        let doc = A2lDocument::parse(&inserted).unwrap();
+        let module = doc.find_blocks("MODULE")[0];
+        assert!(module_xcp(module).is_some());
+        // 其他 IF_DATA 原样保留
+        assert!(inserted
+            .contains("/begin IF_DATA XCP /begin SEGMENT 0 1 0 0 0 /end SEGMENT /end IF_DATA"));
+        assert!(inserted.contains("/begin IF_DATA CANAPE_EXT 100 /end IF_DATA"));
+        assert_eq!(
+            read_xcp_config(&inserted).unwrap(),
+            Some(config()).map(|mut c| {
+                c.events[0].short_name = "10ms".to_string();
+                c
+            })
+        );
+
+        let mut ethernet = config();
+        ethernet.transport = Transport::Ethernet {
+            protocol: IpProtocol::Udp,
+            address: "192.168.1.10".to_string(),
+            port: 5555,
+        };
+        let updated = set_xcp_if_data(&inserted, &ethernet).unwrap();
+        assert_eq!(updated.matches("/begin IF_DATA XCP").count(), 2);
+        assert!(updated.contains("ADDRESS \"192.168.1.10\""));
+        assert!(!updated.contains("XCP_ON_CAN"));
+    }
+
+    #[test]
+    fn test_time_cycle_picks_smallest_unit() {
+        assert_eq!(time_cycle(10_000), Some((100, 5)));
+        assert_eq!(time_cycle(1_000_000), Some((100, 7)));
+        assert_eq!(time_cycle(0), Some((0, 6)));
+        assert_eq!(time_cycle(1_500), Some((150, 4)));
+    }
+}
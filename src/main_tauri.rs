@@ -56,13 +56,34 @@ fn main() {
             commands::load_elf,
             commands::load_package,
             commands::generate_package,
+            commands::cancel_generate_package,
             commands::load_a2l,
             commands::search_elf_entries,
             commands::get_elf_count,
+            commands::get_tree_roots,
+            commands::get_tree_children,
+            commands::get_tree_node,
             commands::search_a2l_variables,
             commands::export_entries,
             commands::delete_variables,
             commands::save_a2l_changes,
+            commands::list_conversions,
+            commands::create_conversions,
+            commands::list_a2l_groups,
+            commands::validate_a2l,
+            commands::update_a2l_addresses,
+            commands::diff_a2l_files,
+            commands::merge_a2l_files,
+            commands::get_xcp_config,
+            commands::set_xcp_config,
+            commands::get_module_params,
+            commands::propose_module_params,
+            commands::set_module_params,
+            commands::create_a2l_group,
+            commands::update_a2l_group,
+            commands::delete_a2l_group,
+            commands::find_variable_references,
+            commands::rename_a2l_variable,
             commands::set_endianness,
         ])
         .run(tauri::generate_context!())